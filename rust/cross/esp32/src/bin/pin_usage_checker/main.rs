@@ -34,6 +34,8 @@ fn _pins() {
     let _rx_over_devkit = peripherals.GPIO3;
     let _tx_over_adapter = peripherals.GPIO23;
     let _rx_over_adapter = peripherals.GPIO22;
+    let _scpi_tx = peripherals.GPIO21;
+    let _scpi_rx = peripherals.GPIO4;
 
     // Pins reserved for the display
     let _display_chip_select = peripherals.GPIO19;
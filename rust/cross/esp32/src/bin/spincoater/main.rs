@@ -10,6 +10,7 @@
 use core::cell::RefCell;
 
 use embassy_executor::Spawner;
+use embassy_time::Timer;
 use embedded_hal_bus::spi::RefCellDevice;
 use esp_backtrace as _;
 use esp_hal::{
@@ -24,8 +25,9 @@ use esp_hal::{
     time::Rate,
     timer::timg::TimerGroup,
 };
+use esp_rtos::embassy::InterruptExecutor;
 use esp32::{
-    SECOND_CORE_STACK,
+    SECOND_CORE_EXECUTOR, SECOND_CORE_EXECUTOR_PRIORITY, SECOND_CORE_STACK,
     gpio::{
         display::{
             DISPLAY, ORIENTATION, SPI, SPI_BUFFER, SPI_BUFFER_SIZE,
@@ -36,7 +38,7 @@ use esp32::{
         interrupt_handler,
         pwm::{FREQUENCY, PERIOD, PERIPHERAL_CLOCK_PRESCALER},
     },
-    runners::rpm::{RPM_BUFFER, Runner, channel::RUNNER_CHANNEL},
+    runners::rpm::{RPM_BUFFER, Runner, channel::RUNNER_CHANNEL, run},
 };
 use ibm437::IBM437_9X14_REGULAR;
 use mipidsi::{interface::SpiInterface, models::ILI9341Rgb565};
@@ -95,20 +97,6 @@ async fn main(spawner: Spawner) -> ! {
         encoder_memory_cell.replace(encoder);
     });
 
-    // Run the encoder task/ISR on the second core so it doesn't block the program.
-    let software_interrupts = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
-    esp_rtos::start_second_core(
-        peripherals.CPU_CTRL,
-        software_interrupts.software_interrupt0,
-        software_interrupts.software_interrupt1,
-        SECOND_CORE_STACK.take(),
-        || {
-            // Set the interrupt handler for GPIO.
-            let mut io = Io::new(peripherals.IO_MUX);
-            io.set_interrupt_handler(interrupt_handler);
-        },
-    );
-
     // Initialize PWM
     let clock_cfg = PeripheralClockConfig::with_prescaler(PERIPHERAL_CLOCK_PRESCALER);
     let mut mcpwm = McPwm::new(peripherals.MCPWM0, clock_cfg);
@@ -222,5 +210,28 @@ async fn main(spawner: Spawner) -> ! {
         rpm_buffer,
     );
 
-    runner.run().await
+    // Run the encoder ISR and the runner on the second core
+    // so the control loop isn't delayed by display redraws.
+    let software_interrupts = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
+    esp_rtos::start_second_core(
+        peripherals.CPU_CTRL,
+        software_interrupts.software_interrupt0,
+        software_interrupts.software_interrupt1,
+        SECOND_CORE_STACK.take(),
+        move || {
+            // Set the interrupt handler for GPIO.
+            let mut io = Io::new(peripherals.IO_MUX);
+            io.set_interrupt_handler(interrupt_handler);
+            let executor = SECOND_CORE_EXECUTOR.init(InterruptExecutor::new(
+                software_interrupts.software_interrupt2,
+            ));
+            executor
+                .start(SECOND_CORE_EXECUTOR_PRIORITY)
+                .must_spawn(run(runner));
+        },
+    );
+
+    loop {
+        Timer::after_secs(1).await;
+    }
 }
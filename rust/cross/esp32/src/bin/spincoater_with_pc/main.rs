@@ -8,6 +8,7 @@
 #![deny(clippy::large_stack_frames)]
 
 use embassy_executor::Spawner;
+use embassy_futures::join::join;
 use embassy_time::Timer;
 use esp_backtrace as _;
 use esp_hal::{
@@ -16,21 +17,24 @@ use esp_hal::{
     interrupt::software::SoftwareInterruptControl,
     mcpwm::{McPwm, PeripheralClockConfig, operator::PwmPinConfig, timer::PwmWorkingMode},
     timer::timg::TimerGroup,
-    uart::Uart,
 };
 use esp_println::println;
+use esp_rtos::embassy::InterruptExecutor;
 use esp32::{
-    REQUEST_CHANNEL, REQUEST_RESPONSE_SIGNAL, SECOND_CORE_STACK,
+    JOG_CHANNEL, JOG_RESPONSE_SIGNAL, REQUEST_CHANNEL, REQUEST_RESPONSE_SIGNAL,
+    SECOND_CORE_EXECUTOR, SECOND_CORE_EXECUTOR_PRIORITY, SECOND_CORE_STACK,
     gpio::{
         encoder::ENCODER,
         interrupt_handler,
         pwm::{FREQUENCY, PERIOD, PERIPHERAL_CLOCK_PRESCALER, SETPOINTS},
+        vacuum_pump::VACUUM_PUMP,
     },
-    rpc::{Context, Dispatcher, FRAME_BUFFER, WIRE_STORAGE},
+    link::{self, LinkRx, LinkTx, LinkWireTx},
+    rpc::{Context, Dispatcher, FRAME_BUFFER, Requester, WIRE_STORAGE},
     runners::motion_profile::{Runner, run},
 };
 use postcard_rpc::server::{Dispatch, Server};
-use sc_messages::{icd::BAUD_RATE, pwm::STOP_DUTY};
+use sc_messages::pwm::STOP_DUTY;
 
 // This creates a default app-descriptor required by the esp-idf bootloader.
 // For more information see: <https://docs.espressif.com/projects/esp-idf/en/stable/esp32/api-reference/system/app_image_format.html#application-description>
@@ -83,20 +87,6 @@ async fn main(spawner: Spawner) -> ! {
         encoder_memory_cell.replace(encoder);
     });
 
-    // Run the encoder task/ISR on the second core so it doesn't block the program.
-    let software_interrupts = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
-    esp_rtos::start_second_core(
-        peripherals.CPU_CTRL,
-        software_interrupts.software_interrupt0,
-        software_interrupts.software_interrupt1,
-        SECOND_CORE_STACK.take(),
-        || {
-            // Set the interrupt handler for GPIO.
-            let mut io = Io::new(peripherals.IO_MUX);
-            io.set_interrupt_handler(interrupt_handler);
-        },
-    );
-
     // Initialize PWM
     let clock_cfg = PeripheralClockConfig::with_prescaler(PERIPHERAL_CLOCK_PRESCALER);
     let mut mcpwm = McPwm::new(peripherals.MCPWM0, clock_cfg);
@@ -115,6 +105,9 @@ async fn main(spawner: Spawner) -> ! {
 
     // Initialize vacuum pump pin
     let vacuum_pump_pin = Output::new(peripherals.GPIO17, Level::Low, OutputConfig::default());
+    VACUUM_PUMP.with(|vacuum_pump_memory_cell| {
+        vacuum_pump_memory_cell.replace(vacuum_pump_pin);
+    });
 
     // Setup communication between tasks
     let request_channel = REQUEST_CHANNEL.take();
@@ -124,36 +117,55 @@ async fn main(spawner: Spawner) -> ! {
 
     let server_signal = REQUEST_RESPONSE_SIGNAL.take();
 
+    let jog_channel = JOG_CHANNEL.take();
+    let jog_signal = JOG_RESPONSE_SIGNAL.take();
+
     // Setup context
-    let context = Context::new(request_channel.sender(), server_signal, vacuum_pump_pin);
+    let requester = Requester::new(request_channel.sender(), server_signal);
+    let context = Context::new(requester, jog_channel.sender(), jog_signal);
+
+    // Serve SCPI on the secondary UART if enabled
+    cfg_select! {
+        feature = "scpi_uart" => {
+            let scpi_config = esp_hal::uart::Config::default().with_baudrate(esp32::scpi::BAUD_RATE);
+            let scpi_uart = esp_hal::uart::Uart::new(peripherals.UART2, scpi_config)
+                .expect("Failed to initialize the SCPI UART")
+                .with_tx(peripherals.GPIO21)
+                .with_rx(peripherals.GPIO4)
+                .into_async();
+            spawner.must_spawn(esp32::scpi::serve(scpi_uart, requester));
+        }
+        _ => {
+            let _ = spawner;
+        }
+    }
 
     // Setup UART and postcard-rpc after we're done with the spawner
-    let config = esp_hal::uart::Config::default().with_baudrate(BAUD_RATE);
     // Select pins based on the cargo feature
     cfg_select! {
         feature = "uart_over_adapter" => {
-            let uart = Uart::new(peripherals.UART1, config)
-                .expect("Failed to initialize UART")
-                .with_tx(peripherals.GPIO23)
-                .with_rx(peripherals.GPIO22)
-                .into_async();
+            let link = link::Parts {
+                uart: peripherals.UART1.into(),
+                tx: peripherals.GPIO23.into(),
+                rx: peripherals.GPIO22.into(),
+            };
         }
         _ => {
             println!("Taking control of the UART port. Please close RTT and open the host PC program.");
             // We have to wait for the print statement to arrive at `espflash`'s RTT monitor before taking control.
             Timer::after_millis(100).await;
-            let uart = Uart::new(peripherals.UART1, config)
-                .expect("Failed to initialize UART")
-                .with_tx(peripherals.GPIO1)
-                .with_rx(peripherals.GPIO3)
-                .into_async();
+            let link = link::Parts {
+                uart: peripherals.UART1.into(),
+                tx: peripherals.GPIO1.into(),
+                rx: peripherals.GPIO3.into(),
+            };
         }
     }
-    let (rx, tx) = uart.split();
     let dispatcher = Dispatcher::new(context, ());
     let (wire_rx, wire_tx) = WIRE_STORAGE
-        .init(rx, tx)
+        .init(LinkRx, LinkTx)
         .expect("Failed to create wire RX and TX");
+    let wire_tx = LinkWireTx::new(wire_tx);
     let frame_buffer = FRAME_BUFFER.take();
     let vkk = dispatcher.min_key_len();
     let mut server = Server::new(
@@ -170,10 +182,39 @@ async fn main(spawner: Spawner) -> ! {
         request_channel.receiver(),
         server.sender(),
         server_signal,
+        jog_channel.receiver(),
+        jog_signal,
     );
-    spawner.must_spawn(run(runner));
 
-    loop {
-        let _ = server.run().await;
-    }
+    // Run the encoder ISR and the runner on the second core
+    // so the control loop isn't delayed by postcard-rpc.
+    let software_interrupts = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
+    esp_rtos::start_second_core(
+        peripherals.CPU_CTRL,
+        software_interrupts.software_interrupt0,
+        software_interrupts.software_interrupt1,
+        SECOND_CORE_STACK.take(),
+        move || {
+            // Set the interrupt handler for GPIO.
+            let mut io = Io::new(peripherals.IO_MUX);
+            io.set_interrupt_handler(interrupt_handler);
+            let executor = SECOND_CORE_EXECUTOR.init(InterruptExecutor::new(
+                software_interrupts.software_interrupt2,
+            ));
+            executor
+                .start(SECOND_CORE_EXECUTOR_PRIORITY)
+                .must_spawn(run(runner));
+        },
+    );
+
+    // The link owns the UART so it can switch baud rates while the server runs.
+    join(link::run(link), async {
+        loop {
+            let _ = server.run().await;
+            // The next host connects at the default baud rate.
+            link::reset();
+        }
+    })
+    .await
+    .0
 }
@@ -5,7 +5,7 @@ pub mod ui;
 use esp_hal::gpio::Output;
 use mousefood::{EmbeddedBackend, prelude::Rgb565};
 use ratatui::Terminal;
-use sc_messages::touchscreen::TouchPoint;
+use sc_messages::{jog, pwm::DutyCycle, touchscreen::TouchPoint};
 use static_cell::StaticCell;
 
 use crate::{
@@ -14,7 +14,10 @@ use crate::{
         terminal::channel::{TerminalReceiver, TuiEvent},
         touchscreen::xpt_2046::MAX_VALUE,
     },
-    runners::rpm::channel::{RunAt, RunnerRequest, RunnerSender},
+    runners::rpm::{
+        JOG_LIMITS,
+        channel::{RunAt, RunnerRequest, RunnerSender},
+    },
 };
 
 /// The static cell for the terminal.
@@ -28,6 +31,15 @@ const RPM: u16 = 5000;
 /// The default time in seconds.
 const TIME: u16 = 10;
 
+/// The screens the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    /// Run at a plate RPM for some time.
+    RunAt,
+    /// Hold a duty cycle without closed-loop control.
+    Jog,
+}
+
 /// The state of the terminal.
 pub struct TerminalState {
     /// The vacuum pump pin.
@@ -36,6 +48,8 @@ pub struct TerminalState {
     from_all: TerminalReceiver,
     /// A sender of requests to the runner.
     to_runner: RunnerSender,
+    /// The screen being shown.
+    screen: Screen,
     /// Whether the spincoater is running.
     is_running: bool,
     /// The most recent touch input.
@@ -44,6 +58,8 @@ pub struct TerminalState {
     target_rpm: u16,
     /// The time setting in seconds.
     target_time: u16,
+    /// The jog duty cycle setting.
+    jog_duty_cycle: DutyCycle,
     /// The current rpm in plate RPM.
     rpm: Option<u16>,
     /// The current time in seconds.
@@ -62,10 +78,12 @@ impl TerminalState {
             vacuum_pump_pin,
             from_all,
             to_runner,
+            screen: Screen::RunAt,
             is_running: false,
             touch_point: None,
             target_rpm: RPM,
             target_time: TIME,
+            jog_duty_cycle: JOG_LIMITS.min_duty_cycle,
             rpm: None,
             time: None,
         }
@@ -101,6 +119,18 @@ impl TerminalState {
 
         if self.is_running {
             match (point.x, point.y) {
+                (0..MIDDLE, 0..FIRST_THIRD) if self.screen == Screen::Jog => {
+                    self.decrease_jog_duty_cycle();
+                    self.to_runner
+                        .send(RunnerRequest::Jog(self.jog_duty_cycle))
+                        .await;
+                }
+                (MIDDLE.., 0..FIRST_THIRD) if self.screen == Screen::Jog => {
+                    self.increase_jog_duty_cycle();
+                    self.to_runner
+                        .send(RunnerRequest::Jog(self.jog_duty_cycle))
+                        .await;
+                }
                 (0..MIDDLE, SECOND_THIRD..) => {
                     self.to_runner.send(RunnerRequest::Stop).await;
                     self.is_running = false;
@@ -111,35 +141,63 @@ impl TerminalState {
                 _ => {}
             }
         } else {
-            match (point.x, point.y) {
-                (0..MIDDLE, 0..FIRST_THIRD) => {
+            match (self.screen, point.x, point.y) {
+                (Screen::RunAt, 0..MIDDLE, 0..FIRST_THIRD) => {
                     self.target_rpm = self.target_rpm.saturating_sub(100);
                 }
-                (MIDDLE.., 0..FIRST_THIRD) => {
+                (Screen::RunAt, MIDDLE.., 0..FIRST_THIRD) => {
                     self.target_rpm = self.target_rpm.saturating_add(100);
                 }
-                (0..MIDDLE, FIRST_THIRD..SECOND_THIRD) => {
+                (Screen::RunAt, 0..MIDDLE, FIRST_THIRD..SECOND_THIRD) => {
                     self.target_time = self.target_time.saturating_sub(1);
                 }
-                (MIDDLE.., FIRST_THIRD..SECOND_THIRD) => {
+                (Screen::RunAt, MIDDLE.., FIRST_THIRD..SECOND_THIRD) => {
                     self.target_time = self.target_time.saturating_add(1);
                 }
-                (0..MIDDLE, SECOND_THIRD..) => {
-                    self.to_runner
-                        .send(RunnerRequest::Run(RunAt::new(
-                            self.target_rpm,
-                            self.target_time,
-                        )))
-                        .await;
+                (Screen::Jog, 0..MIDDLE, 0..FIRST_THIRD) => self.decrease_jog_duty_cycle(),
+                (Screen::Jog, MIDDLE.., 0..FIRST_THIRD) => self.increase_jog_duty_cycle(),
+                (Screen::Jog, _, FIRST_THIRD..SECOND_THIRD) => {}
+                (_, 0..FIRST_THIRD, SECOND_THIRD..) => {
+                    let request = match self.screen {
+                        Screen::RunAt => {
+                            RunnerRequest::Run(RunAt::new(self.target_rpm, self.target_time))
+                        }
+                        Screen::Jog => RunnerRequest::Jog(self.jog_duty_cycle),
+                    };
+                    self.to_runner.send(request).await;
                     self.is_running = true;
                 }
-                (MIDDLE.., SECOND_THIRD..) => {
+                (_, FIRST_THIRD..SECOND_THIRD, SECOND_THIRD..) => {
+                    self.screen = match self.screen {
+                        Screen::RunAt => Screen::Jog,
+                        Screen::Jog => Screen::RunAt,
+                    };
+                }
+                (_, SECOND_THIRD.., SECOND_THIRD..) => {
                     self.vacuum_pump_pin.toggle();
                 }
             }
         }
         self.touch_point.replace(point);
     }
+
+    /// Lowers the jog duty cycle by [`jog::DUTY_STEP`] without going below [`JOG_LIMITS`].
+    fn decrease_jog_duty_cycle(&mut self) {
+        self.jog_duty_cycle = self
+            .jog_duty_cycle
+            .saturating_sub(jog::DUTY_STEP)
+            .max(*JOG_LIMITS.min_duty_cycle)
+            .into();
+    }
+
+    /// Raises the jog duty cycle by [`jog::DUTY_STEP`] without going above [`JOG_LIMITS`].
+    fn increase_jog_duty_cycle(&mut self) {
+        self.jog_duty_cycle = self
+            .jog_duty_cycle
+            .saturating_add(jog::DUTY_STEP)
+            .min(*JOG_LIMITS.max_duty_cycle)
+            .into();
+    }
 }
 
 /// This task updates the terminal whenever another task requests it to.
@@ -7,8 +7,9 @@ use ratatui::{
     text::{Line, Text, ToSpan},
     widgets::{Block, Paragraph},
 };
+use sc_messages::jog;
 
-use crate::gpio::display::terminal::TerminalState;
+use crate::gpio::display::terminal::{Screen, TerminalState};
 
 impl TerminalState {
     /// Draws the current information to the terminal.
@@ -35,44 +36,69 @@ impl TerminalState {
 
         let main_layout = Layout::vertical([Constraint::Ratio(1, 3); 3]);
         let [rpm_area, time_area, bottom_area] = main_area.layout(&main_layout);
-        let rpm_block = Block::bordered()
-            .title(Line::from_iter([
+        let actual_rpm = match &self.rpm {
+            Some(rpm) => rpm.to_span(),
+            None => "?".to_span(),
+        };
+        let actual_time = match &self.time {
+            Some(time) => time.to_span(),
+            None => "?".to_span(),
+        };
+
+        let rpm_block = match self.screen {
+            Screen::RunAt => Block::bordered().title(Line::from_iter([
                 "Plate RPM: ".to_span(),
                 self.target_rpm.to_span(),
                 " | Actual: ".to_span(),
-                match &self.rpm {
-                    Some(rpm) => rpm.to_span(),
-                    None => "?".to_span(),
-                },
-            ]))
-            .title_alignment(HorizontalAlignment::Center);
+                actual_rpm,
+            ])),
+            Screen::Jog => Block::bordered().title(Line::from_iter([
+                "Jog duty: ".to_span(),
+                self.jog_duty_cycle.to_span(),
+                " | Plate RPM: ".to_span(),
+                actual_rpm,
+            ])),
+        }
+        .title_alignment(HorizontalAlignment::Center);
 
-        let time_block = Block::bordered()
-            .title(Line::from_iter([
+        let time_block = match self.screen {
+            Screen::RunAt => Block::bordered().title(Line::from_iter([
                 "Time (s): ".to_span(),
                 self.target_time.to_span(),
                 " | Actual: ".to_span(),
-                match &self.time {
-                    Some(time) => time.to_span(),
-                    None => "?".to_span(),
-                },
-            ]))
-            .title_alignment(HorizontalAlignment::Center);
+                actual_time,
+            ])),
+            Screen::Jog => {
+                Block::bordered().title(Line::from_iter(["Time (s): ".to_span(), actual_time]))
+            }
+        }
+        .title_alignment(HorizontalAlignment::Center);
 
-        if !self.is_running {
+        // The jog duty cycle can be changed while running.
+        if !self.is_running || self.screen == Screen::Jog {
             let rpm_block_inner = rpm_block.inner(rpm_area);
 
             let rpm_layout = Layout::horizontal([Constraint::Ratio(1, 2); 2]);
             let [rpm_decrease_area, rpm_increase_area] = rpm_block_inner.layout(&rpm_layout);
 
+            let (decrease_text, increase_text) = match self.screen {
+                Screen::RunAt => (Line::raw("-100"), Line::raw("+100")),
+                Screen::Jog => (
+                    Line::from_iter(["-".to_span(), jog::DUTY_STEP.to_span()]),
+                    Line::from_iter(["+".to_span(), jog::DUTY_STEP.to_span()]),
+                ),
+            };
+
             let rpm_decrease = Block::bordered();
-            let rpm_decrease_text = Paragraph::new("-100").centered().block(rpm_decrease);
+            let rpm_decrease_text = Paragraph::new(decrease_text).centered().block(rpm_decrease);
             frame.render_widget(rpm_decrease_text, rpm_decrease_area);
 
             let rpm_increase = Block::bordered();
-            let rpm_increase_text = Paragraph::new("+100").centered().block(rpm_increase);
+            let rpm_increase_text = Paragraph::new(increase_text).centered().block(rpm_increase);
             frame.render_widget(rpm_increase_text, rpm_increase_area);
+        }
 
+        if !self.is_running && self.screen == Screen::RunAt {
             let time_block_inner = time_block.inner(time_area);
 
             let time_layout = Layout::horizontal([Constraint::Ratio(1, 2); 2]);
@@ -90,8 +116,28 @@ impl TerminalState {
         frame.render_widget(rpm_block, rpm_area);
         frame.render_widget(time_block, time_area);
 
-        let bottom_layout = Layout::horizontal([Constraint::Ratio(1, 2); 2]);
-        let [start_stop_area, vacuum_pump_area] = bottom_area.layout(&bottom_layout);
+        // The screen can only be switched while idle.
+        let (start_stop_area, vacuum_pump_area) = if self.is_running {
+            let bottom_layout = Layout::horizontal([Constraint::Ratio(1, 2); 2]);
+            let [start_stop_area, vacuum_pump_area] = bottom_area.layout(&bottom_layout);
+            (start_stop_area, vacuum_pump_area)
+        } else {
+            let bottom_layout = Layout::horizontal([Constraint::Ratio(1, 3); 3]);
+            let [start_stop_area, screen_area, vacuum_pump_area] =
+                bottom_area.layout(&bottom_layout);
+
+            let screen_block = Block::bordered()
+                .title("Mode")
+                .title_alignment(HorizontalAlignment::Center);
+            let screen_text = Paragraph::new(match self.screen {
+                Screen::RunAt => "Jog",
+                Screen::Jog => "RPM",
+            })
+            .centered()
+            .block(screen_block);
+            frame.render_widget(screen_text, screen_area);
+            (start_stop_area, vacuum_pump_area)
+        };
 
         let start_stop_block = Block::bordered();
         let start_stop_text = Paragraph::new(if self.is_running { "Stop" } else { "Start" })
@@ -3,13 +3,14 @@
 //! See [Espressif's documentation](https://docs.espressif.com/projects/esp-idf/en/stable/esp32/api-reference/peripherals/gpio.html)
 //! for more information on GPIO.
 
-use esp_hal::handler;
+use esp_hal::{handler, interrupt::Priority};
 
 use crate::gpio::encoder::{ENCODER, ENCODER_STATE, EncoderState};
 
 pub mod display;
 pub mod encoder;
 pub mod pwm;
+pub mod vacuum_pump;
 
 /// The handler for all GPIO interrupts.
 /// Since you can only have one GPIO handler,
@@ -18,9 +19,12 @@ pub mod pwm;
 /// See [`set_interrupt_handler`](esp_hal::gpio::Io::set_interrupt_handler) for ISR requirements,
 /// and see [`listen`](esp_hal::gpio::Input::listen) for an example.
 ///
+/// This runs above [`SECOND_CORE_EXECUTOR_PRIORITY`](crate::SECOND_CORE_EXECUTOR_PRIORITY)
+/// so encoder timestamps are never delayed by the runner sharing its core.
+///
 /// # Panics
 /// Panics if [`MOTOR_REVOLUTIONS_DOUBLED`] overflows.
-#[handler]
+#[handler(priority = Priority::Priority3)]
 pub fn interrupt_handler() {
     // Check motor encoder.
     if ENCODER.with(|encoder| {
@@ -0,0 +1,26 @@
+//! This module contains the vacuum pump that holds the substrate on the plate.
+
+use esp_hal::gpio::{Level, Output};
+use esp_sync::NonReentrantMutex;
+
+/// Provides global access to the vacuum pump pin, which is active high.
+///
+/// Both postcard-rpc and SCPI control the vacuum pump through this.
+pub static VACUUM_PUMP: NonReentrantMutex<Option<Output>> = NonReentrantMutex::new(None);
+
+/// Turns the vacuum pump on or off.
+///
+/// Does nothing if [`VACUUM_PUMP`] hasn't been initialized.
+pub fn set_enabled(enabled: bool) {
+    VACUUM_PUMP.with(|pin| {
+        if let Some(pin) = pin.as_mut() {
+            pin.set_level(Level::from(enabled));
+        }
+    });
+}
+
+/// Whether the vacuum pump is on.
+#[must_use]
+pub fn is_enabled() -> bool {
+    VACUUM_PUMP.with(|pin| pin.as_ref().is_some_and(Output::is_set_high))
+}
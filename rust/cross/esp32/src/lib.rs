@@ -9,15 +9,21 @@
 #![warn(clippy::large_stack_frames)]
 
 pub mod gpio;
+pub mod link;
 pub mod pid;
 pub mod rpc;
 pub mod runners;
+pub mod scpi;
 
-use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Channel, signal::Signal};
+use embassy_sync::{channel::Channel, mutex::Mutex, signal::Signal};
 use embassy_time::Duration;
-use esp_hal::system::Stack;
+use esp_hal::{interrupt::Priority, system::Stack};
 use esp_rtos::embassy::InterruptExecutor;
-use sc_messages::motion_profile::{Request, RequestRefused};
+use esp_sync::RawMutex;
+use sc_messages::{
+    jog,
+    motion_profile::{Request, RequestRefused},
+};
 use static_cell::{ConstStaticCell, StaticCell};
 
 use crate::gpio::pwm::SETPOINT_LIST_LENGTH;
@@ -26,8 +32,17 @@ use crate::gpio::pwm::SETPOINT_LIST_LENGTH;
 pub static SECOND_CORE_STACK: ConstStaticCell<Stack<8192>> = ConstStaticCell::new(Stack::new());
 
 /// The executor for the second core.
+///
+/// The runners are spawned on this executor so the control loop isn't delayed by
+/// display redraws or postcard-rpc serialization on the first core.
 pub static SECOND_CORE_EXECUTOR: StaticCell<InterruptExecutor<2>> = StaticCell::new();
 
+/// The interrupt priority of [`SECOND_CORE_EXECUTOR`].
+///
+/// This must stay below the priority of [`gpio::interrupt_handler`],
+/// otherwise the runner would delay encoder readings.
+pub const SECOND_CORE_EXECUTOR_PRIORITY: Priority = Priority::Priority2;
+
 /// The period that the main control loop runs at.
 ///
 /// The further you raise this past `20`, the greater your risk of filling up [`gpio::encoder::RPM_RING_BUFFER`] is.
@@ -39,14 +54,29 @@ pub const REQUEST_CHANNEL_LENGTH: usize = SETPOINT_LIST_LENGTH;
 
 /// Used for passing motion profile requests from the server to the request handler.
 ///
-/// This uses [`NoopRawMutex`] because data is only shared in one executor.
-pub static REQUEST_CHANNEL: ConstStaticCell<
-    Channel<NoopRawMutex, Request, REQUEST_CHANNEL_LENGTH>,
-> = ConstStaticCell::new(Channel::new());
+/// This uses [`RawMutex`] because the server and the runner are on different cores.
+pub static REQUEST_CHANNEL: ConstStaticCell<Channel<RawMutex, Request, REQUEST_CHANNEL_LENGTH>> =
+    ConstStaticCell::new(Channel::new());
 
 /// Used for passing request responses from the request handler to the server.
 ///
 /// This is a signal because the server always waits for one response after sending a request.
-pub static REQUEST_RESPONSE_SIGNAL: ConstStaticCell<
-    Signal<NoopRawMutex, Result<(), RequestRefused>>,
-> = ConstStaticCell::new(Signal::new());
+pub static REQUEST_RESPONSE_SIGNAL: ConstStaticCell<Signal<RawMutex, Result<(), RequestRefused>>> =
+    ConstStaticCell::new(Signal::new());
+
+/// Held from sending a request through [`REQUEST_CHANNEL`] until its response arrives,
+/// so postcard-rpc and SCPI never receive each other's responses.
+pub static REQUEST_LOCK: Mutex<RawMutex, ()> = Mutex::new(());
+
+/// The length of the buffer used by [`JOG_CHANNEL`].
+///
+/// The server always waits for a response before sending the next jog request.
+pub const JOG_CHANNEL_LENGTH: usize = 1;
+
+/// Used for passing jog requests from the server to the runner.
+pub static JOG_CHANNEL: ConstStaticCell<Channel<RawMutex, jog::Request, JOG_CHANNEL_LENGTH>> =
+    ConstStaticCell::new(Channel::new());
+
+/// Used for passing jog request responses from the runner to the server.
+pub static JOG_RESPONSE_SIGNAL: ConstStaticCell<Signal<RawMutex, jog::RequestResult>> =
+    ConstStaticCell::new(Signal::new());
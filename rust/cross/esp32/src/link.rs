@@ -0,0 +1,308 @@
+//! This module contains the UART link to the host PC, whose baud rate can be switched while postcard-rpc uses it.
+//!
+//! The baud rate can only be changed on an unsplit [`Uart`], so [`run`] keeps the UART peripheral and pins,
+//! and recreates the [`Uart`] at every new baud rate.
+//! In between, its receiving and transmitting halves move bytes independently
+//! between the UART and the [`LinkRx`] and [`LinkTx`] given to postcard-rpc.
+
+use core::{convert::Infallible, fmt::Arguments, future::pending};
+
+use embassy_futures::select::{Either, Either4, select, select4};
+use embassy_sync::{pipe::Pipe, signal::Signal};
+use embassy_time::{Duration, Instant, Timer};
+use embedded_io_async_06::{ErrorType, Read, Write};
+use esp_hal::{
+    Async,
+    gpio::AnyPin,
+    uart::{AnyUart, Config, TxError, Uart, UartRx, UartTx},
+};
+use esp_sync::{NonReentrantMutex, RawMutex};
+use postcard_rpc::{
+    Endpoint,
+    header::{VarHeader, VarKey, VarKeyKind},
+    server::{WireTx, WireTxErrorKind, impls::embedded_io_async_v0_6::EioWireTx},
+};
+use sc_messages::{
+    icd::{BAUD_RATE, BaudRateEndpoint},
+    link::{RequestRefused, RequestResult, SWITCH_TIMEOUT_MILLIS},
+};
+use serde::Serialize;
+
+use crate::runners::status::RUN_STATUS;
+
+/// The number of bytes buffered in each direction between the UART and postcard-rpc.
+pub const PIPE_SIZE: usize = 256;
+
+/// The bytes received from the host PC that postcard-rpc hasn't read yet.
+static FROM_HOST: Pipe<RawMutex, PIPE_SIZE> = Pipe::new();
+
+/// The bytes written by postcard-rpc that haven't been sent to the host PC yet.
+static TO_HOST: Pipe<RawMutex, PIPE_SIZE> = Pipe::new();
+
+/// Provides global access to the progress of a baud rate switch.
+///
+/// The baud rate endpoint writes to this, and [`LinkWireTx`] and [`run`] carry out the switch.
+static SWITCH: NonReentrantMutex<Switch> = NonReentrantMutex::new(Switch::Idle);
+
+/// Sent by [`LinkWireTx`] once the reply accepting a baud rate is written, so [`run`] switches to it after sending the reply.
+static SWITCH_SIGNAL: Signal<RawMutex, u32> = Signal::new();
+
+/// Sent by [`reset`] so [`run`] goes back to [`BAUD_RATE`], where the next host connects.
+static RESET_SIGNAL: Signal<RawMutex, ()> = Signal::new();
+
+/// The progress of a baud rate switch.
+#[derive(Debug, Clone, Copy)]
+enum Switch {
+    /// No switch is in progress.
+    Idle,
+    /// The host proposed this baud rate, and the reply accepting it hasn't been written yet.
+    Pending(u32),
+    /// The baud rate was switched, and falls back to [`BAUD_RATE`] unless the host confirms it before this deadline.
+    Confirming(Instant),
+}
+
+/// The UART config for a baud rate.
+fn config(baud_rate: u32) -> Config {
+    Config::default().with_baudrate(baud_rate)
+}
+
+/// Accepts a proposed baud rate, which is switched to after the reply is sent.
+///
+/// # Errors
+/// Returns an error if the runner is running or jogging, or still publishing how it ended,
+/// since its states would be lost while switching.
+pub fn propose(baud_rate: u32) -> RequestResult {
+    if RUN_STATUS.with(|status| status.active) {
+        return Err(RequestRefused::Running);
+    }
+    SWITCH.with(|switch| *switch = Switch::Pending(baud_rate));
+    Ok(())
+}
+
+/// Keeps the baud rate that was switched to.
+///
+/// # Errors
+/// Returns an error if no switch is waiting for confirmation.
+pub fn confirm() -> RequestResult {
+    SWITCH.with(|switch| match switch {
+        Switch::Confirming(_) => {
+            *switch = Switch::Idle;
+            Ok(())
+        }
+        Switch::Idle | Switch::Pending(_) => Err(RequestRefused::NotSwitching),
+    })
+}
+
+/// Goes back to [`BAUD_RATE`], dropping any switch in progress.
+///
+/// This is called when the host disconnects and when the server session ends,
+/// since the next host always connects at [`BAUD_RATE`].
+pub fn reset() {
+    SWITCH.with(|switch| *switch = Switch::Idle);
+    SWITCH_SIGNAL.reset();
+    RESET_SIGNAL.signal(());
+}
+
+/// The UART peripheral and pins used for the link.
+pub struct Parts {
+    /// The UART peripheral connected to the host PC.
+    pub uart: AnyUart<'static>,
+    /// The pin that transmits to the host PC.
+    pub tx: AnyPin<'static>,
+    /// The pin that receives from the host PC.
+    pub rx: AnyPin<'static>,
+}
+
+/// Moves bytes between the UART and postcard-rpc, switching baud rates when the host asks to
+/// and going back to [`BAUD_RATE`] on [`reset`].
+///
+/// This must run alongside the postcard-rpc server.
+///
+/// # Panics
+/// Panics if the UART can't be created at [`BAUD_RATE`].
+pub async fn run(mut parts: Parts) -> ! {
+    let mut baud_rate = BAUD_RATE;
+    loop {
+        let uart = match Uart::new(parts.uart.reborrow(), config(baud_rate)) {
+            Ok(uart) => uart,
+            Err(error) => {
+                assert_ne!(baud_rate, BAUD_RATE, "Failed to initialize UART: {error:?}");
+                // The host won't be able to confirm, so it will fall back too.
+                SWITCH.with(|switch| *switch = Switch::Idle);
+                baud_rate = BAUD_RATE;
+                continue;
+            }
+        };
+        let (rx, tx) = uart
+            .with_tx(parts.tx.reborrow())
+            .with_rx(parts.rx.reborrow())
+            .into_async()
+            .split();
+        let link = select4(receive(rx), transmit(tx), fall_back(), RESET_SIGNAL.wait());
+        baud_rate = match link.await {
+            Either4::First(never) => never,
+            Either4::Second(proposed) => {
+                let deadline =
+                    Instant::now().saturating_add(Duration::from_millis(SWITCH_TIMEOUT_MILLIS));
+                SWITCH.with(|switch| *switch = Switch::Confirming(deadline));
+                proposed
+            }
+            Either4::Third(()) | Either4::Fourth(()) => BAUD_RATE,
+        };
+    }
+}
+
+/// Moves bytes from the UART to postcard-rpc.
+///
+/// Waiting for postcard-rpc to read only holds up receiving, never transmitting.
+async fn receive(mut rx: UartRx<'_, Async>) -> ! {
+    let mut from_host = [0; PIPE_SIZE];
+    loop {
+        // Framing errors are expected while the two sides are at different baud rates.
+        if let Ok(read) = rx.read_async(&mut from_host).await {
+            FROM_HOST
+                .write_all(from_host.get(..read).unwrap_or_default())
+                .await;
+        }
+    }
+}
+
+/// Moves bytes from postcard-rpc to the UART until a baud rate is accepted.
+///
+/// Returns the accepted baud rate once everything written before the reply accepting it,
+/// including the reply, has been sent at the current one.
+async fn transmit(mut tx: UartTx<'_, Async>) -> u32 {
+    let mut to_host = [0; PIPE_SIZE];
+    loop {
+        match select(TO_HOST.read(&mut to_host), SWITCH_SIGNAL.wait()).await {
+            Either::First(read) => {
+                let _ = send(&mut tx, to_host.get(..read).unwrap_or_default()).await;
+            }
+            Either::Second(baud_rate) => {
+                while let Ok(read) = TO_HOST.try_read(&mut to_host) {
+                    let _ = send(&mut tx, to_host.get(..read).unwrap_or_default()).await;
+                }
+                let _ = tx.flush_async().await;
+                return baud_rate;
+            }
+        }
+    }
+}
+
+/// Returns once the host hasn't confirmed the switched baud rate in time, so [`run`] falls back to [`BAUD_RATE`].
+async fn fall_back() {
+    let deadline = SWITCH.with(|switch| match switch {
+        Switch::Confirming(deadline) => Some(*deadline),
+        Switch::Idle | Switch::Pending(_) => None,
+    });
+    if let Some(deadline) = deadline {
+        Timer::at(deadline).await;
+        let unconfirmed = SWITCH.with(|switch| {
+            let unconfirmed = matches!(switch, Switch::Confirming(_));
+            if unconfirmed {
+                *switch = Switch::Idle;
+            }
+            unconfirmed
+        });
+        if unconfirmed {
+            return;
+        }
+    }
+    pending().await
+}
+
+/// Sends all of `bytes` to the host PC.
+async fn send(tx: &mut UartTx<'_, Async>, mut bytes: &[u8]) -> Result<(), TxError> {
+    while !bytes.is_empty() {
+        let written = tx.write_async(bytes).await?;
+        bytes = bytes.get(written..).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// The receiving half of the link given to postcard-rpc.
+#[derive(Debug, Default)]
+pub struct LinkRx;
+
+impl ErrorType for LinkRx {
+    type Error = Infallible;
+}
+
+impl Read for LinkRx {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(FROM_HOST.read(buf).await)
+    }
+}
+
+/// The transmitting half of the link given to postcard-rpc.
+#[derive(Debug, Default)]
+pub struct LinkTx;
+
+impl ErrorType for LinkTx {
+    type Error = Infallible;
+}
+
+impl Write for LinkTx {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(TO_HOST.write(buf).await)
+    }
+}
+
+/// The postcard-rpc wire that sends frames through [`LinkTx`].
+///
+/// It tells [`run`] to switch baud rates once the whole reply accepting one is written,
+/// so publishes and logs sent around it can't start the switch early.
+#[derive(Clone)]
+pub struct LinkWireTx {
+    inner: EioWireTx<RawMutex, LinkTx>,
+}
+
+impl LinkWireTx {
+    /// Wraps the wire that postcard-rpc created for [`LinkTx`].
+    #[must_use]
+    pub fn new(inner: EioWireTx<RawMutex, LinkTx>) -> Self {
+        Self { inner }
+    }
+}
+
+impl WireTx for LinkWireTx {
+    type Error = WireTxErrorKind;
+
+    async fn send<T: Serialize + ?Sized>(
+        &self,
+        hdr: VarHeader,
+        msg: &T,
+    ) -> Result<(), Self::Error> {
+        self.inner.send(hdr, msg).await?;
+        if hdr.key == VarKey::Key8(BaudRateEndpoint::RESP_KEY) {
+            // Only an accepted proposal is pending, so refusals and confirmations don't switch.
+            let proposed = SWITCH.with(|switch| match *switch {
+                Switch::Pending(baud_rate) => {
+                    *switch = Switch::Idle;
+                    Some(baud_rate)
+                }
+                Switch::Idle | Switch::Confirming(_) => None,
+            });
+            if let Some(baud_rate) = proposed {
+                SWITCH_SIGNAL.signal(baud_rate);
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_raw(&self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.inner.send_raw(buf).await
+    }
+
+    async fn send_log_str(&self, kkind: VarKeyKind, s: &str) -> Result<(), Self::Error> {
+        self.inner.send_log_str(kkind, s).await
+    }
+
+    async fn send_log_fmt<'a>(
+        &self,
+        kkind: VarKeyKind,
+        a: Arguments<'a>,
+    ) -> Result<(), Self::Error> {
+        self.inner.send_log_fmt(kkind, a).await
+    }
+}
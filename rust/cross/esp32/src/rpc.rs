@@ -1,29 +1,38 @@
-use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Sender, signal::Signal};
-use esp_hal::{
-    Async,
-    gpio::Output,
-    uart::{UartRx, UartTx},
-};
+use embassy_sync::{channel::Sender, signal::Signal};
 use esp_sync::RawMutex;
 use postcard_rpc::{
     define_dispatch,
     header::{VarHeader, VarSeq},
     server::{
         self,
-        impls::embedded_io_async_v0_6::{EioWireRx, EioWireTx, WireStorage},
+        impls::embedded_io_async_v0_6::{EioWireRx, WireStorage},
     },
 };
 use sc_messages::{
+    diagnostics::{Calibration, ControllerParameters, DeviceInfo, LoopTiming},
     icd::{
-        ENDPOINTS_LIST, HostDisconnecting, MotionRequestEndpoint, TOPICS_TO_CLIENT_LIST,
-        TOPICS_TO_SERVER_LIST, VacuumPumpRequestEndpoint,
+        BaudRateEndpoint, DeviceInfoEndpoint, ENDPOINTS_LIST, HostDisconnecting,
+        JogRequestEndpoint, LoopTimingEndpoint, MotionRequestEndpoint, RunAtEndpoint,
+        TOPICS_TO_CLIENT_LIST, TOPICS_TO_SERVER_LIST, TelemetryConfigEndpoint,
+        VacuumPumpRequestEndpoint,
     },
+    jog, link,
     motion_profile::{self, RequestRefused},
-    vacuum_pump,
+    pwm::{HALF_POWER_DUTY, STOP_DUTY},
+    telemetry, vacuum_pump,
 };
 use static_cell::ConstStaticCell;
 
-use crate::REQUEST_CHANNEL_LENGTH;
+use crate::{
+    JOG_CHANNEL_LENGTH, LOOP_PERIOD, REQUEST_CHANNEL_LENGTH, REQUEST_LOCK,
+    gpio::pwm::{RPM_TO_DUTY_DENOMINATOR, RPM_TO_DUTY_INTERCEPT, RPM_TO_DUTY_NUMERATOR},
+    link::{LinkRx, LinkTx, LinkWireTx},
+    pid::K_P_INVERSE,
+    runners::{
+        telemetry::TELEMETRY_CONFIG,
+        timing::{LOOP_STATISTICS, as_micros},
+    },
+};
 
 /// The size of the buffers used by postcard-rpc.
 pub const BUFFER_SIZE: usize = 2048;
@@ -33,46 +42,99 @@ pub static FRAME_BUFFER: ConstStaticCell<[u8; BUFFER_SIZE]> =
     ConstStaticCell::new([0; BUFFER_SIZE]);
 
 /// The storage that provides wire Tx and Rx.
-pub static WIRE_STORAGE: WireStorage<
-    UartRx<'static, Async>,
-    UartTx<'static, Async>,
-    RawMutex,
-    BUFFER_SIZE,
-    BUFFER_SIZE,
-> = WireStorage::new();
+pub static WIRE_STORAGE: WireStorage<LinkRx, LinkTx, RawMutex, BUFFER_SIZE, BUFFER_SIZE> =
+    WireStorage::new();
 
 /// According to [the example](https://github.com/jamesmunns/postcard-rpc/blob/17dc2360a21c5caad5a20efb6a0a276df29ec945/example/firmware/src/bin/comms-02.rs#L277),
 /// publish requires 0.
 pub const SEQUENCE_NUMBER: VarSeq = VarSeq::Seq2(0);
 
 /// This signal is sent to the motion profile runner whenever the host notifies that it is disconnecting.
+///
+/// This is the only way the runner learns that the host is gone, since publishing through the link never fails.
+/// A host that vanishes without notifying leaves a motion profile running to completion,
+/// while jogging stops once no jog duty cycle arrives within the jog timeout.
 pub static HOST_DISCONNECTED: Signal<RawMutex, ()> = Signal::new();
 
-pub type WireTx = EioWireTx<RawMutex, UartTx<'static, Async>>;
+pub type WireTx = LinkWireTx;
 
-pub type WireRx = EioWireRx<UartRx<'static, Async>>;
+pub type WireRx = EioWireRx<LinkRx>;
+
+/// Sends motion profile requests to the runner and waits for its responses.
+///
+/// This is shared by postcard-rpc and SCPI.
+#[derive(Clone, Copy)]
+pub struct Requester {
+    /// Used to pass the commands to the runner.
+    to_runner: Sender<'static, RawMutex, motion_profile::Request, REQUEST_CHANNEL_LENGTH>,
+    from_runner: &'static Signal<RawMutex, Result<(), RequestRefused>>,
+}
+
+impl Requester {
+    /// Initializes the requester.
+    #[must_use]
+    pub fn new(
+        to_runner: Sender<'static, RawMutex, motion_profile::Request, REQUEST_CHANNEL_LENGTH>,
+        from_runner: &'static Signal<RawMutex, Result<(), RequestRefused>>,
+    ) -> Self {
+        Self {
+            to_runner,
+            from_runner,
+        }
+    }
+
+    /// Forwards a request to the motion profile runner and returns its response.
+    pub async fn request(&self, request: motion_profile::Request) -> Result<(), RequestRefused> {
+        let _lock = REQUEST_LOCK.lock().await;
+        self.to_runner.send(request).await;
+        self.from_runner.wait().await
+    }
+
+    /// Replaces the motion profile with the run's setpoints and starts it.
+    ///
+    /// Returns [`RequestRefused::InvalidRun`] without touching the motion profile if the run is invalid,
+    /// or the first refusal from the motion profile runner, if any.
+    pub async fn run_at(&self, run_at: motion_profile::RunAt) -> Result<(), RequestRefused> {
+        if !run_at.is_valid() {
+            return Err(RequestRefused::InvalidRun);
+        }
+        let [first_setpoint, last_setpoint] = run_at.setpoints();
+        // Hold the lock for the whole sequence so no other request lands in the middle of it.
+        let _lock = REQUEST_LOCK.lock().await;
+        for request in [
+            motion_profile::Request::ClearSetpoints,
+            motion_profile::Request::Add(first_setpoint),
+            motion_profile::Request::Add(last_setpoint),
+            motion_profile::Request::Start,
+        ] {
+            self.to_runner.send(request).await;
+            self.from_runner.wait().await?;
+        }
+        Ok(())
+    }
+}
 
 /// Information shared to all handlers.
 pub struct Context {
     /// Used to pass the commands to the runner.
-    to_runner: Sender<'static, NoopRawMutex, motion_profile::Request, REQUEST_CHANNEL_LENGTH>,
-    from_runner: &'static Signal<NoopRawMutex, Result<(), RequestRefused>>,
-    /// Used to control the vacuum pump.
-    vacuum_pump_pin: Output<'static>,
+    requester: Requester,
+    /// Used to pass jog requests to the runner.
+    to_jog_runner: Sender<'static, RawMutex, jog::Request, JOG_CHANNEL_LENGTH>,
+    from_jog_runner: &'static Signal<RawMutex, jog::RequestResult>,
 }
 
 impl Context {
     /// Initializes the context.
     #[must_use]
     pub fn new(
-        to_runner: Sender<'static, NoopRawMutex, motion_profile::Request, REQUEST_CHANNEL_LENGTH>,
-        from_runner: &'static Signal<NoopRawMutex, Result<(), RequestRefused>>,
-        vacuum_pump_pin: Output<'static>,
+        requester: Requester,
+        to_jog_runner: Sender<'static, RawMutex, jog::Request, JOG_CHANNEL_LENGTH>,
+        from_jog_runner: &'static Signal<RawMutex, jog::RequestResult>,
     ) -> Self {
         Self {
-            to_runner,
-            from_runner,
-            vacuum_pump_pin,
+            requester,
+            to_jog_runner,
+            from_jog_runner,
         }
     }
 }
@@ -85,8 +147,31 @@ async fn handle_motion_profile_request(
     _: VarHeader,
     request: motion_profile::Request,
 ) -> Result<(), RequestRefused> {
-    context.to_runner.send(request).await;
-    context.from_runner.wait().await
+    context.requester.request(request).await
+}
+
+/// Handles constant-speed run requests from the host PC
+/// by replacing the motion profile with the run's setpoints and starting it.
+///
+/// Returns [`RequestRefused::InvalidRun`] for a zero RPM or time,
+/// or the first refusal from the motion profile runner, if any.
+async fn handle_run_at_request(
+    context: &mut Context,
+    _: VarHeader,
+    run_at: motion_profile::RunAt,
+) -> Result<(), RequestRefused> {
+    context.requester.run_at(run_at).await
+}
+
+/// Forwards jog requests to the motion profile runner
+/// and returns its response.
+async fn handle_jog_request(
+    context: &mut Context,
+    _: VarHeader,
+    request: jog::Request,
+) -> jog::RequestResult {
+    context.to_jog_runner.send(request).await;
+    context.from_jog_runner.wait().await
 }
 
 /// Handles vacuum pump requests immediately.
@@ -94,15 +179,69 @@ async fn handle_motion_profile_request(
     clippy::needless_pass_by_value,
     reason = "request is cheaper to pass by value than by reference."
 )]
-fn handle_vacuum_pump_request(context: &mut Context, _: VarHeader, request: vacuum_pump::Request) {
+fn handle_vacuum_pump_request(_: &mut Context, _: VarHeader, request: vacuum_pump::Request) {
+    match request {
+        vacuum_pump::Request::Enable => crate::gpio::vacuum_pump::set_enabled(true),
+        vacuum_pump::Request::Disable => crate::gpio::vacuum_pump::set_enabled(false),
+    }
+}
+
+/// Reports the control loop timing statistics of the current or most recent run.
+fn handle_loop_timing_request(_: &mut Context, _: VarHeader, _: ()) -> LoopTiming {
+    LOOP_STATISTICS.with(|statistics| statistics.loop_timing())
+}
+
+/// Reports the firmware version and the parameters that affect how runs behave.
+fn handle_device_info_request(_: &mut Context, _: VarHeader, _: ()) -> DeviceInfo {
+    DeviceInfo {
+        firmware_version: [
+            env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap_or_default(),
+            env!("CARGO_PKG_VERSION_MINOR").parse().unwrap_or_default(),
+            env!("CARGO_PKG_VERSION_PATCH").parse().unwrap_or_default(),
+        ],
+        calibration: Calibration {
+            rpm_to_duty_numerator: RPM_TO_DUTY_NUMERATOR,
+            rpm_to_duty_denominator: RPM_TO_DUTY_DENOMINATOR,
+            rpm_to_duty_intercept: RPM_TO_DUTY_INTERCEPT,
+        },
+        controller: ControllerParameters {
+            k_p_inverse: K_P_INVERSE,
+            loop_period: as_micros(LOOP_PERIOD),
+            min_duty_cycle: STOP_DUTY,
+            max_duty_cycle: HALF_POWER_DUTY,
+        },
+    }
+}
+
+/// Replaces the telemetry config if it is valid.
+///
+/// The runner applies it from the start of the next run.
+fn handle_telemetry_config_request(
+    _: &mut Context,
+    _: VarHeader,
+    config: telemetry::Config,
+) -> telemetry::RequestResult {
+    config.validate()?;
+    TELEMETRY_CONFIG.with(|current| *current = config);
+    Ok(())
+}
+
+/// Accepts or confirms a faster baud rate, see [`crate::link`].
+fn handle_baud_rate_request(
+    _: &mut Context,
+    _: VarHeader,
+    request: link::Request,
+) -> link::RequestResult {
+    request.validate()?;
     match request {
-        vacuum_pump::Request::Enable => context.vacuum_pump_pin.set_high(),
-        vacuum_pump::Request::Disable => context.vacuum_pump_pin.set_low(),
+        link::Request::Propose(baud_rate) => crate::link::propose(baud_rate),
+        link::Request::Confirm => crate::link::confirm(),
     }
 }
 
 fn handle_host_disconnect(_: &mut Context, _: VarHeader, _: (), _: &server::Sender<WireTx>) {
     HOST_DISCONNECTED.signal(());
+    crate::link::reset();
 }
 
 define_dispatch! {
@@ -119,6 +258,12 @@ define_dispatch! {
         |-----------------|-------|-----------------|
         | MotionRequestEndpoint | async | handle_motion_profile_request |
         | VacuumPumpRequestEndpoint | blocking | handle_vacuum_pump_request |
+        | LoopTimingEndpoint | blocking | handle_loop_timing_request |
+        | JogRequestEndpoint | async | handle_jog_request |
+        | RunAtEndpoint | async | handle_run_at_request |
+        | DeviceInfoEndpoint | blocking | handle_device_info_request |
+        | TelemetryConfigEndpoint | blocking | handle_telemetry_config_request |
+        | BaudRateEndpoint | blocking | handle_baud_rate_request |
     };
 
     topics_in: {
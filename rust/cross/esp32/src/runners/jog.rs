@@ -0,0 +1,63 @@
+//! This module contains the functionality shared by runners that support jog mode.
+//!
+//! In jog mode, the duty cycle is set directly without closed-loop control.
+
+use embassy_time::{Duration, Instant};
+use sc_messages::{
+    jog::{Limits, RequestRefused, RequestResult},
+    pwm::DutyCycle,
+};
+
+/// An active jog.
+pub struct Jog {
+    /// The limits that were active when the jog started.
+    limits: Limits,
+    /// The duty cycle to hold.
+    duty_cycle: DutyCycle,
+    /// The instant after which the jog times out.
+    deadline: Instant,
+}
+
+impl Jog {
+    /// Starts jogging at a duty cycle.
+    ///
+    /// # Errors
+    /// Returns an error if the duty cycle is outside the limits.
+    pub fn new(limits: Limits, duty_cycle: DutyCycle) -> Result<Self, RequestRefused> {
+        limits.check(duty_cycle)?;
+        Ok(Self {
+            limits,
+            duty_cycle,
+            deadline: Self::deadline(&limits),
+        })
+    }
+
+    /// Calculates the deadline for a request received now.
+    fn deadline(limits: &Limits) -> Instant {
+        Instant::now().saturating_add(Duration::from_millis(u64::from(limits.timeout_millis)))
+    }
+
+    /// Changes the duty cycle and restarts the timeout.
+    ///
+    /// # Errors
+    /// Returns an error if the duty cycle is outside the limits.
+    /// The previous duty cycle and timeout are kept.
+    pub fn set(&mut self, duty_cycle: DutyCycle) -> RequestResult {
+        self.limits.check(duty_cycle)?;
+        self.deadline = Self::deadline(&self.limits);
+        self.duty_cycle = duty_cycle;
+        Ok(())
+    }
+
+    /// The duty cycle to hold.
+    #[must_use]
+    pub fn duty_cycle(&self) -> DutyCycle {
+        self.duty_cycle
+    }
+
+    /// Whether no duty cycle was set within the timeout.
+    #[must_use]
+    pub fn timed_out(&self) -> bool {
+        Instant::now() > self.deadline
+    }
+}
@@ -1,19 +1,43 @@
 //! This module contains the runners we use to control the spincoater's motor.
 
+pub mod jog;
 pub mod motion_profile;
 pub mod rpm;
+pub mod status;
+pub mod telemetry;
+pub mod timing;
 
 use crate::LOOP_PERIOD;
-use embassy_time::{Instant, Timer};
+use embassy_time::{Duration, Instant, Timer};
+
+/// The timing of a single control loop iteration, as measured by [`sleep`].
+#[derive(Debug, Clone, Copy)]
+pub struct Iteration {
+    /// The instant at the end of [`sleep`].
+    /// This value should be passed to the next call to [`sleep`].
+    pub end: Instant,
+    /// The time between the previous end of [`sleep`] and this one.
+    pub period: Duration,
+    /// The time spent executing the loop before [`sleep`] was called.
+    pub execution: Duration,
+}
+
+impl Iteration {
+    /// Whether the loop took longer than [`LOOP_PERIOD`] to execute.
+    #[must_use]
+    pub fn overran(&self) -> bool {
+        self.execution > LOOP_PERIOD
+    }
+}
 
 /// Sleeps if less than [`LOOP_PERIOD`] time has passed since the last end of this function.
 ///
-/// Returns the instant at the end of this function call.
-/// This value should be passed to the next call to this method.
-async fn sleep(previous_sleep_end: Instant) -> Instant {
+/// Returns the timing of the iteration that just ended.
+/// [`Iteration::end`] should be passed to the next call to this method.
+async fn sleep(previous_sleep_end: Instant) -> Iteration {
     let elapsed_since_previous_sleep_end = previous_sleep_end.elapsed();
     // Only sleep if less than LOOP_PERIOD time has passed since the previous loop start.
-    match LOOP_PERIOD.checked_sub(elapsed_since_previous_sleep_end) {
+    let end = match LOOP_PERIOD.checked_sub(elapsed_since_previous_sleep_end) {
         Some(time_to_sleep) => {
             let before_sleep = Instant::now();
             Timer::after(time_to_sleep).await;
@@ -23,5 +47,10 @@ async fn sleep(previous_sleep_end: Instant) -> Instant {
                 .expect("This program will not run for 584558 years.")
         }
         None => Instant::now(),
+    };
+    Iteration {
+        end,
+        period: end.saturating_duration_since(previous_sleep_end),
+        execution: elapsed_since_previous_sleep_end,
     }
 }
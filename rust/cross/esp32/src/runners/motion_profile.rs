@@ -1,42 +1,67 @@
 //! This module contains the functionality for running motion profiles sent by the host PC.
 
 use crate::{
-    REQUEST_CHANNEL_LENGTH,
+    JOG_CHANNEL_LENGTH, REQUEST_CHANNEL_LENGTH,
     gpio::{
         encoder::{ENCODER, ENCODER_STATE, EncoderState, calculate_average_rpm},
         pwm::{SETPOINT_LIST_LENGTH, linear_conversion},
     },
     pid::{error, next_control_output},
     rpc::{HOST_DISCONNECTED, SEQUENCE_NUMBER, WireTx},
-    runners::sleep,
+    runners::{
+        jog::Jog,
+        sleep,
+        status::RUN_STATUS,
+        telemetry::Telemetry,
+        timing::{LOOP_STATISTICS, LoopStatistics, as_micros},
+    },
 };
-use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Receiver, signal::Signal};
+use embassy_futures::select::{Either, select};
+use embassy_sync::{channel::Receiver, signal::Signal};
 use embassy_time::Instant;
 use esp_hal::{gpio::Event, mcpwm::operator::PwmPin, peripherals::MCPWM0};
+use esp_sync::RawMutex;
 use heapless::Vec;
 use postcard_rpc::server::Sender;
 use sc_messages::{
-    icd::MotionProfileStateTopic,
-    motion_profile::{self, Request, RequestRefused, Setpoint},
+    icd::{MotionProfileOutcomeTopic, MotionProfileStateTopic},
+    jog,
+    motion_profile::{self, Outcome, Request, RequestRefused, Setpoint},
     pwm::{DutyCycle, HALF_POWER_DUTY, STOP_DUTY},
 };
 
+/// What the runner should execute after setup.
+enum Mode {
+    /// Execute the motion profile.
+    MotionProfile,
+    /// Hold a duty cycle until stopped or timed out.
+    Jog(Jog),
+}
+
 /// The runner that executes motion profiles.
 pub struct Runner {
     setpoints: &'static mut Vec<Setpoint, SETPOINT_LIST_LENGTH>,
     pwm_pin: PwmPin<'static, MCPWM0<'static>, 0, true>,
-    from_server: Receiver<'static, NoopRawMutex, Request, REQUEST_CHANNEL_LENGTH>,
+    from_server: Receiver<'static, RawMutex, Request, REQUEST_CHANNEL_LENGTH>,
     to_server: Sender<WireTx>,
-    server_request_responder: &'static Signal<NoopRawMutex, Result<(), RequestRefused>>,
+    server_request_responder: &'static Signal<RawMutex, Result<(), RequestRefused>>,
+    from_jog_server: Receiver<'static, RawMutex, jog::Request, JOG_CHANNEL_LENGTH>,
+    jog_request_responder: &'static Signal<RawMutex, jog::RequestResult>,
+    /// The limits applied to the next jog.
+    jog_limits: jog::Limits,
+    /// Publishes the states of the current run.
+    telemetry: Telemetry,
 }
 
 impl Runner {
     pub fn new(
         setpoints: &'static mut Vec<Setpoint, SETPOINT_LIST_LENGTH>,
         pwm_pin: PwmPin<'static, MCPWM0<'static>, 0, true>,
-        from_server: Receiver<'static, NoopRawMutex, Request, REQUEST_CHANNEL_LENGTH>,
+        from_server: Receiver<'static, RawMutex, Request, REQUEST_CHANNEL_LENGTH>,
         to_server: Sender<WireTx>,
-        server_request_responder: &'static Signal<NoopRawMutex, Result<(), RequestRefused>>,
+        server_request_responder: &'static Signal<RawMutex, Result<(), RequestRefused>>,
+        from_jog_server: Receiver<'static, RawMutex, jog::Request, JOG_CHANNEL_LENGTH>,
+        jog_request_responder: &'static Signal<RawMutex, jog::RequestResult>,
     ) -> Self {
         Self {
             setpoints,
@@ -44,6 +69,10 @@ impl Runner {
             from_server,
             to_server,
             server_request_responder,
+            from_jog_server,
+            jog_request_responder,
+            jog_limits: jog::Limits::DEFAULT,
+            telemetry: Telemetry::new(),
         }
     }
 
@@ -55,7 +84,9 @@ impl Runner {
     /// Runs the main control loop.
     async fn run(mut self) -> ! {
         loop {
-            self.setup().await;
+            let mode = self.setup().await;
+            // A new telemetry config applies from the start of the next run.
+            self.telemetry = Telemetry::new();
             // Since we are starting again, we must reset the encoder state.
             ENCODER_STATE.with(EncoderState::reset);
             // Start listening for interrupts
@@ -65,7 +96,16 @@ impl Runner {
                     .expect("The runner cannot function without the encoder.")
                     .listen(Event::RisingEdge);
             });
-            self.execute_motion_profile().await;
+            let outcome = match mode {
+                Mode::MotionProfile => {
+                    let outcome = self.execute_motion_profile().await;
+                    self.clear();
+                    outcome
+                }
+                // Jogging leaves the motion profile intact.
+                Mode::Jog(jog) => self.execute_jog(jog).await,
+            };
+            self.finish(outcome).await;
             // Stop listening for interrupts
             ENCODER.with(|encoder| {
                 encoder
@@ -73,18 +113,27 @@ impl Runner {
                     .expect("The runner cannot function without the encoder.")
                     .unlisten();
             });
-            self.clear();
         }
     }
 
     /// Sets up the motion profile.
     ///
-    /// Repeatedly waits for setpoints until a start message is received.
-    async fn setup(&mut self) {
+    /// Repeatedly waits for setpoints and jog limits until a start or jog message is received.
+    async fn setup(&mut self) -> Mode {
         // We only care if the host disconnects during execution.
         HOST_DISCONNECTED.reset();
         loop {
-            match self.from_server.receive().await {
+            let request =
+                match select(self.from_server.receive(), self.from_jog_server.receive()).await {
+                    Either::First(request) => request,
+                    Either::Second(request) => {
+                        if let Some(jog) = self.setup_jog(request) {
+                            return Mode::Jog(jog);
+                        }
+                        continue;
+                    }
+                };
+            match request {
                 Request::Add(setpoint) => match self.setpoints.push(setpoint.clone()) {
                     Ok(()) => self.server_request_responder.signal(Ok(())),
                     Err(_) => self
@@ -96,28 +145,168 @@ impl Runner {
                     self.server_request_responder.signal(Ok(()));
                 }
                 Request::Start => {
+                    // Mark the run active before the host hears that it started.
+                    RUN_STATUS.with(|status| status.active = true);
                     self.server_request_responder.signal(Ok(()));
                     // `postcard_rpc` sometimes sends setpoints out of order, so we have to sort them.
                     self.setpoints.sort();
-                    break;
+                    return Mode::MotionProfile;
+                }
+                // Nothing is running, which is what stopping asks for.
+                Request::Stop => self.server_request_responder.signal(Ok(())),
+            }
+        }
+    }
+
+    /// Handles a jog request while idle.
+    ///
+    /// Returns the jog to execute if jogging should start.
+    fn setup_jog(&mut self, request: jog::Request) -> Option<Jog> {
+        match request {
+            jog::Request::SetLimits(limits) => {
+                let result = limits.validate();
+                if result.is_ok() {
+                    self.jog_limits = limits;
+                }
+                self.jog_request_responder.signal(result);
+                None
+            }
+            jog::Request::Set(duty_cycle) => match Jog::new(self.jog_limits, duty_cycle) {
+                Ok(jog) => {
+                    RUN_STATUS.with(|status| status.active = true);
+                    self.jog_request_responder.signal(Ok(()));
+                    Some(jog)
                 }
-                Request::Stop => {
-                    self.server_request_responder
-                        .signal(Err(RequestRefused::NotRunning));
+                Err(refused) => {
+                    self.jog_request_responder.signal(Err(refused));
+                    None
                 }
+            },
+            jog::Request::Stop => {
+                self.jog_request_responder
+                    .signal(Err(jog::RequestRefused::NotJogging));
+                None
             }
         }
     }
 
+    /// Holds the jog's duty cycle until it is stopped or times out,
+    /// publishing the measured RPM through [`Telemetry`] every iteration.
+    ///
+    /// There is no setpoint in jog mode, so the published setpoint RPM is always 0.
+    async fn execute_jog(&mut self, mut jog: Jog) -> Outcome {
+        LOOP_STATISTICS.with(LoopStatistics::reset);
+        let starting_time = Instant::now();
+        let mut previous_sleep_end = starting_time;
+        loop {
+            self.pwm_pin.set_timestamp(*jog.duty_cycle());
+
+            let iteration = sleep(previous_sleep_end).await;
+            previous_sleep_end = iteration.end;
+            let overruns = LOOP_STATISTICS.with(|statistics| {
+                statistics.record(&iteration);
+                statistics.overruns()
+            });
+
+            // Check for jog requests.
+            if let Ok(request) = self.from_jog_server.try_receive() {
+                match request {
+                    jog::Request::SetLimits(_) => self
+                        .jog_request_responder
+                        .signal(Err(jog::RequestRefused::Jogging)),
+                    jog::Request::Set(duty_cycle) => {
+                        self.jog_request_responder.signal(jog.set(duty_cycle));
+                    }
+                    jog::Request::Stop => {
+                        self.jog_request_responder.signal(Ok(()));
+                        return Outcome::Stopped;
+                    }
+                }
+            }
+
+            // Stopping the motion profile also stops jogging,
+            // and every other motion profile request must wait until jogging is done.
+            if let Ok(request) = self.from_server.try_receive() {
+                match request {
+                    Request::Add(_) | Request::ClearSetpoints | Request::Start => self
+                        .server_request_responder
+                        .signal(Err(RequestRefused::Jogging)),
+                    Request::Stop => {
+                        self.server_request_responder.signal(Ok(()));
+                        return Outcome::Stopped;
+                    }
+                }
+            }
+
+            // Check for host disconnects.
+            if HOST_DISCONNECTED.try_take().is_some() {
+                return Outcome::HostDisconnected;
+            }
+
+            if jog.timed_out() {
+                self.pwm_pin.set_timestamp(STOP_DUTY);
+                let _ = self.to_server.log_str("Jog timed out.").await;
+                return Outcome::TimedOut;
+            }
+
+            // Logging
+            let current_rpm =
+                ENCODER_STATE.with(|state| calculate_average_rpm(&state.rpm_ring_buffer));
+            let state = motion_profile::State {
+                setpoint_rpm: 0,
+                current_rpm,
+                rpm_error: error(0, current_rpm),
+                duty_cycle: jog.duty_cycle(),
+                time: starting_time.elapsed().as_micros(),
+                loop_period: as_micros(iteration.period),
+                execution_time: as_micros(iteration.execution),
+                overruns,
+                sample: self.telemetry.next_sample(),
+            };
+            RUN_STATUS.with(|status| status.state = Some(state.clone()));
+            self.telemetry.publish(&self.to_server, &state).await;
+        }
+    }
+
+    /// Stops the motor and publishes the states still waiting in the batch,
+    /// then reports how the run ended, followed by the fact that there is no more state.
+    ///
+    /// The run stays active until all of this is published.
+    async fn finish(&mut self, outcome: Outcome) {
+        self.pwm_pin.set_timestamp(STOP_DUTY);
+        RUN_STATUS.with(|status| {
+            status.state = None;
+            status.outcome = Some(outcome);
+        });
+        self.telemetry.flush(&self.to_server).await;
+        let _ = self
+            .to_server
+            .publish::<MotionProfileOutcomeTopic>(SEQUENCE_NUMBER, &outcome)
+            .await;
+        let _ = self
+            .to_server
+            .publish::<MotionProfileStateTopic>(SEQUENCE_NUMBER, &None)
+            .await;
+        RUN_STATUS.with(|status| status.active = false);
+    }
+
     /// Executes the motion profile,
-    /// logging info every iteration and checking for a stop command.
-    async fn execute_motion_profile(&mut self) {
+    /// publishing its state through [`Telemetry`] every iteration and checking for a stop command.
+    ///
+    /// Returns how the motion profile ended.
+    async fn execute_motion_profile(&mut self) -> Outcome {
+        LOOP_STATISTICS.with(LoopStatistics::reset);
         let starting_time = Instant::now();
         let mut previous_sleep_end = starting_time;
         let mut setpoint_idx = 0;
         loop {
             // Sleep must be called at the start so LOOP_PERIOD time can pass before the current rpm is calculated.
-            previous_sleep_end = sleep(previous_sleep_end).await;
+            let iteration = sleep(previous_sleep_end).await;
+            previous_sleep_end = iteration.end;
+            let overruns = LOOP_STATISTICS.with(|statistics| {
+                statistics.record(&iteration);
+                statistics.overruns()
+            });
 
             // Check for stop requests.
             if let Ok(command) = self.from_server.try_receive() {
@@ -133,25 +322,32 @@ impl Runner {
                             .to_server
                             .log_str("Motion profile stopped early.")
                             .await;
-                        break;
+                        return Outcome::Stopped;
                     }
                 }
             }
 
+            // Jogging must wait until the motion profile is done.
+            if self.from_jog_server.try_receive().is_ok() {
+                self.jog_request_responder
+                    .signal(Err(jog::RequestRefused::MotionProfileRunning));
+            }
+
             // Check for host disconnects.
             if HOST_DISCONNECTED.try_take().is_some() {
                 self.pwm_pin.set_timestamp(STOP_DUTY);
-                break;
+                return Outcome::HostDisconnected;
             }
 
             let elapsed_since_start_micros = starting_time.elapsed().as_micros();
 
             // Feedforward
-            let Some((setpoint_rpm, setpoint_duty_cycle)) = self
+            let (setpoint_rpm, setpoint_duty_cycle) = match self
                 .feedforward(&mut setpoint_idx, elapsed_since_start_micros)
                 .await
-            else {
-                break;
+            {
+                Ok(setpoint) => setpoint,
+                Err(outcome) => return outcome,
             };
 
             // Feedback
@@ -166,36 +362,27 @@ impl Runner {
             self.pwm_pin.set_timestamp(duty_cycle);
 
             // Logging
-            let state = Some(motion_profile::State {
+            let state = motion_profile::State {
                 setpoint_rpm,
                 current_rpm,
                 rpm_error,
                 duty_cycle: DutyCycle::from(duty_cycle),
                 time: elapsed_since_start_micros,
-            });
-            if self
-                .to_server
-                .publish::<MotionProfileStateTopic>(SEQUENCE_NUMBER, &state)
-                .await
-                .is_err()
-            {
-                // The host PC disconnected, so we need to stop.
-                self.pwm_pin.set_timestamp(STOP_DUTY);
-                break;
-            }
+                loop_period: as_micros(iteration.period),
+                execution_time: as_micros(iteration.execution),
+                overruns,
+                sample: self.telemetry.next_sample(),
+            };
+            RUN_STATUS.with(|status| status.state = Some(state.clone()));
+            self.telemetry.publish(&self.to_server, &state).await;
         }
-        // Report that there is no more state.
-        let _ = self
-            .to_server
-            .publish::<MotionProfileStateTopic>(SEQUENCE_NUMBER, &None)
-            .await;
     }
 
     /// Calculates the setpoint rpm and duty cycle for this timestep.
     ///
-    /// If there are no more setpoints to use, the method will disable PWM, log that the motion profile finished, and return [`None`].
+    /// If there are no more setpoints to use, the method will disable PWM, log that the motion profile finished, and return [`Outcome::Completed`].
     ///
-    /// If the rpm doesn't fit in a [`u16`], the method will disable PWM, log the error, and then return [`None`].
+    /// If the rpm doesn't fit in a [`u16`], the method will disable PWM, log the error, and then return [`Outcome::Fault`].
     ///
     /// It must disable PWM itself because it awaits upon failure,
     /// and we don't want to wait on some other task before disabling PWM.
@@ -203,14 +390,14 @@ impl Runner {
         &mut self,
         setpoint_idx: &mut usize,
         elapsed_since_start_micros: u64,
-    ) -> Option<(u16, DutyCycle)> {
+    ) -> Result<(u16, DutyCycle), Outcome> {
         // Get next pair of setpoints.
         let Some((previous_setpoint, current_setpoint)) =
             self.next_setpoint_pair(setpoint_idx, elapsed_since_start_micros)
         else {
             self.pwm_pin.set_timestamp(STOP_DUTY);
             let _ = self.to_server.log_str("Motion profile done.").await;
-            return None;
+            return Err(Outcome::Completed);
         };
 
         // Get setpoint rpm.
@@ -227,10 +414,10 @@ impl Runner {
                 .to_server
                 .log_str("Failed to calculate setpoint RPM. Stopping!")
                 .await;
-            return None;
+            return Err(Outcome::Fault);
         };
         // Then we need to linearly interpolate to find the required duty cycle.
-        Some((setpoint_rpm, linear_conversion(setpoint_rpm)))
+        Ok((setpoint_rpm, linear_conversion(setpoint_rpm)))
     }
 
     /// Gets the next pair of setpoints.
@@ -1,48 +1,34 @@
 //! This module contains the channel functionality for the RPM runner.
 
-use embassy_sync::{
-    blocking_mutex::raw::NoopRawMutex,
-    channel::{Channel, Receiver, Sender},
-};
+use embassy_sync::channel::{Channel, Receiver, Sender};
+use esp_sync::RawMutex;
+use sc_messages::pwm::DutyCycle;
 use static_cell::ConstStaticCell;
 
+/// The rpm and time.
+///
+/// This is shared with the host PC's constant-speed runs.
+pub use sc_messages::motion_profile::RunAt;
+
 /// The maximum number of messages allowed at a time in each channel to/from the terminal.
 pub const RUNNER_CHANNEL_SIZE: usize = 1;
 /// Used for passing messages to the terminal.
 ///
-/// This uses `NoopRawMutex` because data is only shared in one executor.
+/// This uses [`RawMutex`] because the terminal and the runner are on different cores.
 /// This does not use a zerocopy channel because [`RunRequest`] is cheap to copy.
-pub static RUNNER_CHANNEL: ConstStaticCell<
-    Channel<NoopRawMutex, RunnerRequest, RUNNER_CHANNEL_SIZE>,
-> = ConstStaticCell::new(Channel::new());
+pub static RUNNER_CHANNEL: ConstStaticCell<Channel<RawMutex, RunnerRequest, RUNNER_CHANNEL_SIZE>> =
+    ConstStaticCell::new(Channel::new());
 
 /// The type of the runner channel sender.
-pub type RunnerSender = Sender<'static, NoopRawMutex, RunnerRequest, RUNNER_CHANNEL_SIZE>;
+pub type RunnerSender = Sender<'static, RawMutex, RunnerRequest, RUNNER_CHANNEL_SIZE>;
 
 /// The type of the runner channel receiver.
-pub type RunnerReceiver = Receiver<'static, NoopRawMutex, RunnerRequest, RUNNER_CHANNEL_SIZE>;
+pub type RunnerReceiver = Receiver<'static, RawMutex, RunnerRequest, RUNNER_CHANNEL_SIZE>;
 
 /// The message types sent between the terminal and runner.
 pub enum RunnerRequest {
     Run(RunAt),
+    /// Start jogging at a duty cycle, or change the duty cycle while jogging.
+    Jog(DutyCycle),
     Stop,
 }
-
-/// The rpm and time.
-#[derive(Debug)]
-pub struct RunAt {
-    /// Plate RPM.
-    pub rpm: u16,
-    /// Time in seconds.
-    pub time: u16,
-}
-
-impl RunAt {
-    /// Creates a run request.
-    ///
-    /// RPM should be plate RPM and time should be seconds.
-    #[must_use]
-    pub fn new(rpm: u16, time: u16) -> Self {
-        Self { rpm, time }
-    }
-}
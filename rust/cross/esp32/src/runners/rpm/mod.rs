@@ -12,13 +12,16 @@ use crate::{
         pwm::linear_conversion,
     },
     pid::{error, next_control_output},
-    runners::sleep,
+    runners::{jog::Jog, sleep},
 };
 use channel::{RunAt, RunnerReceiver, RunnerRequest};
 use embassy_time::{Duration, Instant};
 use esp_hal::{gpio::Event, mcpwm::operator::PwmPin, peripherals::MCPWM0};
 use heapless::HistoryBuf;
-use sc_messages::pwm::{HALF_POWER_DUTY, STOP_DUTY};
+use sc_messages::{
+    jog::Limits,
+    pwm::{HALF_POWER_DUTY, STOP_DUTY},
+};
 use static_cell::ConstStaticCell;
 
 /// The size of the RPM vector.
@@ -33,6 +36,14 @@ pub static RPM_BUFFER: ConstStaticCell<HistoryBuf<usize, RPM_VEC_SIZE>> =
 /// The time between motor/time updates on the terminal.
 const LOG_PERIOD: Duration = Duration::from_millis(500);
 
+/// The safety limits for jogging from the touchscreen.
+///
+/// Every touch restarts the timeout, so it is longer than the host PC's default.
+pub const JOG_LIMITS: Limits = Limits {
+    timeout_millis: 60_000,
+    ..Limits::DEFAULT
+};
+
 /// The runner that executes single RPM values.
 pub struct Runner {
     pwm_pin: PwmPin<'static, MCPWM0<'static>, 0, true>,
@@ -64,24 +75,23 @@ impl Runner {
     /// Panics if the encoder is not in the mutex.
     pub async fn run(mut self) -> ! {
         loop {
-            if let RunnerRequest::Run(run_at) = self.from_terminal.receive().await {
-                // Since we are starting again, we must reset the encoder state.
-                ENCODER_STATE.with(EncoderState::reset);
-                // Start listening for interrupts
-                ENCODER.with(|encoder| {
-                    encoder
-                        .as_mut()
-                        .expect("The runner cannot function without the encoder.")
-                        .listen(Event::RisingEdge);
-                });
-                self.execute(run_at).await;
-                // Stop listening for interrupts
-                ENCODER.with(|encoder| {
-                    encoder
-                        .as_mut()
-                        .expect("The runner cannot function without the encoder.")
-                        .unlisten();
-                });
+            match self.from_terminal.receive().await {
+                RunnerRequest::Run(run_at) => {
+                    start_encoder();
+                    self.execute(run_at).await;
+                    stop_encoder();
+                }
+                RunnerRequest::Jog(duty_cycle) => {
+                    if let Ok(jog) = Jog::new(JOG_LIMITS, duty_cycle) {
+                        start_encoder();
+                        self.execute_jog(jog).await;
+                        stop_encoder();
+                    } else {
+                        // The terminal is waiting for the jog to finish.
+                        self.to_terminal.send(TuiEvent::RunnerFinished).await;
+                    }
+                }
+                RunnerRequest::Stop => {}
             }
         }
     }
@@ -100,7 +110,7 @@ impl Runner {
 
         loop {
             // Sleep must be called at the start so LOOP_PERIOD time can pass before the current rpm is calculated.
-            previous_sleep_end = sleep(previous_sleep_end).await;
+            previous_sleep_end = sleep(previous_sleep_end).await.end;
 
             // Check for stop requests.
             if let Ok(RunnerRequest::Stop) = self.from_terminal.try_receive() {
@@ -125,19 +135,100 @@ impl Runner {
             self.pwm_pin.set_timestamp(duty_cycle);
 
             // Logging
-            self.rpm_buffer
-                .write(usize::from(motor_to_plate_revolutions(current_rpm)));
-            if previous_log.elapsed() > LOG_PERIOD {
-                let average_rpm = calculate_average_rpm(self.rpm_buffer);
-                let state = RunAt::new(average_rpm, time_since_start_secs);
-                self.to_terminal.send(TuiEvent::Runner(state)).await;
-                previous_log = Instant::now();
+            self.log(current_rpm, time_since_start_secs, &mut previous_log)
+                .await;
+        }
+        self.pwm_pin.set_timestamp(STOP_DUTY);
+        // Report that there is no more state.
+        self.to_terminal.send(TuiEvent::RunnerFinished).await;
+    }
+
+    /// Holds the jog's duty cycle until it is stopped or times out,
+    /// logging the measured RPM like [`Runner::execute`].
+    #[allow(clippy::cast_possible_truncation)]
+    async fn execute_jog(&mut self, mut jog: Jog) {
+        let starting_time = Instant::now();
+        let mut previous_sleep_end = starting_time;
+        let mut previous_log = starting_time;
+
+        loop {
+            self.pwm_pin.set_timestamp(*jog.duty_cycle());
+
+            previous_sleep_end = sleep(previous_sleep_end).await.end;
+
+            // Check for stop and jog requests.
+            match self.from_terminal.try_receive() {
+                Ok(RunnerRequest::Stop) => break,
+                Ok(RunnerRequest::Jog(duty_cycle)) => {
+                    // The terminal keeps its duty cycle within the limits,
+                    // so refusals only happen if it is out of date.
+                    let _ = jog.set(duty_cycle);
+                }
+                Ok(RunnerRequest::Run(_)) | Err(_) => {}
             }
+
+            if jog.timed_out() {
+                break;
+            }
+
+            // Logging
+            let current_rpm =
+                ENCODER_STATE.with(|state| calculate_average_rpm(&state.rpm_ring_buffer));
+            let time_since_start_secs = starting_time.elapsed().as_secs() as u16;
+            self.log(current_rpm, time_since_start_secs, &mut previous_log)
+                .await;
         }
         self.pwm_pin.set_timestamp(STOP_DUTY);
         // Report that there is no more state.
         self.to_terminal.send(TuiEvent::RunnerFinished).await;
     }
+
+    /// Records the current motor RPM and sends the average plate RPM to the terminal
+    /// if [`LOG_PERIOD`] has passed since the previous log.
+    async fn log(
+        &mut self,
+        current_rpm: u16,
+        time_since_start_secs: u16,
+        previous_log: &mut Instant,
+    ) {
+        self.rpm_buffer
+            .write(usize::from(motor_to_plate_revolutions(current_rpm)));
+        if previous_log.elapsed() > LOG_PERIOD {
+            let average_rpm = calculate_average_rpm(self.rpm_buffer);
+            let state = RunAt::new(average_rpm, time_since_start_secs);
+            self.to_terminal.send(TuiEvent::Runner(state)).await;
+            *previous_log = Instant::now();
+        }
+    }
+}
+
+/// Resets the encoder state and starts listening for encoder interrupts.
+///
+/// # Panics
+/// Panics if the encoder is not in the mutex.
+fn start_encoder() {
+    // Since we are starting again, we must reset the encoder state.
+    ENCODER_STATE.with(EncoderState::reset);
+    // Start listening for interrupts
+    ENCODER.with(|encoder| {
+        encoder
+            .as_mut()
+            .expect("The runner cannot function without the encoder.")
+            .listen(Event::RisingEdge);
+    });
+}
+
+/// Stops listening for encoder interrupts.
+///
+/// # Panics
+/// Panics if the encoder is not in the mutex.
+fn stop_encoder() {
+    ENCODER.with(|encoder| {
+        encoder
+            .as_mut()
+            .expect("The runner cannot function without the encoder.")
+            .unlisten();
+    });
 }
 
 /// Runs the [`Runner`] forever.
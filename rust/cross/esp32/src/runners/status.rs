@@ -0,0 +1,39 @@
+//! This module contains the latest run status for interfaces that poll instead of subscribing to topics.
+
+use esp_sync::NonReentrantMutex;
+use sc_messages::motion_profile::{Outcome, State};
+
+/// Provides global access to the latest run status.
+///
+/// The runner writes to this whenever it publishes, and SCPI queries read from it.
+pub static RUN_STATUS: NonReentrantMutex<RunStatus> = NonReentrantMutex::new(RunStatus::new());
+
+/// The latest state and outcome published by the runner.
+#[derive(Debug, Clone)]
+pub struct RunStatus {
+    /// The latest state of the current run, or [`None`] if nothing is running.
+    pub state: Option<State>,
+    /// How the previous run ended.
+    pub outcome: Option<Outcome>,
+    /// Whether the runner is running a motion profile or jogging,
+    /// from accepting the start until it has published how the run ended.
+    pub active: bool,
+}
+
+impl RunStatus {
+    /// Creates a status with nothing running.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            state: None,
+            outcome: None,
+            active: false,
+        }
+    }
+}
+
+impl Default for RunStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
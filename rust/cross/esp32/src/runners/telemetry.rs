@@ -0,0 +1,96 @@
+//! This module contains the decimation and batching of the states published by the runners.
+
+use esp_sync::NonReentrantMutex;
+use postcard_rpc::server::Sender;
+use sc_messages::{
+    icd::{MotionProfileStateBatchTopic, MotionProfileStateTopic},
+    motion_profile::State,
+    telemetry::{Config, StateBatch},
+};
+
+use crate::rpc::{SEQUENCE_NUMBER, WireTx};
+
+/// Provides global access to the telemetry config.
+///
+/// The telemetry endpoint writes to this and the runner reads it at the start of every run.
+pub static TELEMETRY_CONFIG: NonReentrantMutex<Config> = NonReentrantMutex::new(Config::DEFAULT);
+
+/// Publishes a run's states according to the [`Config`] it started with.
+#[derive(Debug)]
+pub struct Telemetry {
+    /// The config read at the start of the run.
+    config: Config,
+    /// The number of iterations to skip before the next state is published.
+    skip: u8,
+    /// The states waiting to be published together.
+    batch: StateBatch,
+    /// The index of the next published state.
+    next_sample: u32,
+}
+
+impl Telemetry {
+    /// Starts publishing a run with the current [`TELEMETRY_CONFIG`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            config: TELEMETRY_CONFIG.with(|config| *config),
+            skip: 0,
+            batch: StateBatch::new(),
+            next_sample: 0,
+        }
+    }
+
+    /// The index the next published state gets, which the runner stores in its [`State`].
+    ///
+    /// It only increases when a state is published, so the host can detect the ones it missed.
+    #[must_use]
+    pub fn next_sample(&self) -> u32 {
+        self.next_sample
+    }
+
+    /// Records a control loop iteration's state,
+    /// publishing it unless it is decimated away or its batch isn't full yet.
+    ///
+    /// A state that can't be published is dropped, and the host counts it as missed.
+    /// The link never fails to send, so this can't tell that the host is gone,
+    /// see [`HOST_DISCONNECTED`](crate::rpc::HOST_DISCONNECTED) for how the runner finds out.
+    pub async fn publish(&mut self, to_server: &Sender<WireTx>, state: &State) {
+        if let Some(skip) = self.skip.checked_sub(1) {
+            self.skip = skip;
+            return;
+        }
+        self.skip = self.config.decimation.saturating_sub(1);
+        self.next_sample = self.next_sample.wrapping_add(1);
+
+        if !self.config.is_batched() {
+            let _ = to_server
+                .publish::<MotionProfileStateTopic>(SEQUENCE_NUMBER, &Some(state.clone()))
+                .await;
+            return;
+        }
+        // The batch is never full here, because it is published as soon as it is.
+        let _ = self.batch.push(state.clone());
+        if self.batch.len() >= usize::from(self.config.batch_size) || self.batch.is_full() {
+            self.flush(to_server).await;
+        }
+    }
+
+    /// Publishes the states waiting in the batch, if there are any.
+    ///
+    /// This must be called at the end of the run, before its outcome is published.
+    pub async fn flush(&mut self, to_server: &Sender<WireTx>) {
+        if self.batch.is_empty() {
+            return;
+        }
+        let _ = to_server
+            .publish::<MotionProfileStateBatchTopic>(SEQUENCE_NUMBER, &self.batch)
+            .await;
+        self.batch.clear();
+    }
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,74 @@
+//! This module contains the control loop timing statistics reported to the host PC.
+
+use embassy_time::Duration;
+use esp_sync::NonReentrantMutex;
+use sc_messages::diagnostics::{Histogram, LoopTiming};
+
+use crate::{LOOP_PERIOD, runners::Iteration};
+
+/// Provides global access to the timing statistics of the current or most recent run.
+///
+/// The runner writes to this and the diagnostics endpoint reads from it.
+pub static LOOP_STATISTICS: NonReentrantMutex<LoopStatistics> =
+    NonReentrantMutex::new(LoopStatistics::new());
+
+/// Converts a duration to micros, truncating to [`u32::MAX`].
+#[must_use]
+pub fn as_micros(duration: Duration) -> u32 {
+    u32::try_from(duration.as_micros()).unwrap_or(u32::MAX)
+}
+
+/// The timing statistics of a run.
+#[derive(Debug, Default)]
+pub struct LoopStatistics {
+    /// The time between the starts of consecutive iterations.
+    period: Histogram,
+    /// The time spent executing each iteration.
+    execution: Histogram,
+    /// The number of iterations that overran [`LOOP_PERIOD`].
+    overruns: u32,
+}
+
+impl LoopStatistics {
+    /// Creates empty statistics.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            period: Histogram::new(),
+            execution: Histogram::new(),
+            overruns: 0,
+        }
+    }
+
+    /// Records an iteration.
+    pub fn record(&mut self, iteration: &Iteration) {
+        self.period.record(as_micros(iteration.period));
+        self.execution.record(as_micros(iteration.execution));
+        if iteration.overran() {
+            self.overruns = self.overruns.saturating_add(1);
+        }
+    }
+
+    /// The number of iterations that overran [`LOOP_PERIOD`].
+    #[must_use]
+    pub fn overruns(&self) -> u32 {
+        self.overruns
+    }
+
+    /// Clears all statistics.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Summarizes the statistics for the host PC.
+    #[must_use]
+    pub fn loop_timing(&self) -> LoopTiming {
+        LoopTiming {
+            target_period: as_micros(LOOP_PERIOD),
+            iterations: self.period.count(),
+            overruns: self.overruns,
+            period: self.period.statistics(),
+            execution: self.execution.statistics(),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! This module serves the SCPI-like text protocol in [`sc_messages::scpi`] on a second UART,
+//! so lab automation frameworks can use the spincoater without the host PC program.
+//!
+//! Requests are passed to the same runner as postcard-rpc's, so the two can be used at the same time.
+
+use esp_hal::{Async, uart::Uart};
+use heapless::{String, Vec};
+use sc_messages::{
+    motion_profile::{self, Outcome, RunAt, State},
+    scpi::{Error, Instrument, MAX_LINE_LENGTH, Session},
+};
+
+use crate::{gpio::vacuum_pump, rpc::Requester, runners::status::RUN_STATUS};
+
+/// The baud rate of the SCPI UART.
+///
+/// This is fixed rather than shared with postcard-rpc's so serial terminals always know what to expect.
+pub const BAUD_RATE: u32 = 115_200;
+
+/// The size of the buffer for SCPI responses, which are at most one short line.
+const RESPONSE_LENGTH: usize = 128;
+
+/// The size of the buffer for reading from the UART.
+const READ_BUFFER_LENGTH: usize = 64;
+
+/// The MCU, as seen by SCPI.
+struct Mcu {
+    /// Used to pass the commands to the runner.
+    requester: Requester,
+}
+
+impl Instrument for Mcu {
+    async fn motion_profile_request(
+        &mut self,
+        request: motion_profile::Request,
+    ) -> Result<(), Error> {
+        self.requester
+            .request(request)
+            .await
+            .map_err(Error::Refused)
+    }
+
+    async fn run_at(&mut self, run_at: RunAt) -> Result<(), Error> {
+        self.requester.run_at(run_at).await.map_err(Error::Refused)
+    }
+
+    async fn set_vacuum(&mut self, on: bool) -> Result<(), Error> {
+        vacuum_pump::set_enabled(on);
+        Ok(())
+    }
+
+    fn vacuum(&self) -> bool {
+        vacuum_pump::is_enabled()
+    }
+
+    fn state(&self) -> Option<State> {
+        RUN_STATUS.with(|status| status.state.clone())
+    }
+
+    fn outcome(&self) -> Option<Outcome> {
+        RUN_STATUS.with(|status| status.outcome)
+    }
+}
+
+/// Reads SCPI commands from the UART line by line and writes the responses back.
+#[embassy_executor::task]
+pub async fn serve(mut uart: Uart<'static, Async>, requester: Requester) -> ! {
+    let mut mcu = Mcu { requester };
+    let mut session = Session::new();
+    let mut line = Vec::<u8, MAX_LINE_LENGTH>::new();
+    // Whether the current line was longer than `MAX_LINE_LENGTH`.
+    let mut overflowed = false;
+    let mut response = String::<RESPONSE_LENGTH>::new();
+    let mut buffer = [0; READ_BUFFER_LENGTH];
+    loop {
+        let Ok(length) = uart.read_async(&mut buffer).await else {
+            // Framing or overflow errors only corrupt the current line.
+            overflowed = true;
+            continue;
+        };
+        for &byte in buffer.get(..length).unwrap_or_default() {
+            if byte != b'\n' && byte != b'\r' {
+                if line.push(byte).is_err() {
+                    overflowed = true;
+                }
+                continue;
+            }
+            match core::str::from_utf8(&line) {
+                Ok(text) if !overflowed => {
+                    response.clear();
+                    // Responses that don't fit are truncated, which can't happen with RESPONSE_LENGTH.
+                    let _ = session.handle_line(&mut mcu, text, &mut response).await;
+                    let mut unwritten = response.as_bytes();
+                    while let Some(written) = uart
+                        .write_async(unwritten)
+                        .await
+                        .ok()
+                        .filter(|written| *written > 0)
+                    {
+                        unwritten = unwritten.get(written..).unwrap_or_default();
+                    }
+                }
+                _ => session.push_error(Error::Syntax),
+            }
+            line.clear();
+            overflowed = false;
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! This module contains the live chart of the current run.
+
+use std::time::Duration;
+
+use ratatui::{
+    crossterm::event::KeyCode,
+    layout::{Constraint, Layout},
+    prelude::{Frame, Rect},
+    style::{Color, Style, Stylize},
+    symbols::Marker,
+    text::Line,
+    widgets::{Axis, Block, Chart, Dataset, GraphType, LegendPosition},
+};
+use sc_messages::{
+    motion_profile::Setpoint,
+    pwm::{MAX_POWER_DUTY, PERIOD, STOP_DUTY},
+};
+
+use crate::app::state::{MOTOR_TO_PLATE_CONVERSION, MotionProfileState};
+
+/// The number of points used to draw the visible part of the profile reference.
+const REFERENCE_POINTS: u32 = 240;
+
+/// The number of consecutive reference points drawn (and then skipped) in each dash.
+const DASH_LENGTH: u32 = 4;
+
+/// The shortest time window (in seconds) that can be zoomed into.
+const MIN_WINDOW: f64 = 0.5;
+
+/// The duty cycle (in percent) at [`STOP_DUTY`].
+pub const STOP_DUTY_PERCENT: f64 = STOP_DUTY as f64 / PERIOD as f64 * 100.0;
+
+/// The duty cycle (in percent) at [`MAX_POWER_DUTY`].
+pub const MAX_POWER_DUTY_PERCENT: f64 = MAX_POWER_DUTY as f64 / PERIOD as f64 * 100.0;
+
+/// The samples of the current or most recent run, and the profile it is following.
+///
+/// All points are (time in seconds, value).
+#[derive(Debug, Default)]
+pub struct RunChart {
+    /// The setpoint plate RPM of each sample.
+    setpoint: Vec<(f64, f64)>,
+    /// The measured plate RPM of each sample.
+    measured: Vec<(f64, f64)>,
+    /// The duty cycle (in percent) of each sample.
+    duty_cycle: Vec<(f64, f64)>,
+    /// The setpoints (in plate RPM) of the loaded profile, sorted by time.
+    profile: Vec<(f64, f64)>,
+    /// The width of the visible time window in seconds.
+    /// [`None`] shows the whole run.
+    window: Option<f64>,
+    /// How far (in seconds) the visible window is panned back from the newest sample.
+    offset: f64,
+    /// Whether the MCU discarded the profile after running it.
+    /// The next setpoints start a new profile.
+    profile_consumed: bool,
+}
+
+impl RunChart {
+    /// Discards the samples of the previous run.
+    pub fn start_run(&mut self) {
+        self.setpoint.clear();
+        self.measured.clear();
+        self.duty_cycle.clear();
+    }
+
+    /// Marks the end of the current run.
+    ///
+    /// The MCU discards motion profiles after running them, but keeps them after jogs.
+    pub fn finish_run(&mut self, jogged: bool) {
+        if !jogged {
+            self.profile_consumed = true;
+        }
+    }
+
+    /// Adds a sample to the current run.
+    pub fn push(&mut self, state: &MotionProfileState) {
+        let time = Duration::from_micros(state.time).as_secs_f64();
+        self.setpoint.push((time, state.setpoint_plate_rpm));
+        self.measured.push((time, state.current_plate_rpm));
+        self.duty_cycle
+            .push((time, f64::from(state.duty_cycle_f32) * 100.0));
+    }
+
+    /// Replaces the loaded profile with motor RPM setpoints.
+    ///
+    /// The MCU always starts at (0, 0), so that point is added.
+    pub fn set_profile<'a>(&mut self, setpoints: impl IntoIterator<Item = &'a Setpoint>) {
+        self.clear_profile();
+        self.add_to_profile(setpoints);
+    }
+
+    /// Adds motor RPM setpoints to the loaded profile.
+    pub fn add_to_profile<'a>(&mut self, setpoints: impl IntoIterator<Item = &'a Setpoint>) {
+        if self.profile_consumed {
+            self.clear_profile();
+        }
+        if self.profile.is_empty() {
+            self.profile.push((0.0, 0.0));
+        }
+        self.profile.extend(setpoints.into_iter().map(|setpoint| {
+            (
+                Duration::from_micros(setpoint.time).as_secs_f64(),
+                f64::from(setpoint.rpm) * MOTOR_TO_PLATE_CONVERSION,
+            )
+        }));
+        self.profile.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+
+    /// Forgets the loaded profile.
+    pub fn clear_profile(&mut self) {
+        self.profile.clear();
+        self.profile_consumed = false;
+    }
+
+    /// Handles zoom and pan keys.
+    ///
+    /// Returns whether the key was used.
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        let full_width = self.end().max(MIN_WINDOW);
+        match code {
+            KeyCode::Char('+' | '=') => {
+                let window = self.window.unwrap_or(full_width) / 2.0;
+                self.window = Some(window.max(MIN_WINDOW));
+            }
+            KeyCode::Char('-') => {
+                self.window = self
+                    .window
+                    .map(|window| window * 2.0)
+                    .filter(|window| *window < full_width);
+                if self.window.is_none() {
+                    self.offset = 0.0;
+                }
+            }
+            KeyCode::Left => {
+                if let Some(window) = self.window {
+                    self.offset = (self.offset + window / 4.0).min(full_width - window);
+                }
+            }
+            KeyCode::Right => {
+                if let Some(window) = self.window {
+                    self.offset = (self.offset - window / 4.0).max(0.0);
+                }
+            }
+            KeyCode::Char('0') => {
+                self.window = None;
+                self.offset = 0.0;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// The time (in seconds) of the newest sample or the end of the profile, whichever is later.
+    fn end(&self) -> f64 {
+        let last_sample = self.measured.last().map_or(0.0, |(time, _)| *time);
+        let last_setpoint = self.profile.last().map_or(0.0, |(time, _)| *time);
+        last_sample.max(last_setpoint)
+    }
+
+    /// The visible time range in seconds.
+    ///
+    /// When zoomed in, the window follows the newest sample unless it was panned back.
+    fn x_bounds(&self) -> [f64; 2] {
+        match self.window {
+            None => [0.0, self.end().max(MIN_WINDOW)],
+            Some(window) => {
+                let newest = self.measured.last().map_or(self.end(), |(time, _)| *time);
+                let end = (newest - self.offset).max(window);
+                [end - window, end]
+            }
+        }
+    }
+
+    /// Interpolates the profile's plate RPM at a time.
+    fn profile_rpm(&self, time: f64) -> Option<f64> {
+        let next_idx = self.profile.iter().position(|(t, _)| *t >= time)?;
+        let (next_time, next_rpm) = self.profile[next_idx];
+        let Some(&(previous_time, previous_rpm)) = next_idx
+            .checked_sub(1)
+            .and_then(|idx| self.profile.get(idx))
+        else {
+            return Some(next_rpm);
+        };
+        if next_time <= previous_time {
+            return Some(next_rpm);
+        }
+        let fraction = (time - previous_time) / (next_time - previous_time);
+        Some(previous_rpm + (next_rpm - previous_rpm) * fraction)
+    }
+
+    /// Samples the visible part of the profile, leaving gaps so it is drawn dashed.
+    fn reference_points(&self, [start, end]: [f64; 2]) -> Vec<(f64, f64)> {
+        (0..REFERENCE_POINTS)
+            .filter(|i| (i / DASH_LENGTH).is_multiple_of(2))
+            .filter_map(|i| {
+                let time = start + (end - start) * f64::from(i) / f64::from(REFERENCE_POINTS - 1);
+                self.profile_rpm(time).map(|rpm| (time, rpm))
+            })
+            .collect()
+    }
+
+    /// The highest plate RPM in the samples or the profile.
+    fn max_rpm(&self) -> f64 {
+        self.profile
+            .iter()
+            .chain(&self.setpoint)
+            .chain(&self.measured)
+            .map(|(_, rpm)| *rpm)
+            .fold(0.0, f64::max)
+    }
+
+    /// Renders the plate RPM chart above the duty cycle chart.
+    ///
+    /// The profile reference is only drawn if `show_profile` is true.
+    pub fn render(&self, block: Block<'_>, show_profile: bool, area: Rect, frame: &mut Frame) {
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let layout = Layout::vertical([Constraint::Ratio(2, 3), Constraint::Ratio(1, 3)]);
+        let [rpm_area, duty_cycle_area] = inner.layout(&layout);
+
+        let x_bounds = self.x_bounds();
+        let x_labels = || {
+            [
+                format!("{:.1}", x_bounds[0]),
+                format!("{:.1}", f64::midpoint(x_bounds[0], x_bounds[1])),
+                format!("{:.1}", x_bounds[1]),
+            ]
+        };
+
+        let reference = if show_profile {
+            self.reference_points(x_bounds)
+        } else {
+            Vec::new()
+        };
+        // Leave 10% of headroom above the highest RPM.
+        let max_rpm = (self.max_rpm() * 1.1).max(100.0);
+        let rpm_chart = Chart::new(vec![
+            Dataset::default()
+                .name("Profile")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Scatter)
+                .style(Style::new().fg(Color::DarkGray))
+                .data(&reference),
+            Dataset::default()
+                .name("Setpoint")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::new().fg(Color::Yellow))
+                .data(&self.setpoint),
+            Dataset::default()
+                .name("Measured")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::new().fg(Color::Cyan))
+                .data(&self.measured),
+        ])
+        .x_axis(Axis::default().bounds(x_bounds).labels(x_labels()))
+        .y_axis(
+            Axis::default()
+                .title("Plate RPM")
+                .bounds([0.0, max_rpm])
+                .labels([
+                    "0".to_string(),
+                    format!("{:.0}", max_rpm / 2.0),
+                    format!("{max_rpm:.0}"),
+                ]),
+        )
+        .legend_position(Some(LegendPosition::TopLeft));
+        frame.render_widget(rpm_chart, rpm_area);
+
+        let duty_cycle_chart = Chart::new(vec![
+            Dataset::default()
+                .name("Duty cycle")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::new().fg(Color::Magenta))
+                .data(&self.duty_cycle),
+        ])
+        .x_axis(
+            Axis::default()
+                .title(Line::from("Time (s)").italic())
+                .bounds(x_bounds)
+                .labels(x_labels()),
+        )
+        .y_axis(
+            Axis::default()
+                .title("Duty (%)")
+                .bounds([STOP_DUTY_PERCENT, MAX_POWER_DUTY_PERCENT])
+                .labels([
+                    format!("{STOP_DUTY_PERCENT:.1}"),
+                    format!("{MAX_POWER_DUTY_PERCENT:.1}"),
+                ]),
+        )
+        .legend_position(Some(LegendPosition::TopLeft));
+        frame.render_widget(duty_cycle_chart, duty_cycle_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_utils::state;
+
+    /// Checks the visible time range up to rounding.
+    fn assert_bounds(chart: &RunChart, expected: [f64; 2]) {
+        let actual = chart.x_bounds();
+        assert!(
+            actual
+                .iter()
+                .zip(expected)
+                .all(|(actual, expected)| (actual - expected).abs() < 1e-9),
+            "{actual:?} is not close to {expected:?}"
+        );
+    }
+
+    /// A setpoint `secs` seconds into the profile.
+    fn setpoint(rpm: u16, secs: u64) -> Setpoint {
+        Setpoint {
+            rpm,
+            time: secs * 1_000_000,
+        }
+    }
+
+    #[test]
+    fn empty_chart_shows_the_shortest_window() {
+        let chart = RunChart::default();
+        assert_bounds(&chart, [0.0, MIN_WINDOW]);
+        assert!(chart.max_rpm().abs() < f64::EPSILON);
+        assert!(chart.reference_points(chart.x_bounds()).is_empty());
+    }
+
+    #[test]
+    fn empty_profile_starts_at_zero() {
+        let mut chart = RunChart::default();
+        chart.set_profile(&[]);
+        assert_eq!(chart.profile, [(0.0, 0.0)]);
+        assert_bounds(&chart, [0.0, MIN_WINDOW]);
+        assert!(chart.max_rpm().abs() < f64::EPSILON);
+        // Only the start of the profile is known.
+        assert_eq!(chart.reference_points(chart.x_bounds()), [(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn bounds_cover_the_profile_and_the_samples() {
+        let mut chart = RunChart::default();
+        chart.set_profile(&[setpoint(2400, 10)]);
+        assert_bounds(&chart, [0.0, 10.0]);
+        assert!((chart.max_rpm() - 1000.0).abs() < 1e-9);
+
+        chart.push(&state(12_000_000, 2400, 3600));
+        assert_bounds(&chart, [0.0, 12.0]);
+        assert!((chart.max_rpm() - 1500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zoomed_window_follows_the_newest_sample() {
+        let mut chart = RunChart::default();
+        chart.push(&state(0, 0, 0));
+        chart.push(&state(12_000_000, 0, 0));
+        assert!(chart.handle_key(KeyCode::Char('+')));
+        assert_bounds(&chart, [6.0, 12.0]);
+        assert!(chart.handle_key(KeyCode::Left));
+        assert_bounds(&chart, [4.5, 10.5]);
+        assert!(chart.handle_key(KeyCode::Char('0')));
+        assert_bounds(&chart, [0.0, 12.0]);
+        assert!(!chart.handle_key(KeyCode::Char('x')));
+    }
+
+    #[test]
+    fn runs_consume_the_profile_but_jogs_dont() {
+        let mut chart = RunChart::default();
+        chart.set_profile(&[setpoint(2400, 10)]);
+        chart.finish_run(true);
+        chart.add_to_profile(&[setpoint(2400, 20)]);
+        assert_eq!(chart.profile.len(), 3);
+
+        chart.finish_run(false);
+        chart.add_to_profile(&[setpoint(4800, 5)]);
+        assert_eq!(chart.profile, [(0.0, 0.0), (5.0, 2000.0)]);
+    }
+}
@@ -0,0 +1,559 @@
+//! This module contains the comparison of two or more runs.
+//!
+//! Runs are aligned on profile time, which starts at 0 when each run starts,
+//! and every run's metrics are compared to the first run's.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use color_eyre::Result;
+use ratatui::{
+    layout::{Constraint, Layout},
+    prelude::{Frame, Rect},
+    style::{Color, Style, Stylize},
+    symbols::Marker,
+    text::Line,
+    widgets::{Axis, Block, Cell, Chart, Dataset, GraphType, LegendPosition, Row, Table},
+};
+
+use crate::app::{history::read_samples, report::RunReport, state::MotionProfileState};
+
+/// The colors of the compared runs, in order.
+const COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Yellow,
+    Color::Red,
+    Color::Blue,
+];
+
+/// The color of the compared run at `index`.
+#[must_use]
+pub fn color(index: usize) -> Color {
+    COLORS[index % COLORS.len()]
+}
+
+/// A run loaded from its motor data file.
+#[derive(Debug, Clone)]
+pub struct ComparedRun {
+    /// The motor data file's name without its extension.
+    pub name: String,
+    /// The samples of the run.
+    pub samples: Vec<MotionProfileState>,
+    /// The saved report, or one computed from the samples if it wasn't saved.
+    pub report: RunReport,
+}
+
+impl ComparedRun {
+    /// Loads a motor data file and its report.
+    ///
+    /// # Errors
+    /// Returns an error if the motor data file can't be read.
+    pub fn load(data_path: &Path) -> Result<Self> {
+        let samples = read_samples(data_path)?;
+        let report = RunReport::load(data_path, &samples);
+        let name = data_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self {
+            name,
+            samples,
+            report,
+        })
+    }
+
+    /// The (time in seconds, value) points of one value of each sample.
+    fn points(&self, value: impl Fn(&MotionProfileState) -> f64) -> Vec<(f64, f64)> {
+        self.samples
+            .iter()
+            .map(|state| {
+                (
+                    Duration::from_micros(state.time).as_secs_f64(),
+                    value(state),
+                )
+            })
+            .collect()
+    }
+
+    /// The measured plate RPM at a profile time, interpolated between samples.
+    ///
+    /// Returns [`None`] outside the run.
+    fn measured_at(&self, time: u64) -> Option<f64> {
+        let next_idx = self.samples.iter().position(|state| state.time >= time)?;
+        let next = &self.samples[next_idx];
+        let Some(previous) = next_idx.checked_sub(1).map(|idx| &self.samples[idx]) else {
+            return (next.time == time).then_some(next.current_plate_rpm);
+        };
+        if next.time <= previous.time {
+            return Some(next.current_plate_rpm);
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let fraction = (time - previous.time) as f64 / (next.time - previous.time) as f64;
+        Some(
+            previous.current_plate_rpm
+                + (next.current_plate_rpm - previous.current_plate_rpm) * fraction,
+        )
+    }
+}
+
+/// The metrics compared between runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Metric {
+    /// The time of the last sample.
+    Duration,
+    /// The percentage of samples within tolerance.
+    WithinTolerance,
+    /// The percentage of samples with a clamped duty cycle.
+    DutySaturation,
+    /// The root mean square of the RPM error of every sample.
+    RmsError,
+    /// The largest absolute RPM error.
+    PeakError,
+    /// The mean rise time of the steps.
+    RiseTime,
+    /// The mean overshoot of the steps.
+    Overshoot,
+    /// The mean settling time of the steps.
+    SettlingTime,
+    /// The number of control loop overruns.
+    Overruns,
+    /// The root mean square of the difference from the first run's measured RPM.
+    Difference,
+}
+
+impl Metric {
+    /// Every metric, in the order they are shown.
+    const ALL: [Self; 10] = [
+        Self::Duration,
+        Self::WithinTolerance,
+        Self::DutySaturation,
+        Self::RmsError,
+        Self::PeakError,
+        Self::RiseTime,
+        Self::Overshoot,
+        Self::SettlingTime,
+        Self::Overruns,
+        Self::Difference,
+    ];
+
+    /// The name of the metric with its unit.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Duration => "Duration (s)",
+            Self::WithinTolerance => "Within tolerance (%)",
+            Self::DutySaturation => "Duty saturation (%)",
+            Self::RmsError => "RMS error (RPM)",
+            Self::PeakError => "Peak error (RPM)",
+            Self::RiseTime => "Mean rise time (s)",
+            Self::Overshoot => "Mean overshoot (%)",
+            Self::SettlingTime => "Mean settling time (s)",
+            Self::Overruns => "Overruns",
+            Self::Difference => "RMS difference from first (RPM)",
+        }
+    }
+
+    /// The number of decimals the metric is shown with.
+    fn precision(self) -> usize {
+        match self {
+            Self::Duration | Self::RiseTime | Self::SettlingTime => 2,
+            Self::Overruns => 0,
+            _ => 1,
+        }
+    }
+}
+
+/// The mean of some values, or [`None`] if there are none.
+#[allow(clippy::cast_precision_loss)]
+fn mean(values: impl IntoIterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values
+        .into_iter()
+        .fold((0.0, 0_usize), |(sum, count), value| {
+            (sum + value, count + 1)
+        });
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// Two or more runs aligned on profile time.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// The runs, with the one the others are compared to first.
+    runs: Vec<ComparedRun>,
+}
+
+impl Comparison {
+    /// Loads the motor data files to compare.
+    /// The first one is the one the others are compared to.
+    ///
+    /// # Errors
+    /// Returns an error if a motor data file can't be read.
+    pub fn load(data_paths: &[PathBuf]) -> Result<Self> {
+        Ok(Self {
+            runs: data_paths
+                .iter()
+                .map(|path| ComparedRun::load(path))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// The runs, with the one the others are compared to first.
+    #[must_use]
+    pub fn runs(&self) -> &[ComparedRun] {
+        &self.runs
+    }
+
+    /// The value of a metric for a run, if the run has it.
+    #[allow(clippy::cast_precision_loss)]
+    fn value(&self, metric: Metric, run: &ComparedRun) -> Option<f64> {
+        let report = &run.report;
+        match metric {
+            Metric::Duration => Some(report.duration),
+            Metric::WithinTolerance => Some(report.within_tolerance_percent),
+            Metric::DutySaturation => Some(report.duty_saturation_percent),
+            Metric::RmsError => mean(
+                run.samples
+                    .iter()
+                    .map(|state| state.plate_rpm_error.powi(2)),
+            )
+            .map(f64::sqrt),
+            Metric::PeakError => run
+                .samples
+                .iter()
+                .map(|state| state.plate_rpm_error.abs())
+                .reduce(f64::max),
+            Metric::RiseTime => mean(report.steps.iter().filter_map(|step| step.rise_time)),
+            Metric::Overshoot => mean(report.steps.iter().map(|step| step.overshoot_percent)),
+            Metric::SettlingTime => mean(report.steps.iter().filter_map(|step| step.settling_time)),
+            Metric::Overruns => report
+                .final_state
+                .as_ref()
+                .map(|state| f64::from(state.overruns)),
+            Metric::Difference => {
+                let first = self.runs.first()?;
+                mean(run.samples.iter().filter_map(|state| {
+                    let first_rpm = first.measured_at(state.time)?;
+                    Some((state.current_plate_rpm - first_rpm).powi(2))
+                }))
+                .map(f64::sqrt)
+            }
+        }
+    }
+
+    /// The cells of a metric's row: the first run's value, then every other run's value
+    /// and its difference from the first run's.
+    fn cells(&self, metric: Metric) -> Vec<String> {
+        let precision = metric.precision();
+        let first = self.runs.first().and_then(|run| self.value(metric, run));
+        self.runs
+            .iter()
+            .enumerate()
+            .map(|(i, run)| match (self.value(metric, run), first) {
+                (None, _) => "-".to_string(),
+                (Some(value), Some(first)) if i > 0 => {
+                    format!("{value:.precision$} ({:+.precision$})", value - first)
+                }
+                (Some(value), _) => format!("{value:.precision$}"),
+            })
+            .collect()
+    }
+
+    /// Renders the overlaid runs above their metrics.
+    ///
+    /// Each run's setpoint is dotted and its measured RPM is solid, in the run's color.
+    pub fn render(&self, block: Block<'_>, area: Rect, frame: &mut Frame) {
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let layout = Layout::vertical([
+            Constraint::Fill(2),
+            Constraint::Fill(1),
+            Constraint::Length(u16::try_from(Metric::ALL.len() + 1).unwrap_or(u16::MAX)),
+        ]);
+        let [rpm_area, error_area, table_area] = inner.layout(&layout);
+
+        self.render_charts(rpm_area, error_area, frame);
+        self.render_metrics(table_area, frame);
+    }
+
+    /// Renders the setpoint and measured RPM above the error of every run.
+    fn render_charts(&self, rpm_area: Rect, error_area: Rect, frame: &mut Frame) {
+        let setpoints = self
+            .runs
+            .iter()
+            .map(|run| run.points(|state| state.setpoint_plate_rpm))
+            .collect::<Vec<_>>();
+        let measured = self
+            .runs
+            .iter()
+            .map(|run| run.points(|state| state.current_plate_rpm))
+            .collect::<Vec<_>>();
+        let errors = self
+            .runs
+            .iter()
+            .map(|run| run.points(|state| state.plate_rpm_error))
+            .collect::<Vec<_>>();
+
+        let end = self
+            .runs
+            .iter()
+            .map(|run| run.report.duration)
+            .fold(0.5, f64::max);
+        let x_labels = [
+            "0.0".to_string(),
+            format!("{:.1}", end / 2.0),
+            format!("{end:.1}"),
+        ];
+        let max_of = |series: &[Vec<(f64, f64)>]| {
+            series
+                .iter()
+                .flatten()
+                .map(|(_, value)| value.abs())
+                .fold(0.0, f64::max)
+        };
+        // Leave 10% of headroom above the highest RPM.
+        let max_rpm = (max_of(&setpoints).max(max_of(&measured)) * 1.1).max(100.0);
+        let max_error = (max_of(&errors) * 1.1).max(10.0);
+
+        let mut rpm_datasets = Vec::new();
+        let mut error_datasets = Vec::new();
+        for (i, run) in self.runs.iter().enumerate() {
+            let style = Style::new().fg(color(i));
+            rpm_datasets.push(
+                Dataset::default()
+                    .marker(Marker::Dot)
+                    .graph_type(GraphType::Scatter)
+                    .style(style)
+                    .data(&setpoints[i]),
+            );
+            rpm_datasets.push(
+                Dataset::default()
+                    .name(run.name.clone())
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(style)
+                    .data(&measured[i]),
+            );
+            error_datasets.push(
+                Dataset::default()
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(style)
+                    .data(&errors[i]),
+            );
+        }
+
+        let rpm_chart = Chart::new(rpm_datasets)
+            .x_axis(Axis::default().bounds([0.0, end]).labels(x_labels.clone()))
+            .y_axis(
+                Axis::default()
+                    .title("Plate RPM")
+                    .bounds([0.0, max_rpm])
+                    .labels([
+                        "0".to_string(),
+                        format!("{:.0}", max_rpm / 2.0),
+                        format!("{max_rpm:.0}"),
+                    ]),
+            )
+            .legend_position(Some(LegendPosition::TopLeft));
+        frame.render_widget(rpm_chart, rpm_area);
+
+        let error_chart = Chart::new(error_datasets)
+            .x_axis(
+                Axis::default()
+                    .title(Line::from("Time (s)").italic())
+                    .bounds([0.0, end])
+                    .labels(x_labels),
+            )
+            .y_axis(
+                Axis::default()
+                    .title("Error (RPM)")
+                    .bounds([-max_error, max_error])
+                    .labels([
+                        format!("{:.0}", -max_error),
+                        "0".to_string(),
+                        format!("{max_error:.0}"),
+                    ]),
+            );
+        frame.render_widget(error_chart, error_area);
+    }
+
+    /// Renders the metrics of every run and their differences from the first run's.
+    fn render_metrics(&self, area: Rect, frame: &mut Frame) {
+        let header = Row::new(
+            std::iter::once(Cell::from("Metric")).chain(
+                self.runs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, run)| Cell::from(run.name.clone()).fg(color(i))),
+            ),
+        )
+        .style(Style::new().bold());
+        let rows = Metric::ALL.iter().map(|metric| {
+            Row::new(std::iter::once(metric.name().to_string()).chain(self.cells(*metric)))
+        });
+        let widths = std::iter::once(Constraint::Length(32))
+            .chain(self.runs.iter().map(|_| Constraint::Fill(1)));
+        frame.render_widget(Table::new(rows, widths).header(header), area);
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut columns = vec![
+            std::iter::once("Metric".to_string())
+                .chain(Metric::ALL.iter().map(|metric| metric.name().to_string()))
+                .collect::<Vec<_>>(),
+        ];
+        let rows = Metric::ALL
+            .iter()
+            .map(|metric| self.cells(*metric))
+            .collect::<Vec<_>>();
+        for (i, run) in self.runs.iter().enumerate() {
+            columns.push(
+                std::iter::once(run.name.clone())
+                    .chain(rows.iter().map(|row| row[i].clone()))
+                    .collect(),
+            );
+        }
+        let widths = columns
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+        for row in 0..=Metric::ALL.len() {
+            let line = columns
+                .iter()
+                .zip(&widths)
+                .map(|(column, width)| format!("{:<width$}", column[row]))
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sc_messages::pwm::{DutyCycle, STOP_DUTY};
+
+    use super::*;
+
+    /// Checks that two floats are equal up to rounding.
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("The value should exist");
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{actual} is not close to {expected}"
+        );
+    }
+
+    /// A sample at `time` micros into the run with a plate RPM setpoint and measurement.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn sample(time: u64, setpoint: f64, current: f64) -> MotionProfileState {
+        MotionProfileState {
+            setpoint_rpm: setpoint as u16,
+            setpoint_plate_rpm: setpoint,
+            current_rpm: current as u16,
+            current_plate_rpm: current,
+            rpm_error: 0,
+            plate_rpm_error: setpoint - current,
+            duty_cycle: DutyCycle::new(STOP_DUTY + 100),
+            duty_cycle_f32: 0.0,
+            time,
+            loop_period: 0,
+            execution_time: 0,
+            overruns: 0,
+            sample: 0,
+            dropped: 0,
+        }
+    }
+
+    /// A run of (time, setpoint, measured) samples.
+    fn run(name: &str, samples: &[(u64, f64, f64)]) -> ComparedRun {
+        let samples = samples
+            .iter()
+            .map(|&(time, setpoint, current)| sample(time, setpoint, current))
+            .collect::<Vec<_>>();
+        ComparedRun {
+            name: name.to_string(),
+            report: RunReport::new(&samples, None),
+            samples,
+        }
+    }
+
+    #[test]
+    fn measured_rpm_is_interpolated_between_samples() {
+        let run = run(
+            "run",
+            &[
+                (0, 100.0, 0.0),
+                (10_000, 100.0, 100.0),
+                (20_000, 100.0, 50.0),
+            ],
+        );
+        assert_close(run.measured_at(0), 0.0);
+        assert_close(run.measured_at(2_500), 25.0);
+        assert_close(run.measured_at(10_000), 100.0);
+        assert_close(run.measured_at(15_000), 75.0);
+        assert_close(run.measured_at(20_000), 50.0);
+        // Nothing is made up past the end of the run.
+        assert_eq!(run.measured_at(20_001), None);
+    }
+
+    #[test]
+    fn runs_are_aligned_on_profile_time() {
+        let first = run("first", &[(0, 100.0, 0.0), (20_000, 100.0, 200.0)]);
+        // Sampled halfway between the first run's samples, and once past its end.
+        let second = run("second", &[(10_000, 100.0, 110.0), (30_000, 100.0, 100.0)]);
+        let comparison = Comparison {
+            runs: vec![first.clone(), second.clone()],
+        };
+        // Only the sample at 10 ms overlaps the first run, where it measured 100 RPM.
+        assert_close(comparison.value(Metric::Difference, &second), 10.0);
+        assert_close(comparison.value(Metric::Difference, &first), 0.0);
+    }
+
+    #[test]
+    fn differences_are_from_the_first_run() {
+        let comparison = Comparison {
+            runs: vec![
+                run("first", &[(0, 100.0, 90.0), (1_000_000, 100.0, 100.0)]),
+                run("second", &[(0, 100.0, 70.0), (1_500_000, 100.0, 100.0)]),
+                run("third", &[(0, 100.0, 100.0), (500_000, 100.0, 100.0)]),
+            ],
+        };
+        assert_eq!(
+            comparison.cells(Metric::Duration),
+            ["1.00", "1.50 (+0.50)", "0.50 (-0.50)"]
+        );
+        assert_eq!(
+            comparison.cells(Metric::PeakError),
+            ["10.0", "30.0 (+20.0)", "0.0 (-10.0)"]
+        );
+        // The first run is compared to itself.
+        assert_eq!(comparison.cells(Metric::Difference)[0], "0.0");
+    }
+
+    #[test]
+    fn missing_values_are_not_compared() {
+        let comparison = Comparison {
+            runs: vec![
+                run("empty", &[]),
+                run("full", &[(0, 100.0, 90.0), (10_000, 100.0, 100.0)]),
+            ],
+        };
+        assert_eq!(comparison.cells(Metric::PeakError), ["-", "10.0"]);
+        assert_eq!(comparison.cells(Metric::Overruns), ["-", "0"]);
+        // Nothing in the second run overlaps the empty one.
+        assert_eq!(comparison.cells(Metric::Difference), ["-", "-"]);
+    }
+}
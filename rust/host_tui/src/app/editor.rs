@@ -0,0 +1,824 @@
+//! This module contains the motion profile editor.
+//!
+//! Profiles are edited as a list of segments in plate RPM and seconds,
+//! and converted to the motor RPM and micros setpoints the MCU expects when saved or sent.
+
+use std::{env, path::Path, time::Duration};
+
+use color_eyre::{Result, eyre::eyre};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    layout::{Constraint, HorizontalAlignment, Layout},
+    prelude::{Frame, Rect},
+    style::{Color, Style, Stylize},
+    symbols::Marker,
+    text::{Line, Text},
+    widgets::{
+        Axis, Block, BorderType, Cell, Chart, Dataset, GraphType, Paragraph, Row, Table, TableState,
+    },
+};
+use sc_messages::{
+    MOTOR_REVOLUTIONS, PLATE_REVOLUTIONS,
+    motion_profile::{MAX_SETPOINTS, Setpoint},
+    plate_to_motor_rpm,
+};
+
+/// The highest plate RPM whose motor RPM fits in a [`u16`].
+#[allow(clippy::cast_possible_truncation)]
+const MAX_PLATE_RPM: u16 = (u16::MAX as u32 * PLATE_REVOLUTIONS / MOTOR_REVOLUTIONS) as u16;
+
+/// The shortest segment duration.
+///
+/// Steps need room for two setpoints, and the MCU only checks setpoints every loop period anyway.
+const MIN_DURATION: Duration = Duration::from_millis(1);
+
+/// How a segment gets to its plate RPM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentKind {
+    /// Linearly change from the previous RPM to this one over the whole duration.
+    Ramp,
+    /// Jump to this RPM immediately and hold it for the whole duration.
+    Step,
+}
+
+impl SegmentKind {
+    /// The name shown in the editor.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Ramp => "Ramp",
+            Self::Step => "Step",
+        }
+    }
+}
+
+/// One part of a motion profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    /// How the segment gets to its RPM.
+    pub kind: SegmentKind,
+    /// The plate RPM at the end of the segment.
+    pub rpm: u16,
+    /// How long the segment lasts.
+    pub duration: Duration,
+}
+
+impl Default for Segment {
+    fn default() -> Self {
+        Self {
+            kind: SegmentKind::Step,
+            rpm: 1000,
+            duration: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Converts segments into setpoints that follow the MCU's starting (0, 0) setpoint.
+///
+/// Durations are truncated to micros.
+#[must_use]
+pub fn to_setpoints(segments: &[Segment]) -> Vec<Setpoint> {
+    let mut setpoints = Vec::new();
+    let mut previous_rpm = 0;
+    let mut time: u64 = 0;
+    for segment in segments {
+        let rpm = plate_to_motor_rpm(segment.rpm);
+        let end = time.saturating_add(micros(segment.duration));
+        // A step to a new RPM needs a setpoint right after the previous one,
+        // otherwise the MCU would ramp to it.
+        if segment.kind == SegmentKind::Step && segment.rpm != previous_rpm {
+            setpoints.push(Setpoint {
+                rpm,
+                time: time.saturating_add(1),
+            });
+        }
+        setpoints.push(Setpoint { rpm, time: end });
+        previous_rpm = segment.rpm;
+        time = end;
+    }
+    setpoints
+}
+
+/// Converts setpoints (in any order) back into segments.
+///
+/// This undoes [`to_setpoints`], up to rounding from the plate to motor RPM conversion.
+#[must_use]
+pub fn from_setpoints(mut setpoints: Vec<Setpoint>) -> Vec<Segment> {
+    setpoints.sort();
+    let mut segments = Vec::new();
+    let mut previous = Setpoint { rpm: 0, time: 0 };
+    let mut setpoints = setpoints.into_iter().peekable();
+    while let Some(setpoint) = setpoints.next() {
+        // A setpoint 1 micro after the previous one, followed by one at the same RPM, is a step.
+        let is_step = setpoint.rpm != previous.rpm
+            && setpoint.time == previous.time.saturating_add(1)
+            && setpoints
+                .peek()
+                .is_some_and(|next| next.rpm == setpoint.rpm);
+        let end = if is_step {
+            setpoints.next().unwrap_or_else(|| setpoint.clone())
+        } else {
+            setpoint
+        };
+        segments.push(Segment {
+            // Holding the previous RPM is a step to the same RPM.
+            kind: if is_step || end.rpm == previous.rpm {
+                SegmentKind::Step
+            } else {
+                SegmentKind::Ramp
+            },
+            rpm: motor_to_plate_rpm(end.rpm),
+            duration: Duration::from_micros(end.time.saturating_sub(previous.time)),
+        });
+        previous = end;
+    }
+    segments
+}
+
+/// Converts motor RPM to plate RPM, rounding to the nearest RPM.
+fn motor_to_plate_rpm(rpm: u16) -> u16 {
+    let rpm = (u32::from(rpm) * PLATE_REVOLUTIONS + MOTOR_REVOLUTIONS / 2) / MOTOR_REVOLUTIONS;
+    u16::try_from(rpm).unwrap_or(u16::MAX)
+}
+
+/// Converts a duration to micros, truncating to [`u64::MAX`].
+fn micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+/// A problem found while validating a profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// The profile cannot be saved or sent.
+    Error(String),
+    /// The profile can be used, but probably doesn't do what was intended.
+    Warning(String),
+}
+
+/// Checks that a profile can be executed by the MCU.
+#[must_use]
+pub fn validate(segments: &[Segment]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if segments.is_empty() {
+        issues.push(Issue::Error("The profile is empty.".to_string()));
+    }
+    let mut previous_rpm = 0;
+    for (row, segment) in (1..).zip(segments) {
+        if segment.duration < MIN_DURATION {
+            issues.push(Issue::Error(format!(
+                "Row {row}: the duration must be at least {} s.",
+                MIN_DURATION.as_secs_f64()
+            )));
+        }
+        if segment.rpm > MAX_PLATE_RPM {
+            issues.push(Issue::Error(format!(
+                "Row {row}: the plate RPM must be at most {MAX_PLATE_RPM}."
+            )));
+        }
+        if segment.kind == SegmentKind::Ramp && segment.rpm < previous_rpm {
+            issues.push(Issue::Warning(format!(
+                "Row {row}: the MCU holds the previous RPM during ramps down. Consider a step."
+            )));
+        }
+        previous_rpm = segment.rpm;
+    }
+    let setpoints = to_setpoints(segments).len();
+    if setpoints > MAX_SETPOINTS {
+        issues.push(Issue::Error(format!(
+            "The profile needs {setpoints} setpoints, but the MCU only accepts {MAX_SETPOINTS}."
+        )));
+    }
+    issues
+}
+
+/// The editable columns of the segment table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    /// [`Segment::kind`]
+    Kind,
+    /// [`Segment::rpm`]
+    Rpm,
+    /// [`Segment::duration`]
+    Duration,
+}
+
+impl Column {
+    /// The index of the column in the table.
+    fn index(self) -> usize {
+        match self {
+            Self::Kind => 1,
+            Self::Rpm => 2,
+            Self::Duration => 3,
+        }
+    }
+}
+
+/// What the app should do after a key press in the editor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorAction {
+    /// Nothing else needs to happen.
+    None,
+    /// Replace the MCU's motion profile with these setpoints.
+    Send(Vec<Setpoint>),
+}
+
+/// The state of the motion profile editor.
+#[derive(Debug, Clone)]
+pub struct ProfileEditor {
+    /// The profile being edited.
+    segments: Vec<Segment>,
+    /// The selected row.
+    row: usize,
+    /// The selected column.
+    column: Column,
+    /// The text typed into the selected cell.
+    /// This is only [`Some`] while a cell is being edited.
+    input: Option<String>,
+    /// The result of the most recent file or MCU operation.
+    status: Option<String>,
+}
+
+impl Default for ProfileEditor {
+    fn default() -> Self {
+        Self {
+            segments: vec![Segment::default()],
+            row: 0,
+            column: Column::Rpm,
+            input: None,
+            status: None,
+        }
+    }
+}
+
+impl ProfileEditor {
+    /// Whether a cell is being edited.
+    ///
+    /// While editing, the editor needs every key press.
+    #[must_use]
+    pub fn is_editing(&self) -> bool {
+        self.input.is_some()
+    }
+
+    /// Handles a key press.
+    ///
+    /// # Errors
+    /// Returns an error if the current directory can't be read for a file dialog.
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> Result<EditorAction> {
+        if let Some(input) = &mut self.input {
+            match key_event.code {
+                KeyCode::Esc => self.input = None,
+                KeyCode::Enter => self.commit_input(),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(character) if character.is_ascii_digit() || character == '.' => {
+                    input.push(character);
+                }
+                _ => {}
+            }
+            return Ok(EditorAction::None);
+        }
+        let shift = key_event.modifiers.contains(KeyModifiers::SHIFT);
+        match key_event.code {
+            KeyCode::Up if shift => self.move_row_up(),
+            KeyCode::Down if shift => self.move_row_down(),
+            KeyCode::Up => self.row = self.row.saturating_sub(1),
+            KeyCode::Down => self.row = (self.row + 1).min(self.segments.len().saturating_sub(1)),
+            KeyCode::Left => {
+                self.column = match self.column {
+                    Column::Kind | Column::Rpm => Column::Kind,
+                    Column::Duration => Column::Rpm,
+                };
+            }
+            KeyCode::Right => {
+                self.column = match self.column {
+                    Column::Kind => Column::Rpm,
+                    Column::Rpm | Column::Duration => Column::Duration,
+                };
+            }
+            KeyCode::Enter => self.start_input(),
+            KeyCode::Char('a') => self.add_row(),
+            KeyCode::Char('d') | KeyCode::Delete => self.delete_row(),
+            KeyCode::Char('l') => self.load()?,
+            KeyCode::Char('s') => self.save()?,
+            KeyCode::Char('u') => {
+                if let Some(setpoints) = self.valid_setpoints() {
+                    self.status = Some("Sent the profile to the MCU.".to_string());
+                    return Ok(EditorAction::Send(setpoints));
+                }
+            }
+            _ => {}
+        }
+        Ok(EditorAction::None)
+    }
+
+    /// Starts editing the selected cell.
+    ///
+    /// The kind column has only two values, so it is toggled instead.
+    fn start_input(&mut self) {
+        let Some(segment) = self.segments.get_mut(self.row) else {
+            return;
+        };
+        match self.column {
+            Column::Kind => {
+                segment.kind = match segment.kind {
+                    SegmentKind::Ramp => SegmentKind::Step,
+                    SegmentKind::Step => SegmentKind::Ramp,
+                };
+            }
+            Column::Rpm => self.input = Some(segment.rpm.to_string()),
+            Column::Duration => self.input = Some(segment.duration.as_secs_f64().to_string()),
+        }
+    }
+
+    /// Writes the typed text to the selected cell if it parses.
+    fn commit_input(&mut self) {
+        let Some(input) = self.input.take() else {
+            return;
+        };
+        let Some(segment) = self.segments.get_mut(self.row) else {
+            return;
+        };
+        match self.column {
+            Column::Kind => {}
+            Column::Rpm => match input.parse() {
+                Ok(rpm) => segment.rpm = rpm,
+                Err(_) => self.status = Some(format!("\"{input}\" is not a plate RPM.")),
+            },
+            Column::Duration => match input.parse().map(Duration::try_from_secs_f64) {
+                Ok(Ok(duration)) => segment.duration = duration,
+                _ => self.status = Some(format!("\"{input}\" is not a number of seconds.")),
+            },
+        }
+    }
+
+    /// Adds a copy of the selected segment after it.
+    fn add_row(&mut self) {
+        let segment = self.segments.get(self.row).copied().unwrap_or_default();
+        let index = (self.row + 1).min(self.segments.len());
+        self.segments.insert(index, segment);
+        self.row = index;
+    }
+
+    /// Deletes the selected segment.
+    fn delete_row(&mut self) {
+        if self.row < self.segments.len() {
+            self.segments.remove(self.row);
+        }
+        self.row = self.row.min(self.segments.len().saturating_sub(1));
+    }
+
+    /// Swaps the selected segment with the one above it.
+    fn move_row_up(&mut self) {
+        if self.row > 0 && self.row < self.segments.len() {
+            self.segments.swap(self.row, self.row - 1);
+            self.row -= 1;
+        }
+    }
+
+    /// Swaps the selected segment with the one below it.
+    fn move_row_down(&mut self) {
+        if self.row + 1 < self.segments.len() {
+            self.segments.swap(self.row, self.row + 1);
+            self.row += 1;
+        }
+    }
+
+    /// Converts the profile into setpoints if it has no errors.
+    ///
+    /// Otherwise, the status explains why.
+    fn valid_setpoints(&mut self) -> Option<Vec<Setpoint>> {
+        if validate(&self.segments)
+            .iter()
+            .any(|issue| matches!(issue, Issue::Error(_)))
+        {
+            self.status = Some("Fix the errors below first.".to_string());
+            None
+        } else {
+            Some(to_setpoints(&self.segments))
+        }
+    }
+
+    /// Loads a motion profile CSV file chosen in a file dialog.
+    fn load(&mut self) -> Result<()> {
+        let path = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_directory(env::current_dir()?)
+            .set_title("Please choose a motion profile CSV file.")
+            .pick_file();
+        if let Some(path) = path {
+            self.status = Some(match read_profile(&path) {
+                Ok(segments) => {
+                    self.segments = segments;
+                    self.row = 0;
+                    format!("Loaded {}.", path.display())
+                }
+                Err(error) => format!("Failed to load {}: {error}", path.display()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Saves the profile to a motion profile CSV file chosen in a file dialog.
+    fn save(&mut self) -> Result<()> {
+        let Some(setpoints) = self.valid_setpoints() else {
+            return Ok(());
+        };
+        let path = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_directory(env::current_dir()?)
+            .set_title("Please choose where to save the motion profile.")
+            .save_file();
+        if let Some(path) = path {
+            self.status = Some(match write_profile(&path, &setpoints) {
+                Ok(()) => format!("Saved {}.", path.display()),
+                Err(error) => format!("Failed to save {}: {error}", path.display()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Renders the segment table and validation results next to a preview of the profile.
+    pub fn render(&self, area: Rect, frame: &mut Frame) {
+        let layout = Layout::horizontal([Constraint::Ratio(1, 2); 2]);
+        let [left_half, right_half] = area.layout(&layout);
+        let left_half_layout = Layout::vertical([Constraint::Fill(2), Constraint::Fill(1)]);
+        let [table_area, issues_area] = left_half.layout(&left_half_layout);
+
+        self.render_table(table_area, frame);
+        self.render_issues(issues_area, frame);
+        self.render_preview(right_half, frame);
+    }
+
+    fn render_table(&self, area: Rect, frame: &mut Frame) {
+        let instructions = Line::from_iter([
+            " Edit: ".into(),
+            "<Enter>".blue().bold(),
+            " Add: ".into(),
+            "<a>".blue().bold(),
+            " Delete: ".into(),
+            "<d>".blue().bold(),
+            " Move: ".into(),
+            "<Shift+Up/Down>".blue().bold(),
+            " Load: ".into(),
+            "<l>".blue().bold(),
+            " Save: ".into(),
+            "<s>".blue().bold(),
+            " Send: ".into(),
+            "<u> ".blue().bold(),
+        ]);
+        let block = Block::bordered()
+            .title(" Segments ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded)
+            .title_bottom(instructions);
+
+        let mut end = Duration::ZERO;
+        let rows = (1..)
+            .zip(&self.segments)
+            .enumerate()
+            .map(|(i, (row, segment))| {
+                end = end.saturating_add(segment.duration);
+                let mut cells = [
+                    row.to_string(),
+                    segment.kind.name().to_string(),
+                    segment.rpm.to_string(),
+                    segment.duration.as_secs_f64().to_string(),
+                    end.as_secs_f64().to_string(),
+                ];
+                // Show the typed text in place of the value being edited.
+                if i == self.row
+                    && let Some(input) = &self.input
+                    && let Some(cell) = cells.get_mut(self.column.index())
+                {
+                    cell.clone_from(input);
+                    cell.push('_');
+                }
+                Row::new(cells.map(Cell::from))
+            });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(4),
+                Constraint::Length(6),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(
+            Row::new(["#", "Kind", "Plate RPM", "Duration (s)", "End (s)"])
+                .style(Style::new().bold()),
+        )
+        .block(block)
+        .row_highlight_style(Style::new().blue())
+        .cell_highlight_style(Style::new().reversed());
+
+        let mut state = TableState::default().with_selected_cell((self.row, self.column.index()));
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn render_issues(&self, area: Rect, frame: &mut Frame) {
+        let block = Block::bordered()
+            .title(" Validation ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded);
+
+        let mut lines = Vec::new();
+        if let Some(status) = &self.status {
+            lines.push(Line::raw(status.clone()).italic());
+        }
+        let issues = validate(&self.segments);
+        if issues.is_empty() {
+            lines.push(Line::raw("The profile is valid.").fg(Color::Green));
+        }
+        lines.extend(issues.into_iter().map(|issue| match issue {
+            Issue::Error(message) => Line::raw(message).fg(Color::Red),
+            Issue::Warning(message) => Line::raw(message).fg(Color::Yellow),
+        }));
+        frame.render_widget(Paragraph::new(Text::from(lines)).block(block), area);
+    }
+
+    fn render_preview(&self, area: Rect, frame: &mut Frame) {
+        let block = Block::bordered()
+            .title(" Preview ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded);
+
+        // Plot the segments as (time in seconds, plate RPM), highlighting the selected one.
+        let mut points = vec![(0.0, 0.0)];
+        let mut selected = Vec::new();
+        let mut time = Duration::ZERO;
+        let mut previous_rpm = 0.0;
+        for (i, segment) in self.segments.iter().enumerate() {
+            let start = time.as_secs_f64();
+            time = time.saturating_add(segment.duration);
+            let end = time.as_secs_f64();
+            let rpm = f64::from(segment.rpm);
+            let segment_points = match segment.kind {
+                SegmentKind::Ramp => vec![(start, previous_rpm), (end, rpm)],
+                SegmentKind::Step => vec![(start, previous_rpm), (start, rpm), (end, rpm)],
+            };
+            if i == self.row {
+                selected.clone_from(&segment_points);
+            }
+            points.extend(segment_points);
+            previous_rpm = rpm;
+        }
+
+        let end = time.as_secs_f64().max(1.0);
+        // Leave 10% of headroom above the highest RPM.
+        let max_rpm = (points.iter().map(|(_, rpm)| *rpm).fold(0.0, f64::max) * 1.1).max(100.0);
+        let chart = Chart::new(vec![
+            Dataset::default()
+                .name("Profile")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::new().fg(Color::Cyan))
+                .data(&points),
+            Dataset::default()
+                .name("Selected")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::new().fg(Color::Yellow))
+                .data(&selected),
+        ])
+        .block(block)
+        .x_axis(
+            Axis::default()
+                .title("Time (s)")
+                .bounds([0.0, end])
+                .labels([
+                    "0".to_string(),
+                    format!("{:.1}", end / 2.0),
+                    format!("{end:.1}"),
+                ]),
+        )
+        .y_axis(
+            Axis::default()
+                .title("Plate RPM")
+                .bounds([0.0, max_rpm])
+                .labels([
+                    "0".to_string(),
+                    format!("{:.0}", max_rpm / 2.0),
+                    format!("{max_rpm:.0}"),
+                ]),
+        );
+        frame.render_widget(chart, area);
+    }
+}
+
+/// Reads a motion profile CSV file as segments.
+fn read_profile(path: &Path) -> Result<Vec<Segment>> {
+    let setpoints = csv::Reader::from_path(path)?
+        .into_deserialize()
+        .collect::<Result<Vec<Setpoint>, _>>()?;
+    if setpoints.is_empty() {
+        return Err(eyre!("The file has no setpoints."));
+    }
+    Ok(from_setpoints(setpoints))
+}
+
+/// Writes setpoints to a motion profile CSV file.
+fn write_profile(path: &Path, setpoints: &[Setpoint]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for setpoint in setpoints {
+        writer.serialize(setpoint)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A segment lasting `millis`.
+    fn segment(kind: SegmentKind, rpm: u16, millis: u64) -> Segment {
+        Segment {
+            kind,
+            rpm,
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    /// A setpoint at a plate RPM.
+    fn setpoint(plate_rpm: u16, time: u64) -> Setpoint {
+        Setpoint {
+            rpm: plate_to_motor_rpm(plate_rpm),
+            time,
+        }
+    }
+
+    #[test]
+    fn steps_jump_right_after_the_previous_setpoint() {
+        let segments = [
+            segment(SegmentKind::Step, 1000, 5_000),
+            segment(SegmentKind::Step, 3001, 2_500),
+            segment(SegmentKind::Step, 0, 1_000),
+        ];
+        let setpoints = to_setpoints(&segments);
+        assert_eq!(
+            setpoints,
+            [
+                setpoint(1000, 1),
+                setpoint(1000, 5_000_000),
+                setpoint(3001, 5_000_001),
+                setpoint(3001, 7_500_000),
+                setpoint(0, 7_500_001),
+                setpoint(0, 8_500_000),
+            ]
+        );
+        assert_eq!(from_setpoints(setpoints), segments);
+    }
+
+    #[test]
+    fn ramps_end_at_their_rpm() {
+        let segments = [
+            segment(SegmentKind::Ramp, 2000, 3_000),
+            segment(SegmentKind::Ramp, 4000, 1_500),
+        ];
+        let setpoints = to_setpoints(&segments);
+        assert_eq!(
+            setpoints,
+            [setpoint(2000, 3_000_000), setpoint(4000, 4_500_000)]
+        );
+        assert_eq!(from_setpoints(setpoints), segments);
+    }
+
+    #[test]
+    fn holds_are_steps_to_the_same_rpm() {
+        let segments = [
+            segment(SegmentKind::Step, 1000, 5_000),
+            segment(SegmentKind::Step, 1000, 2_000),
+        ];
+        let setpoints = to_setpoints(&segments);
+        // Holding doesn't need a setpoint to jump.
+        assert_eq!(
+            setpoints,
+            [
+                setpoint(1000, 1),
+                setpoint(1000, 5_000_000),
+                setpoint(1000, 7_000_000),
+            ]
+        );
+        assert_eq!(from_setpoints(setpoints), segments);
+        // A ramp to the same RPM is a hold too.
+        assert_eq!(
+            from_setpoints(to_setpoints(&[
+                segment(SegmentKind::Ramp, 0, 1_000),
+                segment(SegmentKind::Ramp, 2000, 1_000),
+                segment(SegmentKind::Ramp, 2000, 1_000),
+            ])),
+            [
+                segment(SegmentKind::Step, 0, 1_000),
+                segment(SegmentKind::Ramp, 2000, 1_000),
+                segment(SegmentKind::Step, 2000, 1_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn setpoints_are_read_in_any_order() {
+        let segments = [
+            segment(SegmentKind::Ramp, 500, 1_000),
+            segment(SegmentKind::Step, 2500, 4_000),
+            segment(SegmentKind::Ramp, 1000, 2_000),
+        ];
+        let mut setpoints = to_setpoints(&segments);
+        setpoints.reverse();
+        assert_eq!(from_setpoints(setpoints), segments);
+    }
+
+    #[test]
+    fn plate_rpm_survives_the_motor_rpm_conversion() {
+        for rpm in (0..=MAX_PLATE_RPM).step_by(7).chain([MAX_PLATE_RPM]) {
+            assert_eq!(motor_to_plate_rpm(plate_to_motor_rpm(rpm)), rpm);
+        }
+        // Motor RPM above the largest plate RPM still converts.
+        assert_eq!(motor_to_plate_rpm(u16::MAX), MAX_PLATE_RPM);
+    }
+
+    #[test]
+    fn valid_profiles_have_no_issues() {
+        assert_eq!(
+            validate(&[
+                segment(SegmentKind::Ramp, 3000, 2_000),
+                segment(SegmentKind::Step, 1000, 10_000),
+                segment(SegmentKind::Step, MAX_PLATE_RPM, 1),
+            ]),
+            []
+        );
+    }
+
+    #[test]
+    fn empty_profiles_and_short_segments_are_errors() {
+        assert!(matches!(validate(&[])[..], [Issue::Error(_)]));
+        assert!(matches!(
+            validate(&[segment(SegmentKind::Step, 1000, 0)])[..],
+            [Issue::Error(_)]
+        ));
+    }
+
+    #[test]
+    fn plate_rpm_must_fit_the_motor_rpm() {
+        let issues = validate(&[
+            segment(SegmentKind::Step, 1000, 1_000),
+            segment(SegmentKind::Step, MAX_PLATE_RPM + 1, 1_000),
+        ]);
+        assert_eq!(
+            issues,
+            [Issue::Error(format!(
+                "Row 2: the plate RPM must be at most {MAX_PLATE_RPM}."
+            ))]
+        );
+    }
+
+    #[test]
+    fn profiles_must_fit_in_the_mcu() {
+        let ramps = |count| vec![segment(SegmentKind::Ramp, 1000, 1_000); count];
+        assert_eq!(to_setpoints(&ramps(MAX_SETPOINTS)).len(), MAX_SETPOINTS);
+        assert_eq!(validate(&ramps(MAX_SETPOINTS)), []);
+        assert_eq!(
+            validate(&ramps(MAX_SETPOINTS + 1)),
+            [Issue::Error(format!(
+                "The profile needs {} setpoints, but the MCU only accepts {MAX_SETPOINTS}.",
+                MAX_SETPOINTS + 1
+            ))]
+        );
+        // Steps to new RPMs take two setpoints each.
+        let steps = (0..=MAX_SETPOINTS / 2)
+            .map(|i| {
+                segment(
+                    SegmentKind::Step,
+                    if i % 2 == 0 { 1000 } else { 2000 },
+                    1_000,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert!(matches!(validate(&steps)[..], [Issue::Error(_)]));
+    }
+
+    #[test]
+    fn ramps_down_are_warned_about() {
+        let issues = validate(&[
+            segment(SegmentKind::Step, 3000, 1_000),
+            segment(SegmentKind::Ramp, 1000, 1_000),
+            segment(SegmentKind::Ramp, 2000, 1_000),
+        ]);
+        assert_eq!(
+            issues,
+            [Issue::Warning(
+                "Row 2: the MCU holds the previous RPM during ramps down. Consider a step."
+                    .to_string()
+            )]
+        );
+        // Steps down are fine.
+        assert_eq!(
+            validate(&[
+                segment(SegmentKind::Step, 3000, 1_000),
+                segment(SegmentKind::Step, 1000, 1_000),
+            ]),
+            []
+        );
+    }
+}
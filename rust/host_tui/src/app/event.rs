@@ -1,5 +1,5 @@
 //! This module decribes events that cause updates to the TUI.
-use std::{convert::Into, fmt::Display, time::Duration};
+use std::{convert::Into, fmt::Display, path::Path, time::Duration};
 
 use chrono::{Local, NaiveTime};
 use color_eyre::{
@@ -7,31 +7,32 @@ use color_eyre::{
     eyre::{OptionExt, eyre},
 };
 use futures::StreamExt;
-use postcard_rpc::{
-    header::VarSeq,
-    host_client::{HostClient, Subscription},
-    standard_icd::{LoggingTopic, WireError},
-};
 use ratatui::crossterm::event::Event as CrosstermEvent;
 use sc_messages::{
-    icd::{
-        HostDisconnecting, MotionProfileStateTopic, MotionRequestEndpoint, TouchPointTopic,
-        VacuumPumpRequestEndpoint,
-    },
-    motion_profile::{self, RequestRefused},
+    diagnostics::{DeviceInfo, LoopTiming},
+    jog,
+    motion_profile::{self, Outcome, RequestRefused, Setpoint},
     touchscreen::TouchPoint,
     vacuum_pump,
 };
-use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use spincoater_client::{Client, Receive};
 use tokio::{
     sync::mpsc::{self, UnboundedSender},
-    time::timeout,
+    time::interval,
 };
 
-use crate::app::{MCU_LOG_CAPACITY, state::MotionProfileState};
+use crate::app::{
+    journal::{self, Journal},
+    metadata::ProfileSource,
+    replay::Replay,
+    state::MotionProfileState,
+};
 
-/// [`postcard_rpc`] requires us to choose a message sequence number and does not explain why.
-const INITIAL_VAR_SEQ: VarSeq = VarSeq::Seq1(0);
+/// The time between [`TuiEvent::Tick`]s.
+///
+/// This must be shorter than the jog timeout so that jogs can be kept alive.
+pub const TICK_PERIOD: Duration = Duration::from_secs(1);
 
 /// All possible TUI events.
 #[derive(Clone, Debug)]
@@ -42,6 +43,8 @@ pub enum TuiEvent {
     Crossterm(CrosstermEvent),
     /// Events from the MCU connection.
     MCU(MCUEvent),
+    /// Emitted every [`TICK_PERIOD`].
+    Tick,
 }
 
 impl From<MCUEvent> for TuiEvent {
@@ -51,16 +54,30 @@ impl From<MCUEvent> for TuiEvent {
 }
 
 /// All possible USB events.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MCUEvent {
     /// The MCU responded to a motion profile request.
     MotionProfileRequestResponse(Response),
+    /// The MCU responded to replacing its motion profile with these setpoints, loaded from these files.
+    ProfileUploadResponse(Vec<Setpoint>, Vec<ProfileSource>, Response),
     /// The MCU responded to a vacuum pump request.
     VacuumPumpRequestResponse,
+    /// The MCU responded to a jog request.
+    JogRequestResponse(jog::RequestResult),
+    /// The MCU responded to a request to replace the jog limits with these.
+    JogLimitsRequestResponse(jog::Limits, jog::RequestResult),
+    /// The MCU responded with its control loop timing statistics.
+    LoopTiming(LoopTiming),
+    /// The MCU responded with its firmware version and parameters.
+    ///
+    /// This is [`None`] if the MCU didn't respond, e.g. because its firmware is too old.
+    DeviceInfo(Option<DeviceInfo>),
     /// The MCU logged a message.
     Log(String),
     /// The MCU sent the motion profile state.
     State(Option<MotionProfileState>),
+    /// The MCU reported how a run ended.
+    Outcome(Outcome),
     /// The MCU sent a touch input.
     Touch(TouchPoint),
 }
@@ -77,6 +94,12 @@ impl From<Option<motion_profile::State>> for MCUEvent {
     }
 }
 
+impl From<Outcome> for MCUEvent {
+    fn from(value: Outcome) -> Self {
+        Self::Outcome(value)
+    }
+}
+
 impl From<TouchPoint> for MCUEvent {
     fn from(value: TouchPoint) -> Self {
         Self::Touch(value)
@@ -84,7 +107,7 @@ impl From<TouchPoint> for MCUEvent {
 }
 
 /// A motion profile response + the time it was received.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Response {
     response: core::result::Result<(), RequestRefused>,
     time: NaiveTime,
@@ -118,44 +141,97 @@ pub struct EventHandler {
     /// The tasks themselves hold the senders.
     from_tasks: mpsc::UnboundedReceiver<Result<TuiEvent>>,
     /// The client allows for sending requests to the MCU.
-    client: HostClient<WireError>,
+    ///
+    /// This is [`None`] while replaying a recorded run.
+    client: Option<Client>,
     /// A sender for cloning and using in future tasks.
     to_handler: mpsc::UnboundedSender<Result<TuiEvent>>,
+    /// The journal of every request, response, topic message and error.
+    ///
+    /// This is [`None`] while replaying a recorded run.
+    journal: Option<Journal>,
 }
 
 impl EventHandler {
     /// Constructs a new instance of [`EventHandler`] and spawns tasks to handle events.
     ///
     /// # Errors
-    /// Returns an error if subscribing to the necessary topics fails.
-    pub async fn new(client: HostClient<WireError>) -> Result<Self> {
+    /// Returns an error if creating the journal or subscribing to the necessary topics fails.
+    pub async fn new(client: Client) -> Result<Self> {
+        let journal = Journal::create()?;
         let (to_handler, from_tasks) = mpsc::unbounded_channel();
-        // Subscribe to the MCU's logs.
-        let log_stream = client
-            .subscribe_exclusive::<LoggingTopic>(MCU_LOG_CAPACITY)
-            .await?;
-        // Subscribe to the MCU's motion profile state.
-        let state_stream = client
-            .subscribe_exclusive::<MotionProfileStateTopic>(MCU_LOG_CAPACITY)
-            .await?;
-        // Subscribe to the MCU's touch points.
-        let touch_stream = client
-            .subscribe_exclusive::<TouchPointTopic>(MCU_LOG_CAPACITY)
-            .await?;
+        // Subscribe to the MCU's topics.
+        let log_stream = client.subscribe_logs().await?;
+        let state_stream = client.subscribe_states().await?;
+        let outcome_stream = client.subscribe_outcomes().await?;
+        let touch_stream = client.subscribe_touch_points().await?;
 
         // Spawn event handler tasks.
         tokio::spawn(await_crossterm_events(to_handler.clone()));
         tokio::spawn(await_messages(log_stream, to_handler.clone()));
         tokio::spawn(await_messages(state_stream, to_handler.clone()));
+        tokio::spawn(await_messages(outcome_stream, to_handler.clone()));
         tokio::spawn(await_messages(touch_stream, to_handler.clone()));
+        tokio::spawn(await_ticks(to_handler.clone()));
 
         Ok(Self {
             from_tasks,
-            client,
+            client: Some(client),
             to_handler,
+            journal: Some(journal),
         })
     }
 
+    /// Constructs an [`EventHandler`] that replays a recorded run instead of connecting to the MCU.
+    #[must_use]
+    pub fn replay(replay: Replay) -> Self {
+        let (to_handler, from_tasks) = mpsc::unbounded_channel();
+        tokio::spawn(await_crossterm_events(to_handler.clone()));
+        tokio::spawn(replay.run(to_handler.clone()));
+        tokio::spawn(await_ticks(to_handler.clone()));
+        Self {
+            from_tasks,
+            client: None,
+            to_handler,
+            journal: None,
+        }
+    }
+
+    /// Whether a recorded run is being replayed instead of connecting to the MCU.
+    #[must_use]
+    pub fn is_replaying(&self) -> bool {
+        self.client.is_none()
+    }
+
+    /// The path of the session journal, if there is one.
+    #[must_use]
+    pub fn journal_path(&self) -> Option<&Path> {
+        self.journal.as_ref().map(Journal::path)
+    }
+
+    /// Appends an event to the journal, if there is one.
+    ///
+    /// The app is closed if the journal can't be written.
+    fn record(&mut self, event: journal::Event) {
+        if let Some(journal) = &mut self.journal
+            && let Err(error) = journal.record(event)
+        {
+            let _ = self.to_handler.send(Err(error));
+        }
+    }
+
+    /// Journals a request and returns the client to send it with,
+    /// or logs that requests can't be sent while replaying.
+    fn client(&mut self, request: journal::Request) -> Option<Client> {
+        if self.client.is_none() {
+            let _ = self.to_handler.send(Ok(TuiEvent::MCU(MCUEvent::Log(
+                "Requests can't be sent to the MCU while replaying a run.".to_string(),
+            ))));
+        }
+        self.record(journal::Event::Sent(request));
+        self.client.clone()
+    }
+
     /// Receives an event from the sender.
     ///
     /// This function blocks until an event is received.
@@ -166,29 +242,83 @@ impl EventHandler {
     /// error occurs in the event thread. In practice, this should not happen unless there is a
     /// problem with the underlying terminal.
     pub async fn next(&mut self) -> Result<Result<TuiEvent>> {
-        self.from_tasks
+        let event = self
+            .from_tasks
             .recv()
             .await
-            .ok_or_eyre("Failed to receive event")
+            .ok_or_eyre("Failed to receive event")?;
+        match &event {
+            Ok(TuiEvent::MCU(mcu_event)) => {
+                self.record(journal::Event::Received(mcu_event.clone()));
+            }
+            Err(error) => self.record(journal::Event::Error(format!("{error:#}"))),
+            Ok(TuiEvent::Crossterm(_) | TuiEvent::Tick) => {}
+        }
+        Ok(event)
     }
 
     /// Spawns a task to send a motion profile request.
     ///
     /// The response will eventually arrive in [`EventHandler::next`].
     pub fn send_motion_profile_request(&mut self, request: motion_profile::Request) {
-        let client = self.client.clone();
+        let Some(client) = self.client(journal::Request::MotionProfile(request.clone())) else {
+            return;
+        };
         let to_handler = self.to_handler.clone();
 
         tokio::spawn(async move {
-            match client.send_resp::<MotionRequestEndpoint>(&request).await {
+            match client.motion_profile_request(&request).await {
                 Ok(response) => {
                     to_handler.send(Ok(TuiEvent::MCU(MCUEvent::MotionProfileRequestResponse(
                         Response::new(response, Local::now().time()),
                     ))))
                 }
-                Err(wire_err) => {
-                    to_handler.send(Err(eyre!("Failed to send command: {}", wire_err)))
+                Err(error) => to_handler.send(Err(error.into())),
+            }
+        });
+    }
+
+    /// Spawns a task to replace the MCU's motion profile.
+    ///
+    /// The setpoints are sent in order by a single task, so they can't overtake each other.
+    /// The response will eventually arrive in [`EventHandler::next`].
+    pub fn send_profile_upload(&mut self, setpoints: Vec<Setpoint>, sources: Vec<ProfileSource>) {
+        let Some(client) = self.client(journal::Request::UploadProfile(setpoints.clone())) else {
+            return;
+        };
+        let to_handler = self.to_handler.clone();
+
+        tokio::spawn(async move {
+            match client.upload_profile(setpoints.clone()).await {
+                Ok(response) => {
+                    to_handler.send(Ok(TuiEvent::MCU(MCUEvent::ProfileUploadResponse(
+                        setpoints,
+                        sources,
+                        Response::new(response, Local::now().time()),
+                    ))))
                 }
+                Err(error) => to_handler.send(Err(error.into())),
+            }
+        });
+    }
+
+    /// Spawns a task to start a constant plate RPM run.
+    ///
+    /// The response will eventually arrive in [`EventHandler::next`].
+    pub fn send_run_at_request(&mut self, run_at: motion_profile::RunAt) {
+        let Some(client) = self.client(journal::Request::RunAt(run_at)) else {
+            return;
+        };
+        let to_handler = self.to_handler.clone();
+
+        tokio::spawn(async move {
+            match client.run_at(&run_at).await {
+                Ok(response) => {
+                    to_handler.send(Ok(TuiEvent::MCU(MCUEvent::MotionProfileRequestResponse(
+                        Response::new(response, Local::now().time()),
+                    ))))
+                }
+                Err(error) => to_handler.send(Err(error.into())),
             }
         });
     }
@@ -197,32 +327,102 @@ impl EventHandler {
     ///
     /// Although this method usually finishes immediately, it times out after 1 second.
     pub async fn send_disconnect_notification(&mut self) {
-        let _ = timeout(
-            Duration::from_secs(1),
-            self.client
-                .publish::<HostDisconnecting>(INITIAL_VAR_SEQ, &()),
-        )
-        .await;
+        self.record(journal::Event::Sent(journal::Request::Disconnect));
+        if let Some(client) = &self.client {
+            client.notify_disconnecting().await;
+        }
     }
+
     /// Spawns a task to send a vacuum pump request.
     ///
     /// The response will eventually arrive in [`EventHandler::next`].
     pub fn send_vacuum_pump_request(&mut self, request: vacuum_pump::Request) {
-        let client = self.client.clone();
+        let Some(client) = self.client(journal::Request::VacuumPump(request.clone())) else {
+            return;
+        };
         let to_handler = self.to_handler.clone();
 
         tokio::spawn(async move {
-            match client
-                .send_resp::<VacuumPumpRequestEndpoint>(&request)
-                .await
-            {
+            match client.vacuum_pump(&request).await {
                 Ok(()) => to_handler.send(Ok(TuiEvent::MCU(MCUEvent::VacuumPumpRequestResponse))),
-                Err(wire_err) => {
-                    to_handler.send(Err(eyre!("Failed to send command: {}", wire_err)))
+                Err(error) => to_handler.send(Err(error.into())),
+            }
+        });
+    }
+
+    /// Spawns a task to send a jog request.
+    ///
+    /// The response will eventually arrive in [`EventHandler::next`].
+    pub fn send_jog_request(&mut self, request: jog::Request) {
+        let Some(client) = self.client(journal::Request::Jog(request.clone())) else {
+            return;
+        };
+        let to_handler = self.to_handler.clone();
+
+        tokio::spawn(async move {
+            match client.jog(&request).await {
+                Ok(response) => {
+                    to_handler.send(Ok(TuiEvent::MCU(MCUEvent::JogRequestResponse(response))))
                 }
+                Err(error) => to_handler.send(Err(error.into())),
+            }
+        });
+    }
+
+    /// Spawns a task to replace the jog limits.
+    ///
+    /// The response will eventually arrive in [`EventHandler::next`].
+    pub fn send_jog_limits_request(&mut self, limits: jog::Limits) {
+        let Some(client) = self.client(journal::Request::Jog(jog::Request::SetLimits(limits)))
+        else {
+            return;
+        };
+        let to_handler = self.to_handler.clone();
+
+        tokio::spawn(async move {
+            match client.jog(&jog::Request::SetLimits(limits)).await {
+                Ok(response) => to_handler.send(Ok(TuiEvent::MCU(
+                    MCUEvent::JogLimitsRequestResponse(limits, response),
+                ))),
+                Err(error) => to_handler.send(Err(error.into())),
             }
         });
     }
+
+    /// Spawns a task to request the control loop timing statistics.
+    ///
+    /// The response will eventually arrive in [`EventHandler::next`].
+    pub fn send_loop_timing_request(&mut self) {
+        let Some(client) = self.client(journal::Request::LoopTiming) else {
+            return;
+        };
+        let to_handler = self.to_handler.clone();
+
+        tokio::spawn(async move {
+            match client.loop_timing().await {
+                Ok(loop_timing) => {
+                    to_handler.send(Ok(TuiEvent::MCU(MCUEvent::LoopTiming(loop_timing))))
+                }
+                Err(error) => to_handler.send(Err(error.into())),
+            }
+        });
+    }
+
+    /// Spawns a task to request the MCU's firmware version and parameters.
+    ///
+    /// The response will eventually arrive in [`EventHandler::next`].
+    pub fn send_device_info_request(&mut self) {
+        let Some(client) = self.client(journal::Request::DeviceInfo) else {
+            return;
+        };
+        let to_handler = self.to_handler.clone();
+
+        tokio::spawn(async move {
+            // Old firmware doesn't have this endpoint, which isn't worth closing the app over.
+            let device_info = client.device_info().await.ok();
+            to_handler.send(Ok(TuiEvent::MCU(MCUEvent::DeviceInfo(device_info))))
+        });
+    }
 }
 
 /// Sends crossterm events to the terminal whenever they occur.
@@ -251,12 +451,23 @@ async fn await_crossterm_events(to_handler: UnboundedSender<Result<TuiEvent>>) {
     }
 }
 
+/// Sends a [`TuiEvent::Tick`] every [`TICK_PERIOD`].
+async fn await_ticks(to_handler: UnboundedSender<Result<TuiEvent>>) {
+    let mut interval = interval(TICK_PERIOD);
+    loop {
+        interval.tick().await;
+        // If the channel is closed, this task is done.
+        if to_handler.send(Ok(TuiEvent::Tick)).is_err() {
+            return;
+        }
+    }
+}
+
 /// Awaits messages from a subscription in a loop, and forwards them to the handler.
-async fn await_messages<S>(
-    mut subscription: Subscription<S>,
-    to_handler: UnboundedSender<Result<TuiEvent>>,
-) where
-    S: DeserializeOwned + Into<MCUEvent>,
+async fn await_messages<S>(mut subscription: S, to_handler: UnboundedSender<Result<TuiEvent>>)
+where
+    S: Receive,
+    S::Message: Into<MCUEvent>,
 {
     // As soon as the stream closes, the terminal must close as well.
     while let Some(state) = subscription.recv().await {
@@ -0,0 +1,657 @@
+//! This module contains the index of past runs and the tab for browsing it.
+//!
+//! The index is a JSON file in [`LOG_DIR`] that is updated whenever a run's metadata is saved.
+//! If it is missing or unreadable, it is rebuilt from the metadata files next to the motor data files.
+
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, HorizontalAlignment, Layout},
+    prelude::{Frame, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, BorderType, Cell, Paragraph, Row, Table, TableState, Wrap},
+};
+use sc_messages::motion_profile::Setpoint;
+use serde::{Deserialize, Serialize};
+
+use crate::app::{
+    LOG_DIR, MOTOR_DATA_SUB_DIR,
+    chart::RunChart,
+    compare::{self, Comparison},
+    html,
+    metadata::{ProfileSource, RunMetadata},
+    plot::{self, PlotFormat},
+    read_json,
+    report::{RunReport, report_path},
+    state::MotionProfileState,
+};
+
+/// The name of the index file in [`LOG_DIR`].
+pub const INDEX_FILE: &str = "run_index.json";
+
+/// The suffix of metadata files, which the index is rebuilt from.
+const METADATA_SUFFIX: &str = "_metadata.json";
+
+/// Reads the samples of a motor data file.
+///
+/// # Errors
+/// Returns an error if the file can't be read or isn't a motor data file.
+pub fn read_samples(data_path: &Path) -> Result<Vec<MotionProfileState>> {
+    Ok(csv::Reader::from_path(data_path)?
+        .into_deserialize()
+        .collect::<Result<Vec<MotionProfileState>, _>>()?)
+}
+
+/// A run in the index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedRun {
+    /// The motor data file.
+    pub data_path: PathBuf,
+    /// See [`RunMetadata`].
+    pub metadata: RunMetadata,
+    /// The percentage of samples within tolerance, once the run has a report.
+    pub within_tolerance_percent: Option<f64>,
+}
+
+impl IndexedRun {
+    /// When the run started, as shown and searched.
+    fn started(&self) -> String {
+        self.metadata.started.format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    /// How the run ended, as shown and searched.
+    fn outcome(&self) -> String {
+        self.metadata
+            .outcome
+            .map_or_else(|| "-".to_string(), |outcome| format!("{outcome:?}"))
+    }
+
+    /// Whether every whitespace-separated word of `query` appears in the run's date, profile,
+    /// operator, sample ID, outcome or file name, ignoring case.
+    #[must_use]
+    pub fn matches(&self, query: &str) -> bool {
+        let text = [
+            self.started(),
+            self.metadata.profile_name(),
+            self.metadata.operator.clone(),
+            self.metadata.sample_id.clone(),
+            self.outcome(),
+            self.metadata.data_file.clone(),
+        ]
+        .join(" ")
+        .to_lowercase();
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| text.contains(word))
+    }
+}
+
+/// The index of past runs, newest first.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RunIndex {
+    /// The runs, newest first.
+    runs: Vec<IndexedRun>,
+}
+
+impl RunIndex {
+    /// Loads the index, or rebuilds it from the metadata files in `data_dir` if it can't be read.
+    ///
+    /// # Errors
+    /// Returns an error if `data_dir` can't be read.
+    pub fn load(path: &Path, data_dir: &Path) -> Result<Self> {
+        match read_json(path) {
+            Ok(Some(index)) => Ok(index),
+            Ok(None) | Err(_) => Self::rebuild(data_dir),
+        }
+    }
+
+    /// Rebuilds the index from the metadata files in `data_dir`.
+    ///
+    /// Metadata files that can't be read are skipped.
+    ///
+    /// # Errors
+    /// Returns an error if `data_dir` exists but can't be read.
+    pub fn rebuild(data_dir: &Path) -> Result<Self> {
+        let entries = match fs::read_dir(data_dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut index = Self::default();
+        for entry in entries {
+            let path = entry?.path();
+            let is_metadata = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with(METADATA_SUFFIX));
+            if !is_metadata {
+                continue;
+            }
+            let Ok(Some(metadata)) = read_json::<RunMetadata>(&path) else {
+                continue;
+            };
+            let data_path = data_dir.join(&metadata.data_file);
+            let report = read_json::<RunReport>(&report_path(&data_path))
+                .ok()
+                .flatten();
+            index.insert(IndexedRun {
+                data_path,
+                metadata,
+                within_tolerance_percent: report.map(|report| report.within_tolerance_percent),
+            });
+        }
+        Ok(index)
+    }
+
+    /// Writes the index as JSON.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+
+    /// Adds a run, or replaces it if its data file is already indexed.
+    pub fn insert(&mut self, run: IndexedRun) {
+        self.runs
+            .retain(|indexed| indexed.data_path != run.data_path);
+        let position = self
+            .runs
+            .partition_point(|indexed| indexed.metadata.started > run.metadata.started);
+        self.runs.insert(position, run);
+    }
+
+    /// The runs, newest first.
+    #[must_use]
+    pub fn runs(&self) -> &[IndexedRun] {
+        &self.runs
+    }
+}
+
+/// What the app should do after a key press in the history tab.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryAction {
+    /// Nothing else needs to happen.
+    None,
+    /// Replace the MCU's motion profile with these setpoints, which were loaded from these files.
+    Upload(Vec<Setpoint>, Vec<ProfileSource>),
+}
+
+/// A run opened from the history tab.
+#[derive(Debug)]
+struct OpenedRun {
+    /// The motor data file.
+    data_path: PathBuf,
+    /// The run's samples and profile.
+    chart: RunChart,
+    /// Whether the run followed a profile, rather than jogging.
+    show_profile: bool,
+    /// The saved report, or one computed from the samples if it wasn't saved.
+    report: RunReport,
+}
+
+/// What is shown next to the list of runs.
+#[derive(Debug)]
+enum Shown {
+    /// Nothing has been opened yet.
+    Nothing,
+    /// A single run.
+    Run(Box<OpenedRun>),
+    /// The marked runs overlaid.
+    Comparison(Comparison),
+}
+
+/// The state of the history tab.
+#[derive(Debug)]
+pub struct RunHistory {
+    /// The index of past runs.
+    index: RunIndex,
+    /// Where the index is saved.
+    index_path: PathBuf,
+    /// The folder of the motor data files.
+    data_dir: PathBuf,
+    /// The search query.
+    query: String,
+    /// Whether the search query is being typed.
+    searching: bool,
+    /// The indices of the runs matching the query.
+    matches: Vec<usize>,
+    /// The selected row of the matches.
+    row: usize,
+    /// The motor data files marked for comparison, in the order they were marked.
+    marked: Vec<PathBuf>,
+    /// What is shown next to the list of runs.
+    shown: Shown,
+    /// The result of the most recent operation.
+    status: Option<String>,
+}
+
+impl RunHistory {
+    /// Loads the index of the runs in the current directory's log folder.
+    ///
+    /// # Errors
+    /// Returns an error if the current directory or the log folder can't be read.
+    pub fn new() -> Result<Self> {
+        let log_dir = env::current_dir()?.join(LOG_DIR);
+        let index_path = log_dir.join(INDEX_FILE);
+        let data_dir = log_dir.join(MOTOR_DATA_SUB_DIR);
+        let mut history = Self {
+            index: RunIndex::load(&index_path, &data_dir)?,
+            index_path,
+            data_dir,
+            query: String::new(),
+            searching: false,
+            matches: Vec::new(),
+            row: 0,
+            marked: Vec::new(),
+            shown: Shown::Nothing,
+            status: None,
+        };
+        history.filter();
+        Ok(history)
+    }
+
+    /// Adds or updates a run in the index and saves it.
+    ///
+    /// # Errors
+    /// Returns an error if the index can't be saved.
+    pub fn record(
+        &mut self,
+        data_path: &Path,
+        metadata: &RunMetadata,
+        report: Option<&RunReport>,
+    ) -> Result<()> {
+        let within_tolerance_percent = report.map(|report| report.within_tolerance_percent);
+        self.index.insert(IndexedRun {
+            data_path: data_path.to_path_buf(),
+            metadata: metadata.clone(),
+            within_tolerance_percent,
+        });
+        self.filter();
+        self.index.save(&self.index_path)
+    }
+
+    /// Whether the search query is being typed.
+    ///
+    /// While searching, the history tab needs every key press.
+    #[must_use]
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// Finds the runs matching the query.
+    fn filter(&mut self) {
+        self.matches = (0..self.index.runs().len())
+            .filter(|i| self.index.runs()[*i].matches(&self.query))
+            .collect();
+        self.row = self.row.min(self.matches.len().saturating_sub(1));
+    }
+
+    /// The selected run.
+    fn selected(&self) -> Option<&IndexedRun> {
+        self.matches
+            .get(self.row)
+            .and_then(|i| self.index.runs().get(*i))
+    }
+
+    /// Handles a key press.
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> HistoryAction {
+        if self.searching {
+            match key_event.code {
+                KeyCode::Esc | KeyCode::Enter => self.searching = false,
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.filter();
+                }
+                KeyCode::Char(character) if !character.is_control() => {
+                    self.query.push(character);
+                    self.filter();
+                }
+                _ => {}
+            }
+            return HistoryAction::None;
+        }
+        match key_event.code {
+            KeyCode::Up => self.row = self.row.saturating_sub(1),
+            KeyCode::Down => self.row = (self.row + 1).min(self.matches.len().saturating_sub(1)),
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Enter => self.open(),
+            KeyCode::Char(' ') => self.toggle_mark(),
+            KeyCode::Char('c') => self.compare(),
+            KeyCode::Char('u') => return self.upload(),
+            KeyCode::Char('r') => self.rebuild(),
+            KeyCode::Char('p') => self.export_plots(),
+            KeyCode::Char('h') => self.export_report(),
+            code => {
+                if let Shown::Run(opened) = &mut self.shown {
+                    opened.chart.handle_key(code);
+                }
+            }
+        }
+        HistoryAction::None
+    }
+
+    /// Loads the plot and report of the selected run.
+    fn open(&mut self) {
+        let Some(run) = self.selected() else {
+            return;
+        };
+        let data_path = run.data_path.clone();
+        let samples = match read_samples(&data_path) {
+            Ok(samples) => samples,
+            Err(error) => {
+                self.status = Some(format!("Failed to open {}: {error}", data_path.display()));
+                return;
+            }
+        };
+        let mut chart = RunChart::default();
+        chart.set_profile(&run.metadata.profile);
+        chart.start_run();
+        for state in &samples {
+            chart.push(state);
+        }
+        let report = read_json(&report_path(&data_path))
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| RunReport::new(&samples, run.metadata.outcome));
+        self.shown = Shown::Run(Box::new(OpenedRun {
+            show_profile: !run.metadata.jog,
+            data_path,
+            chart,
+            report,
+        }));
+        self.status = None;
+    }
+
+    /// Marks the selected run for comparison, or unmarks it.
+    fn toggle_mark(&mut self) {
+        let Some(run) = self.selected() else {
+            return;
+        };
+        let data_path = run.data_path.clone();
+        if let Some(position) = self.marked.iter().position(|path| *path == data_path) {
+            self.marked.remove(position);
+        } else {
+            self.marked.push(data_path);
+        }
+    }
+
+    /// Overlays the marked runs.
+    fn compare(&mut self) {
+        if self.marked.len() < 2 {
+            self.status = Some("Mark at least two runs to compare them.".to_string());
+            return;
+        }
+        match Comparison::load(&self.marked) {
+            Ok(comparison) => {
+                self.shown = Shown::Comparison(comparison);
+                self.status = None;
+            }
+            Err(error) => self.status = Some(format!("Failed to compare the runs: {error}")),
+        }
+    }
+
+    /// Asks the app to upload the selected run's motion profile.
+    fn upload(&mut self) -> HistoryAction {
+        let Some(run) = self.selected() else {
+            return HistoryAction::None;
+        };
+        if run.metadata.profile.is_empty() {
+            self.status = Some("This run has no motion profile to upload.".to_string());
+            return HistoryAction::None;
+        }
+        let action = HistoryAction::Upload(
+            run.metadata.profile.clone(),
+            run.metadata.profile_sources.clone(),
+        );
+        self.status = Some(format!("Uploaded the profile of {}.", run.started()));
+        action
+    }
+
+    /// Saves the plots of the selected run next to its motor data file.
+    fn export_plots(&mut self) {
+        let Some(run) = self.selected() else {
+            return;
+        };
+        self.status = Some(match plot::export(&run.data_path, &PlotFormat::ALL) {
+            Ok(paths) => format!(
+                "Saved the plots to {}.",
+                paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            ),
+            Err(error) => format!("Failed to export the plots: {error}"),
+        });
+    }
+
+    /// Saves the HTML report of the selected run next to its motor data file.
+    fn export_report(&mut self) {
+        let Some(run) = self.selected() else {
+            return;
+        };
+        self.status = Some(match html::export(&run.data_path) {
+            Ok(path) => format!("Saved the HTML report to {}.", path.display()),
+            Err(error) => format!("Failed to save the HTML report: {error}"),
+        });
+    }
+
+    /// Rebuilds the index from the metadata files.
+    fn rebuild(&mut self) {
+        self.status = Some(
+            match RunIndex::rebuild(&self.data_dir)
+                .and_then(|index| index.save(&self.index_path).map(|()| index))
+            {
+                Ok(index) => {
+                    self.index = index;
+                    self.filter();
+                    format!("Indexed {} runs.", self.index.runs().len())
+                }
+                Err(error) => format!("Failed to rebuild the index: {error}"),
+            },
+        );
+    }
+
+    /// Renders the list of runs next to the opened run.
+    pub fn render(&self, area: Rect, frame: &mut Frame) {
+        let layout = Layout::horizontal([Constraint::Ratio(1, 2); 2]);
+        let [left_half, right_half] = area.layout(&layout);
+        let left_half_layout = Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]);
+        let [search_area, table_area] = left_half.layout(&left_half_layout);
+
+        self.render_search(search_area, frame);
+        self.render_table(table_area, frame);
+        self.render_shown(right_half, frame);
+    }
+
+    fn render_search(&self, area: Rect, frame: &mut Frame) {
+        let block = Block::bordered()
+            .title(" Search ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded);
+        let line = if self.searching {
+            Line::raw(format!("{}_", self.query)).style(Style::new().blue())
+        } else if self.query.is_empty() {
+            Line::raw("Date, profile, operator, sample ID or outcome").italic()
+        } else {
+            Line::raw(self.query.clone())
+        };
+        frame.render_widget(Paragraph::new(line).block(block), area);
+    }
+
+    fn render_table(&self, area: Rect, frame: &mut Frame) {
+        let instructions = Line::from_iter([
+            " Search: ".into(),
+            "</>".blue().bold(),
+            " Open: ".into(),
+            "<Enter>".blue().bold(),
+            " Mark: ".into(),
+            "<Space>".blue().bold(),
+            " Compare marked: ".into(),
+            "<c>".blue().bold(),
+            " Upload profile: ".into(),
+            "<u>".blue().bold(),
+            " Plot: ".into(),
+            "<p>".blue().bold(),
+            " HTML report: ".into(),
+            "<h>".blue().bold(),
+            " Rebuild: ".into(),
+            "<r> ".blue().bold(),
+        ]);
+        let block = Block::bordered()
+            .title(format!(
+                " Runs ({}/{}) ",
+                self.matches.len(),
+                self.index.runs().len()
+            ))
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded)
+            .title_bottom(instructions);
+
+        let rows = self
+            .matches
+            .iter()
+            .filter_map(|i| self.index.runs().get(*i))
+            .map(|run| {
+                let mark = self
+                    .marked
+                    .iter()
+                    .position(|path| *path == run.data_path)
+                    .map_or_else(Cell::default, |i| {
+                        Cell::from((i + 1).to_string()).fg(compare::color(i)).bold()
+                    });
+                Row::new(
+                    std::iter::once(mark).chain(
+                        [
+                            run.started(),
+                            run.metadata.sample_id.clone(),
+                            run.metadata.operator.clone(),
+                            run.metadata.profile_name(),
+                            run.outcome(),
+                            run.within_tolerance_percent
+                                .map_or_else(|| "-".to_string(), |percent| format!("{percent:.1}")),
+                        ]
+                        .map(Cell::from),
+                    ),
+                )
+            });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(2),
+                Constraint::Length(16),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+                Constraint::Fill(2),
+                Constraint::Length(16),
+                Constraint::Length(8),
+            ],
+        )
+        .header(
+            Row::new([
+                "", "Started", "Sample", "Operator", "Profile", "Outcome", "In tol %",
+            ])
+            .style(Style::new().bold()),
+        )
+        .block(block)
+        .row_highlight_style(Style::new().blue());
+
+        let mut state = TableState::default().with_selected(Some(self.row));
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn render_shown(&self, area: Rect, frame: &mut Frame) {
+        let title = match &self.shown {
+            Shown::Nothing => " Run ".to_string(),
+            Shown::Run(opened) => {
+                let name = opened
+                    .data_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                format!(" {name} ")
+            }
+            Shown::Comparison(comparison) => {
+                format!(" Comparison of {} runs ", comparison.runs().len())
+            }
+        };
+        let chart_block = Block::bordered()
+            .title(title)
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded);
+        let report_block = Block::bordered()
+            .title(" Report ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded);
+
+        let mut lines = Vec::new();
+        if let Some(status) = &self.status {
+            lines.push(Line::raw(status.clone()).italic());
+        }
+        if self.index.runs().is_empty() {
+            lines.push(
+                Line::raw("No runs have been recorded in this folder yet.").fg(Color::Yellow),
+            );
+        }
+
+        let (chart_area, report_area) = match &self.shown {
+            Shown::Comparison(_) => {
+                // The comparison has its own metrics, so only the status is shown below it.
+                let layout = Layout::vertical([
+                    Constraint::Fill(1),
+                    Constraint::Length(u16::try_from(lines.len() + 2).unwrap_or(u16::MAX)),
+                ]);
+                let [chart_area, report_area] = area.layout(&layout);
+                (chart_area, report_area)
+            }
+            Shown::Nothing | Shown::Run(_) => {
+                let layout = Layout::vertical([Constraint::Fill(3), Constraint::Fill(2)]);
+                let [chart_area, report_area] = area.layout(&layout);
+                (chart_area, report_area)
+            }
+        };
+        match &self.shown {
+            Shown::Nothing => frame.render_widget(
+                Paragraph::new(
+                    "Select a run and press enter to open it, or mark runs and press c to compare them.",
+                )
+                .wrap(Wrap { trim: false })
+                .block(chart_block),
+                chart_area,
+            ),
+            Shown::Run(opened) => {
+                opened
+                    .chart
+                    .render(chart_block, opened.show_profile, chart_area, frame);
+                lines.extend(
+                    opened
+                        .report
+                        .to_string()
+                        .lines()
+                        .map(str::to_string)
+                        .map(Line::raw),
+                );
+            }
+            Shown::Comparison(comparison) => comparison.render(chart_block, chart_area, frame),
+        }
+        if matches!(self.shown, Shown::Comparison(_)) && lines.is_empty() {
+            return;
+        }
+        frame.render_widget(
+            Paragraph::new(Text::from(lines))
+                .wrap(Wrap { trim: false })
+                .block(report_block),
+            report_area,
+        );
+    }
+}
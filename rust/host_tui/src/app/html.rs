@@ -0,0 +1,533 @@
+//! This module contains the self-contained HTML report of a run, for attaching to electronic lab notebooks.
+//!
+//! The report is a single file: its styles, the script that draws its interactive plot and the samples it plots
+//! are embedded in it, and the static SVG plot is shown instead if scripts are disabled.
+
+use std::{
+    fmt::{self, Write},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use color_eyre::Result;
+use serde::Serialize;
+
+use crate::app::{
+    history::read_samples,
+    metadata::{RunMetadata, metadata_path},
+    plot::{profile_points, render_svg, titles},
+    read_json,
+    report::{MIN_TOLERANCE_RPM, RunReport, TOLERANCE_PERCENT},
+    state::{MOTOR_TO_PLATE_CONVERSION, MotionProfileState},
+};
+
+/// The styles of the report.
+const STYLE: &str = include_str!("html/report.css");
+
+/// The script that draws the interactive plot from the embedded samples.
+const SCRIPT: &str = include_str!("html/report.js");
+
+/// Where the HTML report of a motor data file is saved, e.g. `2026-02-10_report.html`.
+#[must_use]
+pub fn html_report_path(data_path: &Path) -> PathBuf {
+    let stem = data_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    data_path.with_file_name(format!("{stem}_report.html"))
+}
+
+/// The samples and profile plotted by the report's script, in seconds and plate RPM.
+#[derive(Debug, Serialize)]
+struct PlotData {
+    /// The time of each sample.
+    time: Vec<f64>,
+    /// The setpoint of each sample.
+    setpoint: Vec<f64>,
+    /// The measured RPM of each sample.
+    measured: Vec<f64>,
+    /// The error of each sample.
+    error: Vec<f64>,
+    /// The duty cycle of each sample, in percent.
+    duty: Vec<f64>,
+    /// The (time, RPM) points of the motion profile.
+    profile: Vec<(f64, f64)>,
+}
+
+impl PlotData {
+    /// Collects the plotted values of a run.
+    fn new(samples: &[MotionProfileState], metadata: Option<&RunMetadata>) -> Self {
+        let values = |value: fn(&MotionProfileState) -> f64| samples.iter().map(value).collect();
+        Self {
+            time: values(|state| seconds(state.time)),
+            setpoint: values(|state| state.setpoint_plate_rpm),
+            measured: values(|state| state.current_plate_rpm),
+            error: values(|state| state.plate_rpm_error),
+            duty: values(|state| f64::from(state.duty_cycle_f32) * 100.0),
+            profile: metadata.map(profile_points).unwrap_or_default(),
+        }
+    }
+}
+
+/// Writes the HTML report of a motor data file next to it, from its saved report and metadata if it has them.
+///
+/// Returns the path of the report.
+///
+/// # Errors
+/// Returns an error if the motor data file can't be read or the report can't be written.
+pub fn export(data_path: &Path) -> Result<PathBuf> {
+    let samples = read_samples(data_path)?;
+    // The metadata is optional, e.g. for runs recorded before it was saved.
+    let metadata = read_json::<RunMetadata>(&metadata_path(data_path))
+        .ok()
+        .flatten();
+    let report = RunReport::load(data_path, &samples);
+    save(data_path, &samples, metadata.as_ref(), &report)
+}
+
+/// Writes the HTML report of a run next to its motor data file.
+///
+/// Returns the path of the report.
+///
+/// # Errors
+/// Returns an error if the plot can't be drawn or the report can't be written.
+pub fn save(
+    data_path: &Path,
+    samples: &[MotionProfileState],
+    metadata: Option<&RunMetadata>,
+    report: &RunReport,
+) -> Result<PathBuf> {
+    let path = html_report_path(data_path);
+    fs::write(&path, render(data_path, samples, metadata, report)?)?;
+    Ok(path)
+}
+
+/// Renders the HTML report of a run.
+///
+/// # Errors
+/// Returns an error if the plot can't be drawn.
+pub fn render(
+    data_path: &Path,
+    samples: &[MotionProfileState],
+    metadata: Option<&RunMetadata>,
+    report: &RunReport,
+) -> Result<String> {
+    let (title, subtitle) = titles(data_path, metadata);
+    // `</` would end the script element early.
+    let data = serde_json::to_string(&PlotData::new(samples, metadata))?.replace("</", "<\\/");
+    let svg = render_svg(data_path, samples, metadata)?;
+
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html lang=\"en\">")?;
+    writeln!(html, "<head>")?;
+    writeln!(html, "<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>{}</title>", escape(&title))?;
+    writeln!(html, "<style>\n{STYLE}</style>")?;
+    writeln!(html, "</head>")?;
+    writeln!(html, "<body>")?;
+    writeln!(
+        html,
+        "<header>\n<h1>{}</h1>\n<p>{}</p>\n</header>",
+        escape(&title),
+        escape(&subtitle)
+    )?;
+
+    writeln!(html, "<section>\n<h2>Plot</h2>")?;
+    writeln!(
+        html,
+        "<p class=\"hint\">Drag across a plot to zoom in, and double-click to zoom out.</p>"
+    )?;
+    writeln!(
+        html,
+        "<div id=\"legend\"></div>\n<div id=\"plots\"></div>\n<div id=\"readout\"></div>"
+    )?;
+    writeln!(html, "<noscript>\n{svg}</noscript>\n</section>")?;
+
+    write_metrics(&mut html, samples, report)?;
+    write_faults(&mut html, report)?;
+    write_segments(&mut html, report)?;
+    write_profile(&mut html, metadata)?;
+    write_metadata(&mut html, metadata)?;
+
+    writeln!(
+        html,
+        "<script type=\"application/json\" id=\"run-data\">{data}</script>"
+    )?;
+    writeln!(html, "<script>\n{SCRIPT}</script>")?;
+    writeln!(html, "</body>\n</html>")?;
+    Ok(html)
+}
+
+/// Converts micros to seconds.
+fn seconds(micros: u64) -> f64 {
+    Duration::from_micros(micros).as_secs_f64()
+}
+
+/// Escapes text for HTML elements and attributes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Writes a row with a heading and a value.
+fn write_row(html: &mut String, heading: &str, value: &str) -> fmt::Result {
+    writeln!(
+        html,
+        "<tr><th>{}</th><td>{}</td></tr>",
+        escape(heading),
+        escape(value)
+    )
+}
+
+/// Writes a table's header row.
+fn write_header(html: &mut String, headings: &[&str]) -> fmt::Result {
+    write!(html, "<thead><tr>")?;
+    for heading in headings {
+        write!(html, "<th>{}</th>", escape(heading))?;
+    }
+    writeln!(html, "</tr></thead>")
+}
+
+/// Writes a row of a table with a header row.
+fn write_cells(html: &mut String, cells: &[String]) -> fmt::Result {
+    write!(html, "<tr>")?;
+    for cell in cells {
+        write!(html, "<td>{}</td>", escape(cell))?;
+    }
+    writeln!(html, "</tr>")
+}
+
+/// Formats an optional time in seconds.
+fn optional(time: Option<f64>) -> String {
+    time.map_or_else(|| "-".to_string(), |time| format!("{time:.2}"))
+}
+
+/// Writes the summary of how well the run followed its setpoints.
+#[allow(clippy::cast_precision_loss)]
+fn write_metrics(
+    html: &mut String,
+    samples: &[MotionProfileState],
+    report: &RunReport,
+) -> fmt::Result {
+    let squared_error: f64 = samples
+        .iter()
+        .map(|state| state.plate_rpm_error.powi(2))
+        .sum();
+    let rms_error = (squared_error / samples.len().max(1) as f64).sqrt();
+    let peak_error = samples
+        .iter()
+        .map(|state| state.plate_rpm_error.abs())
+        .fold(0.0, f64::max);
+
+    writeln!(html, "<section>\n<h2>Metrics</h2>\n<table>")?;
+    write_row(
+        html,
+        "Outcome",
+        &report
+            .outcome
+            .map_or_else(|| "Unknown".to_string(), |outcome| format!("{outcome:?}")),
+    )?;
+    write_row(html, "Duration (s)", &format!("{:.2}", report.duration))?;
+    write_row(html, "Samples", &report.samples.to_string())?;
+    write_row(html, "Dropped samples", &report.dropped_samples.to_string())?;
+    write_row(
+        html,
+        &format!("Within tolerance (±{TOLERANCE_PERCENT}%, at least {MIN_TOLERANCE_RPM} RPM)"),
+        &format!("{:.1}%", report.within_tolerance_percent),
+    )?;
+    write_row(
+        html,
+        "Duty saturation",
+        &format!("{:.1}%", report.duty_saturation_percent),
+    )?;
+    write_row(html, "RMS error (RPM)", &format!("{rms_error:.1}"))?;
+    write_row(html, "Peak error (RPM)", &format!("{peak_error:.1}"))?;
+    if let Some(state) = &report.final_state {
+        write_row(
+            html,
+            "Final setpoint (RPM)",
+            &format!("{:.0}", state.setpoint_plate_rpm),
+        )?;
+        write_row(
+            html,
+            "Final measured RPM",
+            &format!("{:.0}", state.current_plate_rpm),
+        )?;
+        write_row(html, "Overruns", &state.overruns.to_string())?;
+    }
+    writeln!(html, "</table>\n</section>")
+}
+
+/// Writes what went wrong during the run.
+fn write_faults(html: &mut String, report: &RunReport) -> fmt::Result {
+    writeln!(html, "<section>\n<h2>Faults</h2>")?;
+    if report.faults.is_empty() {
+        writeln!(html, "<p class=\"fault-free\">None</p>")?;
+    } else {
+        writeln!(html, "<ul class=\"faults\">")?;
+        for fault in &report.faults {
+            writeln!(html, "<li>{}</li>", escape(fault))?;
+        }
+        writeln!(html, "</ul>")?;
+    }
+    writeln!(html, "</section>")
+}
+
+/// Writes the tracking error of each segment and the response to each step.
+fn write_segments(html: &mut String, report: &RunReport) -> fmt::Result {
+    writeln!(html, "<section>\n<h2>Segments</h2>\n<table>")?;
+    write_header(
+        html,
+        &[
+            "Start (s)",
+            "End (s)",
+            "Kind",
+            "From (RPM)",
+            "To (RPM)",
+            "RMS error (RPM)",
+            "Peak error (RPM)",
+        ],
+    )?;
+    writeln!(html, "<tbody>")?;
+    for segment in &report.segments {
+        write_cells(
+            html,
+            &[
+                format!("{:.2}", segment.start),
+                format!("{:.2}", segment.end),
+                format!("{:?}", segment.kind),
+                format!("{:.0}", segment.start_setpoint),
+                format!("{:.0}", segment.end_setpoint),
+                format!("{:.1}", segment.rms_error),
+                format!("{:.1}", segment.peak_error),
+            ],
+        )?;
+    }
+    writeln!(html, "</tbody>\n</table>\n</section>")?;
+
+    if report.steps.is_empty() {
+        return Ok(());
+    }
+    writeln!(html, "<section>\n<h2>Steps</h2>\n<table>")?;
+    write_header(
+        html,
+        &[
+            "Time (s)",
+            "From (RPM)",
+            "To (RPM)",
+            "Rise time (s)",
+            "Overshoot (%)",
+            "Settling time (s)",
+        ],
+    )?;
+    writeln!(html, "<tbody>")?;
+    for step in &report.steps {
+        write_cells(
+            html,
+            &[
+                format!("{:.2}", step.time),
+                format!("{:.0}", step.from),
+                format!("{:.0}", step.to),
+                optional(step.rise_time),
+                format!("{:.1}", step.overshoot_percent),
+                optional(step.settling_time),
+            ],
+        )?;
+    }
+    writeln!(html, "</tbody>\n</table>\n</section>")
+}
+
+/// Writes the setpoints of the motion profile the run followed.
+fn write_profile(html: &mut String, metadata: Option<&RunMetadata>) -> fmt::Result {
+    writeln!(html, "<section>\n<h2>Motion profile</h2>")?;
+    match metadata {
+        None => writeln!(html, "<p>No metadata was saved with this run.</p>")?,
+        Some(metadata) if metadata.jog => {
+            writeln!(
+                html,
+                "<p>The run was a jog, so it had no motion profile.</p>"
+            )?;
+        }
+        Some(metadata) => {
+            writeln!(html, "<table>")?;
+            write_header(html, &["#", "Time (s)", "Motor RPM", "Plate RPM"])?;
+            writeln!(html, "<tbody>")?;
+            for (i, setpoint) in metadata.profile.iter().enumerate() {
+                write_cells(
+                    html,
+                    &[
+                        (i + 1).to_string(),
+                        format!("{:.3}", seconds(setpoint.time)),
+                        setpoint.rpm.to_string(),
+                        format!("{:.0}", f64::from(setpoint.rpm) * MOTOR_TO_PLATE_CONVERSION),
+                    ],
+                )?;
+            }
+            writeln!(html, "</tbody>\n</table>")?;
+        }
+    }
+    writeln!(html, "</section>")
+}
+
+/// Writes the metadata of the run.
+fn write_metadata(html: &mut String, metadata: Option<&RunMetadata>) -> fmt::Result {
+    writeln!(html, "<section>\n<h2>Metadata</h2>")?;
+    let Some(metadata) = metadata else {
+        writeln!(html, "<p>No metadata was saved with this run.</p>")?;
+        return writeln!(html, "</section>");
+    };
+    let time_format = "%Y-%m-%d %H:%M:%S %:z";
+    writeln!(html, "<table>")?;
+    write_row(html, "Data file", &metadata.data_file)?;
+    write_row(
+        html,
+        "Report file",
+        metadata.report_file.as_deref().unwrap_or("-"),
+    )?;
+    write_row(html, "Operator", &metadata.operator)?;
+    write_row(html, "Sample ID", &metadata.sample_id)?;
+    write_row(
+        html,
+        "Started",
+        &metadata.started.format(time_format).to_string(),
+    )?;
+    write_row(
+        html,
+        "Ended",
+        &metadata.ended.map_or_else(
+            || "-".to_string(),
+            |ended| ended.format(time_format).to_string(),
+        ),
+    )?;
+    write_row(html, "Jog", if metadata.jog { "Yes" } else { "No" })?;
+    for source in &metadata.profile_sources {
+        write_row(
+            html,
+            "Profile file",
+            &format!("{} (SHA-256 {})", source.path.display(), source.sha256),
+        )?;
+    }
+    if let Some(device) = metadata.device {
+        let [major, minor, patch] = device.firmware_version;
+        let calibration = device.calibration;
+        let controller = device.controller;
+        write_row(
+            html,
+            "Firmware version",
+            &format!("{major}.{minor}.{patch}"),
+        )?;
+        write_row(
+            html,
+            "Calibration (duty = RPM × numerator / denominator + intercept)",
+            &format!(
+                "{} / {} + {}",
+                calibration.rpm_to_duty_numerator,
+                calibration.rpm_to_duty_denominator,
+                calibration.rpm_to_duty_intercept
+            ),
+        )?;
+        write_row(
+            html,
+            "Inverse proportional gain",
+            &controller.k_p_inverse.to_string(),
+        )?;
+        write_row(
+            html,
+            "Loop period (µs)",
+            &controller.loop_period.to_string(),
+        )?;
+        write_row(
+            html,
+            "Duty cycle limits",
+            &format!(
+                "{} to {}",
+                controller.min_duty_cycle, controller.max_duty_cycle
+            ),
+        )?;
+    } else {
+        write_row(html, "Firmware version", "Unknown")?;
+    }
+    write_row(html, "Host version", &metadata.host_version)?;
+    writeln!(html, "</table>\n</section>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        metadata::{RunInfo, UploadedProfile},
+        test_utils::{TempFile, data_file, run},
+    };
+
+    #[test]
+    fn reports_are_saved_next_to_the_motor_data() {
+        assert_eq!(
+            html_report_path(Path::new("logs/motor_data/2026-02-10_12-00-00.csv")),
+            Path::new("logs/motor_data/2026-02-10_12-00-00_report.html")
+        );
+    }
+
+    #[test]
+    fn export_writes_a_self_contained_report() {
+        let data = data_file("html_export", &run());
+        // The report is removed along with the data.
+        let html = TempFile::new("html_export_report", "html");
+
+        let path = export(data.path()).expect("Exporting failed");
+        assert_eq!(path, html.path());
+        let html = fs::read_to_string(html.path()).expect("The report should exist");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<svg"));
+        assert!(html.contains("id=\"run-data\""));
+        assert!(html.contains("No metadata was saved with this run."));
+    }
+
+    #[test]
+    fn metadata_cant_break_out_of_the_page() {
+        let run_info = RunInfo {
+            operator: "<script>alert(1)</script>".to_string(),
+            sample_id: "A & B".to_string(),
+        };
+        let metadata = RunMetadata::new(
+            Path::new("run.csv"),
+            &run_info,
+            false,
+            &UploadedProfile::default(),
+            None,
+        );
+        let samples = run();
+        let report = RunReport::new(&samples, None);
+        let html = render(Path::new("run.csv"), &samples, Some(&metadata), &report)
+            .expect("Rendering failed");
+        assert!(!html.contains("<script>alert(1)"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("A &amp; B"));
+    }
+
+    #[test]
+    fn empty_runs_can_be_rendered() {
+        let report = RunReport::new(&[], None);
+        let html = render(Path::new("empty.csv"), &[], None, &report).expect("Rendering failed");
+        assert!(html.contains("</html>"));
+    }
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(
+            escape(r#"<a href="x">Tom's & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom&#39;s &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+}
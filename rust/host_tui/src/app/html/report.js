@@ -0,0 +1,263 @@
+// Draws the interactive plot of a run report from the samples embedded in it.
+//
+// Drag across a plot to zoom in on that time range, and double-click to zoom out.
+// Hovering shows the values of the nearest sample, and the legend hides and shows series.
+"use strict";
+
+const data = JSON.parse(document.getElementById("run-data").textContent);
+const zip = (times, values) => times.map((time, i) => [time, values[i]]);
+const panels = [
+  {
+    name: "rpm",
+    label: "Plate RPM",
+    series: [
+      { name: "Profile", color: "#969696", dash: [8, 6], points: data.profile },
+      { name: "Setpoint", color: "#e69f00", points: zip(data.time, data.setpoint) },
+      { name: "Measured", color: "#0072b2", points: zip(data.time, data.measured) },
+    ],
+  },
+  {
+    name: "error",
+    label: "Error (RPM)",
+    symmetric: true,
+    series: [{ name: "Error", color: "#d55e00", points: zip(data.time, data.error) }],
+  },
+  {
+    name: "duty",
+    label: "Duty (%)",
+    series: [{ name: "Duty cycle", color: "#cc79a7", points: zip(data.time, data.duty) }],
+  },
+];
+const margin = { left: 70, right: 20, top: 10, bottom: 28 };
+const last = (values) => (values.length ? values[values.length - 1] : 0);
+const fullEnd = Math.max(0.5, last(data.time), last(data.profile.map(([time]) => time)));
+const hidden = new Set();
+let view = { start: 0, end: fullEnd };
+// The time under the mouse, and the time range being dragged across.
+let cursor = null;
+let drag = null;
+
+function tickStep(range, count) {
+  const rough = range / count;
+  const power = Math.pow(10, Math.floor(Math.log10(rough)));
+  const fraction = rough / power;
+  return (fraction < 1.5 ? 1 : fraction < 3 ? 2 : fraction < 7 ? 5 : 10) * power;
+}
+
+function ticks(min, max, count) {
+  const step = tickStep(max - min, count);
+  const values = [];
+  for (let value = Math.ceil(min / step) * step; value <= max + step * 1e-9; value += step) {
+    values.push(Math.abs(value) < step * 1e-9 ? 0 : value);
+  }
+  return values;
+}
+
+function format(value) {
+  const magnitude = Math.abs(value);
+  return value.toFixed(magnitude >= 100 ? 0 : magnitude >= 10 ? 1 : 2);
+}
+
+function valueRange(panel) {
+  let min = Infinity;
+  let max = -Infinity;
+  for (const series of panel.series) {
+    if (hidden.has(series.name)) continue;
+    for (const [time, value] of series.points) {
+      if (time < view.start || time > view.end) continue;
+      min = Math.min(min, value);
+      max = Math.max(max, value);
+    }
+  }
+  if (!isFinite(min)) {
+    min = 0;
+    max = 1;
+  }
+  if (panel.symmetric) {
+    max = Math.max(Math.abs(min), Math.abs(max));
+    min = -max;
+  }
+  const padding = (max - min) * 0.1 || 1;
+  return [min - padding, max + padding];
+}
+
+function plotWidth(canvas) {
+  return canvas.clientWidth - margin.left - margin.right;
+}
+
+function timeAt(canvas, event) {
+  const x = event.clientX - canvas.getBoundingClientRect().left - margin.left;
+  const fraction = Math.min(Math.max(x / plotWidth(canvas), 0), 1);
+  return view.start + fraction * (view.end - view.start);
+}
+
+function drawGrid(context, panel, width, height, x, y, min, max) {
+  context.font = "12px sans-serif";
+  context.fillStyle = "#333";
+  context.strokeStyle = "#e4e4e4";
+  context.lineWidth = 1;
+  context.textAlign = "right";
+  context.textBaseline = "middle";
+  for (const value of ticks(min, max, 5)) {
+    context.beginPath();
+    context.moveTo(margin.left, y(value));
+    context.lineTo(width - margin.right, y(value));
+    context.stroke();
+    context.fillText(format(value), margin.left - 6, y(value));
+  }
+  context.textAlign = "center";
+  context.textBaseline = "top";
+  for (const time of ticks(view.start, view.end, 10)) {
+    context.beginPath();
+    context.moveTo(x(time), margin.top);
+    context.lineTo(x(time), height - margin.bottom);
+    context.stroke();
+    context.fillText(format(time), x(time), height - margin.bottom + 6);
+  }
+  context.save();
+  context.translate(14, (height - margin.bottom + margin.top) / 2);
+  context.rotate(-Math.PI / 2);
+  context.textBaseline = "middle";
+  context.fillText(panel.label, 0, 0);
+  context.restore();
+}
+
+function draw(panel) {
+  const canvas = panel.canvas;
+  const ratio = window.devicePixelRatio || 1;
+  const width = canvas.clientWidth;
+  const height = canvas.clientHeight;
+  canvas.width = width * ratio;
+  canvas.height = height * ratio;
+  const context = canvas.getContext("2d");
+  context.setTransform(ratio, 0, 0, ratio, 0, 0);
+  context.clearRect(0, 0, width, height);
+
+  const innerHeight = height - margin.top - margin.bottom;
+  const [min, max] = valueRange(panel);
+  const x = (time) => margin.left + ((time - view.start) / (view.end - view.start)) * plotWidth(canvas);
+  const y = (value) => margin.top + ((max - value) / (max - min)) * innerHeight;
+  drawGrid(context, panel, width, height, x, y, min, max);
+
+  context.save();
+  context.beginPath();
+  context.rect(margin.left, margin.top, plotWidth(canvas), innerHeight);
+  context.clip();
+  context.lineWidth = 2;
+  for (const series of panel.series) {
+    if (hidden.has(series.name)) continue;
+    context.strokeStyle = series.color;
+    context.setLineDash(series.dash || []);
+    context.beginPath();
+    series.points.forEach(([time, value], i) => {
+      if (i === 0) context.moveTo(x(time), y(value));
+      else context.lineTo(x(time), y(value));
+    });
+    context.stroke();
+  }
+  context.setLineDash([]);
+  if (drag) {
+    context.fillStyle = "rgba(0, 114, 178, 0.15)";
+    context.fillRect(x(drag.start), margin.top, x(drag.end) - x(drag.start), innerHeight);
+  }
+  if (cursor !== null) {
+    context.strokeStyle = "rgba(0, 0, 0, 0.5)";
+    context.lineWidth = 1;
+    context.beginPath();
+    context.moveTo(x(cursor), margin.top);
+    context.lineTo(x(cursor), margin.top + innerHeight);
+    context.stroke();
+  }
+  context.restore();
+  context.strokeStyle = "#333";
+  context.strokeRect(margin.left, margin.top, plotWidth(canvas), innerHeight);
+}
+
+function drawAll() {
+  panels.forEach(draw);
+}
+
+function nearestSample(time) {
+  let low = 0;
+  let high = data.time.length - 1;
+  while (low < high) {
+    const middle = (low + high) >> 1;
+    if (data.time[middle] < time) low = middle + 1;
+    else high = middle;
+  }
+  if (low > 0 && time - data.time[low - 1] < data.time[low] - time) low -= 1;
+  return low;
+}
+
+function showReadout() {
+  const readout = document.getElementById("readout");
+  if (cursor === null || data.time.length === 0) {
+    readout.textContent = "";
+    return;
+  }
+  const i = nearestSample(cursor);
+  readout.textContent =
+    `Time ${data.time[i].toFixed(3)} s | Setpoint ${data.setpoint[i].toFixed(0)} RPM | ` +
+    `Measured ${data.measured[i].toFixed(0)} RPM | Error ${data.error[i].toFixed(1)} RPM | ` +
+    `Duty ${data.duty[i].toFixed(2)}%`;
+}
+
+const plots = document.getElementById("plots");
+const legend = document.getElementById("legend");
+for (const panel of panels) {
+  panel.canvas = document.createElement("canvas");
+  panel.canvas.className = panel.name;
+  plots.appendChild(panel.canvas);
+
+  panel.canvas.addEventListener("mousedown", (event) => {
+    const time = timeAt(panel.canvas, event);
+    drag = { from: time, start: time, end: time };
+  });
+  panel.canvas.addEventListener("mousemove", (event) => {
+    cursor = timeAt(panel.canvas, event);
+    if (drag) {
+      drag.start = Math.min(drag.from, cursor);
+      drag.end = Math.max(drag.from, cursor);
+    }
+    showReadout();
+    drawAll();
+  });
+  panel.canvas.addEventListener("mouseleave", () => {
+    cursor = null;
+    showReadout();
+    drawAll();
+  });
+  panel.canvas.addEventListener("dblclick", () => {
+    view = { start: 0, end: fullEnd };
+    drawAll();
+  });
+
+  for (const series of panel.series) {
+    if (series.points.length === 0) continue;
+    const label = document.createElement("label");
+    const checkbox = document.createElement("input");
+    checkbox.type = "checkbox";
+    checkbox.checked = true;
+    checkbox.addEventListener("change", () => {
+      if (checkbox.checked) hidden.delete(series.name);
+      else hidden.add(series.name);
+      drawAll();
+    });
+    const swatch = document.createElement("span");
+    swatch.className = "swatch";
+    swatch.style.background = series.color;
+    label.append(checkbox, swatch, series.name);
+    legend.appendChild(label);
+  }
+}
+
+window.addEventListener("mouseup", () => {
+  // Ignore clicks, which barely move.
+  if (drag && drag.end - drag.start > (view.end - view.start) / 200) {
+    view = { start: drag.start, end: drag.end };
+  }
+  drag = null;
+  drawAll();
+});
+window.addEventListener("resize", drawAll);
+drawAll();
@@ -0,0 +1,204 @@
+//! This module contains the form for setting the jog safety limits and starting a jog.
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, HorizontalAlignment},
+    prelude::{Frame, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, BorderType, Clear, Paragraph},
+};
+use sc_messages::{
+    jog::{Limits, MAX_TIMEOUT_MILLIS},
+    pwm::{DutyCycle, MAX_POWER_DUTY, STOP_DUTY},
+};
+
+use crate::app::event::TICK_PERIOD;
+
+/// The fields of the form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// The duty cycle to jog at.
+    DutyCycle,
+    /// The lowest duty cycle allowed.
+    Min,
+    /// The highest duty cycle allowed.
+    Max,
+    /// The timeout in seconds.
+    Timeout,
+}
+
+impl Field {
+    /// The field after this one.
+    fn next(self) -> Self {
+        match self {
+            Self::DutyCycle => Self::Min,
+            Self::Min => Self::Max,
+            Self::Max => Self::Timeout,
+            Self::Timeout => Self::DutyCycle,
+        }
+    }
+
+    /// The field before this one.
+    fn previous(self) -> Self {
+        match self {
+            Self::DutyCycle => Self::Timeout,
+            Self::Min => Self::DutyCycle,
+            Self::Max => Self::Min,
+            Self::Timeout => Self::Max,
+        }
+    }
+}
+
+/// What the app should do after a key press in the form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JogAction {
+    /// Keep showing the form.
+    Continue,
+    /// Close the form without jogging.
+    Cancel,
+    /// Close the form, set the limits and start jogging at the duty cycle.
+    Submit(Limits, DutyCycle),
+}
+
+/// A form for entering a jog duty cycle and the safety limits it must be within.
+#[derive(Debug, Clone)]
+pub struct JogForm {
+    /// The duty cycle to jog at as typed.
+    duty_cycle: String,
+    /// The lowest duty cycle as typed.
+    min: String,
+    /// The highest duty cycle as typed.
+    max: String,
+    /// The timeout in seconds as typed.
+    timeout: String,
+    /// The field being edited.
+    field: Field,
+    /// Why the previous submission was rejected.
+    error: Option<String>,
+}
+
+impl JogForm {
+    /// Constructs a form filled in with the current limits, jogging at their lowest duty cycle.
+    #[must_use]
+    pub fn new(limits: &Limits) -> Self {
+        Self {
+            duty_cycle: limits.min_duty_cycle.to_string(),
+            min: limits.min_duty_cycle.to_string(),
+            max: limits.max_duty_cycle.to_string(),
+            timeout: (limits.timeout_millis / 1_000).to_string(),
+            field: Field::DutyCycle,
+            error: None,
+        }
+    }
+
+    /// Handles a key press.
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> JogAction {
+        match key_event.code {
+            KeyCode::Esc => return JogAction::Cancel,
+            KeyCode::Enter => match self.parse() {
+                Ok((limits, duty_cycle)) => return JogAction::Submit(limits, duty_cycle),
+                Err(error) => self.error = Some(error),
+            },
+            KeyCode::Tab | KeyCode::Down => self.field = self.field.next(),
+            KeyCode::BackTab | KeyCode::Up => self.field = self.field.previous(),
+            KeyCode::Backspace => {
+                self.input().pop();
+            }
+            KeyCode::Char(digit) if digit.is_ascii_digit() => self.input().push(digit),
+            _ => {}
+        }
+        JogAction::Continue
+    }
+
+    /// The text of the field being edited.
+    fn input(&mut self) -> &mut String {
+        match self.field {
+            Field::DutyCycle => &mut self.duty_cycle,
+            Field::Min => &mut self.min,
+            Field::Max => &mut self.max,
+            Field::Timeout => &mut self.timeout,
+        }
+    }
+
+    /// Parses the form.
+    ///
+    /// # Errors
+    /// Returns a description of the problem if the limits would be refused by the MCU,
+    /// or the duty cycle is outside them.
+    fn parse(&self) -> Result<(Limits, DutyCycle), String> {
+        let duty_range = format!("between {STOP_DUTY} and {MAX_POWER_DUTY}");
+        let parse_duty = |text: &str, name: &str| match text.parse::<u16>() {
+            Ok(value) if (STOP_DUTY..=MAX_POWER_DUTY).contains(&value) => Ok(DutyCycle::new(value)),
+            _ => Err(format!("{name} must be {duty_range}.")),
+        };
+        let duty_cycle = parse_duty(&self.duty_cycle, "Duty cycle")?;
+        let min_duty_cycle = parse_duty(&self.min, "Min duty cycle")?;
+        let max_duty_cycle = parse_duty(&self.max, "Max duty cycle")?;
+
+        // The TUI resends the duty cycle every tick, so the timeout must leave room for that.
+        let min_timeout = TICK_PERIOD.as_secs().saturating_mul(2);
+        let max_timeout = u64::from(MAX_TIMEOUT_MILLIS / 1_000);
+        let timeout = match self.timeout.parse::<u64>() {
+            Ok(timeout) if (min_timeout..=max_timeout).contains(&timeout) => timeout,
+            _ => {
+                return Err(format!(
+                    "Timeout must be between {min_timeout} and {max_timeout} seconds."
+                ));
+            }
+        };
+
+        let limits = Limits {
+            min_duty_cycle,
+            max_duty_cycle,
+            timeout_millis: u32::try_from(timeout.saturating_mul(1_000))
+                .unwrap_or(MAX_TIMEOUT_MILLIS),
+        };
+        if limits.validate().is_err() {
+            return Err("Min duty cycle must not be above max duty cycle.".to_string());
+        }
+        if limits.check(duty_cycle).is_err() {
+            return Err("Duty cycle must be between min and max duty cycle.".to_string());
+        }
+        Ok((limits, duty_cycle))
+    }
+
+    /// Renders the form as a popup in the middle of the area.
+    pub fn render(&self, area: Rect, frame: &mut Frame) {
+        let instructions = Line::from_iter([
+            " Switch field: ".into(),
+            "<Tab>".blue().bold(),
+            " Jog: ".into(),
+            "<Enter>".blue().bold(),
+            " Cancel: ".into(),
+            "<Esc> ".blue().bold(),
+        ]);
+        let block = Block::bordered()
+            .title(" Jog with Limits ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded)
+            .title_bottom(instructions);
+
+        let field_line = |name: &'static str, value: &str, field: Field| {
+            let line = Line::from_iter([name.into(), value.to_string().bold()]);
+            if self.field == field {
+                line.style(Style::new().blue())
+            } else {
+                line
+            }
+        };
+        let mut lines = vec![
+            field_line("Duty cycle: ", &self.duty_cycle, Field::DutyCycle),
+            field_line("Min duty cycle: ", &self.min, Field::Min),
+            field_line("Max duty cycle: ", &self.max, Field::Max),
+            field_line("Timeout (s): ", &self.timeout, Field::Timeout),
+        ];
+        if let Some(error) = &self.error {
+            lines.push(Line::raw(error.clone()).style(Style::new().fg(Color::Red)));
+        }
+
+        let popup_area = area.centered(Constraint::Length(54), Constraint::Length(8));
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(Paragraph::new(Text::from(lines)).block(block), popup_area);
+    }
+}
@@ -0,0 +1,213 @@
+//! This module contains the session journal, which records everything sent to and received from the MCU.
+//!
+//! Each session gets a new JSON Lines file in the `logs/journal` folder.
+//! Every line is an [`Entry`] with the time it was recorded, and lines are only ever appended,
+//! so the file is readable even if the app crashes.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, LineWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use sc_messages::{jog, motion_profile, vacuum_pump};
+use serde::{Deserialize, Serialize};
+
+use crate::app::{JOURNAL_SUB_DIR, create_log_file, event::MCUEvent};
+
+/// A request sent to the MCU.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Request {
+    /// A motion profile request.
+    MotionProfile(motion_profile::Request),
+    /// A constant plate RPM run.
+    RunAt(motion_profile::RunAt),
+    /// A replacement of the whole motion profile.
+    UploadProfile(Vec<motion_profile::Setpoint>),
+    /// A vacuum pump request.
+    VacuumPump(vacuum_pump::Request),
+    /// A jog request.
+    Jog(jog::Request),
+    /// A request for the control loop timing statistics.
+    LoopTiming,
+    /// A request for the firmware version and parameters.
+    DeviceInfo,
+    /// The notification that the app is closing.
+    Disconnect,
+}
+
+/// What happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    /// A request was sent to the MCU.
+    Sent(Request),
+    /// A response or topic message was received from the MCU.
+    Received(MCUEvent),
+    /// Something went wrong, e.g. the connection closed.
+    Error(String),
+}
+
+/// A line of the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// When the event was recorded.
+    pub time: DateTime<Local>,
+    /// What happened.
+    pub event: Event,
+}
+
+/// The journal of a session.
+#[derive(Debug)]
+pub struct Journal {
+    /// The journal file, which is flushed after every line.
+    writer: LineWriter<File>,
+    /// The path of the journal file.
+    path: PathBuf,
+}
+
+impl Journal {
+    /// Creates a new journal file in the `logs/journal` folder.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be created.
+    pub fn create() -> Result<Self> {
+        let (file, path) = create_log_file(JOURNAL_SUB_DIR, "jsonl")?;
+        Ok(Self {
+            writer: LineWriter::new(file),
+            path,
+        })
+    }
+
+    /// The path of the journal file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends an event to the journal.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn record(&mut self, event: Event) -> Result<()> {
+        let entry = Entry {
+            time: Local::now(),
+            event,
+        };
+        serde_json::to_writer(&mut self.writer, &entry)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Reads the entries of a journal file.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be read or a line isn't an [`Entry`].
+    pub fn read(path: &Path) -> Result<Vec<Entry>> {
+        let lines = BufReader::new(File::open(path)?)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()?;
+        let last = lines.len().saturating_sub(1);
+        let mut entries = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                // The last line may be cut off if the app crashed while writing it.
+                Err(_) if i == last => break,
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sc_messages::motion_profile::Outcome;
+
+    use super::*;
+    use crate::app::test_utils::TempFile;
+
+    /// Starts a journal in a temporary file.
+    fn journal(file: &TempFile) -> Journal {
+        Journal {
+            writer: LineWriter::new(
+                File::create(file.path()).expect("Failed to create the journal"),
+            ),
+            path: file.path().to_path_buf(),
+        }
+    }
+
+    /// The events of some entries, formatted so they can be compared.
+    fn events(entries: &[Entry]) -> Vec<String> {
+        entries
+            .iter()
+            .map(|entry| format!("{:?}", entry.event))
+            .collect()
+    }
+
+    #[test]
+    fn recorded_events_are_read_back() {
+        let file = TempFile::new("journal_round_trip", "jsonl");
+        let mut journal = journal(&file);
+        let recorded = [
+            Event::Sent(Request::MotionProfile(motion_profile::Request::Start)),
+            Event::Sent(Request::VacuumPump(vacuum_pump::Request::Enable)),
+            Event::Received(MCUEvent::Log("Started".to_string())),
+            Event::Received(MCUEvent::State(None)),
+            Event::Received(MCUEvent::Outcome(Outcome::Completed)),
+            Event::Sent(Request::Disconnect),
+            Event::Error("The connection closed".to_string()),
+        ];
+        for event in recorded.clone() {
+            journal.record(event).expect("Failed to record an event");
+        }
+
+        let entries = Journal::read(journal.path()).expect("Failed to read the journal");
+        assert_eq!(
+            events(&entries),
+            recorded
+                .iter()
+                .map(|event| format!("{event:?}"))
+                .collect::<Vec<_>>()
+        );
+        assert!(entries.is_sorted_by_key(|entry| entry.time));
+    }
+
+    #[test]
+    fn a_cut_off_last_line_is_ignored() {
+        let file = TempFile::new("journal_cut_off", "jsonl");
+        let mut journal = journal(&file);
+        journal
+            .record(Event::Sent(Request::LoopTiming))
+            .expect("Failed to record an event");
+        journal
+            .writer
+            .write_all(br#"{"time":"2026-01-01T00:00:00+00:00","event":{"sent""#)
+            .expect("Failed to write the journal");
+        journal.writer.flush().expect("Failed to write the journal");
+
+        let entries = Journal::read(journal.path()).expect("Failed to read the journal");
+        assert_eq!(events(&entries), ["Sent(LoopTiming)"]);
+    }
+
+    #[test]
+    fn a_broken_line_before_the_last_is_an_error() {
+        let file = TempFile::new("journal_broken", "jsonl");
+        let mut journal = journal(&file);
+        journal
+            .writer
+            .write_all(b"not an entry\n")
+            .expect("Failed to write the journal");
+        journal
+            .record(Event::Sent(Request::DeviceInfo))
+            .expect("Failed to record an event");
+        assert!(Journal::read(journal.path()).is_err());
+    }
+}
@@ -0,0 +1,431 @@
+//! This module contains the metadata written next to each motor data file,
+//! so runs can be traced back to their profile, device and sample.
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, HorizontalAlignment},
+    prelude::{Frame, Rect},
+    style::{Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, BorderType, Clear, Paragraph},
+};
+use sc_messages::{diagnostics::DeviceInfo, motion_profile::Outcome, motion_profile::Setpoint};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::app::sidecar_path;
+
+/// The longest operator name or sample ID that can be entered.
+const MAX_FIELD_LENGTH: usize = 64;
+
+/// Where the metadata of a motor data file is saved.
+#[must_use]
+pub fn metadata_path(data_path: &Path) -> PathBuf {
+    sidecar_path(data_path, "metadata")
+}
+
+/// A motion profile CSV file that setpoints were loaded from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileSource {
+    /// The path of the file when it was loaded.
+    pub path: PathBuf,
+    /// The SHA-256 hash of the file's contents in hex.
+    pub sha256: String,
+}
+
+impl ProfileSource {
+    /// Hashes the contents of a motion profile CSV file.
+    #[must_use]
+    pub fn new(path: PathBuf, contents: &[u8]) -> Self {
+        Self {
+            path,
+            sha256: format!("{:x}", Sha256::digest(contents)),
+        }
+    }
+}
+
+/// The motion profile the MCU has, as far as the host knows.
+#[derive(Debug, Default, Clone)]
+pub struct UploadedProfile {
+    /// The setpoints sent since the profile was last cleared.
+    setpoints: Vec<Setpoint>,
+    /// The files the setpoints were loaded from.
+    sources: Vec<ProfileSource>,
+    /// Whether the MCU discarded the profile after running it.
+    /// The next setpoints start a new profile.
+    consumed: bool,
+}
+
+impl UploadedProfile {
+    /// Replaces the profile with setpoints loaded from these files, if any.
+    pub fn set(&mut self, setpoints: &[Setpoint], sources: Vec<ProfileSource>) {
+        self.clear();
+        self.setpoints.extend_from_slice(setpoints);
+        self.sources = sources;
+    }
+
+    /// Adds setpoints loaded from a motion profile CSV file to the profile.
+    pub fn add(&mut self, setpoints: &[Setpoint], source: ProfileSource) {
+        if self.consumed {
+            self.clear();
+        }
+        self.setpoints.extend_from_slice(setpoints);
+        self.setpoints.sort();
+        self.sources.push(source);
+    }
+
+    /// Forgets the profile.
+    pub fn clear(&mut self) {
+        self.setpoints.clear();
+        self.sources.clear();
+        self.consumed = false;
+    }
+
+    /// Marks the end of a run.
+    ///
+    /// The MCU discards motion profiles after running them, but keeps them after jogs.
+    pub fn finish_run(&mut self, jogged: bool) {
+        if !jogged {
+            self.consumed = true;
+        }
+    }
+}
+
+/// The metadata of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunMetadata {
+    /// The file name of the motor data file, which is in the same folder.
+    pub data_file: String,
+    /// The file name of the run report, once the run has ended.
+    pub report_file: Option<String>,
+    /// Who ran the spin coater.
+    pub operator: String,
+    /// The sample being coated.
+    pub sample_id: String,
+    /// When the first sample was received.
+    pub started: DateTime<Local>,
+    /// When the MCU reported that the run was over.
+    pub ended: Option<DateTime<Local>>,
+    /// How the run ended, if the MCU reported it.
+    pub outcome: Option<Outcome>,
+    /// Whether the run was a jog rather than a motion profile.
+    pub jog: bool,
+    /// The motion profile sent to the MCU before the run, in motor RPM.
+    pub profile: Vec<Setpoint>,
+    /// The files the motion profile was loaded from, if any.
+    pub profile_sources: Vec<ProfileSource>,
+    /// The firmware version, calibration and controller parameters, if the MCU reported them.
+    pub device: Option<DeviceInfo>,
+    /// The version of this program.
+    pub host_version: String,
+}
+
+impl RunMetadata {
+    /// Starts the metadata of a run that writes to `data_path`.
+    #[must_use]
+    pub fn new(
+        data_path: &Path,
+        run_info: &RunInfo,
+        jog: bool,
+        profile: &UploadedProfile,
+        device: Option<DeviceInfo>,
+    ) -> Self {
+        Self {
+            data_file: file_name(data_path),
+            report_file: None,
+            operator: run_info.operator.clone(),
+            sample_id: run_info.sample_id.clone(),
+            started: Local::now(),
+            ended: None,
+            outcome: None,
+            jog,
+            // Jogs don't use the motion profile.
+            profile: if jog {
+                Vec::new()
+            } else {
+                profile.setpoints.clone()
+            },
+            profile_sources: if jog {
+                Vec::new()
+            } else {
+                profile.sources.clone()
+            },
+            device,
+            host_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Records the end of the run and its report.
+    pub fn finish(&mut self, report_path: &Path, outcome: Option<Outcome>) {
+        self.ended = Some(Local::now());
+        self.report_file = Some(file_name(report_path));
+        self.outcome = outcome;
+    }
+
+    /// A short description of the motion profile, e.g. the names of the files it was loaded from.
+    #[must_use]
+    pub fn profile_name(&self) -> String {
+        if self.jog {
+            return "Jog".to_string();
+        }
+        let names = self
+            .profile_sources
+            .iter()
+            .filter_map(|source| source.path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        if !names.is_empty() {
+            names.join(" + ")
+        } else if self.profile.is_empty() {
+            "Unknown".to_string()
+        } else {
+            format!("{} setpoints", self.profile.len())
+        }
+    }
+
+    /// Writes the metadata as JSON.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+}
+
+/// The file name of a path, which is all that's needed to find files in the same folder.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// What the operator entered about the runs to come.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RunInfo {
+    /// Who runs the spin coater.
+    pub operator: String,
+    /// The sample being coated.
+    pub sample_id: String,
+}
+
+/// The fields of the form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// The operator.
+    Operator,
+    /// The sample ID.
+    SampleId,
+}
+
+/// What the app should do after a key press in the form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunInfoAction {
+    /// Keep showing the form.
+    Continue,
+    /// Close the form without saving.
+    Cancel,
+    /// Close the form and use the new run info.
+    Submit(RunInfo),
+}
+
+/// A form for entering the operator and sample ID recorded with each run.
+#[derive(Debug, Clone)]
+pub struct RunInfoForm {
+    /// The run info as typed.
+    run_info: RunInfo,
+    /// The field being edited.
+    field: Field,
+}
+
+impl RunInfoForm {
+    /// Creates a form that starts with the current run info.
+    #[must_use]
+    pub fn new(run_info: RunInfo) -> Self {
+        Self {
+            run_info,
+            field: Field::Operator,
+        }
+    }
+
+    /// Handles a key press.
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> RunInfoAction {
+        match key_event.code {
+            KeyCode::Esc => return RunInfoAction::Cancel,
+            KeyCode::Enter => {
+                let mut run_info = self.run_info.clone();
+                run_info.operator = run_info.operator.trim().to_string();
+                run_info.sample_id = run_info.sample_id.trim().to_string();
+                return RunInfoAction::Submit(run_info);
+            }
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                self.field = match self.field {
+                    Field::Operator => Field::SampleId,
+                    Field::SampleId => Field::Operator,
+                };
+            }
+            KeyCode::Backspace => {
+                self.input().pop();
+            }
+            KeyCode::Char(character) if !character.is_control() => {
+                let input = self.input();
+                if input.chars().count() < MAX_FIELD_LENGTH {
+                    input.push(character);
+                }
+            }
+            _ => {}
+        }
+        RunInfoAction::Continue
+    }
+
+    /// The text of the field being edited.
+    fn input(&mut self) -> &mut String {
+        match self.field {
+            Field::Operator => &mut self.run_info.operator,
+            Field::SampleId => &mut self.run_info.sample_id,
+        }
+    }
+
+    /// Renders the form as a popup in the middle of the area.
+    pub fn render(&self, area: Rect, frame: &mut Frame) {
+        let instructions = Line::from_iter([
+            " Switch field: ".into(),
+            "<Tab>".blue().bold(),
+            " Save: ".into(),
+            "<Enter>".blue().bold(),
+            " Cancel: ".into(),
+            "<Esc> ".blue().bold(),
+        ]);
+        let block = Block::bordered()
+            .title(" Operator and Sample ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded)
+            .title_bottom(instructions);
+
+        let field_line = |name: &'static str, value: &str, field: Field| {
+            let line = Line::from_iter([name.into(), value.to_string().bold()]);
+            if self.field == field {
+                line.style(Style::new().blue())
+            } else {
+                line
+            }
+        };
+        let lines = vec![
+            field_line("Operator: ", &self.run_info.operator, Field::Operator),
+            field_line("Sample ID: ", &self.run_info.sample_id, Field::SampleId),
+            Line::raw("Recorded with every run until changed.").italic(),
+        ];
+
+        let popup_area = area.centered(Constraint::Length(60), Constraint::Length(6));
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(Paragraph::new(Text::from(lines)).block(block), popup_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{read_json, test_utils::TempFile};
+
+    /// A setpoint at a motor RPM and time in micros.
+    fn setpoint(rpm: u16, time: u64) -> Setpoint {
+        Setpoint { rpm, time }
+    }
+
+    /// The metadata of a run writing to `data_path` that followed `profile`.
+    fn metadata(data_path: &Path, profile: &UploadedProfile, jog: bool) -> RunMetadata {
+        let run_info = RunInfo {
+            operator: "Ada".to_string(),
+            sample_id: "S-42".to_string(),
+        };
+        RunMetadata::new(data_path, &run_info, jog, profile, None)
+    }
+
+    #[test]
+    fn metadata_is_saved_next_to_the_motor_data() {
+        assert_eq!(
+            metadata_path(Path::new("logs/motor_data/2026-02-10_12-00-00.csv")),
+            Path::new("logs/motor_data/2026-02-10_12-00-00_metadata.json")
+        );
+    }
+
+    #[test]
+    fn profile_sources_are_hashed_with_sha256() {
+        let source = ProfileSource::new(PathBuf::from("spin.csv"), b"abc");
+        assert_eq!(source.path, Path::new("spin.csv"));
+        assert_eq!(
+            source.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_ne!(
+            ProfileSource::new(PathBuf::from("spin.csv"), b"abd").sha256,
+            source.sha256
+        );
+    }
+
+    #[test]
+    fn metadata_survives_a_round_trip() {
+        let data_file = TempFile::new("metadata_round_trip", "csv");
+        let mut profile = UploadedProfile::default();
+        profile.set(
+            &[setpoint(2400, 1_000_000), setpoint(2400, 5_000_000)],
+            vec![ProfileSource::new(PathBuf::from("spin.csv"), b"abc")],
+        );
+        let mut saved = metadata(data_file.path(), &profile, false);
+        saved.finish(Path::new("run_report.json"), Some(Outcome::Completed));
+
+        let metadata_file = TempFile::new("metadata_round_trip_metadata", "json");
+        saved.save(metadata_file.path()).expect("Saving failed");
+        let loaded = read_json::<RunMetadata>(metadata_file.path())
+            .expect("Loading failed")
+            .expect("The metadata file should exist");
+        assert_eq!(
+            serde_json::to_value(&loaded).expect("Serializing failed"),
+            serde_json::to_value(&saved).expect("Serializing failed")
+        );
+        assert_eq!(loaded.data_file, file_name(data_file.path()));
+        assert_eq!(loaded.report_file.as_deref(), Some("run_report.json"));
+        assert_eq!(loaded.outcome, Some(Outcome::Completed));
+        assert_eq!(loaded.profile_name(), "spin.csv");
+    }
+
+    #[test]
+    fn jogs_dont_record_the_profile() {
+        let mut profile = UploadedProfile::default();
+        profile.set(&[setpoint(2400, 1_000_000)], Vec::new());
+        let jog = metadata(Path::new("jog.csv"), &profile, true);
+        assert!(jog.profile.is_empty());
+        assert_eq!(jog.profile_name(), "Jog");
+        let run = metadata(Path::new("run.csv"), &profile, false);
+        assert_eq!(run.profile, [setpoint(2400, 1_000_000)]);
+        assert_eq!(run.profile_name(), "1 setpoints");
+    }
+
+    #[test]
+    fn runs_consume_the_uploaded_profile_but_jogs_dont() {
+        let mut profile = UploadedProfile::default();
+        let first = ProfileSource::new(PathBuf::from("first.csv"), b"1");
+        let second = ProfileSource::new(PathBuf::from("second.csv"), b"2");
+        profile.add(&[setpoint(2400, 2_000_000)], first.clone());
+        profile.finish_run(true);
+        profile.add(&[setpoint(4800, 1_000_000)], second.clone());
+        assert_eq!(
+            profile.setpoints,
+            [setpoint(4800, 1_000_000), setpoint(2400, 2_000_000)]
+        );
+        assert_eq!(profile.sources, [first, second.clone()]);
+
+        profile.finish_run(false);
+        profile.add(&[setpoint(4800, 1_000_000)], second.clone());
+        assert_eq!(profile.setpoints, [setpoint(4800, 1_000_000)]);
+        assert_eq!(profile.sources, [second]);
+    }
+}
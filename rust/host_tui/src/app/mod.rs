@@ -1,29 +1,59 @@
 //! This module contains the app representing the TUI.
+pub mod chart;
+pub mod compare;
+pub mod editor;
 pub mod event;
+pub mod history;
+pub mod html;
+pub mod jog_form;
+pub mod journal;
+pub mod metadata;
+pub mod plot;
+pub mod replay;
+pub mod report;
+pub mod run_at;
 pub mod state;
+#[cfg(test)]
+mod test_utils;
+pub mod timing;
 pub mod ui;
 
-use std::fs::{DirBuilder, OpenOptions};
-use std::io::{self};
-use std::path::PathBuf;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use std::{env, fs::File};
 
+use crate::app::chart::RunChart;
+use crate::app::editor::{EditorAction, ProfileEditor};
 use crate::app::event::{EventHandler, MCUEvent, TuiEvent};
-use crate::app::state::MotionProfileState;
+use crate::app::history::{HistoryAction, RunHistory};
+use crate::app::jog_form::{JogAction, JogForm};
+use crate::app::metadata::{
+    ProfileSource, RunInfo, RunInfoAction, RunInfoForm, RunMetadata, UploadedProfile, metadata_path,
+};
+use crate::app::plot::PlotFormat;
+use crate::app::replay::Replay;
+use crate::app::report::{RunReport, report_path};
+use crate::app::run_at::{FormAction, RunAtForm};
+use crate::app::state::{DroppedSamples, MotionProfileState};
 use chrono::Local;
 use color_eyre::{Result, eyre::OptionExt};
 use crossterm::event::Event;
 use csv::{Writer, WriterBuilder};
-use postcard_rpc::host_client::HostClient;
-use postcard_rpc::standard_icd::WireError;
 use ratatui::{
     DefaultTerminal,
     crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
     widgets::ListState,
 };
 use ringbuffer::{AllocRingBuffer, RingBuffer};
-use sc_messages::motion_profile::{self, Setpoint};
+use sc_messages::diagnostics::{DeviceInfo, LoopTiming};
+use sc_messages::jog;
+use sc_messages::motion_profile::{self, Outcome, Setpoint};
+use sc_messages::pwm::DutyCycle;
 use sc_messages::vacuum_pump;
+use serde::Deserialize;
+use spincoater_client::Client;
 
 /// The maximum number of MCU logs kept in the TUI at a time.
 pub const MCU_LOG_CAPACITY: usize = 128;
@@ -37,6 +67,93 @@ pub const MOTOR_DATA_SUB_DIR: &str = "motor_data";
 /// The subdirectory for touchscreen data files.
 pub const TOUCHSCREEN_DATA_SUB_DIR: &str = "touchscreen_data";
 
+/// The subdirectory for session journals.
+pub const JOURNAL_SUB_DIR: &str = "journal";
+
+/// Creates a new log file in a subdirectory of [`LOG_DIR`], named after the current date.
+///
+/// Returns the file and its path.
+///
+/// # Errors
+/// Returns an error if the directory or file can't be created.
+pub fn create_log_file(sub_dir: &str, extension: &str) -> Result<(File, PathBuf)> {
+    let mut dir = env::current_dir()?;
+    dir.push(LOG_DIR);
+    dir.push(sub_dir);
+
+    DirBuilder::new().recursive(true).create(dir.clone())?;
+    let date = Local::now().date_naive().to_string();
+    dir.push(format!("{date}.{extension}"));
+    // If the file already exists, we need to make a new one.
+    let mut open_options = OpenOptions::new();
+    open_options.read(true).append(true).create_new(true);
+    let file = match open_options.open(dir.clone()) {
+        Ok(file) => file,
+        Err(err) => match err.kind() {
+            io::ErrorKind::AlreadyExists => {
+                let mut i = 1;
+                loop {
+                    dir.set_file_name(format!("{date}_({i}).{extension}"));
+                    match open_options.open(dir.clone()) {
+                        Ok(file) => break file,
+                        Err(err) => match err.kind() {
+                            io::ErrorKind::AlreadyExists => i += 1,
+                            _ => return Err(err.into()),
+                        },
+                    }
+                }
+            }
+            _ => return Err(err.into()),
+        },
+    };
+    Ok((file, dir))
+}
+
+/// Opens a new CSV log file in a subdirectory of [`LOG_DIR`].
+///
+/// Returns the writer and the path of the file.
+///
+/// # Errors
+/// Returns an error if the directory or file can't be created.
+pub fn open_log_file(sub_dir: &str) -> Result<(Writer<File>, PathBuf)> {
+    let (file, path) = create_log_file(sub_dir, "csv")?;
+    let writer = WriterBuilder::new().from_writer(file);
+    Ok((writer, path))
+}
+
+/// The path of a JSON file saved next to a motor data file, e.g. `2026-02-10_report.json`.
+#[must_use]
+pub fn sidecar_path(data_path: &Path, suffix: &str) -> PathBuf {
+    let stem = data_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    data_path.with_file_name(format!("{stem}_{suffix}.json"))
+}
+
+/// Reads a JSON file, or returns [`None`] if it doesn't exist.
+///
+/// # Errors
+/// Returns an error if the file can't be read or parsed.
+pub fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Option<T>> {
+    match File::open(path) {
+        Ok(file) => Ok(Some(serde_json::from_reader(BufReader::new(file))?)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// The tabs of the app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    /// Commands, the MCU's state, and logs.
+    Control,
+    /// The motion profile editor.
+    Editor,
+    /// Past runs.
+    History,
+}
+
 /// All the state for the host terminal.
 #[derive(Debug)]
 pub struct App {
@@ -48,6 +165,8 @@ pub struct App {
     commands_state: ListState,
     /// The current state, as reported by the MCU.
     mcu_state: Option<MotionProfileState>,
+    /// The most recent control loop timing statistics, as reported by the MCU.
+    loop_timing: Option<LoopTiming>,
     /// The last [`MCU_LOG_CAPACITY`] commands received from the MCU since the app started.
     ///
     /// When max capacity is reached, the oldest messages are overridden.
@@ -55,8 +174,55 @@ pub struct App {
     /// The motor data file.
     /// This is only [`Some`] when a motion profile is running.
     motor_data_file: Option<Writer<File>>,
+    /// The path of the current or most recent motor data file.
+    motor_data_path: Option<PathBuf>,
+    /// The samples of the current or most recent run, for its report.
+    run_samples: Vec<MotionProfileState>,
+    /// The samples of the current or most recent run that the app missed.
+    dropped_samples: DroppedSamples,
+    /// How the current or most recent run ended, once the MCU reports it.
+    run_outcome: Option<Outcome>,
+    /// Whether the current or most recent run was a jog started by the app.
+    run_jogged: bool,
+    /// The report of the most recent run.
+    report: Option<RunReport>,
+    /// Whether the report is shown over the control tab.
+    show_report: bool,
+    /// The metadata of the current or most recent run.
+    metadata: Option<RunMetadata>,
+    /// The operator and sample ID recorded with each run.
+    run_info: RunInfo,
+    /// The operator and sample ID form.
+    /// This is only [`Some`] while the form is open, and receives all key presses.
+    run_info_form: Option<RunInfoForm>,
+    /// The motion profile the MCU has, as far as the app knows.
+    uploaded_profile: UploadedProfile,
+    /// The MCU's firmware version and parameters, once it reports them.
+    device_info: Option<DeviceInfo>,
     /// The touchscreen data file.
-    touchscreen_data_file: Writer<File>,
+    /// This is [`None`] while replaying a recorded run, which doesn't write any files.
+    touchscreen_data_file: Option<Writer<File>>,
+    /// The duty cycle the MCU should jog at.
+    /// This is only [`Some`] while jogging, and is resent every tick while the MCU reports the jog to keep it alive.
+    jog_duty_cycle: Option<DutyCycle>,
+    /// The jog limits the MCU has, as far as the app knows.
+    jog_limits: jog::Limits,
+    /// The duty cycle to start jogging at once the MCU accepts the limits from [`JogForm`].
+    pending_jog_duty_cycle: Option<DutyCycle>,
+    /// The jog form.
+    /// This is only [`Some`] while the form is open, and receives all key presses.
+    jog_form: Option<JogForm>,
+    /// The constant plate RPM run form.
+    /// This is only [`Some`] while the form is open, and receives all key presses.
+    run_at_form: Option<RunAtForm>,
+    /// The live chart of the current or most recent run.
+    chart: RunChart,
+    /// The tab being shown.
+    tab: Tab,
+    /// The motion profile editor.
+    editor: ProfileEditor,
+    /// The index of past runs and the run opened from it.
+    history: RunHistory,
 }
 
 impl App {
@@ -64,54 +230,79 @@ impl App {
     ///
     /// # Errors
     /// Returns an error if opening the log file fails.
-    pub async fn new(client: HostClient<WireError>) -> Result<Self> {
-        let events = EventHandler::new(client).await?;
+    pub async fn new(client: Client) -> Result<Self> {
+        let mut events = EventHandler::new(client).await?;
+        events.send_device_info_request();
+        let touchscreen_data_file = open_log_file(TOUCHSCREEN_DATA_SUB_DIR)?.0;
+        let mut app = Self::with_events(events, Some(touchscreen_data_file))?;
+        if let Some(path) = app.events.journal_path() {
+            let message = format!("[Journal]: Recording to {}", path.display());
+            let _ = app.mcu_logs.enqueue(message);
+        }
+        Ok(app)
+    }
+
+    /// Shows a message from the host in the MCU logs, like how the connection was set up.
+    pub fn log(&mut self, message: String) {
+        let _ = self.mcu_logs.enqueue(message);
+    }
+
+    /// Constructs an [`App`] that replays a recorded run instead of connecting to the MCU.
+    ///
+    /// # Errors
+    /// Returns an error if the run history can't be loaded.
+    pub fn replay(replay: Replay) -> Result<Self> {
+        let metadata = replay.metadata().cloned();
+        let mut app = Self::with_events(EventHandler::replay(replay), None)?;
+        // Draw the profile the run followed ahead of its samples.
+        if let Some(metadata) = metadata
+            && !metadata.jog
+        {
+            app.chart.set_profile(&metadata.profile);
+            app.uploaded_profile
+                .set(&metadata.profile, metadata.profile_sources);
+        }
+        Ok(app)
+    }
+
+    /// Constructs an [`App`] that receives events from `events`.
+    fn with_events(
+        events: EventHandler,
+        touchscreen_data_file: Option<Writer<File>>,
+    ) -> Result<Self> {
         Ok(Self {
             running: true,
             events,
             mcu_state: None,
+            loop_timing: None,
             commands_state: ListState::default().with_selected(Some(0)),
             mcu_logs: AllocRingBuffer::new(MCU_LOG_CAPACITY),
             motor_data_file: None,
-            touchscreen_data_file: Self::open_log_file(TOUCHSCREEN_DATA_SUB_DIR)?,
+            motor_data_path: None,
+            run_samples: Vec::new(),
+            dropped_samples: DroppedSamples::default(),
+            run_outcome: None,
+            run_jogged: false,
+            report: None,
+            show_report: false,
+            metadata: None,
+            run_info: RunInfo::default(),
+            run_info_form: None,
+            uploaded_profile: UploadedProfile::default(),
+            device_info: None,
+            touchscreen_data_file,
+            jog_duty_cycle: None,
+            jog_limits: jog::Limits::DEFAULT,
+            pending_jog_duty_cycle: None,
+            jog_form: None,
+            run_at_form: None,
+            chart: RunChart::default(),
+            tab: Tab::Control,
+            editor: ProfileEditor::default(),
+            history: RunHistory::new()?,
         })
     }
 
-    /// Opens a log file.
-    fn open_log_file(sub_dir: &str) -> Result<Writer<File>> {
-        let mut dir = env::current_dir()?;
-        dir.push(LOG_DIR);
-        dir.push(sub_dir);
-
-        DirBuilder::new().recursive(true).create(dir.clone())?;
-        let date = Local::now().date_naive().to_string();
-        dir.push(format!("{date}.csv"));
-        // If the file already exists, we need to make a new one.
-        let mut open_options = OpenOptions::new();
-        open_options.read(true).append(true).create_new(true);
-        let file = match open_options.open(dir.clone()) {
-            Ok(file) => file,
-            Err(err) => match err.kind() {
-                io::ErrorKind::AlreadyExists => {
-                    let mut i = 1;
-                    loop {
-                        dir.set_file_name(format!("{date}_({i}).csv"));
-                        match open_options.open(dir.clone()) {
-                            Ok(file) => break file,
-                            Err(err) => match err.kind() {
-                                io::ErrorKind::AlreadyExists => i += 1,
-                                _ => return Err(err.into()),
-                            },
-                        }
-                    }
-                }
-                _ => return Err(err.into()),
-            },
-        };
-        let writer = WriterBuilder::new().from_writer(file);
-        Ok(writer)
-    }
-
     /// Runs the application.
     ///
     /// Attempts to disconnect cleanly upon exit.
@@ -139,6 +330,14 @@ impl App {
                     _ => {}
                 },
                 TuiEvent::MCU(usb_event) => self.handle_mcu_event(usb_event)?,
+                TuiEvent::Tick => {
+                    // A jog that already ended mustn't be restarted, so wait until the MCU reports it.
+                    if let Some(duty_cycle) = self.jog_duty_cycle
+                        && self.mcu_state.is_some()
+                    {
+                        self.events.send_jog_request(jog::Request::Set(duty_cycle));
+                    }
+                }
             }
         }
         Ok(())
@@ -146,103 +345,508 @@ impl App {
 
     /// Handles the key events and updates the state of [`App`].
     fn handle_key_event(&mut self, key_event: KeyEvent) -> Result<()> {
+        if self.run_at_form.is_some() {
+            self.handle_form_key_event(key_event);
+            return Ok(());
+        }
+        if self.jog_form.is_some() {
+            self.handle_jog_key_event(key_event);
+            return Ok(());
+        }
+        if self.run_info_form.is_some() {
+            self.handle_run_info_key_event(key_event);
+            return Ok(());
+        }
+        if self.show_report && self.tab == Tab::Control {
+            if RunReport::closes_popup(key_event.code) {
+                self.show_report = false;
+            }
+            return Ok(());
+        }
+        if self.tab == Tab::Editor && self.editor.is_editing() {
+            return self.handle_editor_key_event(key_event);
+        }
+        if self.tab == Tab::History && self.history.is_searching() {
+            self.handle_history_key_event(key_event);
+            return Ok(());
+        }
         match key_event.code {
             KeyCode::Esc | KeyCode::Char('q') => {
                 self.running = false;
+                return Ok(());
             }
             KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
                 self.running = false;
+                return Ok(());
+            }
+            KeyCode::Tab => {
+                self.tab = match self.tab {
+                    Tab::Control => Tab::Editor,
+                    Tab::Editor => Tab::History,
+                    Tab::History => Tab::Control,
+                };
+                return Ok(());
             }
+            _ => {}
+        }
+        if self.tab == Tab::Editor {
+            return self.handle_editor_key_event(key_event);
+        }
+        if self.tab == Tab::History {
+            self.handle_history_key_event(key_event);
+            return Ok(());
+        }
+        if self.chart.handle_key(key_event.code) {
+            return Ok(());
+        }
+        match key_event.code {
             KeyCode::Up => self.commands_state.scroll_up_by(1),
             KeyCode::Down => self.commands_state.scroll_down_by(1),
-            KeyCode::Enter => match self
-                .commands_state
-                .selected()
-                .ok_or_eyre("One command is always selected")?
-            {
-                // Create a prompt for setting the duty cycle.
-                0 => {
-                    let path = rfd::FileDialog::new()
-                        .add_filter("CSV", &["csv"])
-                        .set_directory(env::current_dir()?)
-                        .set_title("Please choose a motion profile CSV file.")
-                        .pick_file();
-                    if let Some(path) = path {
-                        self.send_motion_profile(path)?;
-                    }
+            KeyCode::Enter => self.run_command(
+                self.commands_state
+                    .selected()
+                    .ok_or_eyre("One command is always selected")?,
+            )?,
+            // Other handlers you could add here.
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Runs the command at `index` in the commands list.
+    fn run_command(&mut self, index: usize) -> Result<()> {
+        match index {
+            // Create a prompt for setting the duty cycle.
+            0 => {
+                let path = rfd::FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .set_directory(env::current_dir()?)
+                    .set_title("Please choose a motion profile CSV file.")
+                    .pick_file();
+                if let Some(path) = path {
+                    self.send_motion_profile(path)?;
+                }
+            }
+            // Clear all setpoints.
+            1 => {
+                self.chart.clear_profile();
+                self.uploaded_profile.clear();
+                self.events
+                    .send_motion_profile_request(motion_profile::Request::ClearSetpoints);
+            }
+            // Start the motion profile.
+            2 => self
+                .events
+                .send_motion_profile_request(motion_profile::Request::Start),
+            // Stop the motion profile.
+            3 => self
+                .events
+                .send_motion_profile_request(motion_profile::Request::Stop),
+            // Enable the vacuum pump.
+            4 => self
+                .events
+                .send_vacuum_pump_request(vacuum_pump::Request::Enable),
+            // Disable the vacuum pump.
+            5 => self
+                .events
+                .send_vacuum_pump_request(vacuum_pump::Request::Disable),
+            // Request the control loop timing statistics.
+            6 => self.events.send_loop_timing_request(),
+            // Start jogging or raise the jog duty cycle.
+            7 => self.raise_jog_duty_cycle(),
+            // Lower the jog duty cycle.
+            8 => self.lower_jog_duty_cycle(),
+            // Stop jogging.
+            9 => {
+                self.jog_duty_cycle = None;
+                self.events.send_jog_request(jog::Request::Stop);
+            }
+            // Open the constant plate RPM run form.
+            10 => self.run_at_form = Some(RunAtForm::default()),
+            // Show the report of the most recent run.
+            11 => {
+                if self.report.is_some() {
+                    self.show_report = true;
+                } else {
+                    let _ = self
+                        .mcu_logs
+                        .enqueue("[Report]: No run has finished yet.".to_string());
                 }
-                // Clear all setpoints.
-                1 => self
-                    .events
-                    .send_motion_profile_request(motion_profile::Request::ClearSetpoints),
-                // Start the motion profile.
-                2 => {
-                    self.motor_data_file
-                        .replace(Self::open_log_file(MOTOR_DATA_SUB_DIR)?);
-                    self.events
-                        .send_motion_profile_request(motion_profile::Request::Start);
+            }
+            // Open the operator and sample ID form.
+            12 => self.run_info_form = Some(RunInfoForm::new(self.run_info.clone())),
+            // Export the plots of the most recent run.
+            13 => self.export_plots(),
+            // Open the jog form.
+            14 => {
+                if self.jog_duty_cycle.is_some() {
+                    let _ = self
+                        .mcu_logs
+                        .enqueue("[Jog]: Stop jogging before changing the limits.".to_string());
+                } else {
+                    self.jog_form = Some(JogForm::new(&self.jog_limits));
                 }
-                // Stop the motion profile.
-                3 => self
-                    .events
-                    .send_motion_profile_request(motion_profile::Request::Stop),
-                // Enable the vacuum pump.
-                4 => self
-                    .events
-                    .send_vacuum_pump_request(vacuum_pump::Request::Enable),
-                // Disable the vacuum pump.
-                5 => self
-                    .events
-                    .send_vacuum_pump_request(vacuum_pump::Request::Disable),
-                _ => {}
-            },
-            // Other handlers you could add here.
+            }
             _ => {}
         }
         Ok(())
     }
 
+    /// Handles the key events for the constant plate RPM run form.
+    fn handle_form_key_event(&mut self, key_event: KeyEvent) {
+        let Some(form) = &mut self.run_at_form else {
+            return;
+        };
+        match form.handle_key_event(key_event) {
+            FormAction::Continue => {}
+            FormAction::Cancel => self.run_at_form = None,
+            FormAction::Submit(run_at) => {
+                self.run_at_form = None;
+                self.chart.set_profile(&run_at.setpoints());
+                self.uploaded_profile.set(&run_at.setpoints(), Vec::new());
+                self.events.send_run_at_request(run_at);
+            }
+        }
+    }
+
+    /// Handles the key events for the jog form.
+    fn handle_jog_key_event(&mut self, key_event: KeyEvent) {
+        let Some(form) = &mut self.jog_form else {
+            return;
+        };
+        match form.handle_key_event(key_event) {
+            JogAction::Continue => {}
+            JogAction::Cancel => self.jog_form = None,
+            JogAction::Submit(limits, duty_cycle) => {
+                self.jog_form = None;
+                // The MCU refuses a duty cycle outside its limits, so jog once they're accepted.
+                self.pending_jog_duty_cycle = Some(duty_cycle);
+                self.events.send_jog_limits_request(limits);
+            }
+        }
+    }
+
+    /// Handles the key events for the operator and sample ID form.
+    fn handle_run_info_key_event(&mut self, key_event: KeyEvent) {
+        let Some(form) = &mut self.run_info_form else {
+            return;
+        };
+        match form.handle_key_event(key_event) {
+            RunInfoAction::Continue => {}
+            RunInfoAction::Cancel => self.run_info_form = None,
+            RunInfoAction::Submit(run_info) => {
+                self.run_info = run_info;
+                self.run_info_form = None;
+            }
+        }
+    }
+
+    /// Handles the key events for the editor tab.
+    fn handle_editor_key_event(&mut self, key_event: KeyEvent) -> Result<()> {
+        match self.editor.handle_key_event(key_event)? {
+            EditorAction::None => {}
+            // The chart and metadata only show the new profile once the MCU accepts all of it.
+            EditorAction::Send(setpoints) => self.events.send_profile_upload(setpoints, Vec::new()),
+        }
+        Ok(())
+    }
+
+    /// Handles the key events for the history tab.
+    fn handle_history_key_event(&mut self, key_event: KeyEvent) {
+        match self.history.handle_key_event(key_event) {
+            HistoryAction::None => {}
+            // The chart and metadata only show the profile once the MCU accepts all of it.
+            HistoryAction::Upload(setpoints, sources) => {
+                self.events.send_profile_upload(setpoints, sources);
+            }
+        }
+    }
+
     fn handle_mcu_event(&mut self, mcu_event: MCUEvent) -> Result<()> {
         match mcu_event {
             MCUEvent::Log(msg) => {
                 let _ = self.mcu_logs.enqueue(format!("[Log]: {msg}"));
             }
-            MCUEvent::State(state) => {
-                self.mcu_state.clone_from(&state);
-                match state {
-                    Some(state) => self
-                        .motor_data_file
-                        .as_mut()
-                        .ok_or_eyre("The motor data file should be open.")?
-                        .serialize(state)?,
-                    None => {
-                        // Close the writer.
-                        let _ = self.motor_data_file.take();
-                    }
-                }
-            }
+            MCUEvent::State(state) => self.handle_state(state)?,
+            MCUEvent::Outcome(outcome) => self.handle_outcome(outcome)?,
             MCUEvent::MotionProfileRequestResponse(response) => {
                 let _ = self.mcu_logs.enqueue(format!("{response}"));
             }
+            MCUEvent::ProfileUploadResponse(setpoints, sources, response) => {
+                if response.response().is_ok() {
+                    self.chart.set_profile(&setpoints);
+                    self.uploaded_profile.set(&setpoints, sources);
+                }
+                let _ = self
+                    .mcu_logs
+                    .enqueue(format!("{response} (uploaded profile)"));
+            }
             MCUEvent::VacuumPumpRequestResponse => {
                 let _ = self.mcu_logs.enqueue("[Vacuum Pump]: Ok".to_string());
             }
+            MCUEvent::JogRequestResponse(response) => {
+                // Stop keeping the jog alive if the MCU can't jog.
+                if let Err(
+                    jog::RequestRefused::MotionProfileRunning | jog::RequestRefused::NotJogging,
+                ) = response
+                {
+                    self.jog_duty_cycle = None;
+                }
+                let _ = self.mcu_logs.enqueue(format!("[Jog]: {response:?}"));
+            }
+            MCUEvent::JogLimitsRequestResponse(limits, response) => {
+                self.handle_jog_limits_response(limits, response);
+            }
+            MCUEvent::LoopTiming(loop_timing) => {
+                self.loop_timing = Some(loop_timing);
+            }
+            MCUEvent::DeviceInfo(device_info) => {
+                if device_info.is_none() {
+                    let _ = self.mcu_logs.enqueue(
+                        "[Device]: The MCU didn't report its firmware version or parameters."
+                            .to_string(),
+                    );
+                }
+                self.device_info = device_info;
+            }
             MCUEvent::Touch(touch_point) => {
                 let _ = self.mcu_logs.enqueue(format!("[Touch]: {touch_point:?}"));
-                self.touchscreen_data_file.serialize(touch_point)?;
+                if let Some(file) = &mut self.touchscreen_data_file {
+                    file.serialize(touch_point)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a state of the current run, or finishes the run when there is no more state.
+    fn handle_state(&mut self, mut state: Option<MotionProfileState>) -> Result<()> {
+        let run_started = self.mcu_state.is_none();
+        if let Some(state) = &mut state {
+            self.record_dropped_samples(state, run_started);
+        }
+        self.mcu_state.clone_from(&state);
+        if let Some(state) = state {
+            if run_started {
+                self.chart.start_run();
+                self.run_samples.clear();
+                self.run_outcome = None;
+                // This is remembered for the whole run, since the jog duty cycle is cleared by the outcome,
+                // which may arrive before the last state.
+                self.run_jogged = self.jog_duty_cycle.is_some();
+            }
+            self.chart.push(&state);
+            self.run_samples.push(state.clone());
+            // The file is only opened once the run started, since the MCU may refuse to start it,
+            // and the run may have been started by someone else, like another client or SCPI.
+            // It still runs if it can't be recorded.
+            if run_started && let Err(error) = self.open_motor_data_file() {
+                // Keep the most recent run's files from being overwritten.
+                self.motor_data_path = None;
+                let _ = self.mcu_logs.enqueue(format!(
+                    "[Warning]: Failed to open the motor data file, so this run isn't recorded: {error}"
+                ));
+            }
+            if run_started {
+                self.start_metadata()?;
+            }
+            if let Some(file) = &mut self.motor_data_file {
+                file.serialize(state)?;
+            }
+        } else {
+            // Any jog is over, whether it was stopped or timed out.
+            self.jog_duty_cycle = None;
+            // Close the writer.
+            let _ = self.motor_data_file.take();
+            self.chart.finish_run(self.run_jogged);
+            self.finish_run()?;
+            // The run is over, so its timing statistics are complete.
+            if !self.events.is_replaying() {
+                self.events.send_loop_timing_request();
+            }
+        }
+        Ok(())
+    }
+
+    /// Records how the current or most recent run ended.
+    fn handle_outcome(&mut self, outcome: Outcome) -> Result<()> {
+        let _ = self.mcu_logs.enqueue(format!("[Outcome]: {outcome:?}"));
+        self.run_outcome = Some(outcome);
+        self.jog_duty_cycle = None;
+        // The outcome and the end of the run are separate topics, so either may arrive first.
+        if self.mcu_state.is_none()
+            && let Some(report) = &mut self.report
+            && report.outcome.is_none()
+        {
+            report.set_outcome(Some(outcome));
+            if let Some(path) = &self.motor_data_path {
+                report.save(&report_path(path))?;
             }
+            if let (Some(metadata), Some(path)) = (&mut self.metadata, &self.motor_data_path) {
+                metadata.outcome = Some(outcome);
+                metadata.save(&metadata_path(path))?;
+                self.history.record(path, metadata, Some(report))?;
+            }
+            if let Some(path) = &self.motor_data_path {
+                html::save(path, &self.run_samples, self.metadata.as_ref(), report)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the samples missed right before `state`, and warns about them.
+    fn record_dropped_samples(&mut self, state: &mut MotionProfileState, run_started: bool) {
+        if run_started {
+            self.dropped_samples.reset();
+        }
+        let dropped = self.dropped_samples.record(state);
+        if dropped > 0 {
+            let _ = self.mcu_logs.enqueue(format!(
+                "[Warning]: Missed {dropped} samples before {:.2} s ({} this run)",
+                Duration::from_micros(state.time).as_secs_f64(),
+                self.dropped_samples.total()
+            ));
+        }
+    }
+
+    /// Opens a new motor data file for the next run.
+    fn open_motor_data_file(&mut self) -> Result<()> {
+        // Replayed runs are already recorded.
+        if self.events.is_replaying() {
+            return Ok(());
+        }
+        let (writer, path) = open_log_file(MOTOR_DATA_SUB_DIR)?;
+        self.motor_data_file = Some(writer);
+        self.motor_data_path = Some(path);
+        Ok(())
+    }
+
+    /// Writes the metadata of the run that just started next to its motor data file.
+    fn start_metadata(&mut self) -> Result<()> {
+        self.metadata = None;
+        let Some(path) = &self.motor_data_path else {
+            return Ok(());
+        };
+        let metadata = RunMetadata::new(
+            path,
+            &self.run_info,
+            self.run_jogged,
+            &self.uploaded_profile,
+            self.device_info,
+        );
+        metadata.save(&metadata_path(path))?;
+        self.history.record(path, &metadata, None)?;
+        self.metadata = Some(metadata);
+        Ok(())
+    }
+
+    /// Summarizes the run that just ended, saves the report and metadata next to its motor data file,
+    /// and shows the report.
+    fn finish_run(&mut self) -> Result<()> {
+        if self.run_samples.is_empty() {
+            return Ok(());
+        }
+        self.uploaded_profile.finish_run(self.run_jogged);
+
+        let report = RunReport::new(&self.run_samples, self.run_outcome);
+        if let Some(path) = &self.motor_data_path {
+            let report_path = report_path(path);
+            report.save(&report_path)?;
+            if let Some(metadata) = &mut self.metadata {
+                metadata.finish(&report_path, self.run_outcome);
+                metadata.save(&metadata_path(path))?;
+                self.history.record(path, metadata, Some(&report))?;
+            }
+            let html_path = html::save(path, &self.run_samples, self.metadata.as_ref(), &report)?;
+            let _ = self.mcu_logs.enqueue(format!(
+                "[Report]: Saved to {} and {}",
+                report_path.display(),
+                html_path.display()
+            ));
         }
+        self.report = Some(report);
+        self.show_report = true;
         Ok(())
     }
 
+    /// Saves the plots of the most recent run next to its motor data file.
+    fn export_plots(&mut self) {
+        let message = match &self.motor_data_path {
+            _ if self.mcu_state.is_some() => {
+                "Wait for the run to finish before exporting its plots.".to_string()
+            }
+            None => "No run has finished yet.".to_string(),
+            Some(path) => match plot::export(path, &PlotFormat::ALL) {
+                Ok(paths) => format!(
+                    "Saved to {}",
+                    paths
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(" and ")
+                ),
+                Err(error) => format!("Failed to export the plots: {error}"),
+            },
+        };
+        let _ = self.mcu_logs.enqueue(format!("[Plot]: {message}"));
+    }
+
+    /// Starts jogging at the lowest duty cycle, or raises the jog duty cycle by [`jog::DUTY_STEP`].
+    fn raise_jog_duty_cycle(&mut self) {
+        let duty_cycle = if let Some(duty_cycle) = self.jog_duty_cycle {
+            duty_cycle
+                .saturating_add(jog::DUTY_STEP)
+                .min(*self.jog_limits.max_duty_cycle)
+                .into()
+        } else {
+            self.jog_limits.min_duty_cycle
+        };
+        self.jog(duty_cycle);
+    }
+
+    /// Lowers the jog duty cycle by [`jog::DUTY_STEP`] if jogging.
+    fn lower_jog_duty_cycle(&mut self) {
+        if let Some(duty_cycle) = self.jog_duty_cycle {
+            self.jog(
+                duty_cycle
+                    .saturating_sub(jog::DUTY_STEP)
+                    .max(*self.jog_limits.min_duty_cycle)
+                    .into(),
+            );
+        }
+    }
+
+    /// Starts the jog entered in [`JogForm`] if the MCU accepted its limits.
+    fn handle_jog_limits_response(&mut self, limits: jog::Limits, response: jog::RequestResult) {
+        let duty_cycle = self.pending_jog_duty_cycle.take();
+        if response.is_ok() {
+            self.jog_limits = limits;
+            if let Some(duty_cycle) = duty_cycle {
+                self.jog(duty_cycle);
+            }
+        }
+        let _ = self.mcu_logs.enqueue(format!("[Jog limits]: {response:?}"));
+    }
+
+    /// Sets the jog duty cycle and sends it to the MCU.
+    fn jog(&mut self, duty_cycle: DutyCycle) {
+        self.jog_duty_cycle = Some(duty_cycle);
+        self.events.send_jog_request(jog::Request::Set(duty_cycle));
+    }
+
     /// Loads a motion profile from a CSV [`PathBuf`] and sends it.
     ///
-    /// Note that [`postcard_rpc`] makes no guarantee about the order in which setpoints are sent,
+    /// Note that `postcard_rpc` makes no guarantee about the order in which setpoints are sent,
     /// but the MCU sorts them before execution.
     fn send_motion_profile(&mut self, path: PathBuf) -> Result<()> {
-        let file = csv::Reader::from_path(path)?;
-        for result in file.into_deserialize() {
-            let setpoint: Setpoint = result?;
+        let contents = fs::read(&path)?;
+        let setpoints = csv::Reader::from_reader(contents.as_slice())
+            .into_deserialize()
+            .collect::<Result<Vec<Setpoint>, _>>()?;
+        self.chart.add_to_profile(&setpoints);
+        self.uploaded_profile
+            .add(&setpoints, ProfileSource::new(path, &contents));
+        for setpoint in setpoints {
             let command = motion_profile::Request::Add(setpoint);
             self.events.send_motion_profile_request(command);
         }
@@ -0,0 +1,467 @@
+//! This module contains the export of run plots as SVG and PNG files for lab notebooks and reports.
+//!
+//! The plots are drawn as SVG with `plotters` and rasterized to PNG with `resvg`, which both run on the CPU.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use color_eyre::{Result, eyre::OptionExt};
+use plotters::{
+    coord::Shift,
+    prelude::{
+        BLACK, ChartBuilder, Color, DashedLineSeries, DrawingArea, IntoDrawingArea, IntoFont,
+        LineSeries, PathElement, RGBColor, SVGBackend, SeriesLabelPosition, ShapeStyle, Text,
+        WHITE,
+    },
+};
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{
+        Options, Tree,
+        fontdb::{Database, Family, Query},
+    },
+};
+
+use crate::app::{
+    chart::{MAX_POWER_DUTY_PERCENT, STOP_DUTY_PERCENT},
+    history::read_samples,
+    metadata::{RunMetadata, metadata_path},
+    read_json,
+    state::{MOTOR_TO_PLATE_CONVERSION, MotionProfileState},
+};
+
+/// The width of the plots in pixels.
+pub const PLOT_WIDTH: u32 = 1200;
+
+/// The height of the plots in pixels.
+pub const PLOT_HEIGHT: u32 = 900;
+
+/// The height of the title and subtitle in pixels.
+const HEADER_HEIGHT: u32 = 70;
+
+/// The font family used for all text.
+///
+/// If the system has no font for it, [`sans_serif_fallback`] picks one.
+const FONT: &str = "sans-serif";
+
+/// Fonts tried in order if the system has no default sans-serif font.
+const FALLBACK_FONTS: [&str; 4] = ["DejaVu Sans", "Liberation Sans", "Noto Sans", "Helvetica"];
+
+/// The color of the loaded profile.
+const PROFILE_COLOR: RGBColor = RGBColor(150, 150, 150);
+
+/// The color of the setpoint.
+const SETPOINT_COLOR: RGBColor = RGBColor(230, 159, 0);
+
+/// The color of the measured RPM.
+const MEASURED_COLOR: RGBColor = RGBColor(0, 114, 178);
+
+/// The color of the error.
+const ERROR_COLOR: RGBColor = RGBColor(213, 94, 0);
+
+/// The color of the duty cycle.
+const DUTY_CYCLE_COLOR: RGBColor = RGBColor(204, 121, 167);
+
+/// The file formats plots can be exported as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PlotFormat {
+    /// Scalable vector graphics.
+    Svg,
+    /// Portable network graphics.
+    Png,
+}
+
+impl PlotFormat {
+    /// Every format.
+    pub const ALL: [Self; 2] = [Self::Svg, Self::Png];
+
+    /// The file extension of the format.
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Svg => "svg",
+            Self::Png => "png",
+        }
+    }
+}
+
+/// Where the plot of a motor data file is saved, e.g. `2026-02-10_plot.svg`.
+#[must_use]
+pub fn plot_path(data_path: &Path, format: PlotFormat) -> PathBuf {
+    let stem = data_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    data_path.with_file_name(format!("{stem}_plot.{}", format.extension()))
+}
+
+/// Plots a motor data file and saves the plots next to it in each format.
+///
+/// The motion profile, operator, sample and device in its metadata file are shown if it has one.
+/// Returns the paths of the plots.
+///
+/// # Errors
+/// Returns an error if the motor data file can't be read or a plot can't be drawn or saved.
+pub fn export(data_path: &Path, formats: &[PlotFormat]) -> Result<Vec<PathBuf>> {
+    let samples = read_samples(data_path)?;
+    // The metadata is optional, e.g. for runs recorded before it was saved.
+    let metadata = read_json::<RunMetadata>(&metadata_path(data_path))
+        .ok()
+        .flatten();
+    let svg = render_svg(data_path, &samples, metadata.as_ref())?;
+    let mut paths = Vec::new();
+    for format in formats {
+        let path = plot_path(data_path, *format);
+        match format {
+            PlotFormat::Svg => fs::write(&path, &svg)?,
+            PlotFormat::Png => fs::write(&path, rasterize(&svg)?)?,
+        }
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// The title and subtitle of a run's plot.
+#[must_use]
+pub fn titles(data_path: &Path, metadata: Option<&RunMetadata>) -> (String, String) {
+    let name = data_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let Some(metadata) = metadata else {
+        return (name, "No metadata was saved with this run.".to_string());
+    };
+    let title = format!("{name}: {}", metadata.profile_name());
+    let or_dash = |text: &str| {
+        if text.is_empty() {
+            "-".to_string()
+        } else {
+            text.to_string()
+        }
+    };
+    let firmware = metadata.device.map_or_else(String::new, |device| {
+        let [major, minor, patch] = device.firmware_version;
+        format!(" | Firmware {major}.{minor}.{patch}")
+    });
+    let subtitle = format!(
+        "Started {} | Operator: {} | Sample: {} | Outcome: {}",
+        metadata.started.format("%Y-%m-%d %H:%M:%S"),
+        or_dash(&metadata.operator),
+        or_dash(&metadata.sample_id),
+        metadata
+            .outcome
+            .map_or_else(|| "Unknown".to_string(), |outcome| format!("{outcome:?}")),
+    ) + &firmware;
+    (title, subtitle)
+}
+
+/// Converts micros to seconds.
+fn seconds(micros: u64) -> f64 {
+    Duration::from_micros(micros).as_secs_f64()
+}
+
+/// Draws the setpoint and measured plate RPM, the error and the duty cycle of a run as SVG.
+///
+/// # Errors
+/// Returns an error if the plot can't be drawn.
+pub fn render_svg(
+    data_path: &Path,
+    samples: &[MotionProfileState],
+    metadata: Option<&RunMetadata>,
+) -> Result<String> {
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, (PLOT_WIDTH, PLOT_HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+        let (header, body) = root.split_vertically(HEADER_HEIGHT);
+        let (title, subtitle) = titles(data_path, metadata);
+        header.draw(&Text::new(title, (20, 12), (FONT, 26).into_font()))?;
+        header.draw(&Text::new(subtitle, (20, 44), (FONT, 16).into_font()))?;
+
+        let body_height = PLOT_HEIGHT - HEADER_HEIGHT;
+        let (rpm_area, rest) = body.split_vertically(body_height / 2);
+        let (error_area, duty_cycle_area) = rest.split_vertically(body_height / 4);
+
+        let profile = metadata.map(profile_points).unwrap_or_default();
+        let end = samples
+            .last()
+            .map(|state| seconds(state.time))
+            .into_iter()
+            .chain(profile.last().map(|(time, _)| *time))
+            .fold(0.5, f64::max);
+
+        draw_rpm(&rpm_area, samples, &profile, end)?;
+        draw_error(&error_area, samples, end)?;
+        draw_duty_cycle(&duty_cycle_area, samples, end)?;
+        root.present()?;
+    }
+    Ok(svg)
+}
+
+/// The (time in seconds, plate RPM) points of the motion profile a run followed, starting from 0 RPM.
+///
+/// Jogs don't follow the motion profile, so they have none.
+#[must_use]
+pub fn profile_points(metadata: &RunMetadata) -> Vec<(f64, f64)> {
+    if metadata.jog {
+        return Vec::new();
+    }
+    let mut profile = std::iter::once((0.0, 0.0))
+        .chain(metadata.profile.iter().map(|setpoint| {
+            (
+                seconds(setpoint.time),
+                f64::from(setpoint.rpm) * MOTOR_TO_PLATE_CONVERSION,
+            )
+        }))
+        .collect::<Vec<_>>();
+    profile.sort_by(|a, b| a.0.total_cmp(&b.0));
+    profile
+}
+
+/// Draws the profile, setpoint and measured plate RPM.
+fn draw_rpm(
+    area: &DrawingArea<SVGBackend<'_>, Shift>,
+    samples: &[MotionProfileState],
+    profile: &[(f64, f64)],
+    end: f64,
+) -> Result<()> {
+    let max_rpm = samples
+        .iter()
+        .flat_map(|state| [state.setpoint_plate_rpm, state.current_plate_rpm])
+        .chain(profile.iter().map(|(_, rpm)| *rpm))
+        .fold(0.0, f64::max);
+    // Leave 10% of headroom above the highest RPM.
+    let max_rpm = (max_rpm * 1.1).max(100.0);
+    let mut chart = ChartBuilder::on(area)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(70)
+        .build_cartesian_2d(0.0..end, 0.0..max_rpm)?;
+    chart
+        .configure_mesh()
+        .y_desc("Plate RPM")
+        .label_style((FONT, 14))
+        .draw()?;
+
+    let legend = |color: RGBColor| {
+        move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color.stroke_width(2))
+    };
+    if !profile.is_empty() {
+        chart
+            .draw_series(DashedLineSeries::new(
+                profile.iter().copied(),
+                8,
+                6,
+                PROFILE_COLOR.stroke_width(2),
+            ))?
+            .label("Profile")
+            .legend(legend(PROFILE_COLOR));
+    }
+    chart
+        .draw_series(LineSeries::new(
+            samples
+                .iter()
+                .map(|state| (seconds(state.time), state.setpoint_plate_rpm)),
+            SETPOINT_COLOR.stroke_width(2),
+        ))?
+        .label("Setpoint")
+        .legend(legend(SETPOINT_COLOR));
+    chart
+        .draw_series(LineSeries::new(
+            samples
+                .iter()
+                .map(|state| (seconds(state.time), state.current_plate_rpm)),
+            MEASURED_COLOR.stroke_width(2),
+        ))?
+        .label("Measured")
+        .legend(legend(MEASURED_COLOR));
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperRight)
+        .label_font((FONT, 14))
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    Ok(())
+}
+
+/// Draws the plate RPM error.
+fn draw_error(
+    area: &DrawingArea<SVGBackend<'_>, Shift>,
+    samples: &[MotionProfileState],
+    end: f64,
+) -> Result<()> {
+    let max_error = samples
+        .iter()
+        .map(|state| state.plate_rpm_error.abs())
+        .fold(0.0, f64::max);
+    let max_error = (max_error * 1.1).max(10.0);
+    let mut chart = ChartBuilder::on(area)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(70)
+        .build_cartesian_2d(0.0..end, -max_error..max_error)?;
+    chart
+        .configure_mesh()
+        .y_desc("Error (RPM)")
+        .label_style((FONT, 14))
+        .draw()?;
+    chart.draw_series(LineSeries::new(
+        samples
+            .iter()
+            .map(|state| (seconds(state.time), state.plate_rpm_error)),
+        ShapeStyle::from(ERROR_COLOR).stroke_width(2),
+    ))?;
+    Ok(())
+}
+
+/// Draws the duty cycle in percent.
+fn draw_duty_cycle(
+    area: &DrawingArea<SVGBackend<'_>, Shift>,
+    samples: &[MotionProfileState],
+    end: f64,
+) -> Result<()> {
+    let mut chart = ChartBuilder::on(area)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(70)
+        .build_cartesian_2d(0.0..end, STOP_DUTY_PERCENT..MAX_POWER_DUTY_PERCENT)?;
+    chart
+        .configure_mesh()
+        .x_desc("Time (s)")
+        .y_desc("Duty (%)")
+        .label_style((FONT, 14))
+        .draw()?;
+    chart.draw_series(LineSeries::new(
+        samples
+            .iter()
+            .map(|state| (seconds(state.time), f64::from(state.duty_cycle_f32) * 100.0)),
+        DUTY_CYCLE_COLOR.stroke_width(2),
+    ))?;
+    Ok(())
+}
+
+/// Makes sure the generic sans-serif family maps to an installed font.
+///
+/// The default is Arial, which many Linux systems don't have.
+fn sans_serif_fallback(fontdb: &mut Database) {
+    let has = |fontdb: &Database, family: Family<'_>| {
+        fontdb
+            .query(&Query {
+                families: &[family],
+                ..Query::default()
+            })
+            .is_some()
+    };
+    if has(fontdb, Family::SansSerif) {
+        return;
+    }
+    let fallback = FALLBACK_FONTS
+        .into_iter()
+        .find(|name| has(fontdb, Family::Name(name)))
+        .map(str::to_string)
+        .or_else(|| {
+            fontdb
+                .faces()
+                .next()
+                .and_then(|face| face.families.first())
+                .map(|(name, _)| name.clone())
+        });
+    if let Some(name) = fallback {
+        fontdb.set_sans_serif_family(name);
+    }
+}
+
+/// Rasterizes an SVG plot to PNG.
+///
+/// # Errors
+/// Returns an error if the SVG can't be parsed or the PNG can't be encoded.
+pub fn rasterize(svg: &str) -> Result<Vec<u8>> {
+    let mut options = Options::default();
+    let fontdb = options.fontdb_mut();
+    fontdb.load_system_fonts();
+    sans_serif_fallback(fontdb);
+    let tree = Tree::from_str(svg, &options)?;
+    let size = tree.size().to_int_size();
+    let mut pixmap =
+        Pixmap::new(size.width(), size.height()).ok_or_eyre("The plot has no area.")?;
+    resvg::render(&tree, Transform::default(), &mut pixmap.as_mut());
+    Ok(pixmap.encode_png()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use sc_messages::motion_profile::Setpoint;
+
+    use super::*;
+    use crate::app::{
+        metadata::{RunInfo, UploadedProfile},
+        test_utils::{TempFile, data_file, run},
+    };
+
+    #[test]
+    fn plots_are_saved_next_to_the_motor_data() {
+        let data_path = Path::new("logs/motor_data/2026-02-10_12-00-00.csv");
+        assert_eq!(
+            plot_path(data_path, PlotFormat::Svg),
+            Path::new("logs/motor_data/2026-02-10_12-00-00_plot.svg")
+        );
+        assert_eq!(
+            plot_path(data_path, PlotFormat::Png),
+            Path::new("logs/motor_data/2026-02-10_12-00-00_plot.png")
+        );
+    }
+
+    #[test]
+    fn export_writes_every_format() {
+        let data = data_file("plot_export", &run());
+        // The plots are removed along with the data.
+        let svg = TempFile::new("plot_export_plot", "svg");
+        let png = TempFile::new("plot_export_plot", "png");
+
+        let paths = export(data.path(), &PlotFormat::ALL).expect("Exporting failed");
+        assert_eq!(paths, [svg.path(), png.path()]);
+        let svg = fs::read_to_string(svg.path()).expect("The SVG should exist");
+        assert!(svg.contains("<svg"));
+        assert!(svg.contains("No metadata was saved with this run."));
+        let png = fs::read(png.path()).expect("The PNG should exist");
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn empty_runs_can_be_drawn() {
+        let svg = render_svg(Path::new("empty.csv"), &[], None).expect("Drawing failed");
+        assert!(svg.contains("<svg"));
+        assert!(!rasterize(&svg).expect("Rasterizing failed").is_empty());
+    }
+
+    #[test]
+    fn profiles_start_from_zero_unless_jogging() {
+        let mut profile = UploadedProfile::default();
+        profile.set(
+            &[Setpoint {
+                rpm: 2400,
+                time: 2_000_000,
+            }],
+            Vec::new(),
+        );
+        let metadata = |jog| {
+            RunMetadata::new(
+                Path::new("run.csv"),
+                &RunInfo::default(),
+                jog,
+                &profile,
+                None,
+            )
+        };
+        let points = profile_points(&metadata(false));
+        assert_eq!(points.len(), 2);
+        assert!(points[0].0.abs() < 1e-9 && points[0].1.abs() < 1e-9);
+        assert!((points[1].0 - 2.0).abs() < 1e-9 && (points[1].1 - 1000.0).abs() < 1e-9);
+        assert!(profile_points(&metadata(true)).is_empty());
+    }
+}
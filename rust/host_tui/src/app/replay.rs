@@ -0,0 +1,268 @@
+//! This module contains the replay of a recorded run or session through the TUI without the MCU.
+//!
+//! The samples of a motor data file are sent as [`MCUEvent::State`]s with the same timing as they were recorded,
+//! or faster, followed by the end of the run and its outcome if its metadata has one.
+//! A session journal is replayed the same way, with every message received from the MCU,
+//! and the requests sent and errors shown as logs.
+
+use std::path::{Path, PathBuf};
+
+use color_eyre::{Result, eyre::eyre};
+use tokio::{
+    sync::mpsc::UnboundedSender,
+    time::{Duration, Instant, sleep_until},
+};
+
+use crate::app::{
+    event::{MCUEvent, TuiEvent},
+    history::read_samples,
+    journal::{self, Journal},
+    metadata::{RunMetadata, metadata_path},
+    read_json,
+};
+
+/// The extension of session journals.
+const JOURNAL_EXTENSION: &str = "jsonl";
+
+/// A recorded run or session to replay.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    /// The motor data file or session journal.
+    path: PathBuf,
+    /// The events to send and when to send them, relative to the first one.
+    events: Vec<(Duration, MCUEvent)>,
+    /// The metadata of the run, if a motor data file with saved metadata is replayed.
+    metadata: Option<RunMetadata>,
+    /// How many times faster than real time the run is replayed.
+    speed: f64,
+}
+
+impl Replay {
+    /// Loads a motor data file and its metadata, or a session journal, to replay at `speed` times real time.
+    ///
+    /// # Errors
+    /// Returns an error if the speed isn't positive or the file can't be read.
+    pub fn load(path: PathBuf, speed: f64) -> Result<Self> {
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(eyre!("The replay speed must be a positive number."));
+        }
+        let is_journal = path
+            .extension()
+            .is_some_and(|extension| extension == JOURNAL_EXTENSION);
+        let (events, metadata) = if is_journal {
+            (journal_events(&path)?, None)
+        } else {
+            // The metadata is optional, e.g. for runs recorded before it was saved.
+            let metadata = read_json::<RunMetadata>(&metadata_path(&path))
+                .ok()
+                .flatten();
+            (run_events(&path, metadata.as_ref())?, metadata)
+        };
+        if events.is_empty() {
+            return Err(eyre!("{} has nothing to replay.", path.display()));
+        }
+        Ok(Self {
+            path,
+            events,
+            metadata,
+            speed,
+        })
+    }
+
+    /// The motor data file or session journal.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The metadata of the run, if a motor data file with saved metadata is replayed.
+    #[must_use]
+    pub fn metadata(&self) -> Option<&RunMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Sends the recorded events to the handler at the recorded times, scaled by the speed.
+    pub async fn run(self, to_handler: UnboundedSender<Result<TuiEvent>>) {
+        let send = |event: MCUEvent| to_handler.send(Ok(TuiEvent::MCU(event))).is_ok();
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if !send(MCUEvent::Log(format!(
+            "Replaying {name} at {}x speed.",
+            self.speed
+        ))) {
+            return;
+        }
+
+        let start = Instant::now();
+        for (elapsed, event) in self.events {
+            sleep_until(start + elapsed.div_f64(self.speed)).await;
+            // If the channel is closed, this task is done.
+            if !send(event) {
+                return;
+            }
+        }
+        send(MCUEvent::Log(format!("Finished replaying {name}.")));
+    }
+}
+
+/// The events of a run recorded in a motor data file.
+fn run_events(
+    data_path: &Path,
+    metadata: Option<&RunMetadata>,
+) -> Result<Vec<(Duration, MCUEvent)>> {
+    let samples = read_samples(data_path)?;
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return Ok(Vec::new());
+    };
+    let first_time = first.time;
+    let elapsed = |time: u64| Duration::from_micros(time.saturating_sub(first_time));
+    // The MCU sends the end of the run one loop period after the last sample.
+    let end = elapsed(last.time) + Duration::from_micros(u64::from(last.loop_period));
+
+    let mut events = vec![(
+        Duration::ZERO,
+        MCUEvent::DeviceInfo(metadata.and_then(|metadata| metadata.device)),
+    )];
+    events.extend(
+        samples
+            .into_iter()
+            .map(|state| (elapsed(state.time), MCUEvent::State(Some(state)))),
+    );
+    events.push((end, MCUEvent::State(None)));
+    if let Some(outcome) = metadata.and_then(|metadata| metadata.outcome) {
+        events.push((end, MCUEvent::Outcome(outcome)));
+    }
+    Ok(events)
+}
+
+/// The events of a session recorded in a journal.
+fn journal_events(path: &Path) -> Result<Vec<(Duration, MCUEvent)>> {
+    let entries = Journal::read(path)?;
+    let Some(first_time) = entries.first().map(|entry| entry.time) else {
+        return Ok(Vec::new());
+    };
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let elapsed = (entry.time - first_time).to_std().unwrap_or_default();
+            let event = match entry.event {
+                journal::Event::Received(event) => event,
+                journal::Event::Sent(request) => MCUEvent::Log(format!("Sent {request:?}")),
+                journal::Event::Error(error) => MCUEvent::Log(format!("Error: {error}")),
+            };
+            (elapsed, event)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use sc_messages::{motion_profile::Outcome, pwm::DutyCycle};
+
+    use super::*;
+    use crate::app::{
+        metadata::{RunInfo, UploadedProfile},
+        state::MotionProfileState,
+        test_utils::data_file,
+    };
+
+    /// A state at `time` micros since the MCU started the run.
+    fn state(time: u64, current_rpm: u16, sample: u32) -> MotionProfileState {
+        MotionProfileState::from(sc_messages::motion_profile::State {
+            setpoint_rpm: 1000,
+            current_rpm,
+            rpm_error: 1000_i16.saturating_sub_unsigned(current_rpm),
+            duty_cycle: DutyCycle::new(0),
+            time,
+            loop_period: 10_000,
+            execution_time: 100,
+            overruns: 0,
+            sample,
+        })
+    }
+
+    #[test]
+    fn samples_become_states_at_their_recorded_times() {
+        let states = [
+            state(50_000, 0, 0),
+            state(60_000, 400, 1),
+            state(75_000, 900, 2),
+        ];
+        let file = data_file("replay_states", &states);
+        let events = run_events(file.path(), None).expect("Failed to read the motor data file");
+
+        assert!(matches!(
+            events[0],
+            (Duration::ZERO, MCUEvent::DeviceInfo(None))
+        ));
+        let replayed = events[1..=states.len()]
+            .iter()
+            .map(|(elapsed, event)| match event {
+                MCUEvent::State(Some(state)) => (*elapsed, state),
+                _ => panic!("Expected a state, got {event:?}"),
+            })
+            .collect::<Vec<_>>();
+        let elapsed = replayed
+            .iter()
+            .map(|(elapsed, _)| *elapsed)
+            .collect::<Vec<_>>();
+        // The replay starts at the first sample.
+        assert_eq!(
+            elapsed,
+            [
+                Duration::ZERO,
+                Duration::from_millis(10),
+                Duration::from_millis(25)
+            ]
+        );
+        for ((_, replayed), recorded) in replayed.iter().zip(&states) {
+            assert_eq!(replayed.time, recorded.time);
+            assert_eq!(replayed.current_rpm, recorded.current_rpm);
+            assert_eq!(replayed.rpm_error, recorded.rpm_error);
+            assert_eq!(replayed.sample, recorded.sample);
+            assert!((replayed.current_plate_rpm - recorded.current_plate_rpm).abs() < 1e-9);
+        }
+
+        // The run ends one loop period after the last sample, without an outcome.
+        assert!(matches!(
+            events[states.len() + 1..],
+            [(end, MCUEvent::State(None))] if end == Duration::from_millis(35)
+        ));
+    }
+
+    #[test]
+    fn the_outcome_comes_from_the_metadata() {
+        let file = data_file("replay_outcome", &[state(0, 0, 0), state(10_000, 500, 1)]);
+        let mut metadata = RunMetadata::new(
+            file.path(),
+            &RunInfo::default(),
+            false,
+            &UploadedProfile::default(),
+            None,
+        );
+        metadata.outcome = Some(Outcome::Stopped);
+        let events =
+            run_events(file.path(), Some(&metadata)).expect("Failed to read the motor data file");
+        assert!(matches!(
+            events[events.len() - 2..],
+            [
+                (end, MCUEvent::State(None)),
+                (outcome_time, MCUEvent::Outcome(Outcome::Stopped)),
+            ] if end == Duration::from_millis(20) && outcome_time == end
+        ));
+    }
+
+    #[test]
+    fn empty_runs_have_nothing_to_replay() {
+        let file = data_file("replay_empty", &[]);
+        assert!(
+            run_events(file.path(), None)
+                .expect("Failed to read the motor data file")
+                .is_empty()
+        );
+        assert!(Replay::load(file.path().to_path_buf(), 1.0).is_err());
+    }
+}
@@ -0,0 +1,581 @@
+//! This module contains the summary of how well a run followed its setpoints.
+//!
+//! The report is computed from the recorded samples alone, so it works for runs whose profile isn't known.
+//! Every RPM is a plate RPM and every time is in seconds since the run started.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::BufWriter,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use color_eyre::Result;
+use ratatui::{
+    crossterm::event::KeyCode,
+    layout::{Constraint, HorizontalAlignment},
+    prelude::{Frame, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, BorderType, Clear, Paragraph, Wrap},
+};
+use sc_messages::{
+    motion_profile::Outcome,
+    pwm::{HALF_POWER_DUTY, STOP_DUTY},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::app::{
+    metadata::{RunMetadata, metadata_path},
+    read_json, sidecar_path,
+    state::MotionProfileState,
+};
+
+/// How far (in percent of the setpoint) the measured RPM may be from the setpoint to be within tolerance.
+pub const TOLERANCE_PERCENT: f64 = 2.0;
+
+/// The narrowest tolerance band (in plate RPM), so low setpoints aren't judged too strictly.
+pub const MIN_TOLERANCE_RPM: f64 = 20.0;
+
+/// The smallest setpoint change (in plate RPM) that is analyzed as a step.
+pub const MIN_STEP_RPM: f64 = 50.0;
+
+/// Constant setpoints shorter than this (in seconds) are treated as part of the surrounding ramp.
+///
+/// Slow ramps repeat the same setpoint for several iterations because setpoints are whole motor RPMs.
+const MIN_HOLD_TIME: f64 = 0.2;
+
+/// How far the measured RPM may be from `setpoint` to be within tolerance.
+#[must_use]
+pub fn tolerance(setpoint: f64) -> f64 {
+    (setpoint.abs() * TOLERANCE_PERCENT / 100.0).max(MIN_TOLERANCE_RPM)
+}
+
+/// Where the report of a motor data file is saved.
+#[must_use]
+pub fn report_path(data_path: &Path) -> PathBuf {
+    sidecar_path(data_path, "report")
+}
+
+/// Converts micros to seconds.
+fn seconds(micros: u64) -> f64 {
+    Duration::from_micros(micros).as_secs_f64()
+}
+
+/// Whether the setpoint is constant or changing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentKind {
+    /// The setpoint stays the same.
+    Hold,
+    /// The setpoint changes every iteration, or jumps.
+    Ramp,
+}
+
+/// The tracking error of a part of the run with a constant or changing setpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Segment {
+    /// Whether the setpoint is constant or changing.
+    pub kind: SegmentKind,
+    /// The time of the first sample.
+    pub start: f64,
+    /// The time of the last sample.
+    pub end: f64,
+    /// The setpoint of the first sample.
+    pub start_setpoint: f64,
+    /// The setpoint of the last sample.
+    pub end_setpoint: f64,
+    /// The root mean square of the RPM error.
+    pub rms_error: f64,
+    /// The largest absolute RPM error.
+    pub peak_error: f64,
+}
+
+/// The response to a change from one constant setpoint to another.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    /// When the setpoint started changing.
+    pub time: f64,
+    /// The setpoint before the step.
+    pub from: f64,
+    /// The setpoint after the step.
+    pub to: f64,
+    /// How far the measured RPM went past the new setpoint, in percent of the step.
+    pub overshoot_percent: f64,
+    /// The time from 10% to 90% of the step, or [`None`] if 90% was never reached.
+    pub rise_time: Option<f64>,
+    /// The time from the start of the step until the measured RPM stayed within tolerance,
+    /// or [`None`] if it never did before the setpoint changed again.
+    pub settling_time: Option<f64>,
+}
+
+/// The summary of a run's control performance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    /// The number of samples received.
+    pub samples: usize,
+    /// The number of samples the host missed, which aren't part of the rest of the report.
+    #[serde(default)]
+    pub dropped_samples: u64,
+    /// The time of the last sample.
+    pub duration: f64,
+    /// The parts of the run with a constant or changing setpoint, in order.
+    pub segments: Vec<Segment>,
+    /// The changes between constant setpoints, in order.
+    pub steps: Vec<Step>,
+    /// The percentage of samples within [`tolerance`] of their setpoint.
+    pub within_tolerance_percent: f64,
+    /// The percentage of samples whose duty cycle was clamped to the motion profile's limits.
+    pub duty_saturation_percent: f64,
+    /// The last sample of the run.
+    pub final_state: Option<MotionProfileState>,
+    /// How the run ended, if the MCU reported it.
+    pub outcome: Option<Outcome>,
+    /// What went wrong during the run.
+    pub faults: Vec<String>,
+}
+
+impl RunReport {
+    /// Summarizes the samples of a run.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(samples: &[MotionProfileState], outcome: Option<Outcome>) -> Self {
+        let percentage = |count: usize| {
+            if samples.is_empty() {
+                0.0
+            } else {
+                count as f64 / samples.len() as f64 * 100.0
+            }
+        };
+        let within_tolerance = samples
+            .iter()
+            .filter(|state| state.plate_rpm_error.abs() <= tolerance(state.setpoint_plate_rpm))
+            .count();
+        let saturated = samples
+            .iter()
+            .filter(|state| *state.duty_cycle <= STOP_DUTY || *state.duty_cycle >= HALF_POWER_DUTY)
+            .count();
+
+        let ranges = split(samples);
+        let mut report = Self {
+            samples: samples.len(),
+            dropped_samples: samples.iter().map(|state| u64::from(state.dropped)).sum(),
+            duration: samples.last().map_or(0.0, |state| seconds(state.time)),
+            segments: ranges
+                .iter()
+                .map(|(kind, range)| segment(*kind, &samples[range.clone()]))
+                .collect(),
+            steps: steps(samples, &ranges),
+            within_tolerance_percent: percentage(within_tolerance),
+            duty_saturation_percent: percentage(saturated),
+            final_state: samples.last().cloned(),
+            outcome: None,
+            faults: Vec::new(),
+        };
+        report.set_outcome(outcome);
+        report
+    }
+
+    /// Reads the saved report of a motor data file, or summarizes its samples if it has none.
+    ///
+    /// The outcome is taken from the run's metadata file if the report has to be summarized.
+    #[must_use]
+    pub fn load(data_path: &Path, samples: &[MotionProfileState]) -> Self {
+        read_json(&report_path(data_path))
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| {
+                let outcome = read_json::<RunMetadata>(&metadata_path(data_path))
+                    .ok()
+                    .flatten()
+                    .and_then(|metadata| metadata.outcome);
+                Self::new(samples, outcome)
+            })
+    }
+
+    /// Records how the run ended, which may arrive after the last sample.
+    pub fn set_outcome(&mut self, outcome: Option<Outcome>) {
+        self.outcome = outcome;
+        self.faults.clear();
+        match outcome {
+            Some(Outcome::Fault) => self.faults.push(
+                "The MCU couldn't calculate the setpoint RPM, so it stopped the motor.".to_string(),
+            ),
+            Some(Outcome::TimedOut) => self
+                .faults
+                .push("No jog duty cycle was received within the jog timeout.".to_string()),
+            Some(Outcome::HostDisconnected) => self
+                .faults
+                .push("The host PC disconnected during the run.".to_string()),
+            Some(Outcome::Completed | Outcome::Stopped) => {}
+            None => self
+                .faults
+                .push("The MCU didn't report how the run ended.".to_string()),
+        }
+        let overruns = self.final_state.as_ref().map_or(0, |state| state.overruns);
+        if overruns > 0 {
+            self.faults.push(format!(
+                "{overruns} control loop iterations overran the target period."
+            ));
+        }
+        if self.dropped_samples > 0 {
+            self.faults.push(format!(
+                "The host missed {} samples, so the data has gaps.",
+                self.dropped_samples
+            ));
+        }
+    }
+
+    /// Writes the report as JSON.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+
+    /// Renders the report as a popup.
+    pub fn render(&self, area: Rect, frame: &mut Frame) {
+        let instructions = Line::from_iter([" Close: ".into(), "<Esc>,<Enter> ".blue().bold()]);
+        let block = Block::bordered()
+            .title(" Run Report ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded)
+            .title_bottom(instructions);
+
+        let popup_area = area.centered(Constraint::Percentage(80), Constraint::Percentage(80));
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(
+            Paragraph::new(self.to_string())
+                .wrap(Wrap { trim: false })
+                .block(block),
+            popup_area,
+        );
+    }
+
+    /// Whether a key press closes the popup.
+    #[must_use]
+    pub fn closes_popup(code: KeyCode) -> bool {
+        matches!(code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q'))
+    }
+}
+
+impl Display for RunReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let outcome = self
+            .outcome
+            .map_or_else(|| "Unknown".to_string(), |outcome| format!("{outcome:?}"));
+        writeln!(
+            f,
+            "Outcome: {outcome} | Duration (s): {:.2} | Samples: {} | Dropped samples: {}",
+            self.duration, self.samples, self.dropped_samples
+        )?;
+        writeln!(
+            f,
+            "Within tolerance (±{TOLERANCE_PERCENT}%, at least {MIN_TOLERANCE_RPM} RPM): {:.1}% | Duty saturation: {:.1}%",
+            self.within_tolerance_percent, self.duty_saturation_percent
+        )?;
+        if let Some(state) = &self.final_state {
+            writeln!(
+                f,
+                "Final state: setpoint {:.0} RPM | measured {:.0} RPM | {} overruns",
+                state.setpoint_plate_rpm, state.current_plate_rpm, state.overruns
+            )?;
+        }
+        if self.faults.is_empty() {
+            writeln!(f, "Faults: None")?;
+        } else {
+            writeln!(f, "Faults:")?;
+            for fault in &self.faults {
+                writeln!(f, "  {fault}")?;
+            }
+        }
+
+        writeln!(f, "Segments:")?;
+        for segment in &self.segments {
+            writeln!(
+                f,
+                "  {:.2}-{:.2} s {:?} {:.0} -> {:.0} RPM: RMS error {:.1} | peak error {:.1}",
+                segment.start,
+                segment.end,
+                segment.kind,
+                segment.start_setpoint,
+                segment.end_setpoint,
+                segment.rms_error,
+                segment.peak_error
+            )?;
+        }
+
+        if !self.steps.is_empty() {
+            writeln!(f, "Steps:")?;
+        }
+        let optional =
+            |time: Option<f64>| time.map_or_else(|| "-".to_string(), |time| format!("{time:.2}"));
+        for step in &self.steps {
+            writeln!(
+                f,
+                "  {:.0} -> {:.0} RPM at {:.2} s: rise (s) {} | overshoot {:.1}% | settling (s) {}",
+                step.from,
+                step.to,
+                step.time,
+                optional(step.rise_time),
+                step.overshoot_percent,
+                optional(step.settling_time)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits the samples into index ranges with a constant or changing setpoint.
+fn split(samples: &[MotionProfileState]) -> Vec<(SegmentKind, Range<usize>)> {
+    // First, group the samples by whether their setpoint changed.
+    let mut groups: Vec<(SegmentKind, Range<usize>)> = Vec::new();
+    // The MCU always starts at 0 RPM.
+    let mut previous_setpoint = 0;
+    for (i, state) in samples.iter().enumerate() {
+        let kind = if state.setpoint_rpm == previous_setpoint {
+            SegmentKind::Hold
+        } else {
+            SegmentKind::Ramp
+        };
+        previous_setpoint = state.setpoint_rpm;
+        match groups.last_mut() {
+            Some((last_kind, range)) if *last_kind == kind => range.end = i + 1,
+            _ => groups.push((kind, i..i + 1)),
+        }
+    }
+
+    // Then, merge short holds into the ramps around them.
+    let mut ranges: Vec<(SegmentKind, Range<usize>)> = Vec::new();
+    for (kind, range) in groups {
+        let held = seconds(samples[range.end - 1].time) - seconds(samples[range.start].time);
+        let merge = kind == SegmentKind::Ramp || held < MIN_HOLD_TIME;
+        match ranges.last_mut() {
+            Some((SegmentKind::Ramp, last)) if merge => last.end = range.end,
+            _ => ranges.push((kind, range)),
+        }
+    }
+    ranges
+}
+
+/// Summarizes the tracking error of a segment.
+#[allow(clippy::cast_precision_loss)]
+fn segment(kind: SegmentKind, samples: &[MotionProfileState]) -> Segment {
+    let squared_error: f64 = samples
+        .iter()
+        .map(|state| state.plate_rpm_error.powi(2))
+        .sum();
+    Segment {
+        kind,
+        start: samples.first().map_or(0.0, |state| seconds(state.time)),
+        end: samples.last().map_or(0.0, |state| seconds(state.time)),
+        start_setpoint: samples
+            .first()
+            .map_or(0.0, |state| state.setpoint_plate_rpm),
+        end_setpoint: samples.last().map_or(0.0, |state| state.setpoint_plate_rpm),
+        rms_error: (squared_error / samples.len().max(1) as f64).sqrt(),
+        peak_error: samples
+            .iter()
+            .map(|state| state.plate_rpm_error.abs())
+            .fold(0.0, f64::max),
+    }
+}
+
+/// Analyzes every ramp or jump followed by a hold as a step.
+fn steps(samples: &[MotionProfileState], ranges: &[(SegmentKind, Range<usize>)]) -> Vec<Step> {
+    ranges
+        .windows(2)
+        .filter_map(|pair| match pair {
+            [(SegmentKind::Ramp, ramp), (SegmentKind::Hold, hold)] => {
+                step(samples, ramp.start, hold.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Analyzes the step that starts changing after sample `start` and holds for `hold`.
+fn step(samples: &[MotionProfileState], start: usize, hold: Range<usize>) -> Option<Step> {
+    // The setpoint and time before the first changed setpoint.
+    let (from, time) = match start.checked_sub(1).and_then(|i| samples.get(i)) {
+        Some(state) => (state.setpoint_plate_rpm, seconds(state.time)),
+        None => (0.0, 0.0),
+    };
+    let to = samples.get(hold.start)?.setpoint_plate_rpm;
+    if (to - from).abs() < MIN_STEP_RPM {
+        return None;
+    }
+    let window = samples.get(start..hold.end)?;
+    // How far along the step the measured RPM is, from 0.0 to 1.0.
+    let progress = |state: &MotionProfileState| (state.current_plate_rpm - from) / (to - from);
+
+    let rise_start = window.iter().position(|state| progress(state) >= 0.1);
+    let rise_time = rise_start.and_then(|rise_start| {
+        let rise_end = window[rise_start..]
+            .iter()
+            .find(|state| progress(state) >= 0.9)?;
+        Some(seconds(rise_end.time) - seconds(window[rise_start].time))
+    });
+
+    let peak = samples[hold]
+        .iter()
+        .map(progress)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let band = tolerance(to);
+    let settling_time = match window
+        .iter()
+        .rposition(|state| (state.current_plate_rpm - to).abs() > band)
+    {
+        None => Some(0.0),
+        Some(last_outside) => window
+            .get(last_outside + 1)
+            .map(|settled| seconds(settled.time) - time),
+    };
+
+    Some(Step {
+        time,
+        from,
+        to,
+        overshoot_percent: ((peak - 1.0) * 100.0).max(0.0),
+        rise_time,
+        settling_time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use sc_messages::pwm::DutyCycle;
+
+    use super::*;
+
+    /// The time between samples in micros.
+    const PERIOD: u64 = 10_000;
+
+    /// Checks that two floats are equal up to rounding.
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{actual} is not close to {expected}"
+        );
+    }
+
+    /// A sample with a plate RPM setpoint and measurement.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn sample(index: u64, setpoint: f64, current: f64) -> MotionProfileState {
+        MotionProfileState {
+            // Only used to tell whether the setpoint changed.
+            setpoint_rpm: setpoint as u16,
+            setpoint_plate_rpm: setpoint,
+            current_rpm: current as u16,
+            current_plate_rpm: current,
+            rpm_error: 0,
+            plate_rpm_error: setpoint - current,
+            duty_cycle: DutyCycle::new(STOP_DUTY + 100),
+            duty_cycle_f32: 0.0,
+            time: index * PERIOD,
+            loop_period: 0,
+            execution_time: 0,
+            overruns: 0,
+            sample: 0,
+            dropped: 0,
+        }
+    }
+
+    /// 0.1 s at 0 plate RPM followed by a step to 1000 plate RPM held for about a second,
+    /// which overshoots by 10% and settles at sample 17.
+    fn step_run() -> Vec<MotionProfileState> {
+        let response = [0.0, 250.0, 500.0, 750.0, 900.0, 1100.0, 1050.0, 1010.0];
+        let mut samples: Vec<_> = (0..10).map(|i| sample(i, 0.0, 0.0)).collect();
+        for i in 10..110 {
+            let current = response.get(i - 10).copied().unwrap_or(1000.0);
+            samples.push(sample(i as u64, 1000.0, current));
+        }
+        samples
+    }
+
+    #[test]
+    fn tolerance_has_a_minimum() {
+        assert_close(tolerance(100.0), MIN_TOLERANCE_RPM);
+        assert_close(tolerance(5000.0), 100.0);
+        assert_close(tolerance(-5000.0), 100.0);
+    }
+
+    #[test]
+    fn step_segments() {
+        let report = RunReport::new(&step_run(), Some(Outcome::Completed));
+        let kinds: Vec<_> = report.segments.iter().map(|segment| segment.kind).collect();
+        assert_eq!(
+            kinds,
+            [SegmentKind::Hold, SegmentKind::Ramp, SegmentKind::Hold]
+        );
+
+        let [before, jump, hold] = &report.segments[..] else {
+            panic!("There should be three segments");
+        };
+        assert_close(before.rms_error, 0.0);
+        assert_close(jump.rms_error, 1000.0);
+        assert_close(jump.peak_error, 1000.0);
+        let squared: f64 = [750.0, 500.0, 250.0, 100.0, 100.0, 50.0, 10.0]
+            .iter()
+            .map(|error: &f64| error.powi(2))
+            .sum();
+        assert_close(hold.rms_error, (squared / 99.0).sqrt());
+        assert_close(hold.peak_error, 750.0);
+    }
+
+    #[test]
+    fn step_response() {
+        let report = RunReport::new(&step_run(), Some(Outcome::Completed));
+        let [step] = &report.steps[..] else {
+            panic!("There should be one step");
+        };
+        assert_close(step.time, 0.09);
+        assert_close(step.from, 0.0);
+        assert_close(step.to, 1000.0);
+        assert_close(step.overshoot_percent, 10.0);
+        // From 25% at sample 11 to 90% at sample 14.
+        assert_close(step.rise_time.unwrap_or_default(), 0.03);
+        // The last sample outside the 20 RPM band is sample 16.
+        assert_close(step.settling_time.unwrap_or_default(), 0.08);
+    }
+
+    #[test]
+    fn run_metrics() {
+        let report = RunReport::new(&step_run(), Some(Outcome::Completed));
+        assert_eq!(report.samples, 110);
+        assert_close(report.duration, 1.09);
+        // The 10 samples at 0 RPM and the 93 from sample 17 on.
+        assert_close(report.within_tolerance_percent, 103.0 / 110.0 * 100.0);
+        assert_close(report.duty_saturation_percent, 0.0);
+        assert!(report.faults.is_empty());
+    }
+
+    #[test]
+    fn small_steps_are_ignored() {
+        let mut samples: Vec<_> = (0..50).map(|i| sample(i, 1000.0, 1000.0)).collect();
+        samples.extend((50..100).map(|i| sample(i, 1000.0 + MIN_STEP_RPM / 2.0, 1000.0)));
+        let report = RunReport::new(&samples, Some(Outcome::Completed));
+        assert_eq!(report.steps.len(), 1);
+        assert_close(report.steps[0].to, 1000.0);
+    }
+
+    #[test]
+    fn faults() {
+        let mut samples = step_run();
+        samples[20].dropped = 3;
+        let mut report = RunReport::new(&samples, None);
+        assert_eq!(report.dropped_samples, 3);
+        assert_eq!(report.faults.len(), 2);
+
+        report.set_outcome(Some(Outcome::Fault));
+        assert_eq!(report.outcome, Some(Outcome::Fault));
+        assert_eq!(report.faults.len(), 2);
+        report.set_outcome(Some(Outcome::Stopped));
+        assert_eq!(report.faults.len(), 1);
+    }
+}
@@ -0,0 +1,147 @@
+//! This module contains the form for starting a constant plate RPM run.
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, HorizontalAlignment},
+    prelude::{Frame, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, BorderType, Clear, Paragraph},
+};
+use sc_messages::motion_profile::RunAt;
+
+/// The plate RPM the form starts with.
+const DEFAULT_RPM: &str = "5000";
+
+/// The time (in seconds) the form starts with.
+const DEFAULT_TIME: &str = "10";
+
+/// The fields of the form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// The plate RPM.
+    Rpm,
+    /// The time in seconds.
+    Time,
+}
+
+/// What the app should do after a key press in the form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormAction {
+    /// Keep showing the form.
+    Continue,
+    /// Close the form without running.
+    Cancel,
+    /// Close the form and start the run.
+    Submit(RunAt),
+}
+
+/// A form for entering a plate RPM and a duration.
+#[derive(Debug, Clone)]
+pub struct RunAtForm {
+    /// The plate RPM as typed.
+    rpm: String,
+    /// The time in seconds as typed.
+    time: String,
+    /// The field being edited.
+    field: Field,
+    /// Why the previous submission was rejected.
+    error: Option<&'static str>,
+}
+
+impl Default for RunAtForm {
+    fn default() -> Self {
+        Self {
+            rpm: DEFAULT_RPM.to_string(),
+            time: DEFAULT_TIME.to_string(),
+            field: Field::Rpm,
+            error: None,
+        }
+    }
+}
+
+impl RunAtForm {
+    /// Handles a key press.
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> FormAction {
+        match key_event.code {
+            KeyCode::Esc => return FormAction::Cancel,
+            KeyCode::Enter => match self.run_at() {
+                Ok(run_at) => return FormAction::Submit(run_at),
+                Err(error) => self.error = Some(error),
+            },
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                self.field = match self.field {
+                    Field::Rpm => Field::Time,
+                    Field::Time => Field::Rpm,
+                };
+            }
+            KeyCode::Backspace => {
+                self.input().pop();
+            }
+            KeyCode::Char(digit) if digit.is_ascii_digit() => self.input().push(digit),
+            _ => {}
+        }
+        FormAction::Continue
+    }
+
+    /// The text of the field being edited.
+    fn input(&mut self) -> &mut String {
+        match self.field {
+            Field::Rpm => &mut self.rpm,
+            Field::Time => &mut self.time,
+        }
+    }
+
+    /// Parses the form.
+    ///
+    /// # Errors
+    /// Returns a description of the problem if a field is empty, zero, or too large.
+    fn run_at(&self) -> Result<RunAt, &'static str> {
+        let rpm = match self.rpm.parse::<u16>() {
+            Ok(0) | Err(_) => return Err("Plate RPM must be between 1 and 65535."),
+            Ok(rpm) => rpm,
+        };
+        let time = match self.time.parse::<u16>() {
+            Ok(0) | Err(_) => return Err("Time must be between 1 and 65535 seconds."),
+            Ok(time) => time,
+        };
+        Ok(RunAt::new(rpm, time))
+    }
+
+    /// Renders the form as a popup in the middle of the area.
+    pub fn render(&self, area: Rect, frame: &mut Frame) {
+        let instructions = Line::from_iter([
+            " Switch field: ".into(),
+            "<Tab>".blue().bold(),
+            " Start: ".into(),
+            "<Enter>".blue().bold(),
+            " Cancel: ".into(),
+            "<Esc> ".blue().bold(),
+        ]);
+        let block = Block::bordered()
+            .title(" Run at Constant Plate RPM ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded)
+            .title_bottom(instructions);
+
+        let field_line = |name: &'static str, value: &str, field: Field| {
+            let line = Line::from_iter([name.into(), value.to_string().bold()]);
+            if self.field == field {
+                line.style(Style::new().blue())
+            } else {
+                line
+            }
+        };
+        let mut lines = vec![
+            field_line("Plate RPM: ", &self.rpm, Field::Rpm),
+            field_line("Time (s): ", &self.time, Field::Time),
+        ];
+        if let Some(error) = self.error {
+            lines.push(Line::raw(error).style(Style::new().fg(Color::Red)));
+        }
+
+        let popup_area = area.centered(Constraint::Length(50), Constraint::Length(6));
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(Paragraph::new(Text::from(lines)).block(block), popup_area);
+    }
+}
@@ -0,0 +1,73 @@
+//! This module contains helpers shared by the tests.
+
+use std::path::{Path, PathBuf};
+
+use sc_messages::{
+    motion_profile,
+    pwm::{DutyCycle, STOP_DUTY},
+};
+
+use crate::app::state::MotionProfileState;
+
+/// A file in the temporary folder, removed when dropped.
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    /// A path in the temporary folder that is unique to this test run.
+    ///
+    /// `name` must be unique among the tests.
+    pub fn new(name: &str, extension: &str) -> Self {
+        Self(std::env::temp_dir().join(format!(
+            "host_tui_{}_{name}.{extension}",
+            std::process::id()
+        )))
+    }
+
+    /// The path of the file.
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        // The test may not have created it.
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Writes the states of a run to a motor data file like the TUI does.
+///
+/// `name` must be unique among the tests.
+pub fn data_file(name: &str, states: &[MotionProfileState]) -> TempFile {
+    let file = TempFile::new(name, "csv");
+    let mut writer = csv::Writer::from_path(file.path()).expect("Failed to create the file");
+    for state in states {
+        writer.serialize(state).expect("Failed to write a state");
+    }
+    writer.flush().expect("Failed to write the file");
+    file
+}
+
+/// A state `time` micros into a run, with a setpoint and measured motor RPM.
+pub fn state(time: u64, setpoint_rpm: u16, current_rpm: u16) -> MotionProfileState {
+    MotionProfileState::from(motion_profile::State {
+        setpoint_rpm,
+        current_rpm,
+        rpm_error: i16::try_from(i32::from(setpoint_rpm) - i32::from(current_rpm))
+            .unwrap_or_default(),
+        duty_cycle: DutyCycle::new(STOP_DUTY + 400),
+        time,
+        loop_period: 1_000,
+        execution_time: 100,
+        overruns: 0,
+        sample: 0,
+    })
+}
+
+/// The states of a short run that steps up to 1000 plate RPM.
+pub fn run() -> Vec<MotionProfileState> {
+    (0..50)
+        .map(|i| state(i * 100_000, 2400, u16::try_from(i * 48).unwrap_or(2400)))
+        .collect()
+}
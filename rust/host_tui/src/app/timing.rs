@@ -0,0 +1,132 @@
+//! This module contains functionality for judging the MCU's control loop timing.
+
+use std::time::Duration;
+
+use ratatui::{
+    prelude::{Frame, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, Paragraph},
+};
+use sc_messages::diagnostics::{LoopTiming, Statistics};
+
+use crate::app::state::MotionProfileState;
+
+/// How far (in percent) the 99th percentile loop period may exceed the target period
+/// before the timing is considered jittery.
+const JITTER_TOLERANCE_PERCENT: u32 = 10;
+
+/// How healthy the control loop timing is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    /// No data has been received yet.
+    Unknown,
+    /// The loop runs at its target period.
+    Good,
+    /// The loop never overran, but its period varies more than [`JITTER_TOLERANCE_PERCENT`].
+    Jittery,
+    /// At least one iteration took longer than the target period to execute.
+    Overrunning,
+}
+
+impl Health {
+    /// Judges the timing statistics of a run.
+    #[must_use]
+    pub fn from_loop_timing(loop_timing: &LoopTiming) -> Self {
+        if loop_timing.iterations == 0 {
+            Self::Unknown
+        } else if loop_timing.overruns > 0 {
+            Self::Overrunning
+        } else if u64::from(loop_timing.period.p99) * 100
+            > u64::from(loop_timing.target_period) * u64::from(100 + JITTER_TOLERANCE_PERCENT)
+        {
+            Self::Jittery
+        } else {
+            Self::Good
+        }
+    }
+
+    /// The color used to display this health.
+    #[must_use]
+    pub fn color(self) -> Color {
+        match self {
+            Self::Unknown => Color::Gray,
+            Self::Good => Color::Green,
+            Self::Jittery => Color::Yellow,
+            Self::Overrunning => Color::Red,
+        }
+    }
+
+    /// A short description of this health.
+    #[must_use]
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Unknown => "Unknown",
+            Self::Good => "Good",
+            Self::Jittery => "Jittery",
+            Self::Overrunning => "Overrunning",
+        }
+    }
+}
+
+/// Converts micros to millis for display.
+fn millis(micros: u32) -> f64 {
+    Duration::from_micros(u64::from(micros)).as_secs_f64() * 1000.0
+}
+
+/// Formats [`Statistics`] as a single line in millis.
+fn statistics_line(name: &str, statistics: &Statistics) -> Line<'static> {
+    Line::raw(format!(
+        "{name} (ms): min {:.2} | mean {:.2} | p50 {:.2} | p95 {:.2} | p99 {:.2} | max {:.2}",
+        millis(statistics.min),
+        millis(statistics.mean),
+        millis(statistics.p50),
+        millis(statistics.p95),
+        millis(statistics.p99),
+        millis(statistics.max),
+    ))
+}
+
+/// Renders the timing health of the latest sample and the latest statistics.
+pub fn render(
+    state: Option<&MotionProfileState>,
+    loop_timing: Option<&LoopTiming>,
+    block: Block<'_>,
+    area: Rect,
+    frame: &mut Frame,
+) {
+    let mut lines = Vec::new();
+    // Overruns in the current run take priority over the statistics of the previous run.
+    let health = match (state, loop_timing) {
+        (Some(state), _) if state.overruns > 0 => Health::Overrunning,
+        (_, Some(loop_timing)) => Health::from_loop_timing(loop_timing),
+        (Some(_), None) => Health::Good,
+        (None, None) => Health::Unknown,
+    };
+    lines.push(Line::from_iter([
+        "Health: ".into(),
+        health.description().bold().fg(health.color()),
+    ]));
+    if let Some(state) = state {
+        lines.push(Line::raw(format!(
+            "Latest period (ms): {:.2} | Execution (ms): {:.2} | Overruns: {}",
+            millis(state.loop_period),
+            millis(state.execution_time),
+            state.overruns
+        )));
+    }
+    if let Some(loop_timing) = loop_timing {
+        lines.push(Line::raw(format!(
+            "Last statistics: {} iterations | {} overruns | target period (ms): {:.2}",
+            loop_timing.iterations,
+            loop_timing.overruns,
+            millis(loop_timing.target_period)
+        )));
+        lines.push(statistics_line("Period", &loop_timing.period));
+        lines.push(statistics_line("Execution", &loop_timing.execution));
+    } else {
+        lines.push(Line::raw("No timing statistics requested yet.").style(Style::new().italic()));
+    }
+    let paragraph = Paragraph::new(Text::from(lines)).block(block);
+    frame.render_widget(paragraph, area);
+}
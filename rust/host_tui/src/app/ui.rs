@@ -5,11 +5,11 @@ use ratatui::{
     layout::{Constraint, HorizontalAlignment, Layout, Rect},
     style::{Style, Stylize},
     text::{Line, Text},
-    widgets::{Block, BorderType, List, ListItem, ListState, Paragraph},
+    widgets::{Block, BorderType, List, ListItem, ListState, Paragraph, Tabs},
 };
 use ringbuffer::RingBuffer;
 
-use crate::app::App;
+use crate::app::{App, Tab, state, timing};
 
 impl App {
     /// Renders the user interface widgets.
@@ -20,20 +20,75 @@ impl App {
     pub fn render(&mut self, frame: &mut Frame) {
         let area = frame.area();
 
-        let layout = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]);
-        let [main_area, footer_area] = area.layout(&layout);
+        let layout = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ]);
+        let [header_area, main_area, footer_area] = area.layout(&layout);
 
         let footer = Text::from("Irvine Hacker Fab").centered();
         frame.render_widget(footer, footer_area);
 
+        self.render_tabs(header_area, frame);
+        match self.tab {
+            Tab::Control => {}
+            Tab::Editor => {
+                self.editor.render(main_area, frame);
+                return;
+            }
+            Tab::History => {
+                self.history.render(main_area, frame);
+                return;
+            }
+        }
+
         let main_layout = Layout::horizontal([Constraint::Ratio(1, 2); 2]);
         let [left_half, right_half] = main_area.layout(&main_layout);
-        let right_half_layout = Layout::vertical([Constraint::Ratio(1, 2); 2]);
-        let [upper_right, lower_right] = right_half.layout(&right_half_layout);
+        let right_half_layout = Layout::vertical([
+            Constraint::Fill(3),
+            Constraint::Length(11),
+            Constraint::Fill(2),
+        ]);
+        let [upper_right, middle_right, lower_right] = right_half.layout(&right_half_layout);
+
+        let left_half_layout = Layout::vertical([Constraint::Fill(1), Constraint::Length(7)]);
+        let [upper_left, lower_left] = left_half.layout(&left_half_layout);
 
-        self.render_commands(left_half, frame);
-        self.render_state(upper_right, frame);
+        self.render_commands(upper_left, frame);
+        self.render_timing(lower_left, frame);
+        self.render_chart(upper_right, frame);
+        self.render_state(middle_right, frame);
         self.render_logs(lower_right, frame);
+
+        if let Some(form) = &self.run_at_form {
+            form.render(main_area, frame);
+        } else if let Some(form) = &self.jog_form {
+            form.render(main_area, frame);
+        } else if let Some(form) = &self.run_info_form {
+            form.render(main_area, frame);
+        } else if self.show_report
+            && let Some(report) = &self.report
+        {
+            report.render(main_area, frame);
+        }
+    }
+
+    fn render_tabs(&self, area: Rect, frame: &mut Frame) {
+        let layout = Layout::horizontal([Constraint::Fill(1), Constraint::Length(16)]);
+        let [tabs_area, instructions_area] = area.layout(&layout);
+
+        let tabs = Tabs::new(["Control", "Profile Editor", "History"])
+            .select(match self.tab {
+                Tab::Control => 0,
+                Tab::Editor => 1,
+                Tab::History => 2,
+            })
+            .highlight_style(Style::new().blue().bold());
+        frame.render_widget(tabs, tabs_area);
+
+        let instructions = Line::from_iter([" Switch: ".into(), "<Tab> ".blue().bold()]);
+        frame.render_widget(instructions, instructions_area);
     }
 
     fn render_commands(&mut self, area: Rect, frame: &mut Frame) {
@@ -65,6 +120,15 @@ impl App {
             "Stop",
             "Enable vacuum pump",
             "Disable vacuum pump",
+            "Get loop timing statistics",
+            "Jog (start or raise duty cycle)",
+            "Jog (lower duty cycle)",
+            "Stop jogging",
+            "Run at constant plate RPM",
+            "Show last run report",
+            "Set operator and sample ID",
+            "Export last run plots (SVG and PNG)",
+            "Jog at a duty cycle with limits",
         ];
         let list = List::new(items)
             .block(cmd_block)
@@ -74,6 +138,26 @@ impl App {
         frame.render_stateful_widget(list, area, list_state);
     }
 
+    fn render_chart(&self, area: Rect, frame: &mut Frame) {
+        let instructions = Line::from_iter([
+            " Zoom: ".into(),
+            "<+>,<->".blue().bold(),
+            " Pan: ".into(),
+            "<Left>,<Right>".blue().bold(),
+            " Reset: ".into(),
+            "<0> ".blue().bold(),
+        ]);
+        let block = Block::bordered()
+            .title(" Live RPM ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded)
+            .title_bottom(instructions);
+
+        // Jogs don't follow the profile.
+        self.chart
+            .render(block, self.jog_duty_cycle.is_none(), area, frame);
+    }
+
     fn render_state(&self, area: Rect, frame: &mut Frame) {
         let block = Block::bordered()
             .title(" MCU State ")
@@ -81,13 +165,28 @@ impl App {
             .border_type(BorderType::Rounded);
 
         if let Some(state) = &self.mcu_state {
-            state.render(block, area, frame);
+            state::render(state, block, area, frame);
         } else {
             let paragraph = Paragraph::new("No MCU State.").block(block);
             frame.render_widget(paragraph, area);
         }
     }
 
+    fn render_timing(&self, area: Rect, frame: &mut Frame) {
+        let block = Block::bordered()
+            .title(" Loop Timing ")
+            .title_alignment(HorizontalAlignment::Center)
+            .border_type(BorderType::Rounded);
+
+        timing::render(
+            self.mcu_state.as_ref(),
+            self.loop_timing.as_ref(),
+            block,
+            area,
+            frame,
+        );
+    }
+
     fn render_logs(&self, area: Rect, frame: &mut Frame) {
         let info_block = Block::bordered()
             .title("MCU Logs")
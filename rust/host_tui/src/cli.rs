@@ -0,0 +1,461 @@
+//! This module contains the headless command line interface for scripting runs.
+//!
+//! Unlike the TUI, the CLI doesn't notify the MCU when it disconnects,
+//! so a run started by one command keeps going until another command stops or waits for it.
+
+use std::{fmt::Debug, fs::File, path::PathBuf, process::ExitCode, time::Duration};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use color_eyre::{Result, eyre::OptionExt};
+use csv::{Writer, WriterBuilder};
+use sc_messages::{
+    diagnostics::DeviceInfo,
+    icd::BAUD_RATE,
+    motion_profile::{Outcome, Setpoint, StateOrDisabled},
+    telemetry, vacuum_pump,
+};
+use spincoater_client::{Client, StateSubscription, Subscription, esp_ports, only_esp_port};
+use tokio::time::{Instant, timeout, timeout_at};
+
+use crate::app::{
+    MOTOR_DATA_SUB_DIR,
+    compare::Comparison,
+    html,
+    metadata::{RunInfo, RunMetadata, UploadedProfile, metadata_path},
+    open_log_file,
+    plot::{self, PlotFormat},
+    report::{RunReport, report_path},
+    state::{DroppedSamples, MotionProfileState},
+};
+
+/// The error when the connection to the MCU closes during a run.
+const CLOSED: &str = "The connection to the MCU closed.";
+
+/// How long to wait for a run's outcome after its final state.
+///
+/// The outcome and the final state are published on separate topics, so either can arrive first.
+const OUTCOME_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The exit code when the MCU refused a request.
+pub const EXIT_REFUSED: u8 = 2;
+
+/// The exit code when a run ended with [`Outcome::Fault`].
+pub const EXIT_FAULT: u8 = 3;
+
+/// The exit code when a run ended before reaching its last setpoint without a fault.
+pub const EXIT_INCOMPLETE: u8 = 4;
+
+/// The exit code when a run didn't end before the timeout.
+pub const EXIT_TIMED_OUT: u8 = 5;
+
+/// Controls the spin coater from the terminal.
+///
+/// Without a subcommand, the interactive TUI is started.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// The serial port the MCU is connected to.
+    ///
+    /// Defaults to the only ESP device plugged in.
+    #[arg(short, long, global = true)]
+    pub port: Option<String>,
+    /// Replay a motor data CSV file in the TUI instead of connecting to the MCU.
+    ///
+    /// The run's outcome and motion profile are read from its metadata file, if it has one.
+    #[arg(long, value_name = "CSV")]
+    pub replay: Option<PathBuf>,
+    /// How many times faster than real time to replay the run.
+    #[arg(long, default_value_t = 1.0, requires = "replay")]
+    pub speed: f64,
+    #[command(flatten)]
+    pub telemetry: Telemetry,
+    /// Switch the link to the MCU to this baud rate after connecting, up to 3000000.
+    ///
+    /// If the link doesn't come up at it, both sides fall back to the default.
+    #[arg(long, global = true, default_value_t = BAUD_RATE)]
+    pub baud_rate: u32,
+    /// The command to run headlessly.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// The headless commands.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the serial ports that ESP devices are plugged into.
+    Ports,
+    /// Replace the MCU's motion profile with a motion profile CSV file.
+    Upload {
+        /// The motion profile CSV file.
+        path: PathBuf,
+    },
+    /// Start the uploaded motion profile.
+    Start {
+        /// Wait for the run to finish and write its data and report to files.
+        #[arg(short, long)]
+        wait: bool,
+        #[command(flatten)]
+        recording: Recording,
+    },
+    /// Wait for the current run to finish and write its data and report to files.
+    Wait(Recording),
+    /// Stop the current run.
+    Stop,
+    /// Turn the vacuum pump on or off.
+    Vacuum {
+        /// Whether the vacuum pump should be on.
+        state: VacuumState,
+    },
+    /// Save plots of a run's setpoint and measured plate RPM, error and duty cycle next to its data.
+    ///
+    /// The MCU isn't needed.
+    Plot {
+        /// The motor data CSV file of the run.
+        path: PathBuf,
+        /// Only save this format. Defaults to both SVG and PNG.
+        #[arg(short, long)]
+        format: Option<PlotFormat>,
+    },
+    /// Write a self-contained HTML report of a run next to its data, e.g. for runs recorded before reports were.
+    ///
+    /// The MCU isn't needed.
+    Report {
+        /// The motor data CSV file of the run.
+        path: PathBuf,
+    },
+    /// Compare the metrics of two or more runs to the first one's.
+    ///
+    /// The MCU isn't needed.
+    Compare {
+        /// The motor data CSV files of the runs.
+        #[arg(num_args = 2.., required = true)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// Where and how long to record a run.
+#[derive(Debug, Args)]
+pub struct Recording {
+    /// The CSV file to write the run's data to.
+    ///
+    /// Defaults to a new file in the `logs/motor_data` folder.
+    /// The run's report and metadata are written next to it.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Give up waiting after this many seconds.
+    #[arg(short, long)]
+    pub timeout: Option<u64>,
+    /// Who runs the spin coater, which is recorded in the run's metadata.
+    #[arg(long, default_value_t)]
+    pub operator: String,
+    /// The sample being coated, which is recorded in the run's metadata.
+    #[arg(long, default_value_t)]
+    pub sample_id: String,
+}
+
+/// How the MCU publishes the states of runs.
+///
+/// The MCU keeps the config until it restarts, and applies it from the start of the next run.
+#[derive(Debug, Args)]
+pub struct Telemetry {
+    /// Only publish every Nth control loop iteration's state.
+    ///
+    /// Defaults to 1, which publishes every state.
+    #[arg(long, global = true, value_name = "N")]
+    pub decimation: Option<u8>,
+    /// Send N states per frame to reduce the load on the UART, up to 16.
+    ///
+    /// Defaults to 1, which sends every state on its own.
+    #[arg(long, global = true, value_name = "N")]
+    pub batch_size: Option<u8>,
+}
+
+impl Telemetry {
+    /// Sends the telemetry config to the MCU if either option was given.
+    ///
+    /// # Errors
+    /// Returns an error if the request couldn't be sent.
+    pub async fn configure(&self, client: &Client) -> Result<telemetry::RequestResult> {
+        if self.decimation.is_none() && self.batch_size.is_none() {
+            return Ok(Ok(()));
+        }
+        let config = telemetry::Config {
+            decimation: self
+                .decimation
+                .unwrap_or(telemetry::Config::DEFAULT.decimation),
+            batch_size: self
+                .batch_size
+                .unwrap_or(telemetry::Config::DEFAULT.batch_size),
+        };
+        Ok(client.telemetry(&config).await?)
+    }
+}
+
+/// The states of the vacuum pump.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum VacuumState {
+    /// The vacuum pump is enabled.
+    On,
+    /// The vacuum pump is disabled.
+    Off,
+}
+
+impl Cli {
+    /// Runs the subcommand, if any.
+    ///
+    /// Returns the exit code of the command.
+    ///
+    /// # Errors
+    /// Returns an error if the MCU can't be reached or a file can't be read or written.
+    pub async fn run(self) -> Result<ExitCode> {
+        let Some(command) = self.command else {
+            return Ok(ExitCode::SUCCESS);
+        };
+        match command {
+            Command::Ports => {
+                for port in esp_ports()? {
+                    println!("{}", port.port_name);
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+            Command::Compare { paths } => {
+                print!("{}", Comparison::load(&paths)?);
+                return Ok(ExitCode::SUCCESS);
+            }
+            Command::Plot { path, format } => {
+                let formats = format.map_or(PlotFormat::ALL.to_vec(), |format| vec![format]);
+                for path in plot::export(&path, &formats)? {
+                    println!("Saved {}", path.display());
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+            Command::Report { path } => {
+                println!("Saved {}", html::export(&path)?.display());
+                return Ok(ExitCode::SUCCESS);
+            }
+            _ => {}
+        }
+
+        let port = match self.port {
+            Some(port) => port,
+            None => only_esp_port()?,
+        };
+        let (client, fallback) = Client::connect_at(&port, self.baud_rate).await?;
+        if let Some(fallback) = fallback {
+            eprintln!("Stayed at {BAUD_RATE} baud. {fallback}.");
+        }
+        let code = exit_code(
+            "change the telemetry config",
+            self.telemetry.configure(&client).await?,
+        );
+        if code != ExitCode::SUCCESS {
+            return Ok(code);
+        }
+        match command {
+            Command::Ports
+            | Command::Compare { .. }
+            | Command::Plot { .. }
+            | Command::Report { .. } => Ok(ExitCode::SUCCESS),
+            Command::Upload { path } => {
+                let setpoints = csv::Reader::from_path(path)?
+                    .into_deserialize()
+                    .collect::<Result<Vec<Setpoint>, _>>()?;
+                let count = setpoints.len();
+                let code = exit_code("upload", client.upload_profile(setpoints).await?);
+                if code == ExitCode::SUCCESS {
+                    println!("Uploaded {count} setpoints.");
+                }
+                Ok(code)
+            }
+            Command::Start { wait, recording } => {
+                if !wait {
+                    return Ok(exit_code("start", client.start().await?));
+                }
+                // Subscribe first so that no state is missed.
+                let run = Run::subscribe(&client).await?;
+                let code = exit_code("start", client.start().await?);
+                if code != ExitCode::SUCCESS {
+                    return Ok(code);
+                }
+                run.wait(recording, false).await
+            }
+            Command::Wait(recording) => Run::subscribe(&client).await?.wait(recording, true).await,
+            Command::Stop => Ok(exit_code("stop", client.stop().await?)),
+            Command::Vacuum { state } => {
+                let request = match state {
+                    VacuumState::On => vacuum_pump::Request::Enable,
+                    VacuumState::Off => vacuum_pump::Request::Disable,
+                };
+                client.vacuum_pump(&request).await?;
+                println!(
+                    "Vacuum pump turned {}.",
+                    format!("{state:?}").to_lowercase()
+                );
+                Ok(ExitCode::SUCCESS)
+            }
+        }
+    }
+}
+
+/// Converts the MCU's response to a request into an exit code.
+///
+/// Returns [`EXIT_REFUSED`] if the MCU refused the request.
+fn exit_code<E: Debug>(request: &str, response: Result<(), E>) -> ExitCode {
+    match response {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(refused) => {
+            eprintln!("The MCU refused to {request}: {refused:?}");
+            ExitCode::from(EXIT_REFUSED)
+        }
+    }
+}
+
+/// The subscriptions needed to follow a run.
+struct Run {
+    /// The MCU's logs, which are printed to stderr.
+    logs: Subscription<String>,
+    /// The motion profile states of the run.
+    states: StateSubscription,
+    /// How the run ended.
+    outcomes: Subscription<Outcome>,
+    /// The firmware version, calibration and controller parameters, if the MCU reported them.
+    device: Option<DeviceInfo>,
+}
+
+impl Run {
+    /// Subscribes to the topics needed to follow a run.
+    async fn subscribe(client: &Client) -> Result<Self> {
+        Ok(Self {
+            logs: client.subscribe_logs().await?,
+            states: client.subscribe_states().await?,
+            outcomes: client.subscribe_outcomes().await?,
+            device: client.device_info().await.ok(),
+        })
+    }
+
+    /// Waits for the current run to finish, writing its data to a CSV file and its report and metadata next to it.
+    ///
+    /// If the run may already be underway, states missed before the first one received aren't counted as dropped.
+    /// The CLI doesn't know the uploaded profile and can't tell jogs apart,
+    /// so the metadata records a motion profile without setpoints.
+    ///
+    /// Returns an exit code based on the run's [`Outcome`].
+    async fn wait(mut self, recording: Recording, mid_run: bool) -> Result<ExitCode> {
+        let (mut writer, path): (Writer<File>, _) = match recording.output {
+            Some(path) => (WriterBuilder::new().from_path(&path)?, path),
+            None => open_log_file(MOTOR_DATA_SUB_DIR)?,
+        };
+        let deadline = recording
+            .timeout
+            .map(|secs| Instant::now() + Duration::from_secs(secs));
+
+        let run_info = RunInfo {
+            operator: recording.operator,
+            sample_id: recording.sample_id,
+        };
+        let device = self.device;
+        let new_metadata =
+            || RunMetadata::new(&path, &run_info, false, &UploadedProfile::default(), device);
+
+        let mut outcome = None;
+        let mut samples = Vec::new();
+        let mut dropped_samples = DroppedSamples::default();
+        let mut metadata = None;
+        loop {
+            let event = match deadline {
+                Some(deadline) => {
+                    let Ok(event) = timeout_at(deadline, self.next_event()).await else {
+                        writer.flush()?;
+                        eprintln!("The run didn't finish before the timeout.");
+                        return Ok(ExitCode::from(EXIT_TIMED_OUT));
+                    };
+                    event?
+                }
+                None => self.next_event().await?,
+            };
+            match event {
+                RunEvent::State(Some(state)) => {
+                    let mut state = MotionProfileState::from(state);
+                    if metadata.is_none() {
+                        if mid_run {
+                            dropped_samples = DroppedSamples::starting_at(state.sample);
+                        }
+                        let started = new_metadata();
+                        started.save(&metadata_path(&path))?;
+                        metadata = Some(started);
+                    }
+                    let dropped = dropped_samples.record(&mut state);
+                    if dropped > 0 {
+                        eprintln!(
+                            "Missed {dropped} samples before {:.2} s.",
+                            Duration::from_micros(state.time).as_secs_f64()
+                        );
+                    }
+                    writer.serialize(&state)?;
+                    samples.push(state);
+                }
+                RunEvent::State(None) => {
+                    if outcome.is_none() {
+                        outcome = self.late_outcome().await;
+                    }
+                    break;
+                }
+                RunEvent::Outcome(run_outcome) => outcome = Some(run_outcome),
+            }
+        }
+        writer.flush()?;
+        println!("Wrote the run's data to {}.", path.display());
+
+        let report = RunReport::new(&samples, outcome);
+        let report_path = report_path(&path);
+        report.save(&report_path)?;
+        println!("Wrote the run's report to {}.", report_path.display());
+        let metadata = metadata.get_or_insert_with(new_metadata);
+        metadata.finish(&report_path, outcome);
+        metadata.save(&metadata_path(&path))?;
+        let html_path = html::save(&path, &samples, Some(metadata), &report)?;
+        println!("Wrote the run's HTML report to {}.", html_path.display());
+        print!("{report}");
+        Ok(match outcome {
+            Some(Outcome::Completed) => ExitCode::SUCCESS,
+            Some(Outcome::Fault) => ExitCode::from(EXIT_FAULT),
+            Some(Outcome::Stopped | Outcome::HostDisconnected | Outcome::TimedOut) | None => {
+                ExitCode::from(EXIT_INCOMPLETE)
+            }
+        })
+    }
+
+    /// Waits up to [`OUTCOME_TIMEOUT`] for an outcome that arrives after the final state.
+    async fn late_outcome(&mut self) -> Option<Outcome> {
+        timeout(OUTCOME_TIMEOUT, self.outcomes.recv())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Waits for the next state or outcome, printing MCU logs to stderr along the way.
+    ///
+    /// # Errors
+    /// Returns an error if the connection to the MCU closed.
+    async fn next_event(&mut self) -> Result<RunEvent> {
+        loop {
+            tokio::select! {
+                log = self.logs.recv() => eprintln!("[Log]: {}", log.ok_or_eyre(CLOSED)?),
+                state = self.states.recv() => {
+                    return Ok(RunEvent::State(state.ok_or_eyre(CLOSED)?));
+                }
+                outcome = self.outcomes.recv() => {
+                    return Ok(RunEvent::Outcome(outcome.ok_or_eyre(CLOSED)?));
+                }
+            }
+        }
+    }
+}
+
+/// The parts of a run that [`Run::wait`] cares about.
+enum RunEvent {
+    /// See [`spincoater_client::Client::subscribe_states`].
+    State(StateOrDisabled),
+    /// See [`spincoater_client::Client::subscribe_outcomes`].
+    Outcome(Outcome),
+}
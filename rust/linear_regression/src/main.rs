@@ -1,8 +1,8 @@
 use std::env;
 
 use color_eyre::eyre::{OptionExt, Result, eyre};
-use host_tui::app::state::MotionProfileState;
 use linreg::linear_regression;
+use spincoater_client::state::MotionProfileState;
 
 fn main() -> Result<()> {
     let path = rfd::FileDialog::new()
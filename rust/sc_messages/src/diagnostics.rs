@@ -0,0 +1,264 @@
+//! This module describes the diagnostics the microcontroller reports about itself.
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+/// Statistics about a duration that is measured every control loop iteration.
+///
+/// All values are in micros.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub struct Statistics {
+    /// The shortest measured duration.
+    pub min: u32,
+    /// The longest measured duration.
+    pub max: u32,
+    /// The average duration.
+    pub mean: u32,
+    /// The duration that 50% of measurements are below.
+    pub p50: u32,
+    /// The duration that 95% of measurements are below.
+    pub p95: u32,
+    /// The duration that 99% of measurements are below.
+    pub p99: u32,
+}
+
+/// The width (in micros) of each [`Histogram`] bucket.
+const BUCKET_WIDTH: u32 = 250;
+
+/// The number of [`Histogram`] buckets.
+///
+/// This covers durations up to 40 ms, twice the microcontroller's control loop period.
+/// Anything longer is counted in the last bucket.
+const BUCKETS: usize = 160;
+
+/// A histogram of durations that can estimate percentiles without storing every measurement.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    /// The number of measurements in each bucket.
+    buckets: [u32; BUCKETS],
+    /// The number of measurements.
+    count: u32,
+    /// The sum of all measurements in micros.
+    sum: u64,
+    /// The shortest measurement in micros.
+    min: u32,
+    /// The longest measurement in micros.
+    max: u32,
+}
+
+impl Histogram {
+    /// Creates an empty histogram.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            buckets: [0; BUCKETS],
+            count: 0,
+            sum: 0,
+            min: u32::MAX,
+            max: 0,
+        }
+    }
+
+    /// Records a measurement in micros.
+    pub fn record(&mut self, micros: u32) {
+        let bucket = usize::try_from(micros / BUCKET_WIDTH)
+            .unwrap_or(usize::MAX)
+            .min(BUCKETS - 1);
+        if let Some(bucket) = self.buckets.get_mut(bucket) {
+            *bucket = bucket.saturating_add(1);
+        }
+        self.count = self.count.saturating_add(1);
+        self.sum = self.sum.saturating_add(u64::from(micros));
+        self.min = self.min.min(micros);
+        self.max = self.max.max(micros);
+    }
+
+    /// The number of measurements.
+    #[must_use]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Estimates the duration that `percent`% of measurements are below.
+    ///
+    /// The estimate is the upper edge of the bucket containing the percentile,
+    /// limited to the longest measurement.
+    fn percentile(&self, percent: u32) -> u32 {
+        // Round up so the 99th percentile of 10 measurements is the longest one.
+        let target = u64::from(self.count)
+            .saturating_mul(u64::from(percent))
+            .div_ceil(100);
+        let mut seen: u64 = 0;
+        for (upper_edge, count) in (1..)
+            .map(|i: u32| i.saturating_mul(BUCKET_WIDTH))
+            .zip(self.buckets)
+        {
+            seen = seen.saturating_add(u64::from(count));
+            if seen >= target {
+                return upper_edge.min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Summarizes the measurements.
+    #[must_use]
+    pub fn statistics(&self) -> Statistics {
+        if self.count == 0 {
+            return Statistics::default();
+        }
+        let mean = self
+            .sum
+            .checked_div(u64::from(self.count))
+            .and_then(|mean| u32::try_from(mean).ok())
+            .unwrap_or(u32::MAX);
+        Statistics {
+            min: self.min,
+            max: self.max,
+            mean,
+            p50: self.percentile(50),
+            p95: self.percentile(95),
+            p99: self.percentile(99),
+        }
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Timing statistics for the control loop of the current or most recent run.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub struct LoopTiming {
+    /// The period (in micros) that the control loop tries to run at.
+    pub target_period: u32,
+    /// The number of measured iterations.
+    pub iterations: u32,
+    /// The number of iterations that took longer than [`LoopTiming::target_period`] to execute.
+    pub overruns: u32,
+    /// The time between the starts of consecutive iterations.
+    pub period: Statistics,
+    /// The time spent executing each iteration, excluding sleep.
+    pub execution: Statistics,
+}
+
+/// The linear relationship between motor RPM and duty cycle that the controller uses as its feedforward term.
+///
+/// The feedforward duty cycle is `rpm * rpm_to_duty_numerator / rpm_to_duty_denominator + rpm_to_duty_intercept`.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub struct Calibration {
+    /// The numerator of the slope.
+    pub rpm_to_duty_numerator: u32,
+    /// The denominator of the slope.
+    pub rpm_to_duty_denominator: u32,
+    /// The duty cycle at 0 RPM.
+    pub rpm_to_duty_intercept: u32,
+}
+
+/// The parameters of the motion profile's control loop.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub struct ControllerParameters {
+    /// The inverse of the proportional gain, in motor RPM error per duty cycle unit.
+    pub k_p_inverse: i16,
+    /// The period (in micros) that the control loop tries to run at.
+    pub loop_period: u32,
+    /// The lowest duty cycle the controller outputs.
+    pub min_duty_cycle: u16,
+    /// The highest duty cycle the controller outputs.
+    pub max_duty_cycle: u16,
+}
+
+/// What the microcontroller is running, so runs can be traced back to it.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub struct DeviceInfo {
+    /// The firmware's version as major, minor and patch.
+    pub firmware_version: [u16; 3],
+    /// See [`Calibration`].
+    pub calibration: Calibration,
+    /// See [`ControllerParameters`].
+    pub controller: ControllerParameters,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A histogram with every measurement in `micros` recorded.
+    fn histogram(micros: impl IntoIterator<Item = u32>) -> Histogram {
+        let mut histogram = Histogram::new();
+        for micros in micros {
+            histogram.record(micros);
+        }
+        histogram
+    }
+
+    #[test]
+    fn empty_histogram_has_default_statistics() {
+        assert_eq!(Histogram::new().statistics(), Statistics::default());
+    }
+
+    #[test]
+    fn single_measurement_is_every_percentile() {
+        let statistics = histogram([20_100]).statistics();
+        assert_eq!(
+            statistics,
+            Statistics {
+                min: 20_100,
+                max: 20_100,
+                mean: 20_100,
+                p50: 20_100,
+                p95: 20_100,
+                p99: 20_100,
+            }
+        );
+    }
+
+    #[test]
+    fn percentiles_are_bucket_upper_edges() {
+        // 90 measurements in [0, 250) and 10 in [20_000, 20_250).
+        let statistics =
+            histogram(core::iter::repeat_n(100, 90).chain(core::iter::repeat_n(20_000, 10)))
+                .statistics();
+        assert_eq!(statistics.min, 100);
+        assert_eq!(statistics.max, 20_000);
+        assert_eq!(statistics.mean, 2_090);
+        assert_eq!(statistics.p50, BUCKET_WIDTH);
+        assert_eq!(statistics.p95, 20_000);
+        assert_eq!(statistics.p99, 20_000);
+    }
+
+    #[test]
+    fn percentile_on_a_bucket_boundary_uses_the_lower_bucket() {
+        // Exactly 50 of 100 measurements are in the first bucket.
+        let statistics =
+            histogram(core::iter::repeat_n(0, 50).chain(core::iter::repeat_n(1_000, 50)))
+                .statistics();
+        assert_eq!(statistics.p50, BUCKET_WIDTH);
+        assert_eq!(statistics.p95, 1_000);
+    }
+
+    #[test]
+    fn high_percentiles_round_up_to_the_longest_measurement() {
+        // The 99th percentile of 10 measurements is the 10th.
+        let statistics = histogram((1..=10).map(|i| i * 1_000)).statistics();
+        assert_eq!(statistics.p50, 5_250);
+        assert_eq!(statistics.p95, 10_000);
+        assert_eq!(statistics.p99, 10_000);
+    }
+
+    #[test]
+    fn long_measurements_are_counted_in_the_last_bucket() {
+        let statistics = histogram([1_000, 1_000_000, u32::MAX]).statistics();
+        assert_eq!(statistics.max, u32::MAX);
+        let last_upper_edge = BUCKET_WIDTH * 160;
+        assert_eq!(statistics.p50, last_upper_edge);
+        assert_eq!(statistics.p99, last_upper_edge);
+        // The sum doesn't overflow.
+        assert_eq!(
+            statistics.mean,
+            u32::try_from((1_000 + 1_000_000 + u64::from(u32::MAX)) / 3).unwrap_or_default()
+        );
+    }
+}
@@ -2,7 +2,13 @@
 use postcard_rpc::{TopicDirection, endpoints, topics};
 
 use crate::{
-    motion_profile::{Request as MotionProfileRequest, RequestResult, StateOrDisabled},
+    diagnostics::{DeviceInfo, LoopTiming},
+    jog::{Request as JogRequest, RequestResult as JogRequestResult},
+    link::{Request as BaudRateRequest, RequestResult as BaudRateRequestResult},
+    motion_profile::{
+        Outcome, Request as MotionProfileRequest, RequestResult, RunAt, StateOrDisabled,
+    },
+    telemetry::{Config as TelemetryConfig, RequestResult as TelemetryRequestResult, StateBatch},
     touchscreen::TouchPoint,
     vacuum_pump::Request as VacuumPumpRequest,
 };
@@ -11,6 +17,7 @@ use crate::{
 ///
 /// This value was taken from [`esp_hal::uart::Config::default`]
 /// and is placed here so [`esp_hal::uart::Config::default`] doesn't change it under our feet.
+/// Both sides start at this baud rate and fall back to it, see [`crate::link`].
 pub const BAUD_RATE: u32 = 115_200;
 
 endpoints! {
@@ -19,6 +26,12 @@ endpoints! {
     |-----------------|---------------|---------------|---------------------|
     | MotionRequestEndpoint | MotionProfileRequest | RequestResult | "endpoints/motion_profile/Request" |
     | VacuumPumpRequestEndpoint | VacuumPumpRequest | () | "endpoints/vacuum_pump/Request" |
+    | LoopTimingEndpoint | () | LoopTiming | "endpoints/diagnostics/LoopTiming" |
+    | JogRequestEndpoint | JogRequest | JogRequestResult | "endpoints/jog/Request" |
+    | RunAtEndpoint | RunAt | RequestResult | "endpoints/motion_profile/RunAt" |
+    | DeviceInfoEndpoint | () | DeviceInfo | "endpoints/diagnostics/DeviceInfo" |
+    | TelemetryConfigEndpoint | TelemetryConfig | TelemetryRequestResult | "endpoints/telemetry/Config" |
+    | BaudRateEndpoint | BaudRateRequest | BaudRateRequestResult | "endpoints/link/BaudRate" |
 }
 
 topics! {
@@ -32,8 +45,10 @@ topics! {
 topics! {
    list = TOPICS_TO_CLIENT_LIST;
    direction = TopicDirection::ToClient;
-   | TopicTy                 | MessageTy       | Path                          |
-   |-------------------------|-----------------|-------------------------------|
-   | MotionProfileStateTopic | StateOrDisabled | "topics/motion_profile/state" |
-   | TouchPointTopic         | TouchPoint      | "topics/touch/point"          |
+   | TopicTy                      | MessageTy       | Path                                |
+   |------------------------------|-----------------|-------------------------------------|
+   | MotionProfileStateTopic      | StateOrDisabled | "topics/motion_profile/state"       |
+   | MotionProfileOutcomeTopic    | Outcome         | "topics/motion_profile/outcome"     |
+   | MotionProfileStateBatchTopic | StateBatch      | "topics/motion_profile/state_batch" |
+   | TouchPointTopic              | TouchPoint      | "topics/touch/point"                |
 }
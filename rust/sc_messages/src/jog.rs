@@ -0,0 +1,186 @@
+//! This module describes jog mode, where the duty cycle is set directly without closed-loop control.
+//!
+//! Jog mode is meant for bench testing motors and ESCs.
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::pwm::{DutyCycle, HALF_POWER_DUTY, MAX_POWER_DUTY, STOP_DUTY};
+
+/// The longest timeout the MCU accepts in [`Limits`].
+pub const MAX_TIMEOUT_MILLIS: u32 = 60_000;
+
+/// How much a front end changes the jog duty cycle with each key press or touch.
+///
+/// This is 5% of the motor controller's power range, [`STOP_DUTY`]..=[`MAX_POWER_DUTY`].
+pub const DUTY_STEP: u16 = 80;
+
+/// The safety limits that every jog duty cycle must be within.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub struct Limits {
+    /// The lowest duty cycle that may be set.
+    pub min_duty_cycle: DutyCycle,
+    /// The highest duty cycle that may be set.
+    pub max_duty_cycle: DutyCycle,
+    /// The time (in millis) after the most recent [`Request::Set`] when the MCU returns to [`STOP_DUTY`].
+    pub timeout_millis: u32,
+}
+
+impl Limits {
+    /// The limits the MCU starts with.
+    ///
+    /// These match the duty cycles the closed-loop runners are clamped to.
+    pub const DEFAULT: Self = Self {
+        min_duty_cycle: DutyCycle::new(STOP_DUTY),
+        max_duty_cycle: DutyCycle::new(HALF_POWER_DUTY),
+        timeout_millis: 5_000,
+    };
+
+    /// Checks that the limits describe a non-empty range within [`STOP_DUTY`]..=[`MAX_POWER_DUTY`]
+    /// and a timeout within 1..=[`MAX_TIMEOUT_MILLIS`].
+    ///
+    /// The range must be checked here, since deserializing a [`DutyCycle`] doesn't clamp it.
+    ///
+    /// # Errors
+    /// Returns [`RequestRefused::InvalidLimits`] if the limits are unusable.
+    pub fn validate(&self) -> RequestResult {
+        if *self.min_duty_cycle < STOP_DUTY
+            || *self.max_duty_cycle > MAX_POWER_DUTY
+            || self.min_duty_cycle > self.max_duty_cycle
+            || !(1..=MAX_TIMEOUT_MILLIS).contains(&self.timeout_millis)
+        {
+            Err(RequestRefused::InvalidLimits)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that a duty cycle is within the limits.
+    ///
+    /// # Errors
+    /// Returns [`RequestRefused::OutOfLimits`] if the duty cycle is outside the limits.
+    pub fn check(&self, duty_cycle: DutyCycle) -> RequestResult {
+        if (self.min_duty_cycle..=self.max_duty_cycle).contains(&duty_cycle) {
+            Ok(())
+        } else {
+            Err(RequestRefused::OutOfLimits)
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Jog messages from the host PC to the microcontroller.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub enum Request {
+    /// Replace the safety limits.
+    ///
+    /// The MCU will only accept this while not jogging.
+    SetLimits(Limits),
+    /// Start jogging at a duty cycle, or change the duty cycle while jogging.
+    ///
+    /// This also restarts the timeout, so the host PC must keep sending it to keep jogging.
+    /// The MCU will only accept this while no motion profile is running.
+    Set(DutyCycle),
+    /// Stop jogging and return to [`STOP_DUTY`].
+    ///
+    /// The MCU will only accept this while jogging.
+    Stop,
+}
+
+/// The possible reasons why the MCU might refuse a jog command.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub enum RequestRefused {
+    /// The duty cycle is outside the safety limits.
+    OutOfLimits,
+    /// The duty cycles are out of order or outside the motor controller's range,
+    /// or the timeout is zero or above [`MAX_TIMEOUT_MILLIS`].
+    InvalidLimits,
+    /// The MCU is jogging.
+    Jogging,
+    /// The MCU is not jogging.
+    NotJogging,
+    /// A motion profile is running.
+    MotionProfileRunning,
+}
+
+/// See [this issue](https://github.com/jamesmunns/postcard-rpc/issues/56) for why we need a type alias.
+pub type RequestResult = Result<(), RequestRefused>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A duty cycle that is deserialized, so it isn't clamped like [`DutyCycle::new`] does.
+    fn deserialized(duty_cycle: u16) -> DutyCycle {
+        let mut buffer = [0; 3];
+        let bytes = postcard_rpc::postcard::to_slice(&duty_cycle, &mut buffer)
+            .expect("A u16 fits in 3 bytes");
+        postcard_rpc::postcard::from_bytes(bytes).expect("A DutyCycle is a u16")
+    }
+
+    /// Limits with these duty cycles and timeout.
+    fn limits(min_duty_cycle: u16, max_duty_cycle: u16, timeout_millis: u32) -> Limits {
+        Limits {
+            min_duty_cycle: DutyCycle::new(min_duty_cycle),
+            max_duty_cycle: DutyCycle::new(max_duty_cycle),
+            timeout_millis,
+        }
+    }
+
+    #[test]
+    fn default_limits_are_valid() {
+        assert_eq!(Limits::DEFAULT.validate(), Ok(()));
+    }
+
+    #[test]
+    fn limits_within_the_motor_controller_range_are_valid() {
+        assert_eq!(
+            limits(STOP_DUTY, MAX_POWER_DUTY, MAX_TIMEOUT_MILLIS).validate(),
+            Ok(())
+        );
+        // A single duty cycle is a non-empty range.
+        assert_eq!(
+            limits(HALF_POWER_DUTY, HALF_POWER_DUTY, 1).validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn unusable_limits_are_invalid() {
+        for invalid in [
+            limits(STOP_DUTY - 1, HALF_POWER_DUTY, 5_000),
+            Limits {
+                max_duty_cycle: deserialized(MAX_POWER_DUTY + 1),
+                ..Limits::DEFAULT
+            },
+            limits(HALF_POWER_DUTY, HALF_POWER_DUTY - 1, 5_000),
+            limits(STOP_DUTY, HALF_POWER_DUTY, 0),
+            limits(STOP_DUTY, HALF_POWER_DUTY, MAX_TIMEOUT_MILLIS + 1),
+        ] {
+            assert_eq!(
+                invalid.validate(),
+                Err(RequestRefused::InvalidLimits),
+                "{invalid:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn check_includes_both_ends() {
+        let limits = limits(STOP_DUTY + 100, HALF_POWER_DUTY, 5_000);
+        for duty_cycle in [STOP_DUTY + 100, STOP_DUTY + 101, HALF_POWER_DUTY] {
+            assert_eq!(limits.check(DutyCycle::new(duty_cycle)), Ok(()));
+        }
+        for duty_cycle in [STOP_DUTY, STOP_DUTY + 99, HALF_POWER_DUTY + 1] {
+            assert_eq!(
+                limits.check(DutyCycle::new(duty_cycle)),
+                Err(RequestRefused::OutOfLimits)
+            );
+        }
+    }
+}
@@ -1,9 +1,14 @@
 //! This cross-platform crate describes the message types sent between the host PC and microcontrollers.
 #![no_std]
 
+pub mod diagnostics;
 pub mod icd;
+pub mod jog;
+pub mod link;
 pub mod motion_profile;
 pub mod pwm;
+pub mod scpi;
+pub mod telemetry;
 pub mod touchscreen;
 pub mod vacuum_pump;
 
@@ -12,3 +17,13 @@ pub const MOTOR_REVOLUTIONS: u32 = 72;
 
 /// The number of plate revolutions per [`MOTOR_REVOLUTIONS`] motor revolutions.
 pub const PLATE_REVOLUTIONS: u32 = 30;
+
+/// Converts plate RPM to motor RPM.
+///
+/// The return value is truncated to fit in a [`u16`].
+#[must_use]
+pub fn plate_to_motor_rpm(rpm: u16) -> u16 {
+    // Operate in u32 to prevent overflow
+    let rpm = u32::from(rpm).saturating_mul(MOTOR_REVOLUTIONS) / PLATE_REVOLUTIONS;
+    u16::try_from(rpm).unwrap_or(u16::MAX)
+}
@@ -0,0 +1,93 @@
+//! This module describes how the host PC and the MCU agree on a faster baud rate.
+//!
+//! Both sides start at [`BAUD_RATE`].
+//! The host proposes a baud rate with [`Request::Propose`], and the MCU switches to it right after accepting.
+//! The host then reopens the serial port at the new baud rate and sends [`Request::Confirm`].
+//! If the MCU doesn't receive the confirmation within [`SWITCH_TIMEOUT_MILLIS`],
+//! it falls back to [`BAUD_RATE`], and so does the host.
+//! The MCU also goes back to [`BAUD_RATE`] when the host disconnects, so the next host can connect.
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::icd::BAUD_RATE;
+
+/// The fastest baud rate the MCU accepts.
+///
+/// This is the fastest baud rate of the USB to UART bridge on the ESP32 `DevKitC`.
+pub const MAX_BAUD_RATE: u32 = 3_000_000;
+
+/// How long (in millis) the MCU waits for [`Request::Confirm`] at a new baud rate before falling back to [`BAUD_RATE`].
+pub const SWITCH_TIMEOUT_MILLIS: u64 = 1_000;
+
+/// Baud rate messages from the host PC to the microcontroller.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub enum Request {
+    /// Switch to this baud rate after replying.
+    ///
+    /// The MCU will only accept this while nothing is running or jogging.
+    Propose(u32),
+    /// The host has reopened the serial port at the proposed baud rate, so the MCU should keep it.
+    ///
+    /// This must be sent at the new baud rate within [`SWITCH_TIMEOUT_MILLIS`] of the proposal being accepted.
+    Confirm,
+}
+
+impl Request {
+    /// Checks that a proposed baud rate is between [`BAUD_RATE`] and [`MAX_BAUD_RATE`].
+    ///
+    /// # Errors
+    /// Returns [`RequestRefused::UnsupportedBaudRate`] if it isn't.
+    pub fn validate(&self) -> RequestResult {
+        match self {
+            Self::Propose(baud_rate) if !(BAUD_RATE..=MAX_BAUD_RATE).contains(baud_rate) => {
+                Err(RequestRefused::UnsupportedBaudRate)
+            }
+            Self::Propose(_) | Self::Confirm => Ok(()),
+        }
+    }
+}
+
+/// The possible reasons why the MCU might refuse a baud rate request.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub enum RequestRefused {
+    /// The proposed baud rate is below [`BAUD_RATE`] or above [`MAX_BAUD_RATE`].
+    UnsupportedBaudRate,
+    /// A motion profile is running or the motor is jogging, including while the MCU publishes how it ended,
+    /// and switching would lose its states.
+    Running,
+    /// No baud rate was proposed, or the MCU already fell back to [`BAUD_RATE`].
+    NotSwitching,
+}
+
+/// See [this issue](https://github.com/jamesmunns/postcard-rpc/issues/56) for why we need a type alias.
+pub type RequestResult = Result<(), RequestRefused>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proposals_must_be_between_the_default_and_max_baud_rates() {
+        assert_eq!(Request::Propose(BAUD_RATE).validate(), Ok(()));
+        assert_eq!(Request::Propose(921_600).validate(), Ok(()));
+        assert_eq!(Request::Propose(MAX_BAUD_RATE).validate(), Ok(()));
+        assert_eq!(
+            Request::Propose(BAUD_RATE - 1).validate(),
+            Err(RequestRefused::UnsupportedBaudRate)
+        );
+        assert_eq!(
+            Request::Propose(MAX_BAUD_RATE + 1).validate(),
+            Err(RequestRefused::UnsupportedBaudRate)
+        );
+        assert_eq!(
+            Request::Propose(0).validate(),
+            Err(RequestRefused::UnsupportedBaudRate)
+        );
+    }
+
+    #[test]
+    fn confirmations_are_always_valid() {
+        assert_eq!(Request::Confirm.validate(), Ok(()));
+    }
+}
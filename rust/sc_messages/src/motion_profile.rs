@@ -3,7 +3,7 @@ use core::cmp::Ordering;
 use postcard_schema::Schema;
 use serde::{Deserialize, Serialize};
 
-use crate::pwm::DutyCycle;
+use crate::{plate_to_motor_rpm, pwm::DutyCycle};
 
 /// The maximum allowed number of setpoints in a single motion profile.
 ///
@@ -39,6 +39,53 @@ impl PartialOrd for Setpoint {
     }
 }
 
+/// A constant plate RPM held for a number of seconds.
+///
+/// This is a shorthand for a motion profile that jumps straight to one RPM.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub struct RunAt {
+    /// Plate RPM.
+    pub rpm: u16,
+    /// Time in seconds.
+    pub time: u16,
+}
+
+impl RunAt {
+    /// Creates a run request.
+    ///
+    /// RPM should be plate RPM and time should be seconds.
+    #[must_use]
+    pub const fn new(rpm: u16, time: u16) -> Self {
+        Self { rpm, time }
+    }
+
+    /// Whether the run has a nonzero RPM and time.
+    ///
+    /// A zero RPM or time makes a degenerate profile, so the MCU refuses such runs with
+    /// [`RequestRefused::InvalidRun`]. Front ends check this too, to explain the problem before sending the run.
+    #[must_use]
+    pub const fn is_valid(&self) -> bool {
+        self.rpm > 0 && self.time > 0
+    }
+
+    /// Converts the run into the setpoints that follow the starting (0, 0) setpoint.
+    ///
+    /// The first setpoint is 1 micro after the start so the RPM steps up immediately
+    /// instead of ramping up over the whole run.
+    /// Motor RPM is truncated to [`u16::MAX`].
+    #[must_use]
+    pub fn setpoints(&self) -> [Setpoint; 2] {
+        let rpm = plate_to_motor_rpm(self.rpm);
+        [
+            Setpoint { rpm, time: 1 },
+            Setpoint {
+                rpm,
+                time: u64::from(self.time).saturating_mul(1_000_000),
+            },
+        ]
+    }
+}
+
 /// The current state of the motion profile.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Schema)]
 pub struct State {
@@ -54,6 +101,17 @@ pub struct State {
     // I would like to use `embassy_time::duration::Duration`,
     // but it doesn't impl Serialize.
     pub time: u64,
+    /// The time (in micros) between the start of the previous control loop iteration and this one.
+    pub loop_period: u32,
+    /// The time (in micros) the previous control loop iteration spent executing, excluding sleep.
+    pub execution_time: u32,
+    /// The number of control loop iterations that have overrun their period since the motion profile started.
+    pub overruns: u32,
+    /// The index of this state among the states published since the motion profile started.
+    ///
+    /// It increases by one for every published state, so a gap means the host missed some.
+    /// States that are decimated away aren't published, and have the index of the next published state.
+    pub sample: u32,
 }
 
 /// Motion profile messages from the host PC to the microcontroller.
@@ -71,9 +129,9 @@ pub enum Request {
     ///
     /// The MCU will only accept this while disabled.
     Start,
-    /// Stop the motion profile and discard it.
+    /// Stop the motion profile and discard it, or stop jogging.
     ///
-    /// The MCU will only accept this while enabled.
+    /// The MCU always accepts this, even while nothing is running.
     Stop,
 }
 
@@ -84,8 +142,28 @@ pub enum RequestRefused {
     TooManySetpoints,
     /// A motion profile is running.
     Running,
-    /// No motion profile is running.
-    NotRunning,
+    /// The MCU is in jog mode.
+    Jogging,
+    /// The [`RunAt`] has a zero RPM or time.
+    InvalidRun,
+}
+
+/// How a run ended.
+///
+/// The MCU publishes this right before it reports that there is no more state.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub enum Outcome {
+    /// The motion profile reached its last setpoint.
+    Completed,
+    /// A stop request ended the run early.
+    Stopped,
+    /// The host PC notified the MCU that it was disconnecting during the run.
+    HostDisconnected,
+    /// No jog duty cycle was received within the jog timeout.
+    TimedOut,
+    /// The MCU could not calculate the setpoint RPM, so it stopped the motor.
+    /// The MCU logs the reason.
+    Fault,
 }
 
 /// See [this issue](https://github.com/jamesmunns/postcard-rpc/issues/56) for why we need a type alias.
@@ -93,3 +171,44 @@ pub type RequestResult = Result<(), RequestRefused>;
 
 /// See [this issue](https://github.com/jamesmunns/postcard-rpc/issues/56) for why we need a type alias.
 pub type StateOrDisabled = Option<State>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_need_a_nonzero_rpm_and_time() {
+        assert!(RunAt::new(1, 1).is_valid());
+        assert!(RunAt::new(u16::MAX, u16::MAX).is_valid());
+        assert!(!RunAt::new(0, 10).is_valid());
+        assert!(!RunAt::new(3000, 0).is_valid());
+        assert!(!RunAt::new(0, 0).is_valid());
+    }
+
+    #[test]
+    fn run_steps_up_immediately_and_holds() {
+        let [first, last] = RunAt::new(1000, 30).setpoints();
+        let rpm = plate_to_motor_rpm(1000);
+        assert_eq!(first, Setpoint { rpm, time: 1 });
+        assert_eq!(
+            last,
+            Setpoint {
+                rpm,
+                time: 30_000_000
+            }
+        );
+    }
+
+    #[test]
+    fn longest_run_fits_in_micros() {
+        let [_, last] = RunAt::new(1, u16::MAX).setpoints();
+        assert_eq!(last.time, u64::from(u16::MAX) * 1_000_000);
+    }
+
+    #[test]
+    fn motor_rpm_is_truncated() {
+        let [first, last] = RunAt::new(u16::MAX, 1).setpoints();
+        assert_eq!(first.rpm, u16::MAX);
+        assert_eq!(last.rpm, u16::MAX);
+    }
+}
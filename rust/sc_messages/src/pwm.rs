@@ -26,9 +26,25 @@ pub const STOP_DUTY: u16 = PERIOD / 40 * 3;
 
 /// A duty cycle.
 /// 0-100% is encoded as 0..[`PERIOD`].
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+#[derive(
+    Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Schema,
+)]
 pub struct DutyCycle(u16);
 
+impl DutyCycle {
+    /// Wraps a [`u16`] in [`DutyCycle`] in a `const` context.
+    ///
+    /// Truncates to [`MAX_POWER_DUTY`].
+    #[must_use]
+    pub const fn new(value: u16) -> Self {
+        if value > MAX_POWER_DUTY {
+            Self(MAX_POWER_DUTY)
+        } else {
+            Self(value)
+        }
+    }
+}
+
 impl Deref for DutyCycle {
     type Target = u16;
 
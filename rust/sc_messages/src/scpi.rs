@@ -0,0 +1,738 @@
+//! A line-oriented, [SCPI](https://en.wikipedia.org/wiki/Standard_Commands_for_Programmable_Instruments)-like
+//! text protocol for lab automation frameworks that can't speak postcard-rpc.
+//!
+//! The host daemon and the MCU both serve this protocol through [`Session`],
+//! so they accept exactly the same commands.
+//!
+//! Keywords can be shortened to their uppercase letters and are case-insensitive,
+//! e.g. `PROFile:CLEar` can be sent as `PROF:CLE` or `profile:clear`.
+//! Only queries (commands ending in `?`) respond.
+//! Errors are queued and read with `SYSTem:ERRor?`.
+
+use core::fmt::{self, Display, Formatter, Write};
+
+use crate::{
+    MOTOR_REVOLUTIONS, PLATE_REVOLUTIONS,
+    motion_profile::{self, Outcome, RequestRefused, RunAt, Setpoint, State},
+};
+
+/// The response to `*IDN?`: manufacturer, model, serial number and version.
+pub const IDENTITY: &str = concat!("IrvineHackerFab,Spincoater,0,", env!("CARGO_PKG_VERSION"));
+
+/// The longest line a [`Session`] has to accept.
+///
+/// This fits `PROFile:LOAD` with [`MAX_LOAD_SETPOINTS`] setpoints, even with the largest numbers.
+pub const MAX_LINE_LENGTH: usize = 512;
+
+/// The most setpoints `PROFile:LOAD` accepts in one line.
+///
+/// Longer motion profiles can be loaded with `PROFile:CLEar` and `PROFile:ADD`, up to [`motion_profile::MAX_SETPOINTS`].
+pub const MAX_LOAD_SETPOINTS: usize = 16;
+
+/// The number of errors kept until `SYSTem:ERRor?` reads them.
+pub const ERROR_QUEUE_LENGTH: usize = 8;
+
+/// The plate RPM of `SPIN:RUN` until `SPIN:RPM` is sent.
+pub const DEFAULT_SPIN_RPM: u16 = 5000;
+
+/// The time (in seconds) of `SPIN:RUN` until `SPIN:TIME` is sent.
+pub const DEFAULT_SPIN_TIME: u16 = 10;
+
+/// A parsed command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command<'a> {
+    /// `*IDN?`
+    Identify,
+    /// `*RST` stops any run, clears the motion profile and turns the vacuum pump off.
+    Reset,
+    /// `*CLS` clears the error queue.
+    ClearStatus,
+    /// `*OPC?` responds with `1` once every previous command has finished.
+    OperationComplete,
+    /// `SYSTem:ERRor?` responds with the oldest queued error.
+    NextError,
+    /// `SPIN:RPM <plate rpm>` sets the plate RPM of `SPIN:RUN`.
+    SetSpinRpm(u16),
+    /// `SPIN:RPM?`
+    SpinRpm,
+    /// `SPIN:TIME <seconds>` sets the time of `SPIN:RUN`.
+    SetSpinTime(u16),
+    /// `SPIN:TIME?`
+    SpinTime,
+    /// `SPIN:RUN` runs at a constant plate RPM, replacing the motion profile.
+    Spin,
+    /// `PROFile:CLEar`
+    ClearProfile,
+    /// `PROFile:ADD <motor rpm>,<time in micros>`
+    AddSetpoint(Setpoint),
+    /// `PROFile:LOAD <motor rpm>,<time in micros>[,<motor rpm>,<time in micros>...]`
+    /// replaces the motion profile with up to [`MAX_LOAD_SETPOINTS`] setpoints.
+    LoadProfile(Setpoints<'a>),
+    /// `PROFile:LOAD "<path>"` replaces the motion profile with a motion profile CSV file.
+    LoadProfileFile(&'a str),
+    /// `RUN` starts the motion profile.
+    Run,
+    /// `STOP` or `ABORt`
+    Stop,
+    /// `VACuum ON|OFF|1|0`
+    SetVacuum(bool),
+    /// `VACuum?`
+    Vacuum,
+    /// `MEASure:RPM?` responds with the measured plate RPM.
+    MeasureRpm,
+    /// `MEASure:RPM:SETpoint?` responds with the setpoint plate RPM.
+    MeasureSetpointRpm,
+    /// `MEASure:DUTY?` responds with the duty cycle from 0.0 to 1.0.
+    MeasureDutyCycle,
+    /// `STATus?` responds with `RUNNING` or `IDLE`.
+    Status,
+    /// `STATus:OUTCome?` responds with how the previous run ended, or `NONE`.
+    Outcome,
+}
+
+/// The setpoints of `PROFile:LOAD`, which have already been validated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setpoints<'a>(&'a str);
+
+impl<'a> Setpoints<'a> {
+    /// Validates a comma-separated list of setpoints.
+    fn parse(parameters: &'a str) -> Result<Self, Error> {
+        let setpoints = Self(parameters);
+        let mut count = 0_usize;
+        let mut numbers = parameters.split(',');
+        while let Some(rpm) = numbers.next() {
+            let time = numbers.next().ok_or(Error::MissingParameter)?;
+            number::<u16>(rpm)?;
+            number::<u64>(time)?;
+            count = count.saturating_add(1);
+        }
+        if count > MAX_LOAD_SETPOINTS {
+            return Err(Error::DataOutOfRange);
+        }
+        Ok(setpoints)
+    }
+
+    /// Returns an iterator over the setpoints.
+    pub fn iter(&self) -> impl Iterator<Item = Setpoint> + 'a {
+        let mut numbers = self.0.split(',');
+        core::iter::from_fn(move || {
+            let rpm = number(numbers.next()?).ok()?;
+            let time = number(numbers.next()?).ok()?;
+            Some(Setpoint { rpm, time })
+        })
+    }
+}
+
+/// Why a command failed.
+///
+/// The codes are the standard SCPI error codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The line isn't a command.
+    Syntax,
+    /// The command doesn't take parameters.
+    ParameterNotAllowed,
+    /// The command needs more parameters.
+    MissingParameter,
+    /// The command doesn't exist.
+    UndefinedHeader,
+    /// A number is too large.
+    DataOutOfRange,
+    /// A parameter isn't one of the allowed values.
+    IllegalParameterValue,
+    /// The MCU refused a request.
+    Refused(RequestRefused),
+    /// The command couldn't be executed.
+    Execution(&'static str),
+    /// Errors were dropped because nobody read them.
+    QueueOverflow,
+}
+
+impl Error {
+    /// The SCPI error code.
+    #[must_use]
+    pub fn code(self) -> i16 {
+        match self {
+            Self::Syntax => -102,
+            Self::ParameterNotAllowed => -108,
+            Self::MissingParameter => -109,
+            Self::UndefinedHeader => -113,
+            Self::DataOutOfRange => -222,
+            Self::IllegalParameterValue => -224,
+            Self::Refused(_) | Self::Execution(_) => -200,
+            Self::QueueOverflow => -350,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{},\"", self.code())?;
+        match self {
+            Self::Syntax => write!(f, "Syntax error")?,
+            Self::ParameterNotAllowed => write!(f, "Parameter not allowed")?,
+            Self::MissingParameter => write!(f, "Missing parameter")?,
+            Self::UndefinedHeader => write!(f, "Undefined header")?,
+            Self::DataOutOfRange => write!(f, "Data out of range")?,
+            Self::IllegalParameterValue => write!(f, "Illegal parameter value")?,
+            Self::Refused(refused) => write!(f, "Execution error; {refused:?}")?,
+            Self::Execution(reason) => write!(f, "Execution error; {reason}")?,
+            Self::QueueOverflow => write!(f, "Queue overflow")?,
+        }
+        write!(f, "\"")
+    }
+}
+
+/// Whether a keyword matches its long form (e.g. `PROFile`) or short form (`PROF`).
+fn keyword(input: &str, long: &str) -> bool {
+    let short_length = long.bytes().take_while(u8::is_ascii_uppercase).count();
+    input.eq_ignore_ascii_case(long)
+        || long
+            .get(..short_length)
+            .is_some_and(|short| input.eq_ignore_ascii_case(short))
+}
+
+/// Parses a number parameter.
+fn number<T: core::str::FromStr>(parameter: &str) -> Result<T, Error> {
+    let parameter = parameter.trim();
+    if parameter.is_empty() {
+        return Err(Error::MissingParameter);
+    }
+    if !parameter.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(Error::IllegalParameterValue);
+    }
+    parameter.parse().map_err(|_| Error::DataOutOfRange)
+}
+
+/// Parses a line.
+///
+/// Returns [`None`] for blank lines.
+///
+/// # Errors
+/// Returns an error if the line isn't a valid command.
+#[allow(clippy::too_many_lines)]
+pub fn parse(line: &str) -> Result<Option<Command<'_>>, Error> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let (header, parameters) = match line.split_once(char::is_whitespace) {
+        Some((header, parameters)) => (header, Some(parameters.trim())),
+        None => (line, None),
+    };
+    let (header, query) = match header.strip_suffix('?') {
+        Some(header) => (header, true),
+        None => (header, false),
+    };
+    let header = header.strip_prefix(':').unwrap_or(header);
+    if header.is_empty() {
+        return Err(Error::Syntax);
+    }
+
+    let mut keywords = header.split(':');
+    let first = keywords.next().unwrap_or_default();
+    let second = keywords.next();
+    let third = keywords.next();
+    if keywords.next().is_some() {
+        return Err(Error::UndefinedHeader);
+    }
+
+    let command = match (first, second, third, query) {
+        (common, None, None, true) if common.eq_ignore_ascii_case("*IDN") => Command::Identify,
+        (common, None, None, false) if common.eq_ignore_ascii_case("*RST") => Command::Reset,
+        (common, None, None, false) if common.eq_ignore_ascii_case("*CLS") => Command::ClearStatus,
+        (common, None, None, true) if common.eq_ignore_ascii_case("*OPC") => {
+            Command::OperationComplete
+        }
+        (system, Some(error), None, true)
+            if keyword(system, "SYSTem") && keyword(error, "ERRor") =>
+        {
+            Command::NextError
+        }
+        (spin, Some(rpm), None, _) if keyword(spin, "SPIN") && keyword(rpm, "RPM") => {
+            if query {
+                Command::SpinRpm
+            } else {
+                Command::SetSpinRpm(number(parameters.ok_or(Error::MissingParameter)?)?)
+            }
+        }
+        (spin, Some(time), None, _) if keyword(spin, "SPIN") && keyword(time, "TIME") => {
+            if query {
+                Command::SpinTime
+            } else {
+                Command::SetSpinTime(number(parameters.ok_or(Error::MissingParameter)?)?)
+            }
+        }
+        (spin, Some(run), None, false)
+            if keyword(spin, "SPIN") && (keyword(run, "RUN") || keyword(run, "STARt")) =>
+        {
+            Command::Spin
+        }
+        (profile, Some(clear), None, false)
+            if keyword(profile, "PROFile") && keyword(clear, "CLEar") =>
+        {
+            Command::ClearProfile
+        }
+        (profile, Some(add), None, false) if keyword(profile, "PROFile") && keyword(add, "ADD") => {
+            let (rpm, time) = parameters
+                .ok_or(Error::MissingParameter)?
+                .split_once(',')
+                .ok_or(Error::MissingParameter)?;
+            Command::AddSetpoint(Setpoint {
+                rpm: number(rpm)?,
+                time: number(time)?,
+            })
+        }
+        (profile, Some(load), None, false)
+            if keyword(profile, "PROFile") && keyword(load, "LOAD") =>
+        {
+            let parameters = parameters.ok_or(Error::MissingParameter)?;
+            match parameters
+                .strip_prefix('"')
+                .and_then(|path| path.strip_suffix('"'))
+            {
+                Some(path) => Command::LoadProfileFile(path),
+                None => Command::LoadProfile(Setpoints::parse(parameters)?),
+            }
+        }
+        (run, None, None, false) if keyword(run, "RUN") => Command::Run,
+        (stop, None, None, false) if keyword(stop, "STOP") || keyword(stop, "ABORt") => {
+            Command::Stop
+        }
+        (vacuum, None, None, _) if keyword(vacuum, "VACuum") => {
+            if query {
+                Command::Vacuum
+            } else {
+                let parameter = parameters.ok_or(Error::MissingParameter)?;
+                if parameter.eq_ignore_ascii_case("ON") || parameter == "1" {
+                    Command::SetVacuum(true)
+                } else if parameter.eq_ignore_ascii_case("OFF") || parameter == "0" {
+                    Command::SetVacuum(false)
+                } else {
+                    return Err(Error::IllegalParameterValue);
+                }
+            }
+        }
+        (measure, Some(rpm), None, true) if keyword(measure, "MEASure") && keyword(rpm, "RPM") => {
+            Command::MeasureRpm
+        }
+        (measure, Some(rpm), Some(setpoint), true)
+            if keyword(measure, "MEASure")
+                && keyword(rpm, "RPM")
+                && keyword(setpoint, "SETpoint") =>
+        {
+            Command::MeasureSetpointRpm
+        }
+        (measure, Some(duty), None, true)
+            if keyword(measure, "MEASure") && keyword(duty, "DUTY") =>
+        {
+            Command::MeasureDutyCycle
+        }
+        (status, None, None, true) if keyword(status, "STATus") => Command::Status,
+        (status, Some(outcome), None, true)
+            if keyword(status, "STATus") && keyword(outcome, "OUTCome") =>
+        {
+            Command::Outcome
+        }
+        _ => return Err(Error::UndefinedHeader),
+    };
+
+    // Only commands that were given their parameters above may have any.
+    let takes_parameters = matches!(
+        command,
+        Command::SetSpinRpm(_)
+            | Command::SetSpinTime(_)
+            | Command::AddSetpoint(_)
+            | Command::LoadProfile(_)
+            | Command::LoadProfileFile(_)
+            | Command::SetVacuum(_)
+    );
+    if parameters.is_some() && !takes_parameters {
+        return Err(Error::ParameterNotAllowed);
+    }
+    Ok(Some(command))
+}
+
+/// The SCPI name of an outcome.
+#[must_use]
+pub fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Completed => "COMPLETED",
+        Outcome::Stopped => "STOPPED",
+        Outcome::HostDisconnected => "HOST_DISCONNECTED",
+        Outcome::TimedOut => "TIMED_OUT",
+        Outcome::Fault => "FAULT",
+    }
+}
+
+/// Converts motor RPM to plate RPM for measurements.
+#[allow(clippy::cast_precision_loss)]
+fn to_plate_rpm(rpm: u16) -> f32 {
+    f32::from(rpm) * PLATE_REVOLUTIONS as f32 / MOTOR_REVOLUTIONS as f32
+}
+
+/// Something that can execute SCPI commands, i.e. the MCU or a connection to it.
+pub trait Instrument {
+    /// Sends a motion profile request.
+    fn motion_profile_request(
+        &mut self,
+        request: motion_profile::Request,
+    ) -> impl Future<Output = Result<(), Error>>;
+
+    /// Runs at a constant plate RPM, replacing the motion profile.
+    fn run_at(&mut self, run_at: RunAt) -> impl Future<Output = Result<(), Error>>;
+
+    /// Replaces the motion profile with a motion profile CSV file.
+    ///
+    /// # Errors
+    /// By default, files aren't supported.
+    fn load_profile_file(&mut self, path: &str) -> impl Future<Output = Result<(), Error>> {
+        let _ = path;
+        async { Err(Error::Execution("Files are not supported")) }
+    }
+
+    /// Turns the vacuum pump on or off.
+    fn set_vacuum(&mut self, on: bool) -> impl Future<Output = Result<(), Error>>;
+
+    /// Whether the vacuum pump is on.
+    fn vacuum(&self) -> bool;
+
+    /// The latest state of the current run, or [`None`] if nothing is running.
+    fn state(&self) -> Option<State>;
+
+    /// How the previous run ended.
+    fn outcome(&self) -> Option<Outcome>;
+}
+
+/// The state of one SCPI connection.
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// The plate RPM of `SPIN:RUN`.
+    spin_rpm: u16,
+    /// The time (in seconds) of `SPIN:RUN`.
+    spin_time: u16,
+    /// The errors that haven't been read yet, oldest first.
+    errors: [Option<Error>; ERROR_QUEUE_LENGTH],
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    /// Creates a session with the default settings and no errors.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            spin_rpm: DEFAULT_SPIN_RPM,
+            spin_time: DEFAULT_SPIN_TIME,
+            errors: [None; ERROR_QUEUE_LENGTH],
+        }
+    }
+
+    /// Queues an error, e.g. [`Error::Syntax`] for a line longer than [`MAX_LINE_LENGTH`].
+    ///
+    /// If the queue is full, the newest error is replaced with [`Error::QueueOverflow`].
+    pub fn push_error(&mut self, error: Error) {
+        if let Some(slot) = self.errors.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(error);
+        } else if let Some(last) = self.errors.last_mut() {
+            *last = Some(Error::QueueOverflow);
+        }
+    }
+
+    /// Removes the oldest error.
+    fn pop_error(&mut self) -> Option<Error> {
+        let error = self.errors[0].take();
+        self.errors.rotate_left(1);
+        error
+    }
+
+    /// Parses and executes a line, writing the response of queries (with a trailing newline) to `response`.
+    ///
+    /// Errors are queued rather than returned.
+    ///
+    /// # Errors
+    /// Returns an error if writing the response fails.
+    pub async fn handle_line<I: Instrument, W: Write>(
+        &mut self,
+        instrument: &mut I,
+        line: &str,
+        response: &mut W,
+    ) -> fmt::Result {
+        let command = match parse(line) {
+            Ok(Some(command)) => command,
+            Ok(None) => return Ok(()),
+            Err(error) => {
+                self.push_error(error);
+                return Ok(());
+            }
+        };
+        match self.execute(instrument, command, response).await {
+            Ok(result) => result,
+            Err(error) => {
+                self.push_error(error);
+                Ok(())
+            }
+        }
+    }
+
+    /// Executes a command.
+    ///
+    /// The outer result is the command's, and the inner one is the response writer's.
+    #[allow(clippy::too_many_lines)]
+    async fn execute<I: Instrument, W: Write>(
+        &mut self,
+        instrument: &mut I,
+        command: Command<'_>,
+        response: &mut W,
+    ) -> Result<fmt::Result, Error> {
+        let written = match command {
+            Command::Identify => writeln!(response, "{IDENTITY}"),
+            Command::Reset => {
+                instrument
+                    .motion_profile_request(motion_profile::Request::Stop)
+                    .await?;
+                instrument
+                    .motion_profile_request(motion_profile::Request::ClearSetpoints)
+                    .await?;
+                instrument.set_vacuum(false).await?;
+                self.spin_rpm = DEFAULT_SPIN_RPM;
+                self.spin_time = DEFAULT_SPIN_TIME;
+                Ok(())
+            }
+            Command::ClearStatus => {
+                self.errors = [None; ERROR_QUEUE_LENGTH];
+                Ok(())
+            }
+            Command::OperationComplete => writeln!(response, "1"),
+            Command::NextError => match self.pop_error() {
+                Some(error) => writeln!(response, "{error}"),
+                None => writeln!(response, "0,\"No error\""),
+            },
+            Command::SetSpinRpm(rpm) => {
+                self.spin_rpm = rpm;
+                Ok(())
+            }
+            Command::SpinRpm => writeln!(response, "{}", self.spin_rpm),
+            Command::SetSpinTime(time) => {
+                self.spin_time = time;
+                Ok(())
+            }
+            Command::SpinTime => writeln!(response, "{}", self.spin_time),
+            Command::Spin => {
+                let run_at = RunAt::new(self.spin_rpm, self.spin_time);
+                if !run_at.is_valid() {
+                    return Err(Error::DataOutOfRange);
+                }
+                instrument.run_at(run_at).await?;
+                Ok(())
+            }
+            Command::ClearProfile => {
+                instrument
+                    .motion_profile_request(motion_profile::Request::ClearSetpoints)
+                    .await?;
+                Ok(())
+            }
+            Command::AddSetpoint(setpoint) => {
+                instrument
+                    .motion_profile_request(motion_profile::Request::Add(setpoint))
+                    .await?;
+                Ok(())
+            }
+            Command::LoadProfile(setpoints) => {
+                instrument
+                    .motion_profile_request(motion_profile::Request::ClearSetpoints)
+                    .await?;
+                for setpoint in setpoints.iter() {
+                    instrument
+                        .motion_profile_request(motion_profile::Request::Add(setpoint))
+                        .await?;
+                }
+                Ok(())
+            }
+            Command::LoadProfileFile(path) => {
+                instrument.load_profile_file(path).await?;
+                Ok(())
+            }
+            Command::Run => {
+                instrument
+                    .motion_profile_request(motion_profile::Request::Start)
+                    .await?;
+                Ok(())
+            }
+            Command::Stop => {
+                instrument
+                    .motion_profile_request(motion_profile::Request::Stop)
+                    .await?;
+                Ok(())
+            }
+            Command::SetVacuum(on) => {
+                instrument.set_vacuum(on).await?;
+                Ok(())
+            }
+            Command::Vacuum => writeln!(response, "{}", u8::from(instrument.vacuum())),
+            Command::MeasureRpm => {
+                let rpm = instrument.state().map_or(0, |state| state.current_rpm);
+                writeln!(response, "{:.1}", to_plate_rpm(rpm))
+            }
+            Command::MeasureSetpointRpm => {
+                let rpm = instrument.state().map_or(0, |state| state.setpoint_rpm);
+                writeln!(response, "{:.1}", to_plate_rpm(rpm))
+            }
+            Command::MeasureDutyCycle => {
+                let duty_cycle = instrument
+                    .state()
+                    .map_or(crate::pwm::STOP_DUTY, |state| *state.duty_cycle);
+                writeln!(
+                    response,
+                    "{:.4}",
+                    f32::from(duty_cycle) / f32::from(crate::pwm::PERIOD)
+                )
+            }
+            Command::Status => {
+                if instrument.state().is_some() {
+                    writeln!(response, "RUNNING")
+                } else {
+                    writeln!(response, "IDLE")
+                }
+            }
+            Command::Outcome => match instrument.outcome() {
+                Some(outcome) => writeln!(response, "{}", outcome_name(outcome)),
+                None => writeln!(response, "NONE"),
+            },
+        };
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `PROFile:LOAD` line with `count` setpoints, all with the largest numbers.
+    fn load_line(count: usize, buffer: &mut [u8; 1024]) -> &str {
+        let mut line = Line { buffer, length: 0 };
+        let _ = write!(line, "PROFile:LOAD ");
+        for i in 0..count {
+            let separator = if i == 0 { "" } else { "," };
+            let _ = write!(line, "{separator}{},{}", u16::MAX, u64::MAX);
+        }
+        let Line { buffer, length } = line;
+        core::str::from_utf8(&buffer[..length]).unwrap_or_default()
+    }
+
+    /// Writes into a fixed buffer, since this crate has no allocator.
+    struct Line<'a> {
+        buffer: &'a mut [u8; 1024],
+        length: usize,
+    }
+
+    impl Write for Line<'_> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.length + s.len();
+            self.buffer
+                .get_mut(self.length..end)
+                .ok_or(fmt::Error)?
+                .copy_from_slice(s.as_bytes());
+            self.length = end;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn keywords_can_be_shortened_and_lowercase() {
+        assert_eq!(parse("PROFile:CLEar"), Ok(Some(Command::ClearProfile)));
+        assert_eq!(parse("prof:cle"), Ok(Some(Command::ClearProfile)));
+        assert_eq!(parse(":SYST:ERR?"), Ok(Some(Command::NextError)));
+        assert_eq!(parse("  "), Ok(None));
+    }
+
+    #[test]
+    fn partial_keywords_are_undefined() {
+        assert_eq!(parse("PRO:CLE"), Err(Error::UndefinedHeader));
+        assert_eq!(parse("PROFil:CLE"), Err(Error::UndefinedHeader));
+        assert_eq!(parse("MEAS:RPM:SET:X?"), Err(Error::UndefinedHeader));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("?"), Err(Error::Syntax));
+        assert_eq!(parse("SPIN:RPM"), Err(Error::MissingParameter));
+        assert_eq!(parse("SPIN:RPM abc"), Err(Error::IllegalParameterValue));
+        assert_eq!(parse("SPIN:RPM -1"), Err(Error::IllegalParameterValue));
+        assert_eq!(parse("SPIN:RPM 65536"), Err(Error::DataOutOfRange));
+        assert_eq!(parse("RUN now"), Err(Error::ParameterNotAllowed));
+        assert_eq!(parse("VAC maybe"), Err(Error::IllegalParameterValue));
+        assert_eq!(parse("PROF:ADD 100"), Err(Error::MissingParameter));
+        assert_eq!(parse("PROF:LOAD 100,1,200"), Err(Error::MissingParameter));
+    }
+
+    #[test]
+    fn parameters() {
+        assert_eq!(parse("SPIN:RPM 3000"), Ok(Some(Command::SetSpinRpm(3000))));
+        assert_eq!(parse("VAC ON"), Ok(Some(Command::SetVacuum(true))));
+        assert_eq!(parse("vac 0"), Ok(Some(Command::SetVacuum(false))));
+        assert_eq!(
+            parse("PROF:ADD 100, 2000"),
+            Ok(Some(Command::AddSetpoint(Setpoint {
+                rpm: 100,
+                time: 2000
+            })))
+        );
+        assert_eq!(
+            parse("PROF:LOAD \"profile.csv\""),
+            Ok(Some(Command::LoadProfileFile("profile.csv")))
+        );
+    }
+
+    #[test]
+    fn load_profile_setpoints() {
+        let Ok(Some(Command::LoadProfile(setpoints))) = parse("PROF:LOAD 100,1000,200,2000") else {
+            panic!("PROFile:LOAD should parse");
+        };
+        let mut setpoints = setpoints.iter();
+        assert_eq!(
+            setpoints.next(),
+            Some(Setpoint {
+                rpm: 100,
+                time: 1000
+            })
+        );
+        assert_eq!(
+            setpoints.next(),
+            Some(Setpoint {
+                rpm: 200,
+                time: 2000
+            })
+        );
+        assert_eq!(setpoints.next(), None);
+    }
+
+    #[test]
+    fn load_profile_limits() {
+        let mut buffer = [0; 1024];
+        let line = load_line(MAX_LOAD_SETPOINTS, &mut buffer);
+        assert!(line.len() <= MAX_LINE_LENGTH);
+        assert!(matches!(parse(line), Ok(Some(Command::LoadProfile(_)))));
+
+        let mut buffer = [0; 1024];
+        let line = load_line(MAX_LOAD_SETPOINTS + 1, &mut buffer);
+        assert_eq!(parse(line), Err(Error::DataOutOfRange));
+    }
+
+    #[test]
+    fn error_queue_overflows() {
+        let mut session = Session::new();
+        for _ in 0..=ERROR_QUEUE_LENGTH {
+            session.push_error(Error::Syntax);
+        }
+        for _ in 1..ERROR_QUEUE_LENGTH {
+            assert_eq!(session.pop_error(), Some(Error::Syntax));
+        }
+        assert_eq!(session.pop_error(), Some(Error::QueueOverflow));
+        assert_eq!(session.pop_error(), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! This module describes how the microcontroller publishes the state of runs.
+//!
+//! By default, every control loop iteration's state is published on its own.
+//! Decimation and batching reduce the load on the UART, so the control rate can be raised
+//! or fields added without saturating the link.
+
+use heapless::Vec;
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::motion_profile::State;
+
+/// The most states that can be sent in a single [`StateBatch`].
+///
+/// A full batch of the largest possible states stays well within the host's 1024-byte frames.
+pub const MAX_BATCH_SIZE: usize = 16;
+
+/// How often and in what size of frames the MCU publishes states.
+///
+/// The MCU applies a new config at the start of the next run.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub struct Config {
+    /// Only every `decimation`th control loop iteration's state is published.
+    ///
+    /// 1 publishes every iteration.
+    pub decimation: u8,
+    /// The number of states sent together in a [`StateBatch`].
+    ///
+    /// 1 publishes each state on its own, like before batching existed.
+    pub batch_size: u8,
+}
+
+impl Config {
+    /// The config the MCU starts with, which publishes every state on its own.
+    pub const DEFAULT: Self = Self {
+        decimation: 1,
+        batch_size: 1,
+    };
+
+    /// Checks that the decimation is nonzero and the batch size is between 1 and [`MAX_BATCH_SIZE`].
+    ///
+    /// # Errors
+    /// Returns the first reason the config is unusable.
+    pub fn validate(&self) -> RequestResult {
+        if self.decimation == 0 {
+            Err(RequestRefused::InvalidDecimation)
+        } else if self.batch_size == 0 || usize::from(self.batch_size) > MAX_BATCH_SIZE {
+            Err(RequestRefused::InvalidBatchSize)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether states are sent in [`StateBatch`]es instead of on their own.
+    #[must_use]
+    pub fn is_batched(&self) -> bool {
+        self.batch_size > 1
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The possible reasons why the MCU might refuse a telemetry config.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Schema)]
+pub enum RequestRefused {
+    /// The decimation is zero.
+    InvalidDecimation,
+    /// The batch size is zero or above [`MAX_BATCH_SIZE`].
+    InvalidBatchSize,
+}
+
+/// See [this issue](https://github.com/jamesmunns/postcard-rpc/issues/56) for why we need a type alias.
+pub type RequestResult = Result<(), RequestRefused>;
+
+/// Consecutive published states of a run, oldest first.
+///
+/// The end of a run is still published as a [`None`] state, after the last batch.
+pub type StateBatch = Vec<State, MAX_BATCH_SIZE>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config with this decimation and batch size.
+    fn config(decimation: u8, batch_size: u8) -> Config {
+        Config {
+            decimation,
+            batch_size,
+        }
+    }
+
+    #[test]
+    fn default_publishes_every_state_on_its_own() {
+        assert_eq!(Config::default().validate(), Ok(()));
+        assert!(!Config::default().is_batched());
+    }
+
+    #[test]
+    fn decimation_must_be_nonzero() {
+        assert_eq!(config(1, 1).validate(), Ok(()));
+        assert_eq!(config(u8::MAX, 1).validate(), Ok(()));
+        assert_eq!(
+            config(0, 1).validate(),
+            Err(RequestRefused::InvalidDecimation)
+        );
+        // The decimation is checked first.
+        assert_eq!(
+            config(0, 0).validate(),
+            Err(RequestRefused::InvalidDecimation)
+        );
+    }
+
+    #[test]
+    fn batch_size_must_fit_in_a_batch() {
+        let max_batch_size = u8::try_from(MAX_BATCH_SIZE).expect("Batch size too large");
+        assert_eq!(config(1, max_batch_size).validate(), Ok(()));
+        assert!(config(1, max_batch_size).is_batched());
+        assert!(config(1, 2).is_batched());
+        assert_eq!(
+            config(1, 0).validate(),
+            Err(RequestRefused::InvalidBatchSize)
+        );
+        assert_eq!(
+            config(1, max_batch_size + 1).validate(),
+            Err(RequestRefused::InvalidBatchSize)
+        );
+    }
+}
@@ -0,0 +1,770 @@
+//! This crate contains an async client for communicating with the spincoater's MCU from the host PC.
+//!
+//! Every request is an async method that resolves to the MCU's response.
+//! Topics published by the MCU are received through [`Subscription`]s,
+//! except for states, which are received through a [`StateSubscription`] whether or not the MCU batches them.
+
+pub mod state;
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use postcard_rpc::{
+    Endpoint,
+    header::{VarSeq, VarSeqKind},
+    host_client::{HostClient, HostErr, SubscribeError},
+    postcard_schema::Schema,
+    standard_icd::{ERROR_PATH, LoggingTopic, WireError},
+};
+use sc_messages::{
+    diagnostics::{DeviceInfo, LoopTiming},
+    icd::{
+        BAUD_RATE, BaudRateEndpoint, DeviceInfoEndpoint, HostDisconnecting, JogRequestEndpoint,
+        LoopTimingEndpoint, MotionProfileOutcomeTopic, MotionProfileStateBatchTopic,
+        MotionProfileStateTopic, MotionRequestEndpoint, RunAtEndpoint, TelemetryConfigEndpoint,
+        TouchPointTopic, VacuumPumpRequestEndpoint,
+    },
+    jog,
+    link::{self, SWITCH_TIMEOUT_MILLIS},
+    motion_profile::{self, Outcome, RequestResult, RunAt, Setpoint, State, StateOrDisabled},
+    telemetry::{self, StateBatch},
+    touchscreen::TouchPoint,
+    vacuum_pump,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+use tokio::time::{Instant, sleep, sleep_until, timeout, timeout_at};
+use tokio_serial::{SerialPortInfo, SerialPortType, available_ports};
+
+pub use postcard_rpc::host_client::Subscription;
+
+/// The size of the outgoing queue.
+pub const TX_QUEUE_SIZE: usize = 128;
+
+/// The size of sequuence numbers used when making requests.
+///
+/// [`postcard_rpc`] gives no hint as to what this should be.
+pub const VAR_SEQUENCE_KIND: VarSeqKind = VarSeqKind::Seq2;
+
+/// The Vendor ID that shows up when you connect an ESP32 `DevKitC` to a PC over USB.
+pub const DEV_KIT_C_VENDOR_ID: u16 = 4292;
+
+/// The Vendor ID that shows up when you connect an ESP-Prog-2 to a PC over USB.
+pub const ESP_PROG_2_VENDOR_ID: u16 = 12346;
+
+/// The number of messages a [`Subscription`] holds before new ones are dropped.
+pub const SUBSCRIPTION_DEPTH: usize = 128;
+
+/// [`postcard_rpc`] requires us to choose a message sequence number and does not explain why.
+const INITIAL_VAR_SEQ: VarSeq = VarSeq::Seq1(0);
+
+/// How long [`Client::notify_disconnecting`] waits before giving up.
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often [`Client::connect_at`] tries to reopen the serial port while the previous connection lets go of it.
+const REOPEN_PERIOD: Duration = Duration::from_millis(10);
+
+/// How long [`Client::connect_at`] waits for the MCU to answer a proposed baud rate.
+const PROPOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long [`Client::connect_at`] waits after the MCU should have fallen back to [`BAUD_RATE`] before reconnecting.
+const FALLBACK_MARGIN: Duration = Duration::from_millis(100);
+
+/// The errors that can occur while communicating with the MCU.
+///
+/// Refused requests are not errors, and are returned in the response instead.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The serial ports couldn't be listed.
+    #[error("Failed to list the serial ports: {0}")]
+    ListPorts(#[from] tokio_serial::Error),
+    /// The serial port couldn't be opened.
+    #[error("Failed to initialize USB connection: {0}")]
+    Connect(String),
+    /// A request couldn't be sent or its response couldn't be received.
+    #[error("Failed to send command: {0}")]
+    Request(#[from] HostErr<WireError>),
+    /// A topic couldn't be subscribed to.
+    #[error("Failed to subscribe to a topic: {0}")]
+    Subscribe(#[from] SubscribeError),
+    /// No ESP devices are plugged in.
+    #[error("No ESP devices detected. Please plug one in and run this program again.")]
+    NoDevices,
+    /// More than one ESP device is plugged in, so the port must be chosen.
+    #[error("Multiple ESP devices detected. Please choose one with --port.")]
+    MultipleDevices,
+    /// The MCU didn't answer at [`BAUD_RATE`], so it may still be at a baud rate from an earlier connection.
+    #[error("The MCU didn't respond. Please reset it and try again.")]
+    Unresponsive,
+}
+
+/// See [`Error`].
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Why [`Client::connect_at`] stayed at or fell back to [`BAUD_RATE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum Fallback {
+    /// The MCU refused the proposed baud rate.
+    #[error("The MCU refused the baud rate: {0:?}")]
+    Refused(link::RequestRefused),
+    /// The link didn't come up at the proposed baud rate in time.
+    #[error("The link didn't come up at the new baud rate in time")]
+    TimedOut,
+}
+
+/// Anything that messages from the MCU are received through, so they can be forwarded generically.
+pub trait Receive: Send {
+    /// The type of the messages.
+    type Message;
+
+    /// Awaits the next message.
+    ///
+    /// Returns [`None`] once the connection closes.
+    fn recv(&mut self) -> impl Future<Output = Option<Self::Message>> + Send;
+}
+
+impl<M> Receive for Subscription<M>
+where
+    M: DeserializeOwned + Send,
+{
+    type Message = M;
+
+    fn recv(&mut self) -> impl Future<Output = Option<M>> + Send {
+        Subscription::recv(self)
+    }
+}
+
+/// The states of runs, whether the MCU publishes them on their own or in batches.
+///
+/// States are received one at a time and in order either way.
+pub struct StateSubscription {
+    /// The states published on their own, and the end of every run.
+    states: Subscription<StateOrDisabled>,
+    /// The states published in batches.
+    batches: Subscription<StateBatch>,
+    /// The states of the current run's batches that haven't been received yet.
+    pending: VecDeque<State>,
+    /// The states of a batch from the next run, held back until the current run ends.
+    next_run: VecDeque<State>,
+    /// The [`State::sample`] of the last state received, or [`None`] between runs.
+    last_sample: Option<u32>,
+}
+
+impl StateSubscription {
+    /// Awaits the next state.
+    ///
+    /// [`None`] is received at the end of every run, after the run's last batch
+    /// and before the next run's first batch.
+    /// Returns [`None`] once the connection closes.
+    pub async fn recv(&mut self) -> Option<StateOrDisabled> {
+        loop {
+            if let Some(state) = self.pending.pop_front() {
+                self.last_sample = Some(state.sample);
+                return Some(Some(state));
+            }
+            // Both topics are filled in the order the MCU published them,
+            // so checking batches first keeps a run's last batch ahead of its end.
+            // A batch that was queued after the end is held back until the end is received.
+            tokio::select! {
+                biased;
+                batch = self.batches.recv(), if self.next_run.is_empty() => {
+                    let batch = batch?;
+                    if self.starts_next_run(&batch) {
+                        self.next_run.extend(batch);
+                    } else {
+                        self.pending.extend(batch);
+                    }
+                }
+                state = self.states.recv() => {
+                    let state = state?;
+                    self.last_sample = state.as_ref().map(|state| state.sample);
+                    if state.is_none() {
+                        self.pending.append(&mut self.next_run);
+                    }
+                    return Some(state);
+                }
+            }
+        }
+    }
+
+    /// Whether a batch belongs to the run after the current one.
+    ///
+    /// Samples increase through a run and restart at the next run,
+    /// so a batch that doesn't continue after the last state received starts a new run.
+    fn starts_next_run(&self, batch: &StateBatch) -> bool {
+        match (self.last_sample, batch.first()) {
+            (Some(last_sample), Some(first)) => first.sample <= last_sample,
+            _ => false,
+        }
+    }
+}
+
+impl Receive for StateSubscription {
+    type Message = StateOrDisabled;
+
+    fn recv(&mut self) -> impl Future<Output = Option<StateOrDisabled>> + Send {
+        StateSubscription::recv(self)
+    }
+}
+
+/// Returns the serial ports that ESP devices are plugged into.
+///
+/// # Errors
+/// Returns an error if the serial ports can't be listed.
+pub fn esp_ports() -> Result<Vec<SerialPortInfo>> {
+    Ok(available_ports()?
+        .into_iter()
+        .filter(|info| match &info.port_type {
+            SerialPortType::UsbPort(usb_port_info) => {
+                usb_port_info.vid == DEV_KIT_C_VENDOR_ID
+                    || usb_port_info.vid == ESP_PROG_2_VENDOR_ID
+            }
+            _ => false,
+        })
+        .collect())
+}
+
+/// Returns the serial port of the only ESP device plugged in.
+///
+/// # Errors
+/// Returns an error if there isn't exactly one ESP device plugged in.
+pub fn only_esp_port() -> Result<String> {
+    let mut ports = esp_ports()?;
+    match (ports.pop(), ports.is_empty()) {
+        (Some(port), true) => Ok(port.port_name),
+        (Some(_), false) => Err(Error::MultipleDevices),
+        (None, _) => Err(Error::NoDevices),
+    }
+}
+
+/// A connection to the MCU.
+///
+/// Cloning the client shares the connection.
+#[derive(Debug, Clone)]
+pub struct Client {
+    /// The underlying [`postcard_rpc`] client.
+    host_client: HostClient<WireError>,
+    /// The number of requests that couldn't be sent or answered.
+    link_errors: Arc<AtomicU64>,
+}
+
+impl From<HostClient<WireError>> for Client {
+    fn from(host_client: HostClient<WireError>) -> Self {
+        Self {
+            host_client,
+            link_errors: Arc::default(),
+        }
+    }
+}
+
+impl Client {
+    /// Connects to the MCU on a serial port.
+    ///
+    /// # Errors
+    /// Returns an error if the serial port can't be opened.
+    pub fn connect(port_name: &str) -> Result<Self> {
+        Self::open(port_name, BAUD_RATE)
+    }
+
+    /// Opens a serial port at `baud_rate`.
+    fn open(port_name: &str, baud_rate: u32) -> Result<Self> {
+        HostClient::try_new_serial_cobs(
+            port_name,
+            ERROR_PATH,
+            TX_QUEUE_SIZE,
+            baud_rate,
+            VAR_SEQUENCE_KIND,
+        )
+        .map(Self::from)
+        .map_err(Error::Connect)
+    }
+
+    /// Connects to the MCU on a serial port at [`BAUD_RATE`], then has both sides switch to `baud_rate`.
+    ///
+    /// If the MCU refuses the baud rate or the link doesn't come up at it,
+    /// both sides stay at or fall back to [`BAUD_RATE`], and the reason is returned with the client.
+    /// Falling back takes a little over [`SWITCH_TIMEOUT_MILLIS`].
+    ///
+    /// # Errors
+    /// Returns an error if the serial port can't be opened, or the proposal couldn't be sent or wasn't answered in time.
+    pub async fn connect_at(port_name: &str, baud_rate: u32) -> Result<(Self, Option<Fallback>)> {
+        Self::negotiate(
+            |baud_rate, deadline| Self::reopen(port_name, baud_rate, deadline),
+            baud_rate,
+        )
+        .await
+    }
+
+    /// Carries out [`Client::connect_at`] over the connections that `open` makes at a baud rate,
+    /// retrying until a deadline.
+    async fn negotiate<F, O>(open: O, baud_rate: u32) -> Result<(Self, Option<Fallback>)>
+    where
+        F: Future<Output = Result<Self>>,
+        O: Fn(u32, Instant) -> F,
+    {
+        let client = open(BAUD_RATE, Instant::now()).await?;
+        if baud_rate == BAUD_RATE {
+            return Ok((client, None));
+        }
+        let proposal = timeout(
+            PROPOSE_TIMEOUT,
+            client.send::<BaudRateEndpoint>(&link::Request::Propose(baud_rate)),
+        )
+        .await;
+        let Ok(proposal) = proposal else {
+            client.close().await;
+            return Err(Error::Unresponsive);
+        };
+        if let Err(refused) = proposal? {
+            return Ok((client, Some(Fallback::Refused(refused))));
+        }
+        // The MCU switches right after replying, and falls back if it isn't confirmed in time.
+        let switch_timeout = Duration::from_millis(SWITCH_TIMEOUT_MILLIS);
+        let fallback = Instant::now() + switch_timeout + FALLBACK_MARGIN;
+        client.close().await;
+
+        // Confirm well before the MCU gives up, so both sides agree on whether the switch worked.
+        let confirm_deadline = Instant::now() + switch_timeout / 2;
+        if let Ok(client) = open(baud_rate, confirm_deadline).await {
+            let confirmation = timeout_at(
+                confirm_deadline,
+                client.send::<BaudRateEndpoint>(&link::Request::Confirm),
+            )
+            .await;
+            if let Ok(Ok(Ok(()))) = confirmation {
+                return Ok((client, None));
+            }
+            client.close().await;
+        }
+        sleep_until(fallback).await;
+        let client = open(BAUD_RATE, fallback + switch_timeout).await?;
+        Ok((client, Some(Fallback::TimedOut)))
+    }
+
+    /// Opens the serial port again at `baud_rate`, retrying until `deadline` while the previous connection lets go of it.
+    async fn reopen(port_name: &str, baud_rate: u32, deadline: Instant) -> Result<Self> {
+        loop {
+            match Self::open(port_name, baud_rate) {
+                Err(_) if Instant::now() < deadline => sleep(REOPEN_PERIOD).await,
+                opened => return opened,
+            }
+        }
+    }
+
+    /// Closes the connection, including for every clone of the client.
+    async fn close(&self) {
+        self.host_client.close();
+        self.host_client.wait_closed().await;
+    }
+
+    /// Whether the connection to the MCU is still open.
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        !self.host_client.is_closed()
+    }
+
+    /// The number of requests since connecting that couldn't be sent or answered.
+    ///
+    /// This is shared by every clone of the client.
+    #[must_use]
+    pub fn link_errors(&self) -> u64 {
+        self.link_errors.load(Ordering::Relaxed)
+    }
+
+    /// Sends a request to an endpoint, counting it in [`Client::link_errors`] if it fails.
+    async fn send<E: Endpoint>(&self, request: &E::Request) -> Result<E::Response>
+    where
+        E::Request: Serialize + Schema,
+        E::Response: DeserializeOwned + Schema,
+    {
+        let response = self.host_client.send_resp::<E>(request).await;
+        if response.is_err() {
+            self.link_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(response?)
+    }
+
+    /// Sends a motion profile request.
+    ///
+    /// # Errors
+    /// Returns an error if the request couldn't be sent.
+    pub async fn motion_profile_request(
+        &self,
+        request: &motion_profile::Request,
+    ) -> Result<RequestResult> {
+        self.send::<MotionRequestEndpoint>(request).await
+    }
+
+    /// Replaces the MCU's motion profile with `setpoints`.
+    ///
+    /// The setpoints are sent one at a time, and sending stops at the first refusal.
+    ///
+    /// # Errors
+    /// Returns an error if a request couldn't be sent.
+    pub async fn upload_profile(
+        &self,
+        setpoints: impl IntoIterator<Item = Setpoint>,
+    ) -> Result<RequestResult> {
+        let requests = std::iter::once(motion_profile::Request::ClearSetpoints)
+            .chain(setpoints.into_iter().map(motion_profile::Request::Add));
+        for request in requests {
+            if let Err(refused) = self.motion_profile_request(&request).await? {
+                return Ok(Err(refused));
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    /// Starts the MCU's motion profile.
+    ///
+    /// # Errors
+    /// Returns an error if the request couldn't be sent.
+    pub async fn start(&self) -> Result<RequestResult> {
+        self.motion_profile_request(&motion_profile::Request::Start)
+            .await
+    }
+
+    /// Stops the current motion profile.
+    ///
+    /// # Errors
+    /// Returns an error if the request couldn't be sent.
+    pub async fn stop(&self) -> Result<RequestResult> {
+        self.motion_profile_request(&motion_profile::Request::Stop)
+            .await
+    }
+
+    /// Starts a constant plate RPM run, replacing the MCU's motion profile.
+    ///
+    /// # Errors
+    /// Returns an error if the request couldn't be sent.
+    pub async fn run_at(&self, run_at: &RunAt) -> Result<RequestResult> {
+        self.send::<RunAtEndpoint>(run_at).await
+    }
+
+    /// Enables or disables the vacuum pump.
+    ///
+    /// # Errors
+    /// Returns an error if the request couldn't be sent.
+    pub async fn vacuum_pump(&self, request: &vacuum_pump::Request) -> Result<()> {
+        self.send::<VacuumPumpRequestEndpoint>(request).await
+    }
+
+    /// Sends a jog request.
+    ///
+    /// # Errors
+    /// Returns an error if the request couldn't be sent.
+    pub async fn jog(&self, request: &jog::Request) -> Result<jog::RequestResult> {
+        self.send::<JogRequestEndpoint>(request).await
+    }
+
+    /// Requests the control loop timing statistics.
+    ///
+    /// # Errors
+    /// Returns an error if the request couldn't be sent.
+    pub async fn loop_timing(&self) -> Result<LoopTiming> {
+        self.send::<LoopTimingEndpoint>(&()).await
+    }
+
+    /// Requests the firmware version and the parameters that affect how runs behave.
+    ///
+    /// # Errors
+    /// Returns an error if the request couldn't be sent.
+    pub async fn device_info(&self) -> Result<DeviceInfo> {
+        self.send::<DeviceInfoEndpoint>(&()).await
+    }
+
+    /// Replaces the MCU's telemetry config, which applies from the start of the next run.
+    ///
+    /// # Errors
+    /// Returns an error if the request couldn't be sent.
+    pub async fn telemetry(&self, config: &telemetry::Config) -> Result<telemetry::RequestResult> {
+        self.send::<TelemetryConfigEndpoint>(config).await
+    }
+
+    /// Notifies the MCU that the host is closing, which stops any run.
+    ///
+    /// Although this method usually finishes immediately, it times out after 1 second.
+    pub async fn notify_disconnecting(&self) {
+        let _ = timeout(
+            DISCONNECT_TIMEOUT,
+            self.host_client
+                .publish::<HostDisconnecting>(INITIAL_VAR_SEQ, &()),
+        )
+        .await;
+    }
+
+    /// Subscribes to the MCU's logs.
+    ///
+    /// # Errors
+    /// Returns an error if the topic is already subscribed to.
+    pub async fn subscribe_logs(&self) -> Result<Subscription<String>> {
+        Ok(self
+            .host_client
+            .subscribe_exclusive::<LoggingTopic>(SUBSCRIPTION_DEPTH)
+            .await?)
+    }
+
+    /// Subscribes to the motion profile state, unpacking batches of states.
+    ///
+    /// [`None`] is received at the end of every run.
+    ///
+    /// # Errors
+    /// Returns an error if the topics are already subscribed to.
+    pub async fn subscribe_states(&self) -> Result<StateSubscription> {
+        Ok(StateSubscription {
+            states: self
+                .host_client
+                .subscribe_exclusive::<MotionProfileStateTopic>(SUBSCRIPTION_DEPTH)
+                .await?,
+            batches: self
+                .host_client
+                .subscribe_exclusive::<MotionProfileStateBatchTopic>(SUBSCRIPTION_DEPTH)
+                .await?,
+            pending: VecDeque::new(),
+            next_run: VecDeque::new(),
+            last_sample: None,
+        })
+    }
+
+    /// Subscribes to run outcomes.
+    ///
+    /// Each outcome is received right before the run's final [`None`] state.
+    ///
+    /// # Errors
+    /// Returns an error if the topic is already subscribed to.
+    pub async fn subscribe_outcomes(&self) -> Result<Subscription<Outcome>> {
+        Ok(self
+            .host_client
+            .subscribe_exclusive::<MotionProfileOutcomeTopic>(SUBSCRIPTION_DEPTH)
+            .await?)
+    }
+
+    /// Subscribes to touchscreen inputs.
+    ///
+    /// # Errors
+    /// Returns an error if the topic is already subscribed to.
+    pub async fn subscribe_touch_points(&self) -> Result<Subscription<TouchPoint>> {
+        Ok(self
+            .host_client
+            .subscribe_exclusive::<TouchPointTopic>(SUBSCRIPTION_DEPTH)
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use postcard_rpc::{postcard, test_utils::local_setup};
+
+    use super::*;
+
+    /// The faster baud rate that is proposed.
+    const FAST_BAUD_RATE: u32 = 921_600;
+
+    /// How a fake MCU answers a baud rate request at the baud rate it was received at,
+    /// or [`None`] if the request is lost.
+    type Answer = fn(u32, link::Request) -> Option<link::RequestResult>;
+
+    /// Negotiates [`FAST_BAUD_RATE`] with a fake MCU.
+    ///
+    /// Returns the result and the baud rate of every connection that was opened.
+    async fn negotiate(answer: Answer) -> (Result<(Client, Option<Fallback>)>, Vec<u32>) {
+        let opened = Arc::new(Mutex::new(Vec::new()));
+        let open = |baud_rate, _| {
+            let opened = Arc::clone(&opened);
+            async move {
+                opened.lock().expect("Lock poisoned").push(baud_rate);
+                let (mut mcu, host_client) = local_setup(SUBSCRIPTION_DEPTH, ERROR_PATH);
+                tokio::spawn(async move {
+                    while let Ok(frame) = mcu.recv_from_client().await {
+                        let request =
+                            postcard::from_bytes(&frame.body).expect("Not a baud rate request");
+                        if let Some(result) = answer(baud_rate, request) {
+                            let _ = mcu
+                                .reply::<BaudRateEndpoint>(frame.header.seq_no.into(), &result)
+                                .await;
+                        }
+                    }
+                });
+                Ok(Client::from(host_client))
+            }
+        };
+        let result = Client::negotiate(open, FAST_BAUD_RATE).await;
+        let opened = opened.lock().expect("Lock poisoned").clone();
+        (result, opened)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn confirmed_baud_rate_is_kept() {
+        let (result, opened) = negotiate(|_, _| Some(Ok(()))).await;
+        let (client, fallback) = result.expect("Negotiation failed");
+        assert_eq!(fallback, None);
+        assert!(client.is_connected());
+        assert_eq!(opened, [BAUD_RATE, FAST_BAUD_RATE]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refused_baud_rate_stays_at_default() {
+        let (result, opened) =
+            negotiate(|_, _| Some(Err(link::RequestRefused::UnsupportedBaudRate))).await;
+        let (client, fallback) = result.expect("Negotiation failed");
+        assert_eq!(
+            fallback,
+            Some(Fallback::Refused(link::RequestRefused::UnsupportedBaudRate))
+        );
+        assert!(client.is_connected());
+        assert_eq!(opened, [BAUD_RATE]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unconfirmed_baud_rate_falls_back() {
+        // The link never comes up at the new baud rate.
+        let (result, opened) =
+            negotiate(|baud_rate, _| (baud_rate == BAUD_RATE).then_some(Ok(()))).await;
+        let (client, fallback) = result.expect("Negotiation failed");
+        assert_eq!(fallback, Some(Fallback::TimedOut));
+        assert!(client.is_connected());
+        assert_eq!(opened, [BAUD_RATE, FAST_BAUD_RATE, BAUD_RATE]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unanswered_proposal_times_out() {
+        // The MCU is still at a baud rate from an earlier connection.
+        let (result, opened) = negotiate(|_, _| None).await;
+        assert!(matches!(result, Err(Error::Unresponsive)));
+        assert_eq!(opened, [BAUD_RATE]);
+    }
+
+    /// A state with a sample index.
+    fn state(sample: u32) -> State {
+        State {
+            setpoint_rpm: 0,
+            current_rpm: 0,
+            rpm_error: 0,
+            duty_cycle: sc_messages::pwm::DutyCycle::new(0),
+            time: 0,
+            loop_period: 0,
+            execution_time: 0,
+            overruns: 0,
+            sample,
+        }
+    }
+
+    /// A message published by a fake MCU, with the sample indices of its states.
+    enum Published {
+        /// A state on its own.
+        State(u32),
+        /// A batch of states.
+        Batch(&'static [u32]),
+        /// The end of a run.
+        End,
+    }
+
+    /// Publishes states from a fake MCU, on their own and in batches,
+    /// and returns the sample index of every state subscribed to, or [`None`] for the end of a run.
+    ///
+    /// Everything is published before the first state is received.
+    async fn receive(published: &[Published]) -> Vec<Option<u32>> {
+        let (mut mcu, host_client) = local_setup(SUBSCRIPTION_DEPTH, ERROR_PATH);
+        let client = Client::from(host_client);
+        let mut states = client.subscribe_states().await.expect("Subscribing failed");
+        let mut count = 0;
+        for (seq_no, message) in (0..).zip(published) {
+            match message {
+                Published::State(sample) => {
+                    count += 1;
+                    mcu.publish::<MotionProfileStateTopic>(seq_no, &Some(state(*sample)))
+                        .await
+                }
+                Published::Batch(samples) => {
+                    count += samples.len();
+                    let batch: StateBatch = samples.iter().copied().map(state).collect();
+                    mcu.publish::<MotionProfileStateBatchTopic>(seq_no, &batch)
+                        .await
+                }
+                Published::End => {
+                    count += 1;
+                    mcu.publish::<MotionProfileStateTopic>(seq_no, &None).await
+                }
+            }
+            .expect("Publishing failed");
+        }
+        // Let every message reach its subscription.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let mut received = Vec::new();
+        for _ in 0..count {
+            let state = states.recv().await.expect("Subscription closed");
+            received.push(state.map(|state| state.sample));
+        }
+        received
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn states_on_their_own_are_received_in_order() {
+        let received = receive(&[
+            Published::State(0),
+            Published::State(1),
+            Published::End,
+            Published::State(0),
+            Published::End,
+        ])
+        .await;
+        assert_eq!(received, [Some(0), Some(1), None, Some(0), None]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn last_batch_comes_before_the_end_of_its_run() {
+        let received = receive(&[
+            Published::Batch(&[0, 1]),
+            Published::Batch(&[2]),
+            Published::End,
+        ])
+        .await;
+        assert_eq!(received, [Some(0), Some(1), Some(2), None]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn next_run_comes_after_the_end_of_the_previous_run() {
+        let received = receive(&[
+            Published::Batch(&[0, 1]),
+            Published::Batch(&[2, 3]),
+            Published::End,
+            Published::Batch(&[0, 1]),
+            Published::Batch(&[2]),
+            Published::End,
+        ])
+        .await;
+        assert_eq!(
+            received,
+            [
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(3),
+                None,
+                Some(0),
+                Some(1),
+                Some(2),
+                None
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn missed_batches_are_skipped_over() {
+        let received = receive(&[
+            Published::Batch(&[0, 1]),
+            Published::Batch(&[4, 5]),
+            Published::End,
+        ])
+        .await;
+        assert_eq!(received, [Some(0), Some(1), Some(4), Some(5), None]);
+    }
+}
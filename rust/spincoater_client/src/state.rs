@@ -0,0 +1,229 @@
+//! This module contains additional functionality for tracking the MCU's state.
+
+use sc_messages::motion_profile;
+use sc_messages::pwm::{DutyCycle, PERIOD};
+use sc_messages::{MOTOR_REVOLUTIONS, PLATE_REVOLUTIONS};
+use serde::{Deserialize, Serialize};
+
+/// The conversion factor from motor revolutions to plate revolutions.
+pub const MOTOR_TO_PLATE_CONVERSION: f64 = PLATE_REVOLUTIONS as f64 / MOTOR_REVOLUTIONS as f64;
+
+/// A wrapper around [`motion_profile::State`] with the plate RPM added.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MotionProfileState {
+    /// The setpoint motor RPM.
+    pub setpoint_rpm: u16,
+    /// The setpoint plate RPM.
+    pub setpoint_plate_rpm: f64,
+    /// The measured motor RPM.
+    pub current_rpm: u16,
+    /// The measured plate RPM.
+    pub current_plate_rpm: f64,
+    /// Setpoint motor RPM - current motor RPM.
+    pub rpm_error: i16,
+    /// Setpoint plate RPM - current plate RPM.
+    pub plate_rpm_error: f64,
+    /// The current duty cycle being set to try and reach the setpoint.
+    pub duty_cycle: DutyCycle,
+    /// The duty cycle from range 0.0..1.0.
+    pub duty_cycle_f32: f32,
+    /// The time (in micros) since the motion profile started.
+    // I would like to use `embassy_time::duration::Duration`,
+    // but it doesn't impl Serialize.
+    #[serde(rename = "time (micros)")]
+    pub time: u64,
+    /// The time (in micros) between the starts of the previous and current control loop iterations.
+    #[serde(rename = "loop period (micros)", default)]
+    pub loop_period: u32,
+    /// The time (in micros) the previous control loop iteration spent executing.
+    #[serde(rename = "execution time (micros)", default)]
+    pub execution_time: u32,
+    /// The number of control loop overruns since the motion profile started.
+    #[serde(default)]
+    pub overruns: u32,
+    /// The index of this state among the states the MCU published during the run.
+    #[serde(default)]
+    pub sample: u32,
+    /// The number of states the host missed right before this one.
+    ///
+    /// This is filled in by [`DroppedSamples`], since the MCU can't know about them.
+    #[serde(default)]
+    pub dropped: u32,
+}
+
+impl From<motion_profile::State> for MotionProfileState {
+    fn from(state: motion_profile::State) -> Self {
+        Self {
+            setpoint_rpm: state.setpoint_rpm,
+            setpoint_plate_rpm: f64::from(state.setpoint_rpm) * MOTOR_TO_PLATE_CONVERSION,
+            current_rpm: state.current_rpm,
+            current_plate_rpm: f64::from(state.current_rpm) * MOTOR_TO_PLATE_CONVERSION,
+            rpm_error: state.rpm_error,
+            plate_rpm_error: f64::from(state.rpm_error) * MOTOR_TO_PLATE_CONVERSION,
+            duty_cycle: state.duty_cycle,
+            duty_cycle_f32: f32::from(*state.duty_cycle) / f32::from(PERIOD),
+            time: state.time,
+            loop_period: state.loop_period,
+            execution_time: state.execution_time,
+            overruns: state.overruns,
+            sample: state.sample,
+            dropped: 0,
+        }
+    }
+}
+
+impl From<&MotionProfileState> for motion_profile::State {
+    fn from(state: &MotionProfileState) -> Self {
+        Self {
+            setpoint_rpm: state.setpoint_rpm,
+            current_rpm: state.current_rpm,
+            rpm_error: state.rpm_error,
+            duty_cycle: state.duty_cycle,
+            time: state.time,
+            loop_period: state.loop_period,
+            execution_time: state.execution_time,
+            overruns: state.overruns,
+            sample: state.sample,
+        }
+    }
+}
+
+/// Detects the states the host missed during a run from the gaps between their sample indices.
+///
+/// States can be missed if the host doesn't keep up with the MCU,
+/// since subscriptions drop whatever doesn't fit in their buffers.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DroppedSamples {
+    /// The sample index the next state should have.
+    next: u32,
+    /// The number of states missed since the run started.
+    total: u32,
+}
+
+impl DroppedSamples {
+    /// Starts counting the missed states of a new run.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Starts counting the missed states of a run that was already underway,
+    /// from the first state received.
+    #[must_use]
+    pub fn starting_at(sample: u32) -> Self {
+        Self {
+            next: sample,
+            total: 0,
+        }
+    }
+
+    /// Records a received state of the run, setting its [`MotionProfileState::dropped`].
+    ///
+    /// Indices that go backwards, like those of data recorded before the MCU numbered its states,
+    /// aren't counted as missed states.
+    /// Returns the number of states missed right before this one.
+    pub fn record(&mut self, state: &mut MotionProfileState) -> u32 {
+        let dropped = state.sample.saturating_sub(self.next);
+        self.next = state.sample.wrapping_add(1);
+        self.total = self.total.saturating_add(dropped);
+        state.dropped = dropped;
+        dropped
+    }
+
+    /// The number of states missed since the run started.
+    #[must_use]
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A state with a sample index.
+    fn state(sample: u32) -> MotionProfileState {
+        MotionProfileState::from(motion_profile::State {
+            setpoint_rpm: 0,
+            current_rpm: 0,
+            rpm_error: 0,
+            duty_cycle: DutyCycle::new(0),
+            time: 0,
+            loop_period: 0,
+            execution_time: 0,
+            overruns: 0,
+            sample,
+        })
+    }
+
+    /// Records states with these sample indices, returning how many were missed before each.
+    fn record(dropped_samples: &mut DroppedSamples, samples: &[u32]) -> Vec<u32> {
+        samples
+            .iter()
+            .map(|&sample| {
+                let mut state = state(sample);
+                let dropped = dropped_samples.record(&mut state);
+                assert_eq!(state.dropped, dropped);
+                dropped
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_gaps() {
+        let mut dropped_samples = DroppedSamples::default();
+        assert_eq!(record(&mut dropped_samples, &[0, 1, 2, 3]), [0, 0, 0, 0]);
+        assert_eq!(dropped_samples.total(), 0);
+    }
+
+    #[test]
+    fn gaps_are_counted() {
+        let mut dropped_samples = DroppedSamples::default();
+        assert_eq!(
+            record(&mut dropped_samples, &[2, 3, 7, 8, 10]),
+            [2, 0, 3, 0, 1]
+        );
+        assert_eq!(dropped_samples.total(), 6);
+    }
+
+    #[test]
+    fn backwards_indices_are_not_gaps() {
+        let mut dropped_samples = DroppedSamples::default();
+        assert_eq!(
+            record(&mut dropped_samples, &[5, 3, 4, 0, 0]),
+            [5, 0, 0, 0, 0]
+        );
+        assert_eq!(dropped_samples.total(), 5);
+        // Counting continues from the index that went backwards.
+        assert_eq!(record(&mut dropped_samples, &[3]), [2]);
+        assert_eq!(dropped_samples.total(), 7);
+    }
+
+    #[test]
+    fn index_after_the_largest_wraps_to_zero() {
+        let mut dropped_samples = DroppedSamples::default();
+        // Jumping to the largest index is still a gap.
+        assert_eq!(
+            record(&mut dropped_samples, &[0, u32::MAX]),
+            [0, u32::MAX - 1]
+        );
+        // The index after it is 0, so wrapping around isn't.
+        assert_eq!(record(&mut dropped_samples, &[0, 1]), [0, 0]);
+        assert_eq!(dropped_samples.total(), u32::MAX - 1);
+    }
+
+    #[test]
+    fn joining_mid_run_counts_from_the_first_state() {
+        let mut dropped_samples = DroppedSamples::starting_at(500);
+        assert_eq!(record(&mut dropped_samples, &[500, 501, 503]), [0, 0, 1]);
+        assert_eq!(dropped_samples.total(), 1);
+    }
+
+    #[test]
+    fn reset_starts_a_new_run() {
+        let mut dropped_samples = DroppedSamples::default();
+        record(&mut dropped_samples, &[0, 4]);
+        dropped_samples.reset();
+        assert_eq!(dropped_samples.total(), 0);
+        assert_eq!(record(&mut dropped_samples, &[0, 1]), [0, 0]);
+    }
+}
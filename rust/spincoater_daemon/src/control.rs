@@ -0,0 +1,160 @@
+//! This module makes sure only one client controls the MCU at a time.
+//!
+//! Any client may watch the MCU, but requests that change it need the token of the current lease.
+
+use std::{
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// How long a lease lasts without being used.
+///
+/// This stops a client that crashed from holding control forever.
+pub const LEASE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The right to control the MCU.
+#[derive(Debug, Clone, Copy)]
+struct Lease {
+    /// The secret that the controlling client sends with its requests.
+    token: Uuid,
+    /// When the lease expires unless it is used again.
+    expires: Instant,
+}
+
+/// Another client controls the MCU.
+#[derive(Debug, Clone, Copy)]
+pub struct Held;
+
+/// Who may control the MCU.
+#[derive(Debug, Default)]
+pub struct Control {
+    /// The current lease, if any.
+    lease: Mutex<Option<Lease>>,
+}
+
+impl Control {
+    /// Locks the lease, forgetting it if it expired.
+    fn lease(&self) -> MutexGuard<'_, Option<Lease>> {
+        let mut lease = self
+            .lease
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if lease.is_some_and(|lease| lease.expires <= Instant::now()) {
+            *lease = None;
+        }
+        lease
+    }
+
+    /// Takes control of the MCU, or renews the lease if `token` already holds it.
+    ///
+    /// Returns the token of the lease.
+    ///
+    /// # Errors
+    /// Returns an error if another client controls the MCU.
+    pub fn acquire(&self, token: Option<Uuid>) -> Result<Uuid, Held> {
+        let mut lease = self.lease();
+        let token = match *lease {
+            None => Uuid::new_v4(),
+            Some(held) if Some(held.token) == token => held.token,
+            Some(_) => return Err(Held),
+        };
+        *lease = Some(Lease {
+            token,
+            expires: Instant::now() + LEASE_TIMEOUT,
+        });
+        Ok(token)
+    }
+
+    /// Checks that `token` holds the lease, and renews it.
+    ///
+    /// # Errors
+    /// Returns an error if `token` doesn't hold the lease.
+    pub fn check(&self, token: Uuid) -> Result<(), Held> {
+        match self.lease().as_mut() {
+            Some(lease) if lease.token == token => {
+                lease.expires = Instant::now() + LEASE_TIMEOUT;
+                Ok(())
+            }
+            _ => Err(Held),
+        }
+    }
+
+    /// Gives up control of the MCU if `token` holds the lease.
+    pub fn release(&self, token: Uuid) {
+        let mut lease = self.lease();
+        if lease.is_some_and(|lease| lease.token == token) {
+            *lease = None;
+        }
+    }
+
+    /// Whether any client controls the MCU.
+    pub fn is_held(&self) -> bool {
+        self.lease().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::advance;
+
+    use super::*;
+
+    #[test]
+    fn only_one_client_controls_at_a_time() {
+        let control = Control::default();
+        assert!(!control.is_held());
+        let token = control.acquire(None).expect("Nobody controls the MCU");
+        assert!(control.is_held());
+        assert!(control.acquire(None).is_err());
+        assert!(control.acquire(Some(Uuid::new_v4())).is_err());
+        assert!(control.check(Uuid::new_v4()).is_err());
+        assert!(control.check(token).is_ok());
+    }
+
+    #[test]
+    fn acquiring_again_keeps_the_token() {
+        let control = Control::default();
+        let token = control.acquire(None).expect("Nobody controls the MCU");
+        assert_eq!(control.acquire(Some(token)).ok(), Some(token));
+    }
+
+    #[test]
+    fn release_only_gives_up_the_holders_lease() {
+        let control = Control::default();
+        let token = control.acquire(None).expect("Nobody controls the MCU");
+        control.release(Uuid::new_v4());
+        assert!(control.is_held());
+        control.release(token);
+        assert!(!control.is_held());
+        assert!(control.check(token).is_err());
+        assert!(control.acquire(None).is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unused_lease_expires() {
+        let control = Control::default();
+        let token = control.acquire(None).expect("Nobody controls the MCU");
+        advance(LEASE_TIMEOUT).await;
+        assert!(!control.is_held());
+        assert!(control.check(token).is_err());
+        // Another client may take over, and the old token no longer renews it.
+        let other = control.acquire(None).expect("The lease expired");
+        assert_ne!(other, token);
+        assert!(control.acquire(Some(token)).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn using_the_lease_renews_it() {
+        let control = Control::default();
+        let token = control.acquire(None).expect("Nobody controls the MCU");
+        advance(LEASE_TIMEOUT / 2).await;
+        assert!(control.check(token).is_ok());
+        advance(LEASE_TIMEOUT / 2).await;
+        assert!(control.is_held());
+        advance(LEASE_TIMEOUT / 2).await;
+        assert!(!control.is_held());
+    }
+}
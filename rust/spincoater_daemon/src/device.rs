@@ -0,0 +1,251 @@
+//! This module contains the daemon's connection to the MCU and what it knows about the MCU.
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use color_eyre::Result;
+use sc_messages::{
+    motion_profile::{Outcome, Setpoint},
+    touchscreen::TouchPoint,
+};
+use serde::Serialize;
+use spincoater_client::{Client, Receive, state::MotionProfileState};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::control::Control;
+
+/// The number of events kept for slow event receivers before they start missing events.
+const EVENT_CAPACITY: usize = 1024;
+
+/// Something the MCU published.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum Event {
+    /// The motion profile state, or [`None`] at the end of a run.
+    State(Option<MotionProfileState>),
+    /// How a run ended.
+    Outcome(Outcome),
+    /// A log message.
+    Log(String),
+    /// A touchscreen input.
+    Touch(TouchPoint),
+}
+
+/// A run starting or ending.
+///
+/// Unlike [`Event`]s, these are never dropped for slow receivers.
+#[derive(Debug, Clone, Copy)]
+pub enum RunEvent {
+    /// The first state of a run arrived.
+    Started,
+    /// A run ended this way.
+    Ended(Outcome),
+}
+
+/// What the daemon knows about the MCU.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Status {
+    /// The serial port the MCU is connected to.
+    pub port: String,
+    /// The latest state of the current run, or [`None`] if nothing is running.
+    pub state: Option<MotionProfileState>,
+    /// How the previous run ended.
+    pub outcome: Option<Outcome>,
+    /// Whether the vacuum pump was last turned on, or [`None`] if it hasn't been changed.
+    pub vacuum: Option<bool>,
+    /// The motion profile last uploaded through the daemon.
+    pub profile: Vec<Setpoint>,
+    /// How many runs there have been since the daemon started.
+    pub runs: RunCounts,
+}
+
+impl Status {
+    /// Keeps the status up to date with an event, returning whether a run started or ended.
+    fn update(&mut self, event: &Event) -> Option<RunEvent> {
+        match event {
+            Event::State(state) => {
+                let started = self.state.is_none() && state.is_some();
+                if started {
+                    self.runs.started += 1;
+                }
+                self.state.clone_from(state);
+                started.then_some(RunEvent::Started)
+            }
+            Event::Outcome(outcome) => {
+                self.outcome = Some(*outcome);
+                self.runs.count(*outcome);
+                Some(RunEvent::Ended(*outcome))
+            }
+            Event::Log(_) | Event::Touch(_) => None,
+        }
+    }
+}
+
+/// How many runs have started since the daemon started, and how they ended.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct RunCounts {
+    /// The number of runs that started.
+    pub started: u64,
+    /// The number of runs that ended with [`Outcome::Completed`].
+    pub completed: u64,
+    /// The number of runs that ended with [`Outcome::Stopped`].
+    pub stopped: u64,
+    /// The number of runs that ended with [`Outcome::HostDisconnected`].
+    pub host_disconnected: u64,
+    /// The number of runs that ended with [`Outcome::TimedOut`].
+    pub timed_out: u64,
+    /// The number of runs that ended with [`Outcome::Fault`].
+    pub fault: u64,
+}
+
+impl RunCounts {
+    /// Counts a run that ended.
+    fn count(&mut self, outcome: Outcome) {
+        let count = match outcome {
+            Outcome::Completed => &mut self.completed,
+            Outcome::Stopped => &mut self.stopped,
+            Outcome::HostDisconnected => &mut self.host_disconnected,
+            Outcome::TimedOut => &mut self.timed_out,
+            Outcome::Fault => &mut self.fault,
+        };
+        *count += 1;
+    }
+}
+
+/// The MCU shared by every client of the daemon.
+#[derive(Debug)]
+pub struct Device {
+    /// The connection to the MCU.
+    pub client: Client,
+    /// Sends every [`Event`] to every subscriber.
+    events: broadcast::Sender<Event>,
+    /// What the daemon knows about the MCU, and who to send [`RunEvent`]s to.
+    tracker: Arc<Tracker>,
+    /// Who may control the MCU.
+    pub control: Control,
+}
+
+impl Device {
+    /// Subscribes to the MCU's topics, keeping the status up to date until the connection closes.
+    ///
+    /// # Errors
+    /// Returns an error if subscribing to a topic fails.
+    pub async fn new(client: Client, port: String) -> Result<Self> {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        let device = Self {
+            events,
+            tracker: Arc::new(Tracker {
+                status: Mutex::new(Status {
+                    port,
+                    ..Status::default()
+                }),
+                run_events: Mutex::new(Vec::new()),
+            }),
+            control: Control::default(),
+            client,
+        };
+        tokio::spawn(forward(
+            device.client.subscribe_states().await?,
+            device.events.clone(),
+            Arc::clone(&device.tracker),
+        ));
+        tokio::spawn(forward(
+            device.client.subscribe_outcomes().await?,
+            device.events.clone(),
+            Arc::clone(&device.tracker),
+        ));
+        tokio::spawn(forward(
+            device.client.subscribe_logs().await?,
+            device.events.clone(),
+            Arc::clone(&device.tracker),
+        ));
+        tokio::spawn(forward(
+            device.client.subscribe_touch_points().await?,
+            device.events.clone(),
+            Arc::clone(&device.tracker),
+        ));
+        Ok(device)
+    }
+
+    /// Receives every [`Event`] published after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Receives every [`RunEvent`] after this call, however slowly they're received.
+    pub fn subscribe_runs(&self) -> mpsc::UnboundedReceiver<RunEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        lock(&self.tracker.run_events).push(sender);
+        receiver
+    }
+
+    /// Locks the status.
+    pub fn status(&self) -> MutexGuard<'_, Status> {
+        lock(&self.tracker.status)
+    }
+}
+
+/// What the daemon knows about the MCU, kept up to date by [`forward`].
+#[derive(Debug)]
+struct Tracker {
+    /// What the daemon knows about the MCU.
+    status: Mutex<Status>,
+    /// Sends every [`RunEvent`] to every subscriber that hasn't gone away.
+    run_events: Mutex<Vec<mpsc::UnboundedSender<RunEvent>>>,
+}
+
+impl Tracker {
+    /// Updates the status with an event, and tells the subscribers if a run started or ended.
+    fn update(&self, event: &Event) {
+        let Some(run_event) = lock(&self.status).update(event) else {
+            return;
+        };
+        lock(&self.run_events).retain(|sender| sender.send(run_event).is_ok());
+    }
+}
+
+/// Locks a mutex.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic while holding the lock can't leave the data half-updated.
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Forwards messages from a subscription to the event subscribers, updating the status first.
+///
+/// The status is updated here rather than by an event subscriber,
+/// since those miss events when they fall behind.
+async fn forward<S>(mut subscription: S, events: broadcast::Sender<Event>, tracker: Arc<Tracker>)
+where
+    S: Receive,
+    S::Message: Into<Event>,
+{
+    while let Some(message) = subscription.recv().await {
+        let event = message.into();
+        tracker.update(&event);
+        // There may be no subscribers right now.
+        let _ = events.send(event);
+    }
+}
+
+impl From<Option<sc_messages::motion_profile::State>> for Event {
+    fn from(value: Option<sc_messages::motion_profile::State>) -> Self {
+        Self::State(value.map(Into::into))
+    }
+}
+
+impl From<Outcome> for Event {
+    fn from(value: Outcome) -> Self {
+        Self::Outcome(value)
+    }
+}
+
+impl From<String> for Event {
+    fn from(value: String) -> Self {
+        Self::Log(value)
+    }
+}
+
+impl From<TouchPoint> for Event {
+    fn from(value: TouchPoint) -> Self {
+        Self::Touch(value)
+    }
+}
@@ -0,0 +1,266 @@
+//! This module contains the daemon's HTTP and WebSocket API and the dashboard.
+
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{
+        FromRequestParts, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{StatusCode, header::AUTHORIZATION, request::Parts},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post, put},
+};
+use sc_messages::{
+    motion_profile::{RequestResult, RunAt, Setpoint},
+    vacuum_pump,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+use crate::device::{Device, Status};
+
+/// The dashboard, which only uses the API below.
+const DASHBOARD: &str = include_str!("../static/index.html");
+
+/// Builds the router for the API and the dashboard.
+pub fn router(device: Arc<Device>) -> Router {
+    Router::new()
+        .route("/", get(|| async { Html(DASHBOARD) }))
+        .route("/api/device", get(device_info))
+        .route(
+            "/api/control",
+            post(acquire_control).delete(release_control),
+        )
+        .route("/api/profile", get(profile).put(upload_profile))
+        .route("/api/start", post(start))
+        .route("/api/stop", post(stop))
+        .route("/api/run_at", post(run_at))
+        .route("/api/vacuum", put(vacuum))
+        .route("/api/ws", get(stream))
+        .route("/metrics", get(crate::metrics::metrics))
+        .with_state(device)
+}
+
+/// The errors returned by the API.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request needs control of the MCU, which the client doesn't have.
+    NotController,
+    /// The request body couldn't be understood.
+    BadRequest(String),
+    /// The MCU refused the request.
+    Refused(String),
+    /// The MCU couldn't be reached.
+    Client(spincoater_client::Error),
+}
+
+impl From<spincoater_client::Error> for ApiError {
+    fn from(value: spincoater_client::Error) -> Self {
+        Self::Client(value)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            Self::NotController => (
+                StatusCode::LOCKED,
+                "Another client controls the spin coater, or the control token expired."
+                    .to_string(),
+            ),
+            Self::BadRequest(error) => (StatusCode::BAD_REQUEST, error),
+            Self::Refused(error) => (StatusCode::CONFLICT, error),
+            Self::Client(error) => (StatusCode::BAD_GATEWAY, error.to_string()),
+        };
+        (status, Json(json!({ "error": error }))).into_response()
+    }
+}
+
+/// See [`ApiError`].
+type ApiResult<T> = Result<T, ApiError>;
+
+/// Converts the MCU's response to a request.
+fn check(response: RequestResult) -> ApiResult<StatusCode> {
+    response
+        .map(|()| StatusCode::NO_CONTENT)
+        .map_err(|refused| ApiError::Refused(format!("{refused:?}")))
+}
+
+/// The token sent in the `Authorization: Bearer <token>` header, if any.
+fn bearer_token(parts: &Parts) -> ApiResult<Option<Uuid>> {
+    let Some(header) = parts.headers.get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+    header
+        .to_str()
+        .ok()
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .and_then(|token| Uuid::parse_str(token).ok())
+        .map(Some)
+        .ok_or_else(|| ApiError::BadRequest("Invalid Authorization header.".to_string()))
+}
+
+/// A client that holds control of the MCU.
+///
+/// Extracting this renews the client's lease.
+pub struct Controller(pub Uuid);
+
+impl FromRequestParts<Arc<Device>> for Controller {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, device: &Arc<Device>) -> ApiResult<Self> {
+        let token = bearer_token(parts)?.ok_or(ApiError::NotController)?;
+        device
+            .control
+            .check(token)
+            .map_err(|_| ApiError::NotController)?;
+        Ok(Self(token))
+    }
+}
+
+/// A client that may or may not hold control of the MCU.
+pub struct MaybeController(Option<Uuid>);
+
+impl FromRequestParts<Arc<Device>> for MaybeController {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _: &Arc<Device>) -> ApiResult<Self> {
+        Ok(Self(bearer_token(parts)?))
+    }
+}
+
+/// The response to `GET /api/device`.
+#[derive(Debug, Serialize)]
+struct DeviceInfo {
+    /// What the daemon knows about the MCU.
+    #[serde(flatten)]
+    status: Status,
+    /// Whether a client controls the MCU.
+    controlled: bool,
+}
+
+/// `GET /api/device`
+async fn device_info(State(device): State<Arc<Device>>) -> Json<DeviceInfo> {
+    Json(DeviceInfo {
+        status: device.status().clone(),
+        controlled: device.control.is_held(),
+    })
+}
+
+/// `POST /api/control` takes control of the MCU or renews the lease.
+async fn acquire_control(
+    State(device): State<Arc<Device>>,
+    MaybeController(token): MaybeController,
+) -> ApiResult<Json<serde_json::Value>> {
+    let token = device
+        .control
+        .acquire(token)
+        .map_err(|_| ApiError::NotController)?;
+    Ok(Json(json!({ "token": token })))
+}
+
+/// `DELETE /api/control` gives up control of the MCU.
+async fn release_control(
+    State(device): State<Arc<Device>>,
+    Controller(token): Controller,
+) -> StatusCode {
+    device.control.release(token);
+    StatusCode::NO_CONTENT
+}
+
+/// `GET /api/profile` returns the motion profile last uploaded through the daemon.
+async fn profile(State(device): State<Arc<Device>>) -> Json<Vec<Setpoint>> {
+    Json(device.status().profile.clone())
+}
+
+/// `PUT /api/profile` replaces the MCU's motion profile with a motion profile CSV file.
+async fn upload_profile(
+    State(device): State<Arc<Device>>,
+    _: Controller,
+    body: String,
+) -> ApiResult<StatusCode> {
+    let setpoints = csv::Reader::from_reader(body.as_bytes())
+        .into_deserialize()
+        .collect::<Result<Vec<Setpoint>, _>>()
+        .map_err(|error| ApiError::BadRequest(error.to_string()))?;
+    let status = check(device.client.upload_profile(setpoints.clone()).await?)?;
+    device.status().profile = setpoints;
+    Ok(status)
+}
+
+/// `POST /api/start`
+async fn start(State(device): State<Arc<Device>>, _: Controller) -> ApiResult<StatusCode> {
+    check(device.client.start().await?)
+}
+
+/// `POST /api/stop`
+async fn stop(State(device): State<Arc<Device>>, _: Controller) -> ApiResult<StatusCode> {
+    check(device.client.stop().await?)
+}
+
+/// `POST /api/run_at` runs at a constant plate RPM, replacing the MCU's motion profile.
+async fn run_at(
+    State(device): State<Arc<Device>>,
+    _: Controller,
+    Json(run_at): Json<RunAt>,
+) -> ApiResult<StatusCode> {
+    if !run_at.is_valid() {
+        return Err(ApiError::BadRequest(
+            "The RPM and time must be nonzero.".to_string(),
+        ));
+    }
+    let status = check(device.client.run_at(&run_at).await?)?;
+    device.status().profile = run_at.setpoints().to_vec();
+    Ok(status)
+}
+
+/// The body of `PUT /api/vacuum`.
+#[derive(Debug, Deserialize)]
+struct Vacuum {
+    /// Whether the vacuum pump should be on.
+    on: bool,
+}
+
+/// `PUT /api/vacuum`
+async fn vacuum(
+    State(device): State<Arc<Device>>,
+    _: Controller,
+    Json(Vacuum { on }): Json<Vacuum>,
+) -> ApiResult<StatusCode> {
+    let request = if on {
+        vacuum_pump::Request::Enable
+    } else {
+        vacuum_pump::Request::Disable
+    };
+    device.client.vacuum_pump(&request).await?;
+    device.status().vacuum = Some(on);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `GET /api/ws` streams every [`crate::device::Event`] as JSON.
+async fn stream(State(device): State<Arc<Device>>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| send_events(device, socket))
+}
+
+/// Sends events to a WebSocket until it closes.
+async fn send_events(device: Arc<Device>, mut socket: WebSocket) {
+    let mut events = device.subscribe();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            // Slow clients miss events rather than slowing down everyone else.
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return,
+        };
+        let Ok(text) = serde_json::to_string(&event) else {
+            continue;
+        };
+        if socket.send(Message::text(text)).await.is_err() {
+            return;
+        }
+    }
+}
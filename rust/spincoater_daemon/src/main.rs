@@ -0,0 +1,124 @@
+//! This crate provides a daemon that shares the spincoater's ESP32 with other programs on the PC.
+
+mod control;
+mod device;
+mod http;
+mod metrics;
+mod modbus;
+mod mqtt;
+mod scpi;
+
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use clap::Parser;
+use color_eyre::Result;
+use spincoater_client::{Client, only_esp_port};
+use tokio::net::TcpListener;
+
+use crate::{device::Device, modbus::Bridge};
+
+/// Shares the spin coater over a local HTTP and WebSocket API and a SCPI-like TCP interface.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// The serial port the MCU is connected to.
+    ///
+    /// Defaults to the only ESP device plugged in.
+    #[arg(short, long)]
+    port: Option<String>,
+    /// The address to serve the API and dashboard on.
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+    /// The address to serve the SCPI-like text interface on.
+    ///
+    /// 5025 is the usual port for SCPI over raw TCP.
+    #[arg(long, default_value = "127.0.0.1:5025")]
+    scpi: SocketAddr,
+    /// The folder that `PROFile:LOAD "<path>"` reads motion profile CSV files from over SCPI.
+    ///
+    /// Paths are relative to it and can't leave it. Without it, loading files over SCPI is disabled.
+    #[arg(long)]
+    profile_dir: Option<PathBuf>,
+    /// The address to serve Modbus TCP on, e.g. `0.0.0.0:502`.
+    #[arg(long)]
+    modbus_tcp: Option<SocketAddr>,
+    /// The serial port to serve Modbus RTU on.
+    #[arg(long)]
+    modbus_rtu: Option<String>,
+    /// The baud rate of Modbus RTU.
+    #[arg(long, default_value_t = 19_200)]
+    modbus_baud_rate: u32,
+    /// The unit ID to respond to over Modbus RTU.
+    #[arg(long, default_value_t = 1)]
+    modbus_unit_id: u8,
+    #[command(flatten)]
+    mqtt: mqtt::MqttArgs,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    color_eyre::install()?;
+    let args = Args::parse();
+
+    let port = match args.port {
+        Some(port) => port,
+        None => only_esp_port()?,
+    };
+    let client = Client::connect(&port)?;
+    let device = Arc::new(Device::new(client, port).await?);
+
+    let scpi_listener = TcpListener::bind(args.scpi).await?;
+    println!("Serving SCPI on {}", scpi_listener.local_addr()?);
+    tokio::spawn(scpi::serve(
+        scpi_listener,
+        Arc::clone(&device),
+        args.profile_dir.map(Arc::from),
+    ));
+
+    let bridge = Bridge::new(Arc::clone(&device));
+    if let Some(address) = args.modbus_tcp {
+        let server = tokio_modbus::server::tcp::Server::new(TcpListener::bind(address).await?);
+        println!("Serving Modbus TCP on {address}");
+        let bridge = bridge.clone();
+        tokio::spawn(async move {
+            let on_connected = |stream, _| {
+                let bridge = bridge.clone();
+                async move { Ok(Some((bridge, stream))) }
+            };
+            if let Err(error) = server.serve(&on_connected, |_| {}).await {
+                eprintln!("Modbus TCP stopped: {error}");
+            }
+        });
+    }
+    if let Some(path) = args.modbus_rtu {
+        let server =
+            tokio_modbus::server::rtu::Server::new_from_path(&path, args.modbus_baud_rate)?;
+        println!(
+            "Serving Modbus RTU on {path} as unit {}",
+            args.modbus_unit_id
+        );
+        let bridge = bridge.with_unit_id(args.modbus_unit_id);
+        tokio::spawn(async move {
+            if let Err(error) = server.serve_forever(bridge).await {
+                eprintln!("Modbus RTU stopped: {error}");
+            }
+        });
+    }
+
+    if let Some(host) = args.mqtt.host.clone() {
+        println!("Bridging to the MQTT broker at {host}:{}", args.mqtt.port);
+        mqtt::start(Arc::clone(&device), host, &args.mqtt);
+    }
+
+    let listener = TcpListener::bind(args.listen).await?;
+    println!("Serving the dashboard on http://{}", listener.local_addr()?);
+    axum::serve(listener, http::router(Arc::clone(&device)))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    // Like the TUI, stop any run when the daemon closes.
+    device.client.notify_disconnecting().await;
+    Ok(())
+}
@@ -0,0 +1,439 @@
+//! This module exports the daemon's view of the MCU in the Prometheus text format.
+//!
+//! See <https://prometheus.io/docs/instrumenting/exposition_formats/>.
+
+use std::{fmt::Write, sync::Arc, time::Duration};
+
+use axum::{extract::State, http::header::CONTENT_TYPE, response::IntoResponse};
+use sc_messages::diagnostics::{LoopTiming, Statistics};
+use spincoater_client::state::MotionProfileState;
+use tokio::time::timeout;
+
+use crate::device::{Device, RunCounts, Status};
+
+/// The content type of the Prometheus text format.
+const CONTENT_TYPE_TEXT: &str = "text/plain; version=0.0.4";
+
+/// How long a scrape waits for the MCU's loop timing before leaving it out.
+const LOOP_TIMING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Writes a metric with its help text and type.
+fn metric(out: &mut String, name: &str, kind: &str, help: &str, samples: &[(&str, f64)]) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{name}{labels} {value}");
+    }
+}
+
+/// Writes a metric without labels.
+fn single(out: &mut String, name: &str, kind: &str, help: &str, value: f64) {
+    metric(out, name, kind, help, &[("", value)]);
+}
+
+/// Converts micros to seconds, which Prometheus uses for every duration.
+fn seconds(micros: u32) -> f64 {
+    f64::from(micros) / 1_000_000.0
+}
+
+/// Writes the percentiles of a duration measured every control loop iteration.
+fn statistics(out: &mut String, name: &str, help: &str, statistics: &Statistics) {
+    metric(
+        out,
+        name,
+        "gauge",
+        help,
+        &[
+            ("{statistic=\"min\"}", seconds(statistics.min)),
+            ("{statistic=\"mean\"}", seconds(statistics.mean)),
+            ("{statistic=\"p50\"}", seconds(statistics.p50)),
+            ("{statistic=\"p95\"}", seconds(statistics.p95)),
+            ("{statistic=\"p99\"}", seconds(statistics.p99)),
+            ("{statistic=\"max\"}", seconds(statistics.max)),
+        ],
+    );
+}
+
+/// Writes the loop timing statistics of the current or most recent run.
+fn loop_timing(out: &mut String, timing: &LoopTiming) {
+    single(
+        out,
+        "spincoater_loop_target_period_seconds",
+        "gauge",
+        "The period that the control loop tries to run at.",
+        seconds(timing.target_period),
+    );
+    single(
+        out,
+        "spincoater_loop_iterations",
+        "gauge",
+        "The number of control loop iterations in the current or most recent run.",
+        f64::from(timing.iterations),
+    );
+    single(
+        out,
+        "spincoater_loop_overruns",
+        "gauge",
+        "The number of control loop iterations in the current or most recent run that overran the target period.",
+        f64::from(timing.overruns),
+    );
+    statistics(
+        out,
+        "spincoater_loop_period_seconds",
+        "The time between the starts of consecutive control loop iterations in the current or most recent run.",
+        &timing.period,
+    );
+    statistics(
+        out,
+        "spincoater_loop_execution_seconds",
+        "The time spent executing each control loop iteration in the current or most recent run.",
+        &timing.execution,
+    );
+}
+
+/// Writes the latest state of the current run.
+fn state_metrics(out: &mut String, state: Option<&MotionProfileState>) {
+    single(
+        out,
+        "spincoater_running",
+        "gauge",
+        "Whether a run is in progress.",
+        f64::from(u8::from(state.is_some())),
+    );
+    metric(
+        out,
+        "spincoater_setpoint_rpm",
+        "gauge",
+        "The setpoint RPM of the current run, or 0 when idle.",
+        &[
+            (
+                "{shaft=\"motor\"}",
+                state.map_or(0.0, |state| f64::from(state.setpoint_rpm)),
+            ),
+            (
+                "{shaft=\"plate\"}",
+                state.map_or(0.0, |state| state.setpoint_plate_rpm),
+            ),
+        ],
+    );
+    metric(
+        out,
+        "spincoater_current_rpm",
+        "gauge",
+        "The measured RPM of the current run, or 0 when idle.",
+        &[
+            (
+                "{shaft=\"motor\"}",
+                state.map_or(0.0, |state| f64::from(state.current_rpm)),
+            ),
+            (
+                "{shaft=\"plate\"}",
+                state.map_or(0.0, |state| state.current_plate_rpm),
+            ),
+        ],
+    );
+    metric(
+        out,
+        "spincoater_rpm_error",
+        "gauge",
+        "The setpoint RPM minus the measured RPM of the current run, or 0 when idle.",
+        &[
+            (
+                "{shaft=\"motor\"}",
+                state.map_or(0.0, |state| f64::from(state.rpm_error)),
+            ),
+            (
+                "{shaft=\"plate\"}",
+                state.map_or(0.0, |state| state.plate_rpm_error),
+            ),
+        ],
+    );
+    single(
+        out,
+        "spincoater_duty_cycle_ratio",
+        "gauge",
+        "The duty cycle of the current run from 0 to 1, or 0 when idle.",
+        state.map_or(0.0, |state| f64::from(state.duty_cycle_f32)),
+    );
+    single(
+        out,
+        "spincoater_control_loop_overruns",
+        "gauge",
+        "The number of control loop overruns in the current run, or 0 when idle.",
+        state.map_or(0.0, |state| f64::from(state.overruns)),
+    );
+}
+
+/// Writes how many runs there have been and how they ended.
+#[allow(clippy::cast_precision_loss)]
+fn run_counts(out: &mut String, runs: &RunCounts) {
+    single(
+        out,
+        "spincoater_runs_started_total",
+        "counter",
+        "The number of runs that started since the daemon started.",
+        runs.started as f64,
+    );
+    metric(
+        out,
+        "spincoater_runs_ended_total",
+        "counter",
+        "The number of runs that ended since the daemon started, by how they ended.",
+        &[
+            ("{outcome=\"completed\"}", runs.completed as f64),
+            ("{outcome=\"stopped\"}", runs.stopped as f64),
+            (
+                "{outcome=\"host_disconnected\"}",
+                runs.host_disconnected as f64,
+            ),
+            ("{outcome=\"timed_out\"}", runs.timed_out as f64),
+            ("{outcome=\"fault\"}", runs.fault as f64),
+        ],
+    );
+    single(
+        out,
+        "spincoater_faults_total",
+        "counter",
+        "The number of runs that ended with a fault or timed out since the daemon started.",
+        (runs.fault + runs.timed_out) as f64,
+    );
+}
+
+/// Renders every metric.
+#[allow(clippy::cast_precision_loss)]
+fn render(up: bool, status: &Status, link_errors: u64, timing: Option<&LoopTiming>) -> String {
+    let mut out = String::new();
+    single(
+        &mut out,
+        "spincoater_up",
+        "gauge",
+        "Whether the daemon is connected to the MCU.",
+        f64::from(u8::from(up)),
+    );
+    state_metrics(&mut out, status.state.as_ref());
+    single(
+        &mut out,
+        "spincoater_vacuum_pump_on",
+        "gauge",
+        "Whether the vacuum pump was last turned on through the daemon.",
+        f64::from(u8::from(status.vacuum.unwrap_or_default())),
+    );
+    run_counts(&mut out, &status.runs);
+    single(
+        &mut out,
+        "spincoater_link_errors_total",
+        "counter",
+        "The number of requests to the MCU that couldn't be sent or answered since the daemon started.",
+        link_errors as f64,
+    );
+    if let Some(timing) = timing {
+        loop_timing(&mut out, timing);
+    }
+    out
+}
+
+/// `GET /metrics`
+pub async fn metrics(State(device): State<Arc<Device>>) -> impl IntoResponse {
+    // Ask for the loop timing first, so the status is as fresh as possible.
+    let timing = if device.client.is_connected() {
+        timeout(LOOP_TIMING_TIMEOUT, device.client.loop_timing())
+            .await
+            .ok()
+            .and_then(Result::ok)
+    } else {
+        None
+    };
+    let status = device.status().clone();
+    let out = render(
+        device.client.is_connected(),
+        &status,
+        device.client.link_errors(),
+        timing.as_ref(),
+    );
+    ([(CONTENT_TYPE, CONTENT_TYPE_TEXT)], out)
+}
+
+#[cfg(test)]
+mod tests {
+    use sc_messages::{motion_profile, pwm::DutyCycle};
+
+    use super::*;
+
+    /// The value of the sample with this name and labels, if there is exactly one.
+    fn sample(out: &str, series: &str) -> Option<f64> {
+        let mut values = out
+            .lines()
+            .filter_map(|line| line.strip_prefix(series)?.strip_prefix(' '));
+        let value = values.next()?.parse().ok();
+        values.next().is_none().then_some(value?)
+    }
+
+    #[test]
+    fn metrics_have_help_and_type() {
+        let mut out = String::new();
+        metric(
+            &mut out,
+            "test_metric",
+            "gauge",
+            "A test metric.",
+            &[("{a=\"b\"}", 1.5), ("{a=\"c\"}", 0.0)],
+        );
+        assert_eq!(
+            out,
+            "# HELP test_metric A test metric.\n\
+             # TYPE test_metric gauge\n\
+             test_metric{a=\"b\"} 1.5\n\
+             test_metric{a=\"c\"} 0\n"
+        );
+    }
+
+    #[test]
+    fn every_sample_follows_its_type() {
+        let timing = LoopTiming {
+            target_period: 1000,
+            ..LoopTiming::default()
+        };
+        let out = render(true, &Status::default(), 0, Some(&timing));
+        let mut declared = Vec::new();
+        for line in out.lines() {
+            if let Some(comment) = line.strip_prefix("# ") {
+                let mut words = comment.splitn(3, ' ');
+                let (Some(keyword), Some(name)) = (words.next(), words.next()) else {
+                    panic!("Malformed comment: {line}");
+                };
+                assert!(matches!(keyword, "HELP" | "TYPE"), "{line}");
+                if keyword == "TYPE" {
+                    assert!(matches!(words.next(), Some("gauge" | "counter")), "{line}");
+                    assert!(!declared.contains(&name), "Declared twice: {name}");
+                    declared.push(name);
+                }
+                continue;
+            }
+            let (series, value) = line.rsplit_once(' ').expect("A sample has a value");
+            let name = series.split('{').next().unwrap_or_default();
+            assert_eq!(declared.last(), Some(&name), "Undeclared sample: {line}");
+            assert!(value.parse::<f64>().is_ok(), "Not a number: {line}");
+            if let Some(labels) = series
+                .strip_prefix(name)
+                .filter(|labels| !labels.is_empty())
+            {
+                assert!(labels.starts_with('{') && labels.ends_with('}'), "{line}");
+            }
+            assert!(
+                !name.ends_with("_total") || out.contains(&format!("# TYPE {name} counter")),
+                "Totals are counters: {name}"
+            );
+        }
+    }
+
+    #[test]
+    fn idle_metrics_are_zero() {
+        let out = render(false, &Status::default(), 3, None);
+        assert_eq!(sample(&out, "spincoater_up"), Some(0.0));
+        assert_eq!(sample(&out, "spincoater_running"), Some(0.0));
+        assert_eq!(
+            sample(&out, "spincoater_current_rpm{shaft=\"plate\"}"),
+            Some(0.0)
+        );
+        assert_eq!(sample(&out, "spincoater_link_errors_total"), Some(3.0));
+        // The loop timing is left out when the MCU doesn't report it.
+        assert!(!out.contains("spincoater_loop_"));
+    }
+
+    #[test]
+    fn running_metrics_follow_the_state() {
+        let state = MotionProfileState::from(motion_profile::State {
+            setpoint_rpm: 3000,
+            current_rpm: 2900,
+            rpm_error: 100,
+            duty_cycle: DutyCycle::new(0),
+            time: 0,
+            loop_period: 0,
+            execution_time: 0,
+            overruns: 2,
+            sample: 0,
+        });
+        let status = Status {
+            state: Some(state.clone()),
+            vacuum: Some(true),
+            ..Status::default()
+        };
+        let out = render(true, &status, 0, None);
+        assert_eq!(sample(&out, "spincoater_running"), Some(1.0));
+        assert_eq!(
+            sample(&out, "spincoater_setpoint_rpm{shaft=\"motor\"}"),
+            Some(3000.0)
+        );
+        assert_eq!(
+            sample(&out, "spincoater_current_rpm{shaft=\"plate\"}"),
+            Some(state.current_plate_rpm)
+        );
+        assert_eq!(
+            sample(&out, "spincoater_rpm_error{shaft=\"motor\"}"),
+            Some(100.0)
+        );
+        assert_eq!(sample(&out, "spincoater_control_loop_overruns"), Some(2.0));
+        assert_eq!(sample(&out, "spincoater_vacuum_pump_on"), Some(1.0));
+    }
+
+    #[test]
+    fn runs_are_counted_by_outcome() {
+        let status = Status {
+            runs: RunCounts {
+                started: 6,
+                completed: 2,
+                stopped: 1,
+                host_disconnected: 0,
+                timed_out: 1,
+                fault: 1,
+            },
+            ..Status::default()
+        };
+        let out = render(true, &status, 0, None);
+        assert_eq!(sample(&out, "spincoater_runs_started_total"), Some(6.0));
+        assert_eq!(
+            sample(&out, "spincoater_runs_ended_total{outcome=\"completed\"}"),
+            Some(2.0)
+        );
+        assert_eq!(
+            sample(
+                &out,
+                "spincoater_runs_ended_total{outcome=\"host_disconnected\"}"
+            ),
+            Some(0.0)
+        );
+        assert_eq!(sample(&out, "spincoater_faults_total"), Some(2.0));
+    }
+
+    #[test]
+    fn loop_timing_is_in_seconds() {
+        let timing = LoopTiming {
+            target_period: 1000,
+            iterations: 500,
+            overruns: 3,
+            period: Statistics {
+                p99: 1500,
+                ..Statistics::default()
+            },
+            execution: Statistics {
+                max: 250,
+                ..Statistics::default()
+            },
+        };
+        let out = render(true, &Status::default(), 0, Some(&timing));
+        assert_eq!(
+            sample(&out, "spincoater_loop_target_period_seconds"),
+            Some(0.001)
+        );
+        assert_eq!(sample(&out, "spincoater_loop_iterations"), Some(500.0));
+        assert_eq!(sample(&out, "spincoater_loop_overruns"), Some(3.0));
+        assert_eq!(
+            sample(&out, "spincoater_loop_period_seconds{statistic=\"p99\"}"),
+            Some(0.0015)
+        );
+        assert_eq!(
+            sample(&out, "spincoater_loop_execution_seconds{statistic=\"max\"}"),
+            Some(0.00025)
+        );
+    }
+}
@@ -0,0 +1,634 @@
+//! This module bridges the MCU to PLCs over Modbus TCP and Modbus RTU.
+//!
+//! Every address is zero-based:
+//!
+//! | Table | Address | Description |
+//! |-------|---------|-------------|
+//! | Coil | 0 | Run. Writing 1 runs at the target plate RPM for the duration, and writing 0 stops. |
+//! | Coil | 1 | Vacuum pump. |
+//! | Discrete input | 0 | Running. |
+//! | Discrete input | 1 | Faulted, i.e. the fault code isn't 0. |
+//! | Holding register | 0 | Target plate RPM. |
+//! | Holding register | 1 | Duration (seconds). |
+//! | Input register | 0 | Measured plate RPM. |
+//! | Input register | 1 | Setpoint plate RPM. |
+//! | Input register | 2 | Run state: 0 = idle, 1 = running. |
+//! | Input register | 3 | Fault code, see [`FaultCode`]. |
+//! | Input register | 4 | How the previous run ended, see [`outcome_code`]. |
+//! | Input register | 5 | Duty cycle (hundredths of a percent). |
+//! | Input register | 6 | Time since the run started (tenths of a second). |
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use sc_messages::{
+    motion_profile::{Outcome, RequestRefused, RequestResult, RunAt},
+    scpi::{DEFAULT_SPIN_RPM, DEFAULT_SPIN_TIME},
+    vacuum_pump,
+};
+use tokio_modbus::{ExceptionCode, Request, Response, SlaveRequest};
+use uuid::Uuid;
+
+use crate::device::Device;
+
+/// The run coil.
+const RUN_COIL: u16 = 0;
+/// The vacuum pump coil.
+const VACUUM_COIL: u16 = 1;
+/// The number of holding registers.
+const HOLDING_REGISTERS: usize = 2;
+
+/// Why the spin coater isn't working as expected.
+///
+/// The fault code is cleared by the next successful write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum FaultCode {
+    /// Nothing is wrong.
+    None = 0,
+    /// The previous run ended with [`Outcome::Fault`].
+    RunFaulted = 1,
+    /// The previous run ended with [`Outcome::TimedOut`].
+    RunTimedOut = 2,
+    /// The MCU couldn't be reached.
+    Disconnected = 3,
+    /// The MCU refused a request with [`RequestRefused::TooManySetpoints`].
+    TooManySetpoints = 10,
+    /// The MCU refused a request with [`RequestRefused::Running`].
+    Running = 11,
+    /// The MCU refused a request with [`RequestRefused::Jogging`].
+    Jogging = 13,
+    /// The MCU refused a request with [`RequestRefused::InvalidRun`].
+    InvalidRun = 14,
+}
+
+impl From<RequestRefused> for FaultCode {
+    fn from(value: RequestRefused) -> Self {
+        match value {
+            RequestRefused::TooManySetpoints => Self::TooManySetpoints,
+            RequestRefused::Running => Self::Running,
+            RequestRefused::Jogging => Self::Jogging,
+            RequestRefused::InvalidRun => Self::InvalidRun,
+        }
+    }
+}
+
+/// The register value of how a run ended.
+///
+/// 0 = no run yet, 1 = completed, 2 = stopped, 3 = host disconnected, 4 = timed out, 5 = fault.
+#[must_use]
+pub fn outcome_code(outcome: Option<Outcome>) -> u16 {
+    match outcome {
+        None => 0,
+        Some(Outcome::Completed) => 1,
+        Some(Outcome::Stopped) => 2,
+        Some(Outcome::HostDisconnected) => 3,
+        Some(Outcome::TimedOut) => 4,
+        Some(Outcome::Fault) => 5,
+    }
+}
+
+/// What the bridge remembers between requests.
+#[derive(Debug)]
+struct Memory {
+    /// The holding registers.
+    holding_registers: [u16; HOLDING_REGISTERS],
+    /// Why the last write failed, if it did.
+    write_fault: Option<FaultCode>,
+    /// The token of the control lease, once a write has needed it.
+    token: Option<Uuid>,
+}
+
+/// The Modbus server shared by every Modbus connection.
+#[derive(Debug, Clone)]
+pub struct Bridge {
+    /// The MCU.
+    device: Arc<Device>,
+    /// The unit ID to respond to, or [`None`] to respond to every unit ID.
+    unit_id: Option<u8>,
+    /// What the bridge remembers between requests.
+    memory: Arc<Mutex<Memory>>,
+}
+
+impl Bridge {
+    /// Creates a bridge with the default target plate RPM and duration.
+    pub fn new(device: Arc<Device>) -> Self {
+        Self {
+            device,
+            unit_id: None,
+            memory: Arc::new(Mutex::new(Memory {
+                holding_registers: [DEFAULT_SPIN_RPM, DEFAULT_SPIN_TIME],
+                write_fault: None,
+                token: None,
+            })),
+        }
+    }
+
+    /// A bridge that shares this one's registers, but only responds to `unit_id`.
+    ///
+    /// RTU needs this because several devices share one bus.
+    pub fn with_unit_id(&self, unit_id: u8) -> Self {
+        Self {
+            unit_id: Some(unit_id),
+            ..self.clone()
+        }
+    }
+
+    /// Locks the memory.
+    fn memory(&self) -> MutexGuard<'_, Memory> {
+        // A panic while holding the lock can't leave the memory half-updated.
+        self.memory
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// The current fault code.
+    fn fault_code(&self) -> FaultCode {
+        if let Some(fault) = self.memory().write_fault {
+            return fault;
+        }
+        match self.device.status().outcome {
+            Some(Outcome::Fault) => FaultCode::RunFaulted,
+            Some(Outcome::TimedOut) => FaultCode::RunTimedOut,
+            _ => FaultCode::None,
+        }
+    }
+
+    /// The coils.
+    fn coils(&self) -> [bool; 2] {
+        let status = self.device.status();
+        [status.state.is_some(), status.vacuum.unwrap_or_default()]
+    }
+
+    /// The discrete inputs.
+    fn discrete_inputs(&self) -> [bool; 2] {
+        let faulted = self.fault_code() != FaultCode::None;
+        [self.device.status().state.is_some(), faulted]
+    }
+
+    /// The input registers.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn input_registers(&self) -> [u16; 7] {
+        let fault_code = self.fault_code() as u16;
+        let status = self.device.status();
+        let state = status.state.as_ref();
+        [
+            state.map_or(0, |state| state.current_plate_rpm.round() as u16),
+            state.map_or(0, |state| state.setpoint_plate_rpm.round() as u16),
+            u16::from(state.is_some()),
+            fault_code,
+            outcome_code(status.outcome),
+            state.map_or(0, |state| (state.duty_cycle_f32 * 10_000.0).round() as u16),
+            state.map_or(0, |state| {
+                u16::try_from(state.time / 100_000).unwrap_or(u16::MAX)
+            }),
+        ]
+    }
+
+    /// Takes control of the MCU, or renews the lease.
+    fn take_control(&self) -> Result<(), ExceptionCode> {
+        let mut memory = self.memory();
+        let token = self
+            .device
+            .control
+            .acquire(memory.token)
+            .map_err(|_| ExceptionCode::ServerDeviceBusy)?;
+        memory.token = Some(token);
+        Ok(())
+    }
+
+    /// Records the result of a write to the MCU.
+    fn record(
+        &self,
+        result: &Result<RequestResult, spincoater_client::Error>,
+    ) -> Result<(), ExceptionCode> {
+        let (fault, exception) = match result {
+            Ok(Ok(())) => (None, None),
+            Ok(Err(refused)) => (
+                Some(FaultCode::from(*refused)),
+                Some(ExceptionCode::ServerDeviceFailure),
+            ),
+            Err(_) => (
+                Some(FaultCode::Disconnected),
+                Some(ExceptionCode::GatewayTargetDevice),
+            ),
+        };
+        self.memory().write_fault = fault;
+        exception.map_or(Ok(()), Err)
+    }
+
+    /// Writes a coil.
+    async fn write_coil(&self, address: u16, value: bool) -> Result<(), ExceptionCode> {
+        self.take_control()?;
+        match (address, value) {
+            (RUN_COIL, true) => {
+                let [rpm, time] = self.memory().holding_registers;
+                let run_at = RunAt::new(rpm, time);
+                if !run_at.is_valid() {
+                    return Err(ExceptionCode::IllegalDataValue);
+                }
+                self.record(&self.device.client.run_at(&run_at).await)?;
+                self.device.status().profile = run_at.setpoints().to_vec();
+            }
+            (RUN_COIL, false) => self.record(&self.device.client.stop().await)?,
+            (VACUUM_COIL, on) => {
+                let request = if on {
+                    vacuum_pump::Request::Enable
+                } else {
+                    vacuum_pump::Request::Disable
+                };
+                self.record(&self.device.client.vacuum_pump(&request).await.map(Ok))?;
+                self.device.status().vacuum = Some(on);
+            }
+            _ => return Err(ExceptionCode::IllegalDataAddress),
+        }
+        Ok(())
+    }
+
+    /// Writes holding registers.
+    fn write_registers(&self, address: u16, values: &[u16]) -> Result<(), ExceptionCode> {
+        let mut memory = self.memory();
+        let registers = usize::from(address)
+            .checked_add(values.len())
+            .and_then(|end| memory.holding_registers.get_mut(usize::from(address)..end))
+            .ok_or(ExceptionCode::IllegalDataAddress)?;
+        registers.copy_from_slice(values);
+        Ok(())
+    }
+
+    /// Handles a request.
+    async fn handle(&self, request: Request<'static>) -> Result<Response, ExceptionCode> {
+        Ok(match request {
+            Request::ReadCoils(address, quantity) => {
+                Response::ReadCoils(read(&self.coils(), address, quantity)?)
+            }
+            Request::ReadDiscreteInputs(address, quantity) => {
+                Response::ReadDiscreteInputs(read(&self.discrete_inputs(), address, quantity)?)
+            }
+            Request::ReadHoldingRegisters(address, quantity) => Response::ReadHoldingRegisters(
+                read(&self.memory().holding_registers, address, quantity)?,
+            ),
+            Request::ReadInputRegisters(address, quantity) => {
+                Response::ReadInputRegisters(read(&self.input_registers(), address, quantity)?)
+            }
+            Request::WriteSingleCoil(address, value) => {
+                self.write_coil(address, value).await?;
+                Response::WriteSingleCoil(address, value)
+            }
+            Request::WriteMultipleCoils(address, values) => {
+                for (coil, &value) in (address..).zip(values.iter()) {
+                    self.write_coil(coil, value).await?;
+                }
+                let quantity =
+                    u16::try_from(values.len()).map_err(|_| ExceptionCode::IllegalDataValue)?;
+                Response::WriteMultipleCoils(address, quantity)
+            }
+            Request::WriteSingleRegister(address, value) => {
+                self.write_registers(address, &[value])?;
+                Response::WriteSingleRegister(address, value)
+            }
+            Request::WriteMultipleRegisters(address, values) => {
+                self.write_registers(address, &values)?;
+                let quantity =
+                    u16::try_from(values.len()).map_err(|_| ExceptionCode::IllegalDataValue)?;
+                Response::WriteMultipleRegisters(address, quantity)
+            }
+            _ => return Err(ExceptionCode::IllegalFunction),
+        })
+    }
+}
+
+/// Reads `quantity` values starting at `address`.
+fn read<T: Copy>(values: &[T], address: u16, quantity: u16) -> Result<Vec<T>, ExceptionCode> {
+    usize::from(address)
+        .checked_add(usize::from(quantity))
+        .and_then(|end| values.get(usize::from(address)..end))
+        .map(<[T]>::to_vec)
+        .ok_or(ExceptionCode::IllegalDataAddress)
+}
+
+impl tokio_modbus::server::Service for Bridge {
+    type Request = SlaveRequest<'static>;
+    type Response = Option<Response>;
+    type Exception = ExceptionCode;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Exception>> + Send>>;
+
+    fn call(&self, request: Self::Request) -> Self::Future {
+        let bridge = self.clone();
+        Box::pin(async move {
+            if bridge
+                .unit_id
+                .is_some_and(|unit_id| unit_id != request.slave)
+            {
+                // Another device on the bus should respond.
+                return Ok(None);
+            }
+            bridge.handle(request.request).await.map(Some)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use postcard_rpc::{
+        Endpoint, Topic,
+        header::{VarHeader, VarKey, VarSeq},
+        host_client::RpcFrame,
+        postcard,
+        standard_icd::{ERROR_PATH, WireError},
+        test_utils::{LocalFakeServer, local_setup},
+    };
+    use sc_messages::{
+        icd::{
+            MotionProfileStateTopic, MotionRequestEndpoint, RunAtEndpoint,
+            VacuumPumpRequestEndpoint,
+        },
+        motion_profile::{self, State},
+        pwm::{DutyCycle, HALF_POWER_DUTY},
+    };
+    use spincoater_client::{Client, SUBSCRIPTION_DEPTH, state::MotionProfileState};
+    use tokio::{
+        net::TcpListener,
+        sync::mpsc::{self, Sender, UnboundedReceiver, UnboundedSender},
+        time::{sleep, timeout},
+    };
+    use tokio_modbus::{
+        client::{Context, Reader, Writer, tcp},
+        server::tcp::Server,
+    };
+
+    use super::*;
+
+    /// A request that the fake MCU received.
+    #[derive(Debug, PartialEq, Eq)]
+    enum Received {
+        RunAt(RunAt),
+        MotionProfile(motion_profile::Request),
+        VacuumPump(vacuum_pump::Request),
+    }
+
+    /// A Modbus client connected to a bridge for a fake MCU.
+    struct Harness {
+        /// The Modbus client.
+        modbus: Context,
+        /// The MCU the bridge forwards to.
+        device: Arc<Device>,
+        /// The requests the fake MCU received.
+        received: UnboundedReceiver<Received>,
+        /// Sends frames to the client as if the MCU published them.
+        to_client: Sender<Vec<u8>>,
+    }
+
+    impl Harness {
+        /// Serves a bridge over Modbus TCP for a fake MCU that answers motion profile requests with `result`,
+        /// and connects to it.
+        async fn new(result: RequestResult) -> Self {
+            let (mcu, host_client) = local_setup::<WireError>(SUBSCRIPTION_DEPTH, ERROR_PATH);
+            let to_client = mcu.to_client.clone();
+            let (sender, received) = mpsc::unbounded_channel();
+            tokio::spawn(fake_mcu(mcu, result, sender));
+            let device = Arc::new(
+                Device::new(Client::from(host_client), "fake".to_string())
+                    .await
+                    .expect("Failed to subscribe"),
+            );
+
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("Failed to bind");
+            let address = listener.local_addr().expect("Not bound");
+            let bridge = Bridge::new(Arc::clone(&device));
+            tokio::spawn(async move {
+                let on_connected = |stream, _: SocketAddr| {
+                    let bridge = bridge.clone();
+                    async move { Ok(Some((bridge, stream))) }
+                };
+                let _ = Server::new(listener).serve(&on_connected, |_| {}).await;
+            });
+            let modbus = tcp::connect(address).await.expect("Failed to connect");
+            Self {
+                modbus,
+                device,
+                received,
+                to_client,
+            }
+        }
+
+        /// Publishes a state as if the MCU sent it, and waits for the bridge to see it.
+        async fn publish_state(&self, state: Option<State>) {
+            let frame = RpcFrame {
+                header: VarHeader {
+                    key: VarKey::Key8(MotionProfileStateTopic::TOPIC_KEY),
+                    seq_no: VarSeq::Seq2(0),
+                },
+                body: postcard::to_stdvec(&state).expect("Failed to serialize"),
+            };
+            let running = state.is_some();
+            self.to_client
+                .send(frame.to_bytes())
+                .await
+                .expect("The client closed");
+            timeout(Duration::from_secs(1), async {
+                while self.device.status().state.is_some() != running {
+                    sleep(Duration::from_millis(1)).await;
+                }
+            })
+            .await
+            .expect("The state never arrived");
+        }
+    }
+
+    /// Answers every request like the MCU would, answering motion profile requests with `result`.
+    async fn fake_mcu(
+        mut mcu: LocalFakeServer,
+        result: RequestResult,
+        received: UnboundedSender<Received>,
+    ) {
+        while let Ok(frame) = mcu.recv_from_client().await {
+            let VarKey::Key8(key) = frame.header.key else {
+                continue;
+            };
+            let seq_no = frame.header.seq_no.into();
+            let body = frame.body.as_slice();
+            let _ = if key == RunAtEndpoint::REQ_KEY {
+                let _ = received.send(Received::RunAt(deserialize(body)));
+                mcu.reply::<RunAtEndpoint>(seq_no, &result).await
+            } else if key == MotionRequestEndpoint::REQ_KEY {
+                let _ = received.send(Received::MotionProfile(deserialize(body)));
+                mcu.reply::<MotionRequestEndpoint>(seq_no, &result).await
+            } else if key == VacuumPumpRequestEndpoint::REQ_KEY {
+                let _ = received.send(Received::VacuumPump(deserialize(body)));
+                mcu.reply::<VacuumPumpRequestEndpoint>(seq_no, &()).await
+            } else {
+                continue;
+            };
+        }
+    }
+
+    /// Deserializes a request sent to the fake MCU.
+    fn deserialize<T: serde::de::DeserializeOwned>(body: &[u8]) -> T {
+        postcard::from_bytes(body).expect("Unexpected request")
+    }
+
+    /// A state halfway through a run.
+    fn running_state() -> State {
+        State {
+            setpoint_rpm: 3000,
+            current_rpm: 2900,
+            rpm_error: 100,
+            duty_cycle: DutyCycle::new(HALF_POWER_DUTY),
+            time: 12_345_678,
+            loop_period: 1000,
+            execution_time: 100,
+            overruns: 0,
+            sample: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn holding_registers_hold_the_run_parameters() {
+        let mut harness = Harness::new(Ok(())).await;
+        let registers = harness.modbus.read_holding_registers(0, 2).await;
+        assert_eq!(
+            registers.expect("Modbus failed"),
+            Ok(vec![DEFAULT_SPIN_RPM, DEFAULT_SPIN_TIME])
+        );
+        let written = harness.modbus.write_multiple_registers(0, &[500, 20]).await;
+        assert_eq!(written.expect("Modbus failed"), Ok(()));
+        let written = harness.modbus.write_single_register(1, 30).await;
+        assert_eq!(written.expect("Modbus failed"), Ok(()));
+        let registers = harness.modbus.read_holding_registers(0, 2).await;
+        assert_eq!(registers.expect("Modbus failed"), Ok(vec![500, 30]));
+    }
+
+    #[tokio::test]
+    async fn addresses_past_the_map_are_illegal() {
+        let mut harness = Harness::new(Ok(())).await;
+        let read = harness.modbus.read_holding_registers(1, 2).await;
+        assert_eq!(
+            read.expect("Modbus failed"),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+        let read = harness.modbus.read_input_registers(0, 8).await;
+        assert_eq!(
+            read.expect("Modbus failed"),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+        let written = harness.modbus.write_single_register(2, 1).await;
+        assert_eq!(
+            written.expect("Modbus failed"),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+        let written = harness.modbus.write_single_coil(2, true).await;
+        assert_eq!(
+            written.expect("Modbus failed"),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+    }
+
+    #[tokio::test]
+    async fn coils_run_stop_and_switch_the_vacuum() {
+        let mut harness = Harness::new(Ok(())).await;
+        let written = harness.modbus.write_multiple_registers(0, &[500, 20]).await;
+        assert_eq!(written.expect("Modbus failed"), Ok(()));
+        for (coil, value) in [(RUN_COIL, true), (RUN_COIL, false), (VACUUM_COIL, true)] {
+            let written = harness.modbus.write_single_coil(coil, value).await;
+            assert_eq!(written.expect("Modbus failed"), Ok(()));
+        }
+        assert_eq!(
+            harness.received.recv().await,
+            Some(Received::RunAt(RunAt::new(500, 20)))
+        );
+        assert_eq!(
+            harness.received.recv().await,
+            Some(Received::MotionProfile(motion_profile::Request::Stop))
+        );
+        assert_eq!(
+            harness.received.recv().await,
+            Some(Received::VacuumPump(vacuum_pump::Request::Enable))
+        );
+        let coils = harness.modbus.read_coils(0, 2).await;
+        assert_eq!(coils.expect("Modbus failed"), Ok(vec![false, true]));
+    }
+
+    #[tokio::test]
+    async fn invalid_run_parameters_are_rejected() {
+        let mut harness = Harness::new(Ok(())).await;
+        let written = harness.modbus.write_single_register(1, 0).await;
+        assert_eq!(written.expect("Modbus failed"), Ok(()));
+        let written = harness.modbus.write_single_coil(RUN_COIL, true).await;
+        assert_eq!(
+            written.expect("Modbus failed"),
+            Err(ExceptionCode::IllegalDataValue)
+        );
+        assert!(harness.received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn refusals_set_the_fault_code() {
+        let mut harness = Harness::new(Err(RequestRefused::Running)).await;
+        let written = harness.modbus.write_single_coil(RUN_COIL, true).await;
+        assert_eq!(
+            written.expect("Modbus failed"),
+            Err(ExceptionCode::ServerDeviceFailure)
+        );
+        let inputs = harness.modbus.read_discrete_inputs(0, 2).await;
+        assert_eq!(inputs.expect("Modbus failed"), Ok(vec![false, true]));
+        let fault_code = harness.modbus.read_input_registers(3, 1).await;
+        assert_eq!(
+            fault_code.expect("Modbus failed"),
+            Ok(vec![FaultCode::Running as u16])
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_need_control() {
+        let mut harness = Harness::new(Ok(())).await;
+        let _token = harness
+            .device
+            .control
+            .acquire(None)
+            .expect("Nobody controls the MCU");
+        let written = harness.modbus.write_single_coil(VACUUM_COIL, true).await;
+        assert_eq!(
+            written.expect("Modbus failed"),
+            Err(ExceptionCode::ServerDeviceBusy)
+        );
+        assert!(harness.received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    async fn input_registers_follow_the_run() {
+        let mut harness = Harness::new(Ok(())).await;
+        let idle = harness.modbus.read_input_registers(0, 7).await;
+        assert_eq!(idle.expect("Modbus failed"), Ok(vec![0; 7]));
+
+        harness.publish_state(Some(running_state())).await;
+        let state = MotionProfileState::from(running_state());
+        let running = harness.modbus.read_input_registers(0, 7).await;
+        assert_eq!(
+            running.expect("Modbus failed"),
+            Ok(vec![
+                state.current_plate_rpm.round() as u16,
+                state.setpoint_plate_rpm.round() as u16,
+                1,
+                FaultCode::None as u16,
+                outcome_code(None),
+                (state.duty_cycle_f32 * 10_000.0).round() as u16,
+                123,
+            ])
+        );
+        let inputs = harness.modbus.read_discrete_inputs(0, 2).await;
+        assert_eq!(inputs.expect("Modbus failed"), Ok(vec![true, false]));
+
+        harness.publish_state(None).await;
+        let run_state = harness.modbus.read_input_registers(2, 1).await;
+        assert_eq!(run_state.expect("Modbus failed"), Ok(vec![0]));
+    }
+}
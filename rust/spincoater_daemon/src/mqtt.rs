@@ -0,0 +1,268 @@
+//! This module bridges the MCU to an MQTT broker.
+//!
+//! Every topic starts with a configurable prefix:
+//!
+//! | Topic | Direction | Payload |
+//! |-------|-----------|---------|
+//! | `<prefix>/available` | Published, retained | `online`, or `offline` when the daemon disconnects. |
+//! | `<prefix>/state` | Published | The latest state as JSON. |
+//! | `<prefix>/event` | Published | `{"type": "run_start"}` or `{"type": "run_end", "outcome": ...}` as JSON. |
+//! | `<prefix>/fault` | Published | The outcome of a run that faulted or timed out as JSON. |
+//! | `<prefix>/log` | Published | A log message. |
+//! | `<prefix>/error` | Published | Why a command failed. |
+//! | `<prefix>/command/start` | Subscribed | Anything. Starts the motion profile. |
+//! | `<prefix>/command/stop` | Subscribed | Anything. Stops the current run. |
+//! | `<prefix>/command/vacuum` | Subscribed | `on` or `off`. |
+//! | `<prefix>/command/run_at` | Subscribed | `{"rpm": <plate rpm>, "time": <seconds>}` as JSON. |
+
+use std::{sync::Arc, time::Duration};
+
+use rumqttc::{AsyncClient, Event as MqttEvent, LastWill, MqttOptions, Packet, Publish, QoS};
+use sc_messages::{
+    motion_profile::{Outcome, RequestResult, RunAt},
+    vacuum_pump,
+};
+use serde_json::json;
+use tokio::sync::{Mutex, broadcast::error::RecvError};
+use uuid::Uuid;
+
+use crate::device::{Device, Event, RunEvent};
+
+/// How often to ping the broker when nothing else is sent.
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+/// How long to wait before reconnecting to the broker.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// The number of messages queued for the broker before publishing waits.
+const QUEUE_CAPACITY: usize = 256;
+
+/// The command line arguments of the MQTT bridge.
+#[derive(Debug, clap::Args)]
+pub struct MqttArgs {
+    /// The MQTT broker to bridge to, e.g. `localhost`.
+    ///
+    /// The bridge is disabled unless this is set.
+    #[arg(id = "mqtt-host", long = "mqtt-host")]
+    pub host: Option<String>,
+    /// The port of the MQTT broker.
+    #[arg(id = "mqtt-port", long = "mqtt-port", default_value_t = 1883)]
+    pub port: u16,
+    /// The client ID to connect to the MQTT broker with.
+    #[arg(
+        id = "mqtt-client-id",
+        long = "mqtt-client-id",
+        default_value = "spincoater"
+    )]
+    pub client_id: String,
+    /// The prefix of every MQTT topic.
+    #[arg(id = "mqtt-prefix", long = "mqtt-prefix", default_value = "spincoater")]
+    pub prefix: String,
+}
+
+/// Connects to the broker and bridges the MCU to it until the daemon closes.
+///
+/// The bridge reconnects by itself if the broker goes away.
+pub fn start(device: Arc<Device>, host: String, args: &MqttArgs) {
+    let prefix = args.prefix.clone();
+    let mut options = MqttOptions::new(&args.client_id, host, args.port);
+    options
+        .set_keep_alive(KEEP_ALIVE)
+        .set_last_will(LastWill::new(
+            format!("{prefix}/available"),
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+    let (client, mut event_loop) = AsyncClient::new(options, QUEUE_CAPACITY);
+    let bridge = Arc::new(Bridge {
+        device,
+        client,
+        prefix,
+        token: Mutex::new(None),
+    });
+
+    tokio::spawn(Arc::clone(&bridge).publish_events());
+    tokio::spawn(async move {
+        loop {
+            match event_loop.poll().await {
+                Ok(MqttEvent::Incoming(Packet::ConnAck(_))) => {
+                    // Subscriptions and retained messages don't survive reconnecting.
+                    tokio::spawn(Arc::clone(&bridge).on_connected());
+                }
+                Ok(MqttEvent::Incoming(Packet::Publish(publish))) => {
+                    tokio::spawn(Arc::clone(&bridge).handle_command(publish));
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    eprintln!("Lost the connection to the MQTT broker: {error}");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    });
+}
+
+/// The connection between the MCU and the broker.
+struct Bridge {
+    /// The MCU.
+    device: Arc<Device>,
+    /// The connection to the broker.
+    client: AsyncClient,
+    /// The prefix of every topic.
+    prefix: String,
+    /// The token of the control lease, once a command has needed it.
+    ///
+    /// This is locked for a whole command so commands run one at a time.
+    token: Mutex<Option<Uuid>>,
+}
+
+impl Bridge {
+    /// The full name of a topic.
+    fn topic(&self, name: &str) -> String {
+        format!("{}/{name}", self.prefix)
+    }
+
+    /// Publishes a message, ignoring the error if the event loop has closed.
+    async fn publish(&self, name: &str, qos: QoS, retain: bool, payload: impl Into<Vec<u8>>) {
+        let _ = self
+            .client
+            .publish(self.topic(name), qos, retain, payload)
+            .await;
+    }
+
+    /// Announces the bridge and subscribes to commands.
+    async fn on_connected(self: Arc<Self>) {
+        self.publish("available", QoS::AtLeastOnce, true, "online")
+            .await;
+        let _ = self
+            .client
+            .subscribe(self.topic("command/+"), QoS::AtLeastOnce)
+            .await;
+    }
+
+    /// Publishes the MCU's events until the connection to the MCU closes.
+    async fn publish_events(self: Arc<Self>) {
+        let mut events = self.device.subscribe();
+        let mut runs = self.device.subscribe_runs();
+        loop {
+            tokio::select! {
+                // Announce a run before its states.
+                biased;
+                Some(run_event) = runs.recv() => self.publish_run_event(run_event).await,
+                event = events.recv() => match event {
+                    Ok(Event::State(Some(state))) => {
+                        let Ok(payload) = serde_json::to_string(&state) else {
+                            continue;
+                        };
+                        // States are replaced 50 times a second, so losing one doesn't matter.
+                        self.publish("state", QoS::AtMostOnce, false, payload).await;
+                    }
+                    Ok(Event::Log(log)) => self.publish("log", QoS::AtLeastOnce, false, log).await,
+                    // Runs starting and ending come from `runs`, which never skips any,
+                    // and the broker is slower than the MCU, so skip the events it missed.
+                    Ok(Event::State(None) | Event::Outcome(_) | Event::Touch(_))
+                    | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return,
+                },
+            }
+        }
+    }
+
+    /// Publishes a run starting or ending.
+    async fn publish_run_event(&self, run_event: RunEvent) {
+        match run_event {
+            RunEvent::Started => {
+                let payload = json!({ "type": "run_start" }).to_string();
+                self.publish("event", QoS::AtLeastOnce, false, payload)
+                    .await;
+            }
+            RunEvent::Ended(outcome) => {
+                let outcome_json = json!(outcome);
+                self.publish(
+                    "event",
+                    QoS::AtLeastOnce,
+                    false,
+                    json!({ "type": "run_end", "outcome": outcome_json }).to_string(),
+                )
+                .await;
+                if matches!(outcome, Outcome::Fault | Outcome::TimedOut) {
+                    self.publish("fault", QoS::AtLeastOnce, false, outcome_json.to_string())
+                        .await;
+                }
+            }
+        }
+    }
+
+    /// Executes a command from the broker, publishing why it failed if it did.
+    async fn handle_command(self: Arc<Self>, publish: Publish) {
+        let Some(command) = publish
+            .topic
+            .strip_prefix(&self.topic("command/"))
+            .map(str::to_string)
+        else {
+            return;
+        };
+        if let Err(error) = self.execute(&command, &publish.payload).await {
+            self.publish(
+                "error",
+                QoS::AtLeastOnce,
+                false,
+                format!("{command}: {error}"),
+            )
+            .await;
+        }
+    }
+
+    /// Executes a command.
+    async fn execute(&self, command: &str, payload: &[u8]) -> Result<(), String> {
+        let mut token = self.token.lock().await;
+        *token = Some(
+            self.device
+                .control
+                .acquire(*token)
+                .map_err(|_| "Another client controls the spin coater.".to_string())?,
+        );
+        match command {
+            "start" => check(self.device.client.start().await),
+            "stop" => check(self.device.client.stop().await),
+            "vacuum" => {
+                let on = match String::from_utf8_lossy(payload).trim() {
+                    "on" | "ON" | "true" | "1" => true,
+                    "off" | "OFF" | "false" | "0" => false,
+                    _ => return Err("The payload must be on or off.".to_string()),
+                };
+                let request = if on {
+                    vacuum_pump::Request::Enable
+                } else {
+                    vacuum_pump::Request::Disable
+                };
+                self.device
+                    .client
+                    .vacuum_pump(&request)
+                    .await
+                    .map_err(|error| error.to_string())?;
+                self.device.status().vacuum = Some(on);
+                Ok(())
+            }
+            "run_at" => {
+                let run_at: RunAt =
+                    serde_json::from_slice(payload).map_err(|error| error.to_string())?;
+                if !run_at.is_valid() {
+                    return Err("The RPM and time must be nonzero.".to_string());
+                }
+                check(self.device.client.run_at(&run_at).await)?;
+                self.device.status().profile = run_at.setpoints().to_vec();
+                Ok(())
+            }
+            _ => Err("Unknown command.".to_string()),
+        }
+    }
+}
+
+/// Converts the MCU's response to a request.
+fn check(response: spincoater_client::Result<RequestResult>) -> Result<(), String> {
+    response
+        .map_err(|error| error.to_string())?
+        .map_err(|refused| format!("{refused:?}"))
+}
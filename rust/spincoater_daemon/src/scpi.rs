@@ -0,0 +1,302 @@
+//! This module serves the SCPI-like text protocol in [`sc_messages::scpi`] over TCP.
+//!
+//! Each connection is a separate [`Session`].
+//! Connections may watch the MCU freely, but the first command that changes it
+//! takes the same control lease as the HTTP API, which is released when the connection closes.
+//!
+//! Motion profile CSV files can only be loaded from the folder given with `--profile-dir`,
+//! since anyone who can connect could otherwise read any CSV file the daemon can.
+
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use sc_messages::{
+    motion_profile::{self, Outcome, RequestResult, RunAt, Setpoint, State},
+    scpi::{Error, Instrument, MAX_LINE_LENGTH, Session},
+    vacuum_pump,
+};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use uuid::Uuid;
+
+use crate::device::Device;
+
+/// Accepts SCPI connections until the listener fails.
+///
+/// Motion profile CSV files are loaded from `profile_dir`, if any.
+pub async fn serve(listener: TcpListener, device: Arc<Device>, profile_dir: Option<Arc<Path>>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(handle_connection(
+            stream,
+            Arc::clone(&device),
+            profile_dir.clone(),
+        ));
+    }
+}
+
+/// Handles one connection until it closes.
+async fn handle_connection(stream: TcpStream, device: Arc<Device>, profile_dir: Option<Arc<Path>>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    let mut connection = Connection {
+        device,
+        token: None,
+        profile_dir,
+    };
+    let mut session = Session::new();
+    while let Ok(true) = read_line(&mut reader, &mut line).await {
+        let mut response = String::new();
+        match str::from_utf8(&line) {
+            // The MCU can't accept longer lines, so neither does the daemon.
+            Ok(line) if line.len() <= MAX_LINE_LENGTH => {
+                // Writing to a String can't fail.
+                let _ = session
+                    .handle_line(&mut connection, line, &mut response)
+                    .await;
+            }
+            _ => session.push_error(Error::Syntax),
+        }
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+    if let Some(token) = connection.token {
+        connection.device.control.release(token);
+    }
+}
+
+/// Reads the next line into `line` without its line ending,
+/// never buffering much more than [`MAX_LINE_LENGTH`] bytes of it.
+///
+/// Longer lines are skipped after reading their start, which leaves `line` longer than [`MAX_LINE_LENGTH`].
+/// Returns `false` once the connection closes.
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    line: &mut Vec<u8>,
+) -> io::Result<bool> {
+    // Leave room for the line ending, so that only longer lines are cut off.
+    let limit = u64::try_from(MAX_LINE_LENGTH.saturating_add(2)).unwrap_or(u64::MAX);
+    line.clear();
+    if (&mut *reader).take(limit).read_until(b'\n', line).await? == 0 {
+        return Ok(false);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        return Ok(true);
+    }
+    // The line was cut off, so skip the rest of it.
+    let mut rest = Vec::new();
+    while line.len() > MAX_LINE_LENGTH {
+        rest.clear();
+        let read = (&mut *reader)
+            .take(limit)
+            .read_until(b'\n', &mut rest)
+            .await?;
+        if read == 0 || rest.last() == Some(&b'\n') {
+            break;
+        }
+    }
+    Ok(true)
+}
+
+/// A connection to the daemon, which executes commands on the shared MCU.
+struct Connection {
+    /// The MCU.
+    device: Arc<Device>,
+    /// The token of the control lease, once a command has needed it.
+    token: Option<Uuid>,
+    /// The folder motion profile CSV files are loaded from, if loading them is enabled.
+    profile_dir: Option<Arc<Path>>,
+}
+
+impl Connection {
+    /// Takes control of the MCU, or renews the lease.
+    fn take_control(&mut self) -> Result<(), Error> {
+        let token = self
+            .device
+            .control
+            .acquire(self.token)
+            .map_err(|_| Error::Execution("Another client controls the spin coater"))?;
+        self.token = Some(token);
+        Ok(())
+    }
+}
+
+/// Resolves the path of a motion profile CSV file within the profile folder.
+///
+/// # Errors
+/// Returns an error if there is no profile folder, or the path is absolute or could leave the folder.
+fn profile_path(profile_dir: Option<&Path>, path: &str) -> Result<PathBuf, Error> {
+    let profile_dir = profile_dir.ok_or(Error::Execution("Loading profile files is disabled"))?;
+    let path = Path::new(path);
+    let inside = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !inside || path.as_os_str().is_empty() {
+        return Err(Error::Execution(
+            "Profile files must be inside the profile folder",
+        ));
+    }
+    Ok(profile_dir.join(path))
+}
+
+/// Converts the MCU's response to a request.
+fn check(response: Result<RequestResult, spincoater_client::Error>) -> Result<(), Error> {
+    response
+        .map_err(|_| Error::Execution("Lost the connection to the MCU"))?
+        .map_err(Error::Refused)
+}
+
+impl Instrument for Connection {
+    async fn motion_profile_request(
+        &mut self,
+        request: motion_profile::Request,
+    ) -> Result<(), Error> {
+        self.take_control()?;
+        match &request {
+            motion_profile::Request::ClearSetpoints => {
+                check(self.device.client.motion_profile_request(&request).await)?;
+                self.device.status().profile.clear();
+            }
+            motion_profile::Request::Add(setpoint) => {
+                check(self.device.client.motion_profile_request(&request).await)?;
+                self.device.status().profile.push(setpoint.clone());
+            }
+            _ => check(self.device.client.motion_profile_request(&request).await)?,
+        }
+        Ok(())
+    }
+
+    async fn run_at(&mut self, run_at: RunAt) -> Result<(), Error> {
+        self.take_control()?;
+        check(self.device.client.run_at(&run_at).await)?;
+        self.device.status().profile = run_at.setpoints().to_vec();
+        Ok(())
+    }
+
+    async fn load_profile_file(&mut self, path: &str) -> Result<(), Error> {
+        // Only the client in control may read files through the daemon.
+        self.take_control()?;
+        let path = profile_path(self.profile_dir.as_deref(), path)?;
+        let setpoints = csv::Reader::from_path(path)
+            .and_then(|reader| {
+                reader
+                    .into_deserialize()
+                    .collect::<Result<Vec<Setpoint>, _>>()
+            })
+            .map_err(|_| Error::Execution("Couldn't read the motion profile file"))?;
+        check(self.device.client.upload_profile(setpoints.clone()).await)?;
+        self.device.status().profile = setpoints;
+        Ok(())
+    }
+
+    async fn set_vacuum(&mut self, on: bool) -> Result<(), Error> {
+        self.take_control()?;
+        let request = if on {
+            vacuum_pump::Request::Enable
+        } else {
+            vacuum_pump::Request::Disable
+        };
+        self.device
+            .client
+            .vacuum_pump(&request)
+            .await
+            .map_err(|_| Error::Execution("Lost the connection to the MCU"))?;
+        self.device.status().vacuum = Some(on);
+        Ok(())
+    }
+
+    fn vacuum(&self) -> bool {
+        self.device.status().vacuum.unwrap_or_default()
+    }
+
+    fn state(&self) -> Option<State> {
+        self.device.status().state.as_ref().map(State::from)
+    }
+
+    fn outcome(&self) -> Option<Outcome> {
+        self.device.status().outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads every line of `input` with [`read_line`].
+    async fn read_lines(mut input: &[u8]) -> Vec<Vec<u8>> {
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        while let Ok(true) = read_line(&mut input, &mut line).await {
+            lines.push(line.clone());
+        }
+        lines
+    }
+
+    #[tokio::test]
+    async fn strips_line_endings() {
+        let lines = read_lines(b"*IDN?\nRUN\r\nSTOP").await;
+        assert_eq!(lines, [&b"*IDN?"[..], b"RUN", b"STOP"]);
+    }
+
+    #[tokio::test]
+    async fn skips_long_lines() {
+        let mut input = vec![b'A'; MAX_LINE_LENGTH * 10];
+        input.extend_from_slice(b"\nRUN\n");
+        let lines = read_lines(&input).await;
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].len() > MAX_LINE_LENGTH);
+        assert!(lines[0].len() <= MAX_LINE_LENGTH + 2);
+        assert_eq!(lines[1], b"RUN");
+    }
+
+    #[tokio::test]
+    async fn accepts_the_longest_line() {
+        let mut input = vec![b'A'; MAX_LINE_LENGTH];
+        input.extend_from_slice(b"\r\n");
+        let lines = read_lines(&input).await;
+        assert_eq!(lines, [vec![b'A'; MAX_LINE_LENGTH]]);
+    }
+
+    #[test]
+    fn profiles_are_loaded_from_the_profile_folder() {
+        let profile_dir = Path::new("profiles");
+        assert_eq!(
+            profile_path(Some(profile_dir), "spin.csv"),
+            Ok(profile_dir.join("spin.csv"))
+        );
+        assert_eq!(
+            profile_path(Some(profile_dir), "coatings/spin.csv"),
+            Ok(profile_dir.join("coatings/spin.csv"))
+        );
+        assert_eq!(
+            profile_path(Some(profile_dir), "./spin.csv"),
+            Ok(profile_dir.join("./spin.csv"))
+        );
+    }
+
+    #[test]
+    fn profiles_cant_leave_the_profile_folder() {
+        let profile_dir = Some(Path::new("profiles"));
+        for path in ["", "/etc/passwd", "../spin.csv", "coatings/../../spin.csv"] {
+            assert!(
+                profile_path(profile_dir, path).is_err(),
+                "{path} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn profiles_need_a_profile_folder() {
+        assert!(profile_path(None, "spin.csv").is_err());
+    }
+}
@@ -0,0 +1,358 @@
+//! This crate contains Python bindings for [`spincoater_client`].
+//!
+//! Python calls block until the MCU responds, but release the GIL while waiting.
+
+use std::{sync::Arc, time::Duration};
+
+use pyo3::{
+    create_exception,
+    exceptions::{PyConnectionError, PyException, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+use sc_messages::{
+    jog,
+    motion_profile::{Outcome, RequestResult, RunAt, Setpoint},
+    pwm::{DutyCycle, MAX_POWER_DUTY},
+    vacuum_pump,
+};
+use spincoater_client::{StateSubscription, Subscription, state::MotionProfileState};
+use tokio::{runtime::Runtime, time::timeout};
+
+/// How long blocking calls wait before checking for a `KeyboardInterrupt`.
+const SIGNAL_CHECK_PERIOD: Duration = Duration::from_millis(100);
+
+/// How long to wait for a run's outcome after its final state.
+const OUTCOME_TIMEOUT: Duration = Duration::from_secs(1);
+
+create_exception!(
+    spincoater,
+    RequestRefused,
+    PyException,
+    "The MCU refused a request."
+);
+
+/// Converts a client error to a Python exception.
+fn client_error(error: &spincoater_client::Error) -> PyErr {
+    PyConnectionError::new_err(error.to_string())
+}
+
+/// Raises [`RequestRefused`] if the MCU refused a request.
+fn check<E: std::fmt::Debug>(response: Result<(), E>) -> PyResult<()> {
+    response.map_err(|refused| RequestRefused::new_err(format!("{refused:?}")))
+}
+
+/// Returns the serial ports that ESP devices are plugged into.
+#[pyfunction]
+fn esp_ports() -> PyResult<Vec<String>> {
+    Ok(spincoater_client::esp_ports()
+        .map_err(|error| client_error(&error))?
+        .into_iter()
+        .map(|port| port.port_name)
+        .collect())
+}
+
+/// A connection to the MCU.
+#[pyclass(frozen)]
+struct Client {
+    /// The runtime that the client's requests and subscriptions run on.
+    runtime: Arc<Runtime>,
+    /// The underlying client.
+    client: spincoater_client::Client,
+}
+
+impl Client {
+    /// Sends a motion profile request while releasing the GIL.
+    fn request<F>(&self, py: Python<'_>, request: F) -> PyResult<()>
+    where
+        F: AsyncFnOnce(&spincoater_client::Client) -> spincoater_client::Result<RequestResult>
+            + Send,
+    {
+        let response = py
+            .detach(|| self.runtime.block_on(request(&self.client)))
+            .map_err(|error| client_error(&error))?;
+        check(response)
+    }
+
+    /// Sends a jog request while releasing the GIL.
+    fn jog_request(&self, py: Python<'_>, request: &jog::Request) -> PyResult<()> {
+        let response = py
+            .detach(|| self.runtime.block_on(self.client.jog(request)))
+            .map_err(|error| client_error(&error))?;
+        check(response)
+    }
+}
+
+#[pymethods]
+impl Client {
+    /// Connects to the MCU on a serial port.
+    #[staticmethod]
+    fn connect(port_name: &str) -> PyResult<Self> {
+        let runtime = Arc::new(Runtime::new()?);
+        // The client's I/O tasks are spawned onto the runtime.
+        let client = {
+            let _guard = runtime.enter();
+            spincoater_client::Client::connect(port_name).map_err(|error| client_error(&error))?
+        };
+        Ok(Self { runtime, client })
+    }
+
+    /// Replaces the MCU's motion profile with `(motor rpm, time in micros)` setpoints.
+    fn upload(&self, py: Python<'_>, setpoints: Vec<(u16, u64)>) -> PyResult<()> {
+        let setpoints = setpoints
+            .into_iter()
+            .map(|(rpm, time)| Setpoint { rpm, time });
+        self.request(py, async |client| client.upload_profile(setpoints).await)
+    }
+
+    /// Starts the MCU's motion profile.
+    fn start(&self, py: Python<'_>) -> PyResult<()> {
+        self.request(py, async |client| client.start().await)
+    }
+
+    /// Stops the current run.
+    fn stop(&self, py: Python<'_>) -> PyResult<()> {
+        self.request(py, async |client| client.stop().await)
+    }
+
+    /// Runs at a constant plate RPM for a number of seconds, replacing the MCU's motion profile.
+    fn run_at(&self, py: Python<'_>, plate_rpm: u16, seconds: u16) -> PyResult<()> {
+        let run_at = RunAt::new(plate_rpm, seconds);
+        if !run_at.is_valid() {
+            return Err(PyValueError::new_err(
+                "plate_rpm and seconds must be nonzero",
+            ));
+        }
+        self.request(py, async |client| client.run_at(&run_at).await)
+    }
+
+    /// Turns the vacuum pump on or off.
+    fn vacuum(&self, py: Python<'_>, on: bool) -> PyResult<()> {
+        let request = if on {
+            vacuum_pump::Request::Enable
+        } else {
+            vacuum_pump::Request::Disable
+        };
+        py.detach(|| self.runtime.block_on(self.client.vacuum_pump(&request)))
+            .map_err(|error| client_error(&error))
+    }
+
+    /// Starts jogging at a duty cycle in ticks of the PWM period, or changes the duty cycle while jogging.
+    ///
+    /// The motor is stopped at `STOP_DUTY`, and the duty cycle can't exceed `MAX_POWER_DUTY`
+    /// or the MCU's jog limits. The MCU stops jogging unless this is called again within its jog timeout.
+    fn jog(&self, py: Python<'_>, duty_cycle: u16) -> PyResult<()> {
+        if duty_cycle > MAX_POWER_DUTY {
+            return Err(PyValueError::new_err(format!(
+                "duty_cycle must be at most {MAX_POWER_DUTY}"
+            )));
+        }
+        self.jog_request(py, &jog::Request::Set(DutyCycle::new(duty_cycle)))
+    }
+
+    /// Stops jogging.
+    fn stop_jog(&self, py: Python<'_>) -> PyResult<()> {
+        self.jog_request(py, &jog::Request::Stop)
+    }
+
+    /// Returns an iterator over the states of the current or next run.
+    ///
+    /// Only one iterator can exist at a time.
+    fn states(&self, py: Python<'_>) -> PyResult<StateIterator> {
+        let (states, outcomes) = py
+            .detach(|| {
+                self.runtime.block_on(async {
+                    Ok((
+                        self.client.subscribe_states().await?,
+                        self.client.subscribe_outcomes().await?,
+                    ))
+                })
+            })
+            .map_err(|error| client_error(&error))?;
+        Ok(StateIterator {
+            runtime: Arc::clone(&self.runtime),
+            states,
+            outcomes,
+            outcome: None,
+            finished: false,
+        })
+    }
+}
+
+/// An iterator over the states of a run.
+///
+/// It stops when the run ends.
+#[pyclass]
+struct StateIterator {
+    /// The runtime that the subscriptions run on.
+    runtime: Arc<Runtime>,
+    /// The motion profile states.
+    states: StateSubscription,
+    /// How runs end.
+    outcomes: Subscription<Outcome>,
+    /// How the run ended, once it has.
+    outcome: Option<Outcome>,
+    /// Whether the run ended.
+    finished: bool,
+}
+
+#[pymethods]
+impl StateIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<State>> {
+        if self.finished {
+            return Ok(None);
+        }
+        loop {
+            let Self {
+                runtime,
+                states,
+                outcomes,
+                outcome,
+                ..
+            } = self;
+            let received = py.detach(|| {
+                runtime.block_on(timeout(SIGNAL_CHECK_PERIOD, async {
+                    loop {
+                        tokio::select! {
+                            // Prefer the outcome if both are ready, so it isn't left behind.
+                            biased;
+                            Some(run_outcome) = outcomes.recv() => *outcome = Some(run_outcome),
+                            state = states.recv() => return state,
+                        }
+                    }
+                }))
+            });
+            match received {
+                Ok(Some(Some(state))) => return Ok(Some(MotionProfileState::from(state).into())),
+                Ok(Some(None)) => {
+                    self.finished = true;
+                    if self.outcome.is_none() {
+                        // The outcome is published on a separate topic, so it can arrive after the final state.
+                        let Self {
+                            runtime, outcomes, ..
+                        } = self;
+                        let late = py
+                            .detach(|| runtime.block_on(timeout(OUTCOME_TIMEOUT, outcomes.recv())));
+                        self.outcome = late.ok().flatten();
+                    }
+                    return Ok(None);
+                }
+                Ok(None) => {
+                    self.finished = true;
+                    return Err(PyConnectionError::new_err(
+                        "The connection to the MCU closed.",
+                    ));
+                }
+                // Let Python handle Ctrl+C while waiting for the run.
+                Err(_) => py.check_signals()?,
+            }
+        }
+    }
+
+    /// How the run ended (e.g. `"Completed"`), or `None` if it hasn't.
+    #[getter]
+    fn outcome(&self) -> Option<String> {
+        self.outcome.map(|outcome| format!("{outcome:?}"))
+    }
+}
+
+/// A sample of a run.
+#[pyclass(frozen, get_all)]
+struct State {
+    /// The time (in seconds) since the run started.
+    time: f64,
+    /// The setpoint motor RPM.
+    setpoint_rpm: u16,
+    /// The setpoint plate RPM.
+    setpoint_plate_rpm: f64,
+    /// The measured motor RPM.
+    current_rpm: u16,
+    /// The measured plate RPM.
+    current_plate_rpm: f64,
+    /// Setpoint motor RPM - current motor RPM.
+    rpm_error: i16,
+    /// Setpoint plate RPM - current plate RPM.
+    plate_rpm_error: f64,
+    /// The duty cycle in ticks of [`sc_messages::pwm::PERIOD`].
+    duty_cycle: u16,
+    /// The duty cycle from range 0.0..1.0.
+    duty_cycle_fraction: f32,
+    /// The time (in micros) between the starts of the previous and current control loop iterations.
+    loop_period: u32,
+    /// The time (in micros) the previous control loop iteration spent executing.
+    execution_time: u32,
+    /// The number of control loop overruns since the run started.
+    overruns: u32,
+    /// The index of this sample among those the MCU published during the run.
+    ///
+    /// A gap between consecutive samples means some were missed.
+    sample: u32,
+}
+
+impl From<MotionProfileState> for State {
+    fn from(state: MotionProfileState) -> Self {
+        Self {
+            time: Duration::from_micros(state.time).as_secs_f64(),
+            setpoint_rpm: state.setpoint_rpm,
+            setpoint_plate_rpm: state.setpoint_plate_rpm,
+            current_rpm: state.current_rpm,
+            current_plate_rpm: state.current_plate_rpm,
+            rpm_error: state.rpm_error,
+            plate_rpm_error: state.plate_rpm_error,
+            duty_cycle: *state.duty_cycle,
+            duty_cycle_fraction: state.duty_cycle_f32,
+            loop_period: state.loop_period,
+            execution_time: state.execution_time,
+            overruns: state.overruns,
+            sample: state.sample,
+        }
+    }
+}
+
+#[pymethods]
+impl State {
+    /// Returns the fields as a `dict`, e.g. for building a `pandas.DataFrame`.
+    fn as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("time", self.time)?;
+        dict.set_item("setpoint_rpm", self.setpoint_rpm)?;
+        dict.set_item("setpoint_plate_rpm", self.setpoint_plate_rpm)?;
+        dict.set_item("current_rpm", self.current_rpm)?;
+        dict.set_item("current_plate_rpm", self.current_plate_rpm)?;
+        dict.set_item("rpm_error", self.rpm_error)?;
+        dict.set_item("plate_rpm_error", self.plate_rpm_error)?;
+        dict.set_item("duty_cycle", self.duty_cycle)?;
+        dict.set_item("duty_cycle_fraction", self.duty_cycle_fraction)?;
+        dict.set_item("loop_period", self.loop_period)?;
+        dict.set_item("execution_time", self.execution_time)?;
+        dict.set_item("overruns", self.overruns)?;
+        dict.set_item("sample", self.sample)?;
+        Ok(dict)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "State(time={}, setpoint_plate_rpm={}, current_plate_rpm={}, duty_cycle={})",
+            self.time, self.setpoint_plate_rpm, self.current_plate_rpm, self.duty_cycle
+        )
+    }
+}
+
+/// Controls the spin coater from Python.
+#[pymodule]
+mod spincoater {
+    #[pymodule_export]
+    use super::{Client, RequestRefused, State, StateIterator, esp_ports};
+
+    /// The duty cycle that stops the motor, in ticks of the PWM period.
+    #[pymodule_export]
+    const STOP_DUTY: u16 = sc_messages::pwm::STOP_DUTY;
+
+    /// The highest duty cycle the motor controller accepts, in ticks of the PWM period.
+    #[pymodule_export]
+    const MAX_POWER_DUTY: u16 = sc_messages::pwm::MAX_POWER_DUTY;
+}